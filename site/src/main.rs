@@ -1,9 +1,14 @@
 use axum::{
     Json,
-    {extract::State, http::StatusCode, response::Html},
+    {
+        extract::{Path, State},
+        http::StatusCode,
+        response::Html,
+    },
 };
 use enum_router::router;
 use hyped::*;
+use pulldown_cmark::{Options, Parser};
 use serde::{Deserialize, Serialize};
 use shared::{Version, VersionResponse};
 use std::{
@@ -19,6 +24,10 @@ use crate::rate_limiting::ApiCallRecord;
 mod proxy;
 mod rate_limiting;
 
+// App versions reported to the clients, older than min version => update is required
+const MIN_APP_VERSION: &str = "1.3.0";
+const LATEST_APP_VERSION: &str = "1.3.9";
+
 // Constants from original dioxus site
 const UP_WAVE_PATH: &str = concat!(
     "M0,128L120,144C240,160,480,192,720,208C960,224,1200,224,1320,224L1440,224L1440,320L1320,320",
@@ -35,6 +44,9 @@ const SIZE_IMG_HERO: (usize, usize) = (1132, 1376);
 const SIZE_VID_HACK_SETTINGS: (usize, usize) = (1126, 1244);
 const VID_HACK_SETTINGS_PATH: &str = "assets/media/hack_settings_1126x1244.mov";
 
+// Blog posts are markdown files, the file name without extension is the slug
+const BLOG_POSTS_DIR: &str = "assets/blog";
+
 const IMG_MARKDOWN_PATH: &str = "assets/media/markdown_and_slash_palette_1132x1376.png";
 const SIZE_IMG_MARKDOWN: (usize, usize) = (1132, 1376);
 
@@ -220,10 +232,24 @@ pub enum Route {
     Root,
     #[get("/api/min-version")]
     MinVersion,
+    #[get("/api/latest-version")]
+    LatestVersion,
+
+    // same payload as "/api/min-version", kept for older clients
+    #[get("/min-version")]
+    LegacyMinVersion,
+    #[get("/latest-version")]
+    LegacyLatestVersion,
 
     #[get("/privacy")]
     Privacy,
 
+    #[get("/blog/{slug}")]
+    Blog(String),
+
+    #[get("/api/roadmap")]
+    ApiRoadmap,
+
     // note that this is how the client will see construct url using genai
     // note that messages are coming from anthropic api url pattern
     #[post("/api/llm-claude/v1/messages")]
@@ -244,18 +270,57 @@ async fn root() -> Html<String> {
 }
 
 async fn min_version() -> Json<VersionResponse> {
+    version_response()
+}
+
+async fn latest_version() -> Json<VersionResponse> {
+    version_response()
+}
+
+async fn legacy_min_version() -> Json<VersionResponse> {
+    version_response()
+}
+
+async fn legacy_latest_version() -> Json<VersionResponse> {
+    version_response()
+}
+
+/// Single source of truth for all version routes, see `MIN_APP_VERSION` and `LATEST_APP_VERSION`
+fn version_response() -> Json<VersionResponse> {
     Json(VersionResponse {
-        min_version: Version("1.3.0".to_string()),
-        latest_version: Version("1.3.9".to_string()),
+        min_version: Version(MIN_APP_VERSION.to_string()),
+        latest_version: Version(LATEST_APP_VERSION.to_string()),
     })
 }
 
+async fn api_roadmap() -> Json<Vec<RoadmapItem>> {
+    Json(roadmap_items())
+}
+
 async fn privacy() -> &'static str {
     let privacy_content = include_str!("../../assets/privacy-policy.md");
 
     privacy_content
 }
 
+async fn blog(Path(slug): Path<String>) -> Result<Html<String>, StatusCode> {
+    // only plain slugs are allowed, so the path can't escape the blog folder
+    let is_valid_slug = !slug.is_empty()
+        && slug
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if !is_valid_slug {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let markdown = tokio::fs::read_to_string(format!("{BLOG_POSTS_DIR}/{slug}.md"))
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    Ok(Html(render_to_string(blog_page(&markdown))))
+}
+
 async fn proxy_anthropic_post(
     State(state): State<Arc<(proxy::Config, Mutex<rate_limiting::RateLimiter>)>>,
     req: axum::extract::Request,
@@ -416,6 +481,28 @@ fn home_page() -> Element {
 
 // Component functions (converted from dioxus components)
 
+fn blog_page(markdown: &str) -> Element {
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+
+    let mut post_html = String::new();
+    pulldown_cmark::html::push_html(&mut post_html, Parser::new_ext(markdown, options));
+
+    theme(
+        ThemeColor::Dark,
+        content((
+            page_header(),
+            space(SpacingSize::Small),
+            div(danger(post_html)).class(&tw_join!(
+                "blog-post",
+                TextStyle::SmallGeneralText,
+                TextColor::Subtle
+            )),
+            space(SpacingSize::Large),
+        )),
+    )
+}
+
 fn theme(color: ThemeColor, children: impl Render + 'static) -> Element {
     let theme_style = match color {
         ThemeColor::Light => ThemeStyle {
@@ -820,96 +907,110 @@ fn roadmap_section() -> Element {
         space(SpacingSize::Medium),
         ol(roadmap_items()
             .into_iter()
-            .map(|(date, completed, name, description)| {
-                roadmap_item(date, completed, name, description)
-            })
+            .map(roadmap_item)
             .collect::<Vec<_>>())
         .class("relative border-s border-nord3"),
     ))
     .class("max-w-4xl mx-auto")
 }
 
-fn roadmap_items() -> Vec<(Option<&'static str>, bool, &'static str, Vec<&'static str>)> {
+/// Also served as json from `/api/roadmap`
+#[derive(Serialize)]
+struct RoadmapItem {
+    date: Option<&'static str>,
+    completed: bool,
+    name: &'static str,
+    description: Vec<&'static str>,
+}
+
+fn roadmap_items() -> Vec<RoadmapItem> {
     vec![
-        (
-            Some("Aug 2025"),
-            true,
-            "Initial launch on macOS",
-            vec![
+        RoadmapItem {
+            date: Some("Aug 2025"),
+            completed: true,
+            name: "Initial launch on macOS",
+            description: vec![
                 "Barebones editing with 4 notes",
                 "Optimized for quick capture",
                 "No Shelv API exposed to JS scripts",
             ],
-        ),
-        (
-            None,
-            false,
-            "Multi-file + workspace support",
-            vec![
+        },
+        RoadmapItem {
+            date: None,
+            completed: false,
+            name: "Multi-file + workspace support",
+            description: vec![
                 "Workspace folder with notes inside",
                 "Import from Obsidian",
                 "File tree + workspace viewer",
             ],
-        ),
-        (
-            None,
-            false,
-            "Agentic mode",
-            vec![
+        },
+        RoadmapItem {
+            date: None,
+            completed: false,
+            name: "Agentic mode",
+            description: vec![
                 "Tools/MCP that allow to search/move/create/edit notes",
                 "UI for having agentic workflows, probably just a chat that is going to be just another file",
                 "Files that define custom workflows, similar to Claude Code",
             ],
-        ),
-        (
-            None,
-            false,
-            "Core editing features",
-            vec![
+        },
+        RoadmapItem {
+            date: None,
+            completed: false,
+            name: "Core editing features",
+            description: vec![
                 "Semantic selection: expand and shrink cursor selection with markdown AST nodes",
                 "Jump to an element, jump to any word on the screen with a couple of keystrokes (similar to Vimium and Helix)",
                 "Search, Redo etc",
             ],
-        ),
-        (None, false, "Support for pasting/rendering images", vec![]),
-        (
-            None,
-            false,
-            "Rich API exposed to JS + better scripting capabilities",
-            vec!["Sharing code among notes"],
-        ),
-        (
-            None,
-            false,
-            "Sync",
-            vec![
+        },
+        RoadmapItem {
+            date: None,
+            completed: false,
+            name: "Support for pasting/rendering images",
+            description: vec![],
+        },
+        RoadmapItem {
+            date: None,
+            completed: false,
+            name: "Rich API exposed to JS + better scripting capabilities",
+            description: vec!["Sharing code among notes"],
+        },
+        RoadmapItem {
+            date: None,
+            completed: false,
+            name: "Sync",
+            description: vec![
                 "I plan to use Automerge for personal syncing, which can be also used for collaboration",
                 "Dump to git, e.g. backup all the notes to git, potentially with AI-generated change summary",
             ],
-        ),
-        (
-            None,
-            false,
-            "Web version",
-            vec!["Mobile (including web) version is TBD"],
-        ),
-        (
-            None,
-            false,
-            "Collaboration",
-            vec![
+        },
+        RoadmapItem {
+            date: None,
+            completed: false,
+            name: "Web version",
+            description: vec!["Mobile (including web) version is TBD"],
+        },
+        RoadmapItem {
+            date: None,
+            completed: false,
+            name: "Collaboration",
+            description: vec![
                 "Share a note via link (co-editing on the web)",
                 "Share workspace, that is, co-ownership of a collection of folder+notes",
             ],
-        ),
+        },
     ]
 }
 
 fn roadmap_item(
-    date: Option<&str>,
-    completed: bool,
-    name: &str,
-    description: Vec<&str>,
+    RoadmapItem {
+        date,
+        completed,
+        name,
+        description,
+    }: RoadmapItem,
 ) -> Element {
     li((
         // Timeline circle with icon
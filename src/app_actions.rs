@@ -1,7 +1,15 @@
-use std::{collections::BTreeMap, io, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    io,
+    path::PathBuf,
+    time::{Instant, SystemTime},
+};
 
 use boa_engine::ast::operations::all_private_identifiers_valid;
-use eframe::egui::{Context, Id, KeyboardShortcut, OpenUrl, ViewportCommand, text::LayoutJob};
+use chrono::Local;
+use eframe::egui::{
+    ColorImage, Context, Id, KeyboardShortcut, OpenUrl, ViewportCommand, text::LayoutJob,
+};
 
 use serde_json::{Value, to_value};
 use similar::{ChangeTag, TextDiff};
@@ -9,31 +17,60 @@ use smallvec::SmallVec;
 
 use crate::{
     app_state::{
-        AppState, CodeBlockAnnotation, FeedbackState, InlineLLMPromptState, InlineLLMResponseChunk,
-        InlinePromptStatus, MsgToApp, ParsedPromptResponse, RenderAction, SlashPalette,
-        TextSelectionAddress, UnsavedChange, VersionState, compute_editor_text_id,
+        AppState, CodeBlockAnnotation, FeedbackState, FindReplace, INLINE_SUGGESTION_CONTEXT_BYTES,
+        InlineLLMPromptState, InlineLLMResponseChunk, InlinePromptStatus, InlineSuggestion,
+        InlineSuggestionStatus, MsgToApp, MultiCursor, ParsedPromptResponse, RenamePrompt,
+        RenderAction, SlashPalette, TablePicker, TextSelectionAddress, UnsavedChange, VersionState,
+        compute_editor_text_id,
     },
     byte_span::{ByteSpan, UnOrderedByteSpan},
-    command::{AppFocus, AppFocusState, CommandContext, CommandList},
+    command::{
+        AppFocus, AppFocusState, CommandContext, CommandList, try_extract_text_command_context,
+    },
     commands::{
+        find_replace::{Finder, find_query_input_id, length_delta},
+        format_note::collapse_blank_lines,
         inline_llm_prompt::compute_inline_prompt_text_input_id,
+        insert_table::{TABLE_PICKER_MAX_COLS, TABLE_PICKER_MAX_ROWS, insert_table},
+        multi_cursor::{MultiCursorEdit, are_valid_selections, edit_at_selections},
+        paste_quote::paste_as_quote,
+        rename_in_code_block::{rename_in_code_block, rename_prompt_input_id},
         run_llm::{CodeBlockAddress, LLM_LANG, prepare_to_run_llm_block},
+        slash_pallete::{palette_term_span, slash_palette_options},
     },
     effects::text_change_effect::{TextChange, apply_text_changes},
     feedback::FeedbackType,
-    persistent_state::NoteFile,
+    focus_session::FocusSession,
+    local_links::{RemoteImageStatus, link_markers, remote_image_urls},
+    note_encryption::{decrypt_with_keychain_passphrase, is_encrypted},
+    note_management::{export_file_name, next_empty_note},
+    note_snapshot::request_note_snapshot,
+    note_switcher::NoteSwitcher,
+    note_template::expand_template,
+    persistent_state::{NoteFile, get_utc_timestamp},
+    recovery_bin::{RECOVERY_BIN_SIZE, RecoveryBin},
     scripting::{
-        note_eval::{JSBlockLang, evaluate_all_live_js_blocks, evaluate_js_block},
+        js_snippets::collect_js_snippets,
+        js_task::{
+            JsTaskProgress, JsTaskRequest, JsTaskUpdate, js_task_blocks, js_task_output_changes,
+            prepare_js_task,
+        },
+        note_eval::{
+            JSBlockLang, evaluate_all_live_js_blocks, evaluate_js_block, find_js_source_block_by_id,
+        },
         settings_eval::{
             Scripts, SettingsNoteEvalContext, eval_js_scripts_in_settings_note,
             eval_kdl_in_settings_note,
         },
     },
-    settings_parsing::LlmSettings,
+    settings_parsing::{FocusLostBehavior, LlmSettings},
+    tags::{TagFilter, tag_filter_input_id},
     text_structure::{
         CodeBlockMeta, SpanIndex, SpanKind, SpanMeta, TextDiffPart, create_error_text_layout_job,
         create_layout_job_from_text_diff,
     },
+    theme::{ColorTheme, configure_styles},
+    tutorial::TutorialState,
 };
 
 #[derive(Clone, Copy, Debug)]
@@ -54,6 +91,77 @@ pub enum SlashPaletteAction {
     ExecuteCommand(usize),
 }
 
+#[derive(Debug)]
+pub enum TablePickerAction {
+    Show(TablePicker),
+    Resize { rows: usize, cols: usize },
+    Confirm,
+    Hide,
+}
+
+#[derive(Debug)]
+pub enum NoteSwitcherAction {
+    Cycle { backwards: bool },
+    Commit,
+}
+
+#[derive(Debug)]
+pub enum TagFilterAction {
+    Show,
+    MoveSelection { backwards: bool },
+    Confirm,
+    Hide,
+}
+
+#[derive(Debug)]
+pub enum RecoveryBinAction {
+    Show,
+    MoveSelection {
+        backwards: bool,
+    },
+    /// Restores the selected entry into the current note
+    Confirm,
+    Hide,
+}
+
+#[derive(Debug)]
+pub enum RenamePromptAction {
+    Show(RenamePrompt),
+    Confirm,
+    Hide,
+}
+
+#[derive(Debug)]
+pub enum FindReplaceAction {
+    Show(FindReplace),
+    ReplaceNext,
+    ReplaceAll,
+    Hide,
+}
+
+#[derive(Debug)]
+pub enum ClearNoteAction {
+    /// Shows the confirmation modal
+    Ask(NoteFile),
+    Confirm,
+    Cancel,
+}
+
+#[derive(Debug)]
+pub enum MultiCursorAction {
+    /// Keeps the selection when the primary cursor moves to the next occurrence
+    AddSelection(ByteSpan),
+    Edit(MultiCursorEdit),
+    Clear,
+}
+
+#[derive(Debug)]
+pub enum TutorialAction {
+    Next,
+    Prev,
+    Finish,
+}
+
 #[derive(Debug)]
 pub enum AppAction {
     SwitchToNote {
@@ -64,6 +172,9 @@ pub enum AppAction {
     // ShowApp,
     OpenLink(String),
     SetWindowPinned(bool),
+    SetCrashReporting(bool),
+    /// Also answers the one time prompt
+    SetStartAtLogin(bool),
     ApplyTextChanges {
         target: NoteFile,
         changes: Vec<TextChange>,
@@ -81,15 +192,63 @@ pub enum AppAction {
     IssueRenderAction(RenderAction),
     FocusRequest(FocusTarget),
     OpenNotesInFinder,
+    /// Opens the note file in `$EDITOR` or the configured `externalEditor`
+    OpenNoteInExternalEditor(NoteFile),
+    /// Writes the markdown of the note to a file picked in a save dialog
+    ExportNoteToPath(NoteFile),
     ShowPrompt(TextSelectionAddress),
     ExecutePrompt,
     AcceptPromptSuggestion {
         accept: bool,
     },
+    /// Asks the LLM how the note continues, see `InlineSuggestion`
+    RequestInlineSuggestion(TextSelectionAddress),
+    /// Inserts the ghost text after the cursor (or drops it)
+    AcceptInlineSuggestion {
+        accept: bool,
+    },
 
     SlashPalette(SlashPaletteAction),
+    TablePicker(TablePickerAction),
+    MultiCursor(MultiCursorAction),
+    RenamePrompt(RenamePromptAction),
+    FindReplace(FindReplaceAction),
+    NoteSwitcher(NoteSwitcherAction),
+    TagFilter(TagFilterAction),
+    /// Switches the note between editing and read only rendered markdown
+    ToggleReadingMode(NoteFile),
+    /// Shows (or hides) the rendered markdown of the selected note next to the editor
+    TogglePreviewPanel,
+    /// Switches the note between wrapped lines and horizontal scrolling
+    ToggleSoftWrap(NoteFile),
+    /// Marks the note to be stored encrypted (or back to plain markdown), the file is rewritten
+    ToggleNoteEncryption(NoteFile),
+    /// Makes the note read only (or editable again)
+    ToggleNoteLock(NoteFile),
+    /// Starts a new focus session (timer + context switches in the footer) or ends the current one
+    ToggleFocusSession,
+    /// Copies the content of the note into the next empty shelf
+    DuplicateNote(NoteFile),
+    /// Opens the note in a separate window, it is edited along with the main one
+    PopOutNote(NoteFile),
+    ClosePoppedOutNote(NoteFile),
+    ClearNote(ClearNoteAction),
+    RecoveryBin(RecoveryBinAction),
+    Tutorial(TutorialAction),
     HideApp,
+    /// Closes the window gracefully, so the notes are saved before exiting
+    QuitApp,
     CopyCodeBlock(NoteFile, SpanIndex),
+    /// Selects the language after the opening fence, so typing replaces it
+    SelectCodeBlockLang(NoteFile, SpanIndex),
+    /// Puts the text into the clipboard as is
+    CopyText(String),
+    /// Keeps the text visible in a floating overlay above the editor, `None` closes it
+    SetPinnedSnippet(Option<String>),
+    /// Inserts the text of the clipboard as a blockquote at the cursor
+    PasteAsQuote(NoteFile),
+    CopyNoteAsImage,
+    NoteSnapshotTaken(ColorImage),
     AppUpdateClicked,
 }
 
@@ -135,12 +294,19 @@ pub struct LLMPromptRequest {
     pub selection_location: TextSelectionAddress,
 }
 
+#[derive(Debug)]
+pub struct LLMSuggestionRequest {
+    /// the end of the note up to the cursor, at most `INLINE_SUGGESTION_CONTEXT_BYTES`
+    pub before_cursor: String,
+    pub address: TextSelectionAddress,
+}
+
 pub struct SettingsForAiRequests<'s> {
     pub commands: &'s CommandList,
     pub llm_settings: Option<&'s LlmSettings>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum HideMode {
     HideApp,
     YieldFocus,
@@ -148,7 +314,24 @@ pub enum HideMode {
 // TODO consider focus, opening links etc as IO operations
 pub trait AppIO {
     fn hide_app(&self, mode: HideMode);
+    /// 1.0 is fully opaque
+    fn set_window_opacity(&self, opacity: f32);
+    /// Returns the path of the backup file, `encrypt` is set for encrypted notes
+    fn backup_note(
+        &self,
+        note_file: NoteFile,
+        content: &str,
+        encrypt: bool,
+    ) -> Result<PathBuf, io::Error>;
+    /// The most recent backups first
+    fn recent_note_backups(&self, limit: usize) -> Result<Vec<NoteBackup>, io::Error>;
     fn open_shelv_folder(&self) -> Result<(), Box<dyn std::error::Error>>;
+    /// `command` overrides `$VISUAL`/`$EDITOR`, changes are picked up by the folder watcher
+    fn open_in_external_editor(
+        &self,
+        note_file: NoteFile,
+        command: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
     fn try_read_note_if_newer(
         &self,
         path: &PathBuf,
@@ -167,6 +350,16 @@ pub trait AppIO {
 
     fn execute_llm_block<'s>(&self, question: LLMBlockRequest, cx: SettingsForAiRequests<'s>);
     fn execute_llm_prompt<'s>(&self, quesion: LLMPromptRequest, cx: SettingsForAiRequests<'s>);
+    /// The whole continuation is sent back at once as `MsgToApp::InlineSuggestionResponse`
+    fn request_llm_suggestion<'s>(
+        &self,
+        request: LLMSuggestionRequest,
+        cx: SettingsForAiRequests<'s>,
+    );
+    /// Progress and the result are sent back as `MsgToApp::JsTaskUpdate`
+    fn run_js_task(&self, request: JsTaskRequest);
+    /// Checks that the image can be loaded, the result is sent back as `MsgToApp::RemoteImageFetched`
+    fn fetch_remote_image(&self, url: String);
 
     fn capture_sentry_message<F>(
         &self,
@@ -179,9 +372,29 @@ pub trait AppIO {
 
     fn copy_to_clipboard(&self, text: String);
 
+    fn copy_image_to_clipboard(&self, image: ColorImage);
+
+    /// None if the clipboard is empty or has no text, e.g. an image
+    fn read_clipboard_text(&self) -> Option<String>;
+
+    /// Asks where to save with a native dialog, `Ok(None)` if it was cancelled
+    fn export_note(
+        &self,
+        suggested_file_name: &str,
+        content: &str,
+    ) -> Result<Option<PathBuf>, io::Error>;
+
     fn start_update_checker(&self);
 
     fn open_app_store_for_shelv_update(&self);
+
+    /// Stops sending reports right away, enabling it back requires a restart
+    fn disable_crash_reporting(&self);
+
+    /// Whether Shelv is registered to start at login
+    fn is_start_at_login_enabled(&self) -> bool;
+
+    fn set_start_at_login(&self, enabled: bool) -> Result<(), Box<dyn std::error::Error>>;
 }
 
 pub fn process_app_action(
@@ -197,6 +410,11 @@ pub fn process_app_action(
             note_file,
             via_shortcut,
         } => {
+            let tidy_up_prev_note = match note_file != state.selected_note {
+                true => collapse_blank_lines_of(state, state.selected_note),
+                false => None,
+            };
+
             if note_file != state.selected_note {
                 state.add_unsaved_change(UnsavedChange::SelectionChanged);
                 if via_shortcut {
@@ -223,18 +441,23 @@ pub fn process_app_action(
                     }
                 }
                 state.selected_note = note_file;
+                state.recent_notes.touch(note_file);
 
                 // reset inline prompt state if we switched to a different note
                 state.inline_llm_prompt = None;
             }
 
-            match via_shortcut {
-                true => [AppAction::DeferToPostRender(Box::new(
-                    AppAction::FocusRequest(FocusTarget::CurrentNote),
-                ))]
-                .into(),
-                false => SmallVec::new(),
-            }
+            let focus_note = via_shortcut.then(|| {
+                AppAction::DeferToPostRender(Box::new(AppAction::FocusRequest(
+                    FocusTarget::CurrentNote,
+                )))
+            });
+
+            focus_note
+                .into_iter()
+                .chain(fill_from_template(state, note_file))
+                .chain(tidy_up_prev_note)
+                .collect()
         }
 
         AppAction::OpenLink(url) => {
@@ -247,11 +470,38 @@ pub fn process_app_action(
             state.add_unsaved_change(UnsavedChange::PinStateChanged);
             SmallVec::new()
         }
+        AppAction::SetCrashReporting(enabled) => {
+            state.crash_reporting = enabled;
+            state.add_unsaved_change(UnsavedChange::CrashReportingChanged);
+            if !enabled {
+                app_io.disable_crash_reporting();
+            }
+            SmallVec::new()
+        }
+        AppAction::SetStartAtLogin(enabled) => {
+            if !state.start_at_login_prompted {
+                state.start_at_login_prompted = true;
+                state.add_unsaved_change(UnsavedChange::StartAtLoginPrompted);
+            }
+            state.show_start_at_login_prompt = false;
+
+            match app_io.set_start_at_login(enabled) {
+                Ok(()) => state.start_at_login = enabled,
+                Err(err) => println!("failed to set start at login to {enabled}, err={err:#?}"),
+            }
+            SmallVec::new()
+        }
         AppAction::ApplyTextChanges {
             target: note_file,
             changes,
             should_trigger_eval,
         } => {
+            // a locked note is read only, no matter which command or prompt produced the changes
+            if state.is_read_only(note_file) {
+                println!("ignoring text changes of the read only note {note_file:?}");
+                return SmallVec::new();
+            }
+
             let note = &mut state.notes.get_mut(&note_file).unwrap();
             let cursor = note.cursor();
             let text = &mut note.text;
@@ -274,6 +524,7 @@ pub fn process_app_action(
                     // reset the inline prompt state if any changes happened
                     // it maybe a bit too  aggressive, but let's live with the simplest approach first
                     state.inline_llm_prompt = None;
+                    state.inline_suggestion = None;
                     should_trigger_eval.then(|| AppAction::EvalNote(note_file))
                 }
                 Err(_) => None,
@@ -298,14 +549,23 @@ pub fn process_app_action(
             SmallVec::new()
         }
 
+        AppAction::QuitApp => {
+            println!("Quit app via ui");
+            ctx.send_viewport_cmd(ViewportCommand::Close);
+            SmallVec::new()
+        }
+
         AppAction::HandleMsgToApp(msg) => {
             match msg {
                 MsgToApp::ToggleVisibility => {
                     let was_visible = !state.hidden;
 
                     if was_visible {
-                        if state.is_pinned && was_visible && !focus_state.viewport_focused {
-                            // if it is pinned just refocus instea of hiding it
+                        let stays_on_focus_lost =
+                            state.is_pinned || state.on_focus_lost() != FocusLostBehavior::Hide;
+
+                        if stays_on_focus_lost && !focus_state.viewport_focused {
+                            // if it is pinned (or stays visible) just refocus instea of hiding it
                             println!("Toggle visibility: hide");
                             ctx.send_viewport_cmd(ViewportCommand::Focus);
                             SmallVec::from_buf([AppAction::defer(AppAction::FocusRequest(
@@ -337,6 +597,20 @@ pub fn process_app_action(
                 MsgToApp::NoteFileChanged(note_file, path) => {
                     match app_io.try_read_note_if_newer(&path, state.last_saved) {
                         Ok(Some(note_content)) => {
+                            let note_content = match is_encrypted(&note_content) {
+                                true => match decrypt_with_keychain_passphrase(&note_content) {
+                                    Ok(decrypted) => decrypted,
+                                    Err(err) => {
+                                        // keep what we have instead of showing the ciphertext
+                                        println!(
+                                            "ignoring {path:#?}, failed to decrypt err={err:?}"
+                                        );
+                                        return SmallVec::new();
+                                    }
+                                },
+                                false => note_content,
+                            };
+
                             if let Some(note) = state.notes.get_mut(&note_file) {
                                 // TODO don't reset the cursor
                                 note.reset_cursor();
@@ -584,6 +858,52 @@ pub fn process_app_action(
                         SmallVec::new()
                     }
                 },
+                MsgToApp::InlineSuggestionResponse { response, address } => {
+                    // the note could have been edited while waiting for the response
+                    let is_awaited = state.inline_suggestion.as_ref().is_some_and(|suggestion| {
+                        suggestion.address == address
+                            && suggestion.status == InlineSuggestionStatus::Requested
+                    });
+
+                    if is_awaited {
+                        state.inline_suggestion = match response {
+                            Ok(text) if !text.trim().is_empty() => Some(InlineSuggestion {
+                                address,
+                                status: InlineSuggestionStatus::Ready(text),
+                            }),
+                            Ok(_) => None,
+                            Err(err) => {
+                                println!("failed to get an inline suggestion: {err}");
+                                None
+                            }
+                        };
+                    }
+
+                    SmallVec::new()
+                }
+                MsgToApp::RemoteImageFetched { url, result } => {
+                    let status = match result {
+                        Ok(()) => RemoteImageStatus::Loaded,
+                        Err(err) => {
+                            println!("failed to fetch the image {url}, err={err}");
+                            RemoteImageStatus::Failed(err)
+                        }
+                    };
+                    state.remote_images.insert(url.clone(), status);
+
+                    for note in state.notes.values_mut() {
+                        let structure = &note.derived_state.structure;
+                        if remote_image_urls(structure).contains(&url) {
+                            note.derived_state.link_markers = link_markers(
+                                structure,
+                                &state.persistence_folder,
+                                &state.remote_images,
+                            );
+                        }
+                    }
+
+                    SmallVec::new()
+                }
                 MsgToApp::UpdateRequired(required_version) => {
                     state.version_state = VersionState::RequiredUpdateAvailable(required_version);
                     SmallVec::new()
@@ -592,10 +912,59 @@ pub fn process_app_action(
                     state.version_state = VersionState::UpdateAvailable(latest_version);
                     SmallVec::new()
                 }
+
+                MsgToApp::JsTaskUpdate {
+                    note_file,
+                    block_id,
+                    update: JsTaskUpdate::Progress(progress),
+                } => {
+                    let Some(latest) = state.js_tasks.get_mut(&(note_file, block_id)) else {
+                        return SmallVec::new();
+                    };
+                    *latest = progress.clone();
+
+                    let derived_state = &mut state.notes.get_mut(&note_file).unwrap().derived_state;
+                    if let Some((index, _)) =
+                        find_js_source_block_by_id(&derived_state.structure, block_id)
+                    {
+                        let annotations = &mut derived_state.code_block_annotations;
+                        annotations.retain(|(annotated, _)| *annotated != index);
+                        annotations.push((index, CodeBlockAnnotation::Progress(progress)));
+                    }
+
+                    SmallVec::new()
+                }
+
+                MsgToApp::JsTaskUpdate {
+                    note_file,
+                    block_id,
+                    update: JsTaskUpdate::Done { result, storage },
+                } => {
+                    state.js_tasks.remove(&(note_file, block_id));
+
+                    let storage_changed = storage
+                        .is_some_and(|storage| state.script_storage.update(note_file, storage));
+
+                    if storage_changed {
+                        state.add_unsaved_change(UnsavedChange::ScriptStorageChanged);
+                    }
+
+                    let structure = &state.notes.get(&note_file).unwrap().derived_state.structure;
+                    match js_task_output_changes(structure, block_id, result) {
+                        Some(changes) => SmallVec::from_buf([AppAction::ApplyTextChanges {
+                            target: note_file,
+                            changes,
+                            should_trigger_eval: true,
+                        }]),
+                        // the block was removed, only the progress annotation is left to clean up
+                        None => SmallVec::from_buf([AppAction::EvalNote(note_file)]),
+                    }
+                }
             }
         }
 
         AppAction::EvalNote(note_file) => {
+            let mut notes_to_reevaluate: SmallVec<[NoteFile; 4]> = SmallVec::new();
             let note = &mut state.notes.get_mut(&note_file).unwrap();
             let text_structure = &note.derived_state.structure;
             let text = &mut note.text;
@@ -630,6 +999,7 @@ pub fn process_app_action(
 
             let requested_changes = match note_file {
                 NoteFile::Note(_) => {
+                    let task_blocks = js_task_blocks(text_structure, text);
                     let run_button_annotations = text_structure
                         .filter_map_codeblocks(|lang| {
                             if let Some(JSBlockLang::Source(link_id)) = JSBlockLang::parse(lang) {
@@ -652,15 +1022,53 @@ pub fn process_app_action(
                                 None
                             }
                         })
+                        .filter(|(index, ..)| !task_blocks.iter().any(|(task, _)| task == index))
                         .map(|(index, _, _, _)| (index, CodeBlockAnnotation::RunButton));
 
+                    // tasks are not re-evaluated live, so they can always be started again
+                    let task_annotations =
+                        task_blocks.iter().map(|(index, block_id)| {
+                            match block_id.and_then(|id| state.js_tasks.get(&(note_file, id))) {
+                                Some(progress) => {
+                                    (*index, CodeBlockAnnotation::Progress(progress.clone()))
+                                }
+                                None => (*index, CodeBlockAnnotation::RunButton),
+                            }
+                        });
+
                     note.derived_state.code_block_annotations.clear();
                     note.derived_state
                         .code_block_annotations
-                        .extend(run_button_annotations);
+                        .extend(run_button_annotations.chain(task_annotations));
+
+                    // each remote image is fetched once, its marker is updated when it is done
+                    for url in remote_image_urls(text_structure) {
+                        if !state.remote_images.contains_key(&url) {
+                            app_io.fetch_remote_image(url.clone());
+                            state.remote_images.insert(url, RemoteImageStatus::Loading);
+                        }
+                    }
+
+                    note.derived_state.link_markers = link_markers(
+                        text_structure,
+                        &state.persistence_folder,
+                        &state.remote_images,
+                    );
 
                     // Evaluate all live JavaScript blocks
-                    evaluate_all_live_js_blocks(text_structure, text)
+                    let mut storage = state.script_storage.note(note_file);
+                    let changes = evaluate_all_live_js_blocks(
+                        text_structure,
+                        text,
+                        state.js_snippets.as_ref(),
+                        &mut storage,
+                    );
+
+                    if state.script_storage.update(note_file, storage) {
+                        state.add_unsaved_change(UnsavedChange::ScriptStorageChanged);
+                    }
+
+                    changes
                 }
 
                 NoteFile::Settings => {
@@ -670,32 +1078,104 @@ pub fn process_app_action(
                         eval_js_scripts_in_settings_note(text, text_structure);
 
                     state.commands.reset_to_defaults();
+                    let mut command_log_settings = None;
+                    let mut theme_settings = None;
+                    // optional settings are reset if they were removed from settings
+                    state.quiet_hours = None;
+                    state.slash_palette_settings = None;
+                    state.dev_settings = None;
+                    state.window_settings = None;
+                    state.editor_settings = None;
+                    state.note_mirrors.clear();
+                    state.note_templates.clear();
+                    state.global_hotkey_warning = None;
+
+                    // global bindings are registered again, the OS refuses a taken shortcut
+                    // even if it was taken by Shelv itself
+                    if let Err(err) = app_io.cleanup_all_global_hotkeys() {
+                        println!("failed to unregister global hotkeys, err={err}");
+                    }
+
                     let cx = SettingsNoteEvalContext {
                         cmd_list: &mut state.commands,
                         scripts: &settings_scripts,
                         app_io,
                         llm_settings: &mut state.llm_settings,
+                        command_log_settings: &mut command_log_settings,
+                        quiet_hours: &mut state.quiet_hours,
+                        slash_palette_settings: &mut state.slash_palette_settings,
+                        dev_settings: &mut state.dev_settings,
+                        window_settings: &mut state.window_settings,
+                        editor_settings: &mut state.editor_settings,
+                        theme_settings: &mut theme_settings,
+                        note_mirrors: &mut state.note_mirrors,
+                        encrypted_notes: &state.encrypted_notes,
+                        note_templates: &mut state.note_templates,
+                        global_hotkey_warning: &mut state.global_hotkey_warning,
                     };
 
                     let mut kdl_annotations = eval_kdl_in_settings_note(&text, &text_structure, cx);
+                    state.command_log.apply_settings(command_log_settings);
+
+                    state.theme.colors = ColorTheme::nord().with_settings(theme_settings.as_ref());
+                    configure_styles(ctx, &state.theme);
+
+                    let tree_sitter_langs = state
+                        .editor_settings
+                        .as_ref()
+                        .map(|editor| editor.tree_sitter_langs.clone())
+                        .unwrap_or_default();
+
+                    if state
+                        .highlight_cache
+                        .set_tree_sitter_langs(tree_sitter_langs)
+                    {
+                        // layouts are recomputed only on text changes, so the code blocks would keep the old highlighting
+                        state.computed_layout = None;
+                        state.preview_layout = None;
+                    }
+
+                    let (js_snippets, snippet_annotations) =
+                        collect_js_snippets(text, text_structure);
 
                     kdl_annotations.extend(block_annotations);
+                    kdl_annotations.extend(snippet_annotations);
                     note.derived_state.code_block_annotations = kdl_annotations;
 
                     state.settings_scripts = Some(settings_scripts);
 
+                    // live blocks in notes might depend on the snippets
+                    if state.js_snippets != js_snippets {
+                        state.js_snippets = js_snippets;
+                        notes_to_reevaluate.extend(
+                            state
+                                .notes
+                                .keys()
+                                .filter(|note_file| matches!(note_file, NoteFile::Note(_)))
+                                .copied(),
+                        );
+                    }
+
                     None
                 }
             };
 
+            // the selected note might have been opened before its template was known, e.g. at launch
+            let template_fill = match note_file {
+                NoteFile::Settings => fill_from_template(state, state.selected_note),
+                NoteFile::Note(_) => None,
+            };
+
             requested_changes
                 .map(|changes| AppAction::ApplyTextChanges {
                     target: note_file,
                     changes,
                     should_trigger_eval: false,
                 })
-                .map(|a| [a].into())
-                .unwrap_or_default()
+                .into_iter()
+                .chain(notes_to_reevaluate.into_iter().map(AppAction::EvalNote))
+                .chain(template_fill)
+                .collect()
         }
 
         AppAction::AskLLM(question) => {
@@ -803,6 +1283,9 @@ pub fn process_app_action(
                 }
             };
 
+            // callouts pointing at the UI are shown on top of the tutorial note
+            state.tutorial = Some(TutorialState::default());
+
             SmallVec::from_iter([
                 AppAction::SwitchToNote {
                     note_file: first_note_id,
@@ -861,6 +1344,34 @@ pub fn process_app_action(
             SmallVec::new()
         }
 
+        AppAction::OpenNoteInExternalEditor(note_file) => {
+            if state.encrypted_notes.contains(&note_file) {
+                // the file contains only the encrypted text
+                println!("Not opening encrypted {note_file:?} in external editor");
+            } else {
+                let command = state
+                    .editor_settings
+                    .as_ref()
+                    .and_then(|editor| editor.external_editor.as_deref());
+
+                if let Err(e) = app_io.open_in_external_editor(note_file, command) {
+                    println!("Error opening {note_file:?} in external editor: {}", e);
+                }
+            }
+            SmallVec::new()
+        }
+
+        AppAction::ExportNoteToPath(note_file) => {
+            let text = &state.notes.get(&note_file).unwrap().text;
+
+            match app_io.export_note(&export_file_name(note_file, text), text) {
+                Ok(Some(path)) => println!("Exported {note_file:?} to {path:?}"),
+                Ok(None) => {}
+                Err(e) => println!("Error exporting {note_file:?}: {}", e),
+            }
+            SmallVec::new()
+        }
+
         AppAction::RunCodeBlock(note_file, span_index) => {
             let note = state.notes.get(&note_file).unwrap();
             let text_structure = &note.derived_state.structure;
@@ -871,7 +1382,32 @@ pub fn process_app_action(
                     lang,
                     lang_byte_span: _,
                 })) if matches!(JSBlockLang::parse(lang), Some(JSBlockLang::Source(_))) => {
-                    evaluate_js_block(span_index, text_structure, &note.text)
+                    let mut storage = state.script_storage.note(note_file);
+
+                    if let Some((request, id_change)) = prepare_js_task(
+                        note_file,
+                        span_index,
+                        text_structure,
+                        &note.text,
+                        state.js_snippets.as_ref(),
+                        &storage,
+                    ) {
+                        return start_js_task(state, app_io, request, id_change);
+                    }
+
+                    let changes = evaluate_js_block(
+                        span_index,
+                        text_structure,
+                        &note.text,
+                        state.js_snippets.as_ref(),
+                        &mut storage,
+                    );
+
+                    if state.script_storage.update(note_file, storage) {
+                        state.add_unsaved_change(UnsavedChange::ScriptStorageChanged);
+                    }
+
+                    changes
                         .map(|changes| {
                             SmallVec::from_buf([AppAction::ApplyTextChanges {
                                 target: note_file,
@@ -906,7 +1442,11 @@ pub fn process_app_action(
                 response_text: "".to_string(),
                 diff_parts: vec![],
                 layout_job: LayoutJob::default(),
-                prompt: "".to_string(),
+                prompt: state
+                    .last_llm_prompts
+                    .get(&address.note_file)
+                    .cloned()
+                    .unwrap_or_default(),
                 status: InlinePromptStatus::NotStarted,
                 fresh_response: false,
                 parsed_response: ParsedPromptResponse::parse_stream(""),
@@ -957,6 +1497,13 @@ pub fn process_app_action(
                 },
             );
 
+            // pre-fills the next prompt in the same note
+            let (note_file, prompt) = (prompt.address.note_file, prompt.prompt.clone());
+            if state.last_llm_prompts.get(&note_file) != Some(&prompt) {
+                state.last_llm_prompts.insert(note_file, prompt);
+                state.add_unsaved_change(UnsavedChange::LastLLMPromptsChanged);
+            }
+
             SmallVec::new()
         }
 
@@ -994,8 +1541,56 @@ pub fn process_app_action(
             }
         }
 
+        AppAction::RequestInlineSuggestion(address) => {
+            let Some(note) = state.notes.get(&address.note_file) else {
+                return SmallVec::new();
+            };
+
+            // the end of the note is enough to continue it, and keeps the request fast
+            let cursor = address.span.start;
+            let context_start = (cursor.saturating_sub(INLINE_SUGGESTION_CONTEXT_BYTES)..cursor)
+                .find(|pos| note.text.is_char_boundary(*pos))
+                .unwrap_or(cursor);
+
+            app_io.request_llm_suggestion(
+                LLMSuggestionRequest {
+                    before_cursor: note.text[context_start..cursor].to_string(),
+                    address,
+                },
+                SettingsForAiRequests {
+                    llm_settings: state.llm_settings.as_ref(),
+                    commands: &state.commands,
+                },
+            );
+
+            SmallVec::new()
+        }
+
+        AppAction::AcceptInlineSuggestion { accept } => {
+            match (accept, state.inline_suggestion.take()) {
+                (
+                    true,
+                    Some(InlineSuggestion {
+                        address,
+                        status: InlineSuggestionStatus::Ready(text),
+                    }),
+                ) => [AppAction::apply_text_changes(
+                    address.note_file,
+                    vec![TextChange::Insert(
+                        address.span,
+                        format!("{text}{}", TextChange::CURSOR),
+                    )],
+                )]
+                .into(),
+                _ => SmallVec::new(),
+            }
+        }
+
         AppAction::OpenFeedbackWindow => {
-            state.feedback = Some(FeedbackState::default());
+            // nothing would be delivered with the crash reporting client closed
+            if state.crash_reporting {
+                state.feedback = Some(FeedbackState::default());
+            }
             SmallVec::new()
         }
 
@@ -1061,6 +1656,7 @@ pub fn process_app_action(
                     let Some(SlashPalette {
                         note_file,
                         slash_byte_pos,
+                        with_slash,
                         search_term,
                         options,
                         ..
@@ -1079,7 +1675,7 @@ pub fn process_app_action(
                             changes: [TextChange::Insert(
                                 ByteSpan::new(
                                     slash_byte_pos,
-                                    slash_byte_pos + search_term.len() + 1,
+                                    slash_byte_pos + search_term.len() + with_slash as usize,
                                 ),
                                 "".to_string(),
                             )]
@@ -1112,6 +1708,9 @@ pub fn process_app_action(
                     );
 
                     state.settings_scripts = Some(scripts);
+                    state
+                        .record_executed_command(&cmd.instance.instruction, actions_from_cmd.len());
+                    state.record_slash_command_usage(&cmd.prefix);
 
                     SmallVec::from_iter(
                         action_after_text_changes
@@ -1121,14 +1720,616 @@ pub fn process_app_action(
                 }
 
                 SP::Hide => {
-                    state.slash_palette = None;
-                    SmallVec::from_iter([AppAction::defer(AppAction::FocusRequest(
-                        FocusTarget::CurrentNote,
-                    ))])
+                    let focus_note =
+                        AppAction::defer(AppAction::FocusRequest(FocusTarget::CurrentNote));
+
+                    let Some(palette) = state.slash_palette.take() else {
+                        return SmallVec::from_iter([focus_note]);
+                    };
+
+                    let remove_slash = state
+                        .slash_palette_settings
+                        .as_ref()
+                        .is_some_and(|settings| settings.remove_slash_on_close);
+
+                    let Some(note) = state.notes.get_mut(&palette.note_file) else {
+                        return SmallVec::from_iter([focus_note]);
+                    };
+
+                    // the cursor goes to the slash position if the term is removed,
+                    // otherwise right after the typed term
+                    match palette_term_span(&note.text, &palette) {
+                        Some(term_span) if remove_slash => SmallVec::from_iter([
+                            AppAction::ApplyTextChanges {
+                                target: palette.note_file,
+                                changes: vec![TextChange::Insert(
+                                    term_span,
+                                    TextChange::CURSOR.to_string(),
+                                )],
+                                should_trigger_eval: false,
+                            },
+                            focus_note,
+                        ]),
+                        Some(term_span) => {
+                            note.update_cursor(UnOrderedByteSpan::new(
+                                term_span.end,
+                                term_span.end,
+                            ));
+                            SmallVec::from_iter([focus_note])
+                        }
+                        // the text was changed around the palette, nothing to restore
+                        None => SmallVec::from_iter([focus_note]),
+                    }
                 }
             }
         }
 
+        AppAction::TablePicker(table_picker_action) => match table_picker_action {
+            TablePickerAction::Show(picker) => {
+                state.table_picker = Some(picker);
+                SmallVec::new()
+            }
+
+            TablePickerAction::Resize { rows, cols } => {
+                if let Some(picker) = state.table_picker.as_mut() {
+                    picker.rows = rows.clamp(1, TABLE_PICKER_MAX_ROWS);
+                    picker.cols = cols.clamp(1, TABLE_PICKER_MAX_COLS);
+                }
+                SmallVec::new()
+            }
+
+            TablePickerAction::Confirm => {
+                let Some(TablePicker {
+                    note_file,
+                    rows,
+                    cols,
+                    ..
+                }) = state.table_picker.take()
+                else {
+                    return SmallVec::new();
+                };
+
+                if note_file != state.selected_note {
+                    return SmallVec::new();
+                }
+
+                let changes = try_extract_text_command_context(state)
+                    .and_then(|cx| insert_table(cx, rows, cols));
+
+                SmallVec::from_iter(
+                    changes
+                        .map(|changes| AppAction::apply_text_changes(note_file, changes))
+                        .into_iter()
+                        .chain([AppAction::defer(AppAction::FocusRequest(
+                            FocusTarget::CurrentNote,
+                        ))]),
+                )
+            }
+
+            TablePickerAction::Hide => {
+                state.table_picker = None;
+                SmallVec::from_iter([AppAction::defer(AppAction::FocusRequest(
+                    FocusTarget::CurrentNote,
+                ))])
+            }
+        },
+
+        AppAction::NoteSwitcher(note_switcher_action) => match note_switcher_action {
+            NoteSwitcherAction::Cycle { backwards } => {
+                state
+                    .note_switcher
+                    .get_or_insert_with(|| NoteSwitcher::new(&state.recent_notes))
+                    .cycle(backwards);
+                SmallVec::new()
+            }
+
+            NoteSwitcherAction::Commit => state
+                .note_switcher
+                .take()
+                .and_then(|switcher| switcher.selected_note())
+                .map(|note_file| AppAction::SwitchToNote {
+                    note_file,
+                    via_shortcut: true,
+                })
+                .into_iter()
+                .collect(),
+        },
+
+        AppAction::ToggleReadingMode(note_file) => {
+            if state.reading_mode_notes.remove(&note_file) {
+                // back to editing
+                SmallVec::from_iter([AppAction::defer(AppAction::FocusRequest(
+                    FocusTarget::CurrentNote,
+                ))])
+            } else {
+                state.reading_mode_notes.insert(note_file);
+                SmallVec::new()
+            }
+        }
+
+        AppAction::TogglePreviewPanel => {
+            state.show_preview_panel = !state.show_preview_panel;
+            state.preview_layout = None;
+            SmallVec::new()
+        }
+
+        AppAction::ToggleSoftWrap(note_file) => {
+            // the layout is recomputed on its own, soft wrap is a part of its params
+            if !state.unwrapped_notes.remove(&note_file) {
+                state.unwrapped_notes.insert(note_file);
+            }
+            SmallVec::new()
+        }
+
+        AppAction::ToggleNoteEncryption(note_file) => {
+            // only shelves, settings are always kept in plain markdown
+            if let NoteFile::Note(_) = note_file {
+                if !state.encrypted_notes.remove(&note_file) {
+                    state.encrypted_notes.insert(note_file);
+                }
+
+                state.add_unsaved_change(UnsavedChange::NoteContentChanged(note_file));
+
+                // mirrors are accepted only for notes in plain text
+                return SmallVec::from_iter([AppAction::EvalNote(NoteFile::Settings)]);
+            }
+
+            SmallVec::new()
+        }
+
+        AppAction::ToggleNoteLock(note_file) => {
+            state.add_unsaved_change(UnsavedChange::LockedNotesChanged);
+
+            if state.locked_notes.remove(&note_file) {
+                // back to editing
+                SmallVec::from_iter([AppAction::defer(AppAction::FocusRequest(
+                    FocusTarget::CurrentNote,
+                ))])
+            } else {
+                state.locked_notes.insert(note_file);
+                SmallVec::new()
+            }
+        }
+
+        AppAction::ToggleFocusSession => {
+            state.focus_session = match state.focus_session {
+                Some(_) => None,
+                None => Some(FocusSession::start(Instant::now())),
+            };
+            SmallVec::new()
+        }
+
+        AppAction::TagFilter(tag_filter_action) => match tag_filter_action {
+            TagFilterAction::Show => {
+                state.tag_filter = Some(TagFilter::new(&state.notes));
+                SmallVec::from_iter([AppAction::defer(AppAction::FocusRequest(
+                    FocusTarget::SpecificId(tag_filter_input_id()),
+                ))])
+            }
+
+            TagFilterAction::MoveSelection { backwards } => {
+                if let Some(filter) = state.tag_filter.as_mut() {
+                    filter.move_selection(backwards);
+                }
+                SmallVec::new()
+            }
+
+            TagFilterAction::Confirm => match state
+                .tag_filter
+                .take()
+                .and_then(|filter| filter.selected_note())
+            {
+                Some(note_file) => SmallVec::from_iter([AppAction::SwitchToNote {
+                    note_file,
+                    via_shortcut: true,
+                }]),
+                None => SmallVec::from_iter([AppAction::defer(AppAction::FocusRequest(
+                    FocusTarget::CurrentNote,
+                ))]),
+            },
+
+            TagFilterAction::Hide => {
+                state.tag_filter = None;
+                SmallVec::from_iter([AppAction::defer(AppAction::FocusRequest(
+                    FocusTarget::CurrentNote,
+                ))])
+            }
+        },
+
+        AppAction::RenamePrompt(rename_prompt_action) => match rename_prompt_action {
+            RenamePromptAction::Show(prompt) => {
+                state.rename_prompt = Some(prompt);
+                SmallVec::from_iter([AppAction::defer(AppAction::FocusRequest(
+                    FocusTarget::SpecificId(rename_prompt_input_id()),
+                ))])
+            }
+
+            RenamePromptAction::Confirm => {
+                let Some(RenamePrompt {
+                    note_file,
+                    block,
+                    token,
+                    new_name,
+                    ..
+                }) = state.rename_prompt.take()
+                else {
+                    return SmallVec::new();
+                };
+
+                let note = state.notes.get(&note_file).unwrap();
+                let changes = rename_in_code_block(&note.text, block, &token, new_name.trim());
+
+                SmallVec::from_iter(
+                    changes
+                        .map(|changes| AppAction::apply_text_changes(note_file, changes))
+                        .into_iter()
+                        .chain([AppAction::defer(AppAction::FocusRequest(
+                            FocusTarget::CurrentNote,
+                        ))]),
+                )
+            }
+
+            RenamePromptAction::Hide => {
+                state.rename_prompt = None;
+                SmallVec::from_iter([AppAction::defer(AppAction::FocusRequest(
+                    FocusTarget::CurrentNote,
+                ))])
+            }
+        },
+
+        AppAction::FindReplace(find_replace_action) => match find_replace_action {
+            FindReplaceAction::Show(find_replace) => {
+                state.find_replace = Some(find_replace);
+                SmallVec::from_iter([AppAction::defer(AppAction::FocusRequest(
+                    FocusTarget::SpecificId(find_query_input_id()),
+                ))])
+            }
+
+            action @ (FindReplaceAction::ReplaceNext | FindReplaceAction::ReplaceAll) => {
+                let Some(find_replace) = state.find_replace.as_mut() else {
+                    return SmallVec::new();
+                };
+
+                let Ok(Some(finder)) = Finder::new(&find_replace.query, find_replace.is_regex)
+                else {
+                    return SmallVec::new();
+                };
+
+                let note = state.notes.get(&find_replace.note_file).unwrap();
+                let scope = find_replace.scope();
+                let replacement = &find_replace.replacement;
+                let is_regex = find_replace.is_regex;
+
+                let changes: Vec<TextChange> = match action {
+                    FindReplaceAction::ReplaceAll => {
+                        finder.replace_all(&note.text, scope, replacement, is_regex)
+                    }
+                    _ => {
+                        let cursor = note
+                            .cursor()
+                            .or(note.last_cursor())
+                            .map(|c| c.ordered().end)
+                            .unwrap_or(0);
+
+                        finder
+                            .replace_next(&note.text, scope, cursor, replacement, is_regex)
+                            .into_iter()
+                            .collect()
+                    }
+                };
+
+                if changes.is_empty() {
+                    return SmallVec::new();
+                }
+
+                // keep the scope in sync with the replaced text
+                if let Some(selection) = find_replace
+                    .selection
+                    .as_mut()
+                    .filter(|_| find_replace.in_selection)
+                {
+                    selection.end = selection.end.saturating_add_signed(length_delta(&changes));
+                }
+
+                SmallVec::from_iter([AppAction::apply_text_changes(
+                    find_replace.note_file,
+                    changes,
+                )])
+            }
+
+            FindReplaceAction::Hide => {
+                state.find_replace = None;
+                SmallVec::from_iter([AppAction::defer(AppAction::FocusRequest(
+                    FocusTarget::CurrentNote,
+                ))])
+            }
+        },
+
+        AppAction::DuplicateNote(note_file) => {
+            let Some(text) = state.notes.get(&note_file).map(|note| note.text.clone()) else {
+                return SmallVec::new();
+            };
+
+            let target = next_empty_note(
+                note_file,
+                state
+                    .notes
+                    .iter()
+                    .map(|(note_file, note)| (*note_file, note.text.as_str())),
+                &state.locked_notes,
+            );
+
+            let Some(target) = target else {
+                println!("no empty shelf to duplicate {note_file:?} into");
+                return SmallVec::new();
+            };
+
+            let target_len = state.notes.get(&target).unwrap().text.len();
+
+            // the copy of an encrypted note is never written in plain
+            let is_encrypted = state.encrypted_notes.contains(&note_file);
+            if is_encrypted {
+                state.encrypted_notes.insert(target);
+            }
+
+            SmallVec::from_iter(
+                [
+                    // as via shortcut, so the cursor is placed at the end of the copy
+                    AppAction::SwitchToNote {
+                        note_file: target,
+                        via_shortcut: true,
+                    },
+                    AppAction::apply_text_changes(
+                        target,
+                        vec![TextChange::Insert(ByteSpan::new(0, target_len), text)],
+                    ),
+                ]
+                .into_iter()
+                // mirrors are accepted only for notes in plain text
+                .chain(is_encrypted.then_some(AppAction::EvalNote(NoteFile::Settings))),
+            )
+        }
+
+        AppAction::PasteAsQuote(note_file) => {
+            if note_file != state.selected_note || state.is_read_only(note_file) {
+                return SmallVec::new();
+            }
+
+            let Some(clipboard) = app_io.read_clipboard_text() else {
+                return SmallVec::new();
+            };
+
+            try_extract_text_command_context(state)
+                .and_then(|cx| paste_as_quote(cx, &clipboard))
+                .map(|changes| AppAction::apply_text_changes(note_file, changes))
+                .into_iter()
+                .collect()
+        }
+
+        AppAction::PopOutNote(note_file) => {
+            // the window itself is shown from the next frame on
+            state.popped_out_notes.entry(note_file).or_insert(None);
+            SmallVec::new()
+        }
+
+        AppAction::ClosePoppedOutNote(note_file) => {
+            state.popped_out_notes.remove(&note_file);
+            SmallVec::new()
+        }
+
+        AppAction::ClearNote(clear_note_action) => match clear_note_action {
+            ClearNoteAction::Ask(note_file) => {
+                if !state.is_read_only(note_file) {
+                    state.clear_note_confirmation = Some(note_file);
+                }
+                SmallVec::new()
+            }
+
+            ClearNoteAction::Confirm => {
+                let Some(note_file) = state.clear_note_confirmation.take() else {
+                    return SmallVec::new();
+                };
+
+                let focus_note =
+                    AppAction::defer(AppAction::FocusRequest(FocusTarget::CurrentNote));
+                let text = &state.notes.get(&note_file).unwrap().text;
+
+                if text.is_empty() {
+                    return [focus_note]
+                        .into_iter()
+                        .chain(fill_from_template(state, note_file))
+                        .collect();
+                }
+
+                // the content is recoverable from the backup, so it is not cleared without one
+                let encrypt = state.encrypted_notes.contains(&note_file);
+                match app_io.backup_note(note_file, text, encrypt) {
+                    Ok(path) => println!("backed up {note_file:?} to {path:?}"),
+                    Err(err) => {
+                        println!("failed to back up {note_file:?}, err={err:#?}");
+                        return SmallVec::from_iter([focus_note]);
+                    }
+                }
+
+                let cleared_text = match state.note_templates.get(&note_file) {
+                    Some(template) => expand_template(template, Local::now()),
+                    None => TextChange::CURSOR.to_string(),
+                };
+
+                SmallVec::from_iter([
+                    focus_note,
+                    AppAction::apply_text_changes(
+                        note_file,
+                        vec![TextChange::Insert(
+                            ByteSpan::new(0, text.len()),
+                            cleared_text,
+                        )],
+                    ),
+                ])
+            }
+
+            ClearNoteAction::Cancel => {
+                state.clear_note_confirmation = None;
+                SmallVec::from_iter([AppAction::defer(AppAction::FocusRequest(
+                    FocusTarget::CurrentNote,
+                ))])
+            }
+        },
+
+        AppAction::RecoveryBin(recovery_bin_action) => match recovery_bin_action {
+            RecoveryBinAction::Show => {
+                let backups = app_io
+                    .recent_note_backups(RECOVERY_BIN_SIZE)
+                    .unwrap_or_else(|err| {
+                        println!("failed to read note backups, err={err:#?}");
+                        vec![]
+                    });
+
+                state.recovery_bin = Some(RecoveryBin::new(
+                    backups,
+                    get_utc_timestamp(SystemTime::now()),
+                ));
+                SmallVec::new()
+            }
+
+            RecoveryBinAction::MoveSelection { backwards } => {
+                if let Some(bin) = state.recovery_bin.as_mut() {
+                    bin.move_selection(backwards);
+                }
+                SmallVec::new()
+            }
+
+            RecoveryBinAction::Confirm => {
+                let focus_note =
+                    AppAction::defer(AppAction::FocusRequest(FocusTarget::CurrentNote));
+
+                let note_file = state.selected_note;
+                let Some(entry) = state
+                    .recovery_bin
+                    .take()
+                    .and_then(|bin| bin.selected_entry().cloned())
+                else {
+                    return SmallVec::from_iter([focus_note]);
+                };
+
+                if state.is_read_only(note_file) {
+                    return SmallVec::from_iter([focus_note]);
+                }
+
+                let text = &state.notes.get(&note_file).unwrap().text;
+
+                // whatever is replaced goes to the bin as well, so restoring is never destructive
+                if !text.is_empty() {
+                    let encrypt = state.encrypted_notes.contains(&note_file);
+                    match app_io.backup_note(note_file, text, encrypt) {
+                        Ok(path) => println!("backed up {note_file:?} to {path:?}"),
+                        Err(err) => {
+                            println!("failed to back up {note_file:?}, err={err:#?}");
+                            return SmallVec::from_iter([focus_note]);
+                        }
+                    }
+                }
+
+                SmallVec::from_iter([
+                    focus_note,
+                    AppAction::apply_text_changes(
+                        note_file,
+                        vec![TextChange::Insert(
+                            ByteSpan::new(0, text.len()),
+                            entry.content,
+                        )],
+                    ),
+                ])
+            }
+
+            RecoveryBinAction::Hide => {
+                state.recovery_bin = None;
+                SmallVec::from_iter([AppAction::defer(AppAction::FocusRequest(
+                    FocusTarget::CurrentNote,
+                ))])
+            }
+        },
+
+        AppAction::MultiCursor(multi_cursor_action) => match multi_cursor_action {
+            MultiCursorAction::AddSelection(selection) => {
+                let note_file = state.selected_note;
+                match state.multi_cursor.as_mut() {
+                    Some(multi_cursor) if multi_cursor.note_file == note_file => {
+                        multi_cursor.selections.push(selection)
+                    }
+                    _ => {
+                        state.multi_cursor = Some(MultiCursor {
+                            note_file,
+                            selections: SmallVec::from_iter([selection]),
+                        })
+                    }
+                }
+                SmallVec::new()
+            }
+
+            MultiCursorAction::Edit(edit) => {
+                let Some(MultiCursor {
+                    note_file,
+                    selections,
+                }) = state.multi_cursor.take()
+                else {
+                    return SmallVec::new();
+                };
+
+                let note = state.notes.get_mut(&note_file).unwrap();
+                let Some(primary) = note.cursor().or(note.last_cursor()) else {
+                    return SmallVec::new();
+                };
+
+                // the primary selection goes last
+                let all_selections: SmallVec<[ByteSpan; 8]> =
+                    selections.into_iter().chain([primary.ordered()]).collect();
+
+                if !are_valid_selections(&note.text, &all_selections) {
+                    return SmallVec::new();
+                }
+
+                let mut updated = edit_at_selections(&mut note.text, &all_selections, &edit);
+                let primary = updated.pop().unwrap();
+
+                note.derived_state.structure =
+                    std::mem::take(&mut note.derived_state.structure).recycle(&note.text);
+                note.update_cursor(primary.unordered());
+
+                state.multi_cursor = Some(MultiCursor {
+                    note_file,
+                    selections: updated.into_iter().collect(),
+                });
+                state.add_unsaved_change(UnsavedChange::NoteContentChanged(note_file));
+                state.inline_llm_prompt = None;
+
+                SmallVec::from_iter([
+                    AppAction::EvalNote(note_file),
+                    AppAction::IssueRenderAction(RenderAction::ScrollToEditorCursorPos),
+                ])
+            }
+
+            MultiCursorAction::Clear => {
+                state.multi_cursor = None;
+                SmallVec::new()
+            }
+        },
+
+        AppAction::Tutorial(tutorial_action) => {
+            match (tutorial_action, state.tutorial.as_mut()) {
+                (TutorialAction::Next, Some(tutorial)) => {
+                    if !tutorial.next() {
+                        state.tutorial = None;
+                    }
+                }
+                (TutorialAction::Prev, Some(tutorial)) => tutorial.prev(),
+                (TutorialAction::Finish, _) => state.tutorial = None,
+                (_, None) => (),
+            }
+
+            SmallVec::new()
+        }
+
         AppAction::CopyCodeBlock(note_file, span_index) => {
             let note = state.notes.get(&note_file).unwrap();
             let text_structure = &note.derived_state.structure;
@@ -1137,13 +2338,58 @@ pub fn process_app_action(
                 .find(|(_, desc)| desc.kind == SpanKind::Text);
 
             if let Some((_, code_content_des)) = thing {
-                let code_content = &note.text[code_content_des.byte_pos.range()];
-                app_io.copy_to_clipboard(code_content.to_string());
+                let code_content = note.text[code_content_des.byte_pos.range()].to_string();
+                app_io.copy_to_clipboard(code_content.clone());
+                state.clipboard_history.record(code_content);
             }
 
             SmallVec::new()
         }
 
+        AppAction::SelectCodeBlockLang(note_file, span_index) => {
+            let note = state.notes.get(&note_file).unwrap();
+
+            let lang_byte_span = match note.derived_state.structure.get_span_with_meta(span_index) {
+                Some((_, SpanMeta::CodeBlock(CodeBlockMeta { lang_byte_span, .. }))) => {
+                    *lang_byte_span
+                }
+                _ => return SmallVec::new(),
+            };
+
+            let lang = &note.text[lang_byte_span.range()];
+
+            SmallVec::from_iter([
+                AppAction::defer(AppAction::FocusRequest(FocusTarget::CurrentNote)),
+                AppAction::apply_text_changes(
+                    note_file,
+                    vec![TextChange::Insert(
+                        lang_byte_span,
+                        format!("{edge}{lang}{edge}", edge = TextChange::CURSOR_EDGE),
+                    )],
+                ),
+            ])
+        }
+
+        AppAction::CopyText(text) => {
+            app_io.copy_to_clipboard(text);
+            SmallVec::new()
+        }
+
+        AppAction::SetPinnedSnippet(snippet) => {
+            state.pinned_snippet = snippet;
+            SmallVec::new()
+        }
+
+        AppAction::CopyNoteAsImage => {
+            request_note_snapshot(ctx);
+            SmallVec::new()
+        }
+
+        AppAction::NoteSnapshotTaken(image) => {
+            app_io.copy_image_to_clipboard(image);
+            SmallVec::new()
+        }
+
         AppAction::AppUpdateClicked => {
             app_io.open_app_store_for_shelv_update();
             SmallVec::new()
@@ -1151,6 +2397,70 @@ pub fn process_app_action(
     }
 }
 
+/// Tasks are identified by their block id, so the block gets one before the task is started
+fn start_js_task(
+    state: &mut AppState,
+    app_io: &impl AppIO,
+    request: JsTaskRequest,
+    id_change: Option<TextChange>,
+) -> SmallVec<[AppAction; 1]> {
+    let note_file = request.note_file;
+    let task_key = (note_file, request.block_id);
+
+    if state.js_tasks.contains_key(&task_key) {
+        println!("js task {task_key:?} is already running");
+        return SmallVec::new();
+    }
+
+    state.js_tasks.insert(task_key, JsTaskProgress::default());
+    app_io.run_js_task(request);
+
+    // the run button is replaced with the progress of the task
+    SmallVec::from_buf([match id_change {
+        Some(change) => AppAction::ApplyTextChanges {
+            target: note_file,
+            changes: vec![change],
+            should_trigger_eval: true,
+        },
+        None => AppAction::EvalNote(note_file),
+    }])
+}
+
+/// Fills the note from its template in settings, only if the note is empty (and not locked)
+fn fill_from_template(state: &AppState, note_file: NoteFile) -> Option<AppAction> {
+    let template = state.note_templates.get(&note_file)?;
+    let note = state.notes.get(&note_file)?;
+
+    (note.text.is_empty() && !state.is_read_only(note_file)).then(|| {
+        AppAction::apply_text_changes(
+            note_file,
+            vec![TextChange::Insert(
+                ByteSpan::new(0, 0),
+                expand_template(template, Local::now()),
+            )],
+        )
+    })
+}
+
+/// Tidies up the note that is being switched away from, see `collapseBlankLines`
+fn collapse_blank_lines_of(state: &AppState, note_file: NoteFile) -> Option<AppAction> {
+    if !state.collapse_blank_lines()
+        || note_file == NoteFile::Settings
+        || state.is_read_only(note_file)
+    {
+        return None;
+    }
+
+    let note = state.notes.get(&note_file)?;
+    let changes = collapse_blank_lines(&note.text, &note.derived_state.structure);
+
+    (!changes.is_empty()).then_some(AppAction::ApplyTextChanges {
+        target: note_file,
+        changes,
+        should_trigger_eval: false,
+    })
+}
+
 fn update_slash_palette(
     focus_state: AppFocusState,
     mut palette: SlashPalette,
@@ -1186,7 +2496,7 @@ fn update_slash_palette(
             return None;
         })?;
 
-    if !text_part.starts_with("/") {
+    if palette.with_slash && !text_part.starts_with("/") {
         // it means that either text was modified above or user deleted the "/"
         println!("## hide Slash Palette: Text doesn't start with '/'\nterm={text_part}");
         return None;
@@ -1196,6 +2506,13 @@ fn update_slash_palette(
         .find(|c: char| c.is_whitespace() || c == '\n')
         .unwrap_or(text_part.len());
 
+    // without "/" the term is only what was typed after the palette was opened,
+    // the text that was already after the cursor shouldn't filter the options
+    let word_end = match palette.with_slash {
+        true => word_end,
+        false => word_end.min(note_cursor.start.saturating_sub(palette.slash_byte_pos)),
+    };
+
     let term_span = ByteSpan::new(palette.slash_byte_pos, palette.slash_byte_pos + word_end);
 
     // needs to be either inside the palette term or immidiately touching it
@@ -1206,7 +2523,7 @@ fn update_slash_palette(
         return None;
     }
 
-    let search_term = &text_part[1..word_end];
+    let search_term = &text_part[palette.with_slash as usize..word_end];
 
     palette.update_count = palette.update_count.wrapping_add(1);
 
@@ -1215,14 +2532,7 @@ fn update_slash_palette(
         return Some(palette);
     }
 
-    // Update options based on search term
-    // This is a placeholder - implement actual filtering logic
-    palette.options = state
-        .commands
-        .available_slash_commands()
-        .filter(|option| option.prefix.starts_with(search_term))
-        .cloned()
-        .collect();
+    (palette.options, palette.most_used_count) = slash_palette_options(state, search_term);
 
     palette.search_term = search_term.to_string();
     palette.selected = 0;
@@ -2,9 +2,10 @@ use std::{
     cmp::Ordering,
     collections::BTreeMap,
     ffi::CString,
-    fs::File,
+    fs::{self, File},
     io::{self, Read},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    process::Command,
     sync::mpsc::SyncSender,
 };
 
@@ -19,10 +20,17 @@ use global_hotkey::GlobalHotKeyManager;
 use shared::{Version, VersionResponse};
 
 use crate::{
-    app_actions::{AppIO, HideMode, LLMBlockRequest, LLMPromptRequest, SettingsForAiRequests},
+    app_actions::{
+        AppIO, HideMode, LLMBlockRequest, LLMPromptRequest, LLMSuggestionRequest,
+        SettingsForAiRequests,
+    },
     app_state::{InlineLLMResponseChunk, MsgToApp},
     command::create_ai_keybindings_documentation,
-    persistent_state::get_utc_timestamp,
+    persistent_state::{
+        NoteBackup, NoteFile, get_utc_timestamp, note_file_name, read_recent_note_backups,
+        save_note_backup, write_atomically,
+    },
+    scripting::js_task::{JsTaskRequest, run_js_task},
 };
 
 use tokio_stream::StreamExt;
@@ -34,7 +42,8 @@ pub struct RegisteredGlobalHotkey {
 }
 
 pub struct RealAppIO {
-    pub hotkeys_manager: GlobalHotKeyManager,
+    /// the error is reported when a global binding from settings is registered
+    pub hotkeys_manager: Result<GlobalHotKeyManager, String>,
     pub registered_hotkeys: BTreeMap<u32, RegisteredGlobalHotkey>,
     pub egui_ctx: egui::Context,
     pub msg_queue: SyncSender<MsgToApp>,
@@ -47,7 +56,7 @@ pub struct RealAppIO {
 
 impl RealAppIO {
     pub fn new(
-        hotkeys_manager: GlobalHotKeyManager,
+        hotkeys_manager: Result<GlobalHotKeyManager, String>,
         egui_ctx: egui::Context,
         msg_queue: SyncSender<MsgToApp>,
         shelv_folder: PathBuf,
@@ -78,6 +87,23 @@ impl AppIO for RealAppIO {
         hide_app_on_macos(mode);
     }
 
+    fn set_window_opacity(&self, opacity: f32) {
+        set_window_opacity_on_macos(opacity);
+    }
+
+    fn backup_note(
+        &self,
+        note_file: NoteFile,
+        content: &str,
+        encrypt: bool,
+    ) -> Result<PathBuf, io::Error> {
+        save_note_backup(note_file, content, encrypt, &self.shelv_folder)
+    }
+
+    fn recent_note_backups(&self, limit: usize) -> Result<Vec<NoteBackup>, io::Error> {
+        read_recent_note_backups(&self.shelv_folder, limit)
+    }
+
     fn try_read_note_if_newer(
         &self,
         path: &PathBuf,
@@ -97,9 +123,14 @@ impl AppIO for RealAppIO {
         shortcut: egui::KeyboardShortcut,
         handler: Box<dyn Fn() -> MsgToApp>,
     ) -> Result<(), String> {
+        let hotkeys_manager = self
+            .hotkeys_manager
+            .as_ref()
+            .map_err(|err| format!("global hotkeys are unavailable, {err}"))?;
+
         let system_hotkey = convert_egui_shortcut_to_global_hotkey(shortcut);
 
-        self.hotkeys_manager
+        hotkeys_manager
             .register(system_hotkey)
             .map_err(|err| err.to_string())?;
 
@@ -116,10 +147,13 @@ impl AppIO for RealAppIO {
     }
 
     fn cleanup_all_global_hotkeys(&mut self) -> Result<(), String> {
-        for hotkey in self.registered_hotkeys.values() {
-            self.hotkeys_manager
-                .unregister(hotkey.system_hotkey)
-                .map_err(|err| err.to_string())?;
+        // nothing could have been registered without the manager
+        if let Ok(hotkeys_manager) = &self.hotkeys_manager {
+            for hotkey in self.registered_hotkeys.values() {
+                hotkeys_manager
+                    .unregister(hotkey.system_hotkey)
+                    .map_err(|err| err.to_string())?;
+            }
         }
         self.registered_hotkeys.clear();
         Ok(())
@@ -129,6 +163,27 @@ impl AppIO for RealAppIO {
         unreachable!("That should not be called, the feature is removed")
     }
 
+    fn run_js_task(&self, request: JsTaskRequest) {
+        let egui_ctx = self.egui_ctx.clone();
+        let sender = self.msg_queue.clone();
+        let (note_file, block_id) = (request.note_file, request.block_id);
+
+        // boa is synchronous, so the task gets a thread of its own
+        tokio::task::spawn_blocking(move || {
+            run_js_task(request, move |update| {
+                sender
+                    .send(MsgToApp::JsTaskUpdate {
+                        note_file,
+                        block_id,
+                        update,
+                    })
+                    .unwrap();
+
+                egui_ctx.request_repaint();
+            })
+        });
+    }
+
     fn execute_llm_prompt(&self, quesion: LLMPromptRequest, cx: SettingsForAiRequests) {
         let egui_ctx = self.egui_ctx.clone();
         let sender = self.msg_queue.clone();
@@ -286,10 +341,91 @@ impl AppIO for RealAppIO {
         });
     }
 
+    fn request_llm_suggestion(&self, request: LLMSuggestionRequest, cx: SettingsForAiRequests) {
+        let egui_ctx = self.egui_ctx.clone();
+        let sender = self.msg_queue.clone();
+
+        let LLMSuggestionRequest {
+            before_cursor,
+            address,
+        } = request;
+
+        let (model, system_prompt, token) = cx
+            .llm_settings
+            .map(|s| (s.model.clone(), s.system_prompt.clone(), s.token.clone()))
+            .unwrap_or_else(|| (SHELV_LLM_PROXY_MODEL.to_string(), None, None));
+
+        let chat_req = ChatRequest::new(Vec::from_iter(
+            [ChatMessage::system(include_str!(
+                "./prompts/inline-suggestion.md"
+            ))]
+            .into_iter()
+            .chain(system_prompt.map(ChatMessage::system))
+            .chain([ChatMessage::user(before_cursor)]),
+        ));
+
+        let (service_target_resolver, auth_resolver) = if model == SHELV_LLM_PROXY_MODEL {
+            prepare_shelv_providers(&self.shelv_api_server, &self.shelv_magic_token)
+        } else {
+            prepare_general_providers(token.as_deref())
+        };
+
+        tokio::spawn(async move {
+            let client = genai::Client::builder()
+                .with_auth_resolver(auth_resolver)
+                .with_service_target_resolver(service_target_resolver)
+                .build();
+
+            // unlike the prompt, the suggestion is shown once it is complete
+            let response = match client
+                .exec_chat_stream(model.as_str(), chat_req, None)
+                .await
+            {
+                Ok(mut stream) => {
+                    let mut suggestion = String::new();
+                    let mut error = None;
+
+                    while let Some(stream_event) = stream.stream.next().await {
+                        match stream_event {
+                            Ok(ChatStreamEvent::Chunk(StreamChunk { content })) => {
+                                suggestion.push_str(&content)
+                            }
+                            Ok(_) => (),
+                            Err(err) => {
+                                error = Some(format!("Error getting response chunk: {err}"));
+                                break;
+                            }
+                        }
+                    }
+
+                    match error {
+                        Some(err) => Err(err),
+                        None => Ok(suggestion.trim_end().to_string()),
+                    }
+                }
+                Err(err) => Err(format!("Error sending request: {:#?}", err)),
+            };
+
+            sender
+                .send(MsgToApp::InlineSuggestionResponse { response, address })
+                .unwrap();
+
+            egui_ctx.request_repaint();
+        });
+    }
+
     fn open_shelv_folder(&self) -> Result<(), Box<dyn std::error::Error>> {
         open_folder_in_finder(&self.shelv_folder)
     }
 
+    fn open_in_external_editor(
+        &self,
+        note_file: NoteFile,
+        command: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        open_in_external_editor(&self.shelv_folder.join(note_file_name(note_file)), command)
+    }
+
     fn capture_sentry_message<F>(
         &self,
         message: &str,
@@ -306,6 +442,71 @@ impl AppIO for RealAppIO {
         self.egui_ctx.copy_text(text);
     }
 
+    fn copy_image_to_clipboard(&self, image: egui::ColorImage) {
+        self.egui_ctx.copy_image(image);
+    }
+
+    fn read_clipboard_text(&self) -> Option<String> {
+        // egui only gets the clipboard with a paste event, so it is read directly
+        let mut clipboard = arboard::Clipboard::new()
+            .inspect_err(|err| println!("failed to access the clipboard: {err}"))
+            .ok()?;
+
+        clipboard.get_text().ok()
+    }
+
+    fn export_note(
+        &self,
+        suggested_file_name: &str,
+        content: &str,
+    ) -> Result<Option<PathBuf>, io::Error> {
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Export Shelf")
+            .set_file_name(suggested_file_name)
+            .add_filter("Markdown", &["md"])
+            .save_file()
+        else {
+            return Ok(None);
+        };
+
+        write_atomically(&path, content)?;
+        Ok(Some(path))
+    }
+
+    fn fetch_remote_image(&self, url: String) {
+        let egui_ctx = self.egui_ctx.clone();
+        let sender = self.msg_queue.clone();
+
+        tokio::spawn(async move {
+            let result = match reqwest::get(url.as_str()).await {
+                Ok(response) if !response.status().is_success() => {
+                    Err(response.status().to_string())
+                }
+                Ok(response) => {
+                    let is_image = response
+                        .headers()
+                        .get(reqwest::header::CONTENT_TYPE)
+                        .and_then(|content_type| content_type.to_str().ok())
+                        .is_some_and(|content_type| content_type.starts_with("image/"));
+
+                    match is_image {
+                        true => Ok(()),
+                        false => Err("not an image".to_string()),
+                    }
+                }
+                Err(err) if err.is_timeout() => Err("timed out".to_string()),
+                Err(err) if err.is_connect() => Err("can't connect".to_string()),
+                Err(err) => Err(err.to_string()),
+            };
+
+            sender
+                .send(MsgToApp::RemoteImageFetched { url, result })
+                .unwrap();
+
+            egui_ctx.request_repaint();
+        });
+    }
+
     fn start_update_checker(&self) {
         let sender = self.msg_queue.clone();
         let current_version = self.current_version.clone();
@@ -365,6 +566,71 @@ impl AppIO for RealAppIO {
                 println!("Error opening app store URL: {}", err);
             });
     }
+
+    fn disable_crash_reporting(&self) {
+        if let Some(client) = sentry::Hub::main().client() {
+            client.close(Some(std::time::Duration::from_secs(2)));
+        }
+    }
+
+    fn is_start_at_login_enabled(&self) -> bool {
+        launch_agent_path().is_some_and(|path| path.exists())
+    }
+
+    fn set_start_at_login(&self, enabled: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let path = launch_agent_path().ok_or("home folder is not found")?;
+
+        if enabled {
+            if let Some(folder) = path.parent() {
+                fs::create_dir_all(folder)?;
+            }
+            fs::write(&path, launch_agent_plist(&std::env::current_exe()?))?;
+        } else {
+            match fs::remove_file(&path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+const LAUNCH_AGENT_LABEL: &str = "app.shelv.login";
+
+/// launchd picks it up on the next login, nothing needs to be loaded right away
+fn launch_agent_path() -> Option<PathBuf> {
+    directories_next::BaseDirs::new().map(|dirs| {
+        dirs.home_dir()
+            .join("Library/LaunchAgents")
+            .join(format!("{LAUNCH_AGENT_LABEL}.plist"))
+    })
+}
+
+fn launch_agent_plist(executable: &Path) -> String {
+    let executable = executable
+        .to_string_lossy()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{LAUNCH_AGENT_LABEL}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{executable}</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+</dict>
+</plist>
+"#
+    )
 }
 
 fn prepare_shelv_providers(
@@ -465,6 +731,53 @@ fn hide_app_on_macos(mode: HideMode) {
     }
 }
 
+fn set_window_opacity_on_macos(opacity: f32) {
+    // egui doesn't have a viewport command for opacity,
+    // https://developer.apple.com/documentation/appkit/nswindow/1419186-alphavalue
+    use objc2::rc::Id;
+    use objc2::runtime::AnyObject;
+    use objc2::{class, msg_send, msg_send_id};
+    unsafe {
+        let app: Id<AnyObject> = msg_send_id![class!(NSApplication), sharedApplication];
+        let windows: Id<AnyObject> = msg_send_id![&app, windows];
+        let count: usize = msg_send![&windows, count];
+
+        for index in 0..count {
+            let window: Id<AnyObject> = msg_send_id![&windows, objectAtIndex: index];
+            let _: () = msg_send![&window, setAlphaValue: opacity as f64];
+        }
+    }
+}
+
+/// Runs the configured command, then `$VISUAL` or `$EDITOR` with the file path as the last argument.
+/// Without any of them the file is opened in the default text editor
+fn open_in_external_editor(
+    path: &Path,
+    command: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let command = command
+        .map(str::to_string)
+        .or_else(|| std::env::var("VISUAL").ok())
+        .or_else(|| std::env::var("EDITOR").ok())
+        .filter(|command| !command.trim().is_empty());
+
+    let mut child = match command {
+        // the path is passed as "$1", so it doesn't need to be escaped
+        Some(command) => Command::new("sh")
+            .arg("-c")
+            .arg(format!("{command} \"$1\""))
+            .arg("sh")
+            .arg(path)
+            .spawn()?,
+        None => Command::new("open").arg("-t").arg(path).spawn()?,
+    };
+
+    // reaps the process once the editor is closed
+    std::thread::spawn(move || child.wait());
+
+    Ok(())
+}
+
 fn open_folder_in_finder(path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
     use objc2::rc::Id;
     use objc2::runtime::AnyObject;
@@ -592,6 +905,12 @@ fn compare_versions(version_a: &Version, version_b: &Version) -> Ordering {
 
 #[cfg(test)]
 mod tests {
+    use crate::{
+        app_actions::AppAction,
+        command::CommandInstruction,
+        test_harness::{IOCall, TestHarness},
+    };
+
     use super::*;
 
     #[test]
@@ -610,4 +929,60 @@ mod tests {
             assert_eq!(compare_versions(&a, &b), expected);
         }
     }
+
+    #[test]
+    fn test_launch_agent_plist() {
+        let plist = launch_agent_plist(Path::new(
+            "/Applications/Tom & Jerry.app/Contents/MacOS/shelv",
+        ));
+
+        assert!(plist.contains("<string>app.shelv.login</string>"));
+        assert!(
+            plist.contains(
+                "<string>/Applications/Tom &amp; Jerry.app/Contents/MacOS/shelv</string>"
+            )
+        );
+        assert!(plist.contains("<key>RunAtLoad</key>\n    <true/>"));
+    }
+
+    #[test]
+    pub fn test_open_in_external_editor() {
+        let mut harness = TestHarness::new("note{||}");
+
+        harness.run_command(CommandInstruction::OpenInExternalEditor);
+        assert_eq!(
+            harness.io.take_calls(),
+            vec![IOCall::OpenInExternalEditor(NoteFile::Note(0), None)]
+        );
+
+        // there is nothing to edit in an encrypted file
+        harness.state.encrypted_notes.insert(NoteFile::Note(0));
+        harness.run_command(CommandInstruction::OpenInExternalEditor);
+        assert_eq!(harness.io.take_calls(), vec![]);
+    }
+
+    #[test]
+    pub fn test_start_at_login_prompt_is_shown_once() {
+        let mut harness = TestHarness::new("note{||}");
+        assert!(harness.state.show_start_at_login_prompt);
+
+        harness.dispatch(AppAction::SetStartAtLogin(false));
+        assert!(!harness.state.show_start_at_login_prompt);
+        assert!(
+            harness
+                .state
+                .should_persist()
+                .is_some_and(|data| data.start_at_login_prompted)
+        );
+
+        harness.run_command(CommandInstruction::ToggleStartAtLogin);
+        assert!(harness.state.start_at_login);
+        assert_eq!(
+            harness.io.take_calls(),
+            vec![
+                IOCall::SetStartAtLogin(false),
+                IOCall::SetStartAtLogin(true)
+            ]
+        );
+    }
 }
@@ -1,13 +1,16 @@
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     hash::{Hash, Hasher},
+    ops::Range,
     path::PathBuf,
     sync::{Arc, mpsc::Receiver},
+    time::{Duration, Instant},
 };
 
+use chrono::Local;
 use eframe::{
     egui::{
-        Id, Rect, Ui,
+        FontId, Id, Rect, TextFormat, Ui,
         text::{CCursor, LayoutJob},
     },
     epaint::Galley,
@@ -19,34 +22,81 @@ use syntect::{highlighting::ThemeSet, parsing::SyntaxSet};
 use shared::Version;
 
 use crate::{
-    app_actions::{AppAction, FocusTarget},
+    app_actions::{
+        AppAction, ClearNoteAction, FocusTarget, NoteSwitcherAction, RecoveryBinAction,
+        TagFilterAction,
+    },
     app_ui::char_index_from_byte_index,
     byte_span::{ByteSpan, UnOrderedByteSpan},
+    clipboard_history::ClipboardHistory,
+    code_block_wrap::merge_unwrapped_rows,
+    code_highlight::CodeHighlightCache,
     command::{
         AppFocus, CommandContext, CommandInstruction, CommandList, CommandScope,
         EditorCommandOutput, SlashPaletteCmd, UiState, call_with_text_ctx,
     },
+    command_log::{CommandLog, CommandOutcome},
+    command_usage::CommandUsage,
     commands::{
+        code_block_navigation::jump_to_code_block,
+        convert_to_tasks::convert_to_tasks,
+        cycle_task_state::cycle_task_state,
+        enter_in_code_block::on_enter_inside_code_block,
         enter_in_list::on_enter_inside_list_item,
+        escape_markdown::{escape_markdown, unescape_markdown},
+        extract_code_block::extract_code_block,
+        find_replace::show_find_replace,
+        format_note::format_note,
+        heading_navigation::{HeadingJump, copy_with_heading_path, jump_to_heading},
         inline_llm_prompt::inline_llm_prompt_command_handler,
+        insert_horizontal_rule::insert_horizontal_rule,
+        insert_note_reference::{NOTE_LINK_PREFIX, insert_note_link, insert_note_path},
+        insert_table::show_table_picker,
         insert_text::call_replace_text,
+        insert_timestamp::insert_timestamp,
+        join_lines::join_lines,
         kdl_lang::on_enter_inside_kdl_block,
+        multi_cursor::select_next_occurrence,
+        pin_selection::pin_selection,
+        rename_in_code_block::show_rename_prompt,
         run_llm::{CodeBlockAddress, prepare_to_run_llm_block},
-        slash_pallete::show_slash_pallete,
+        slash_pallete::{show_slash_pallete, toggle_slash_pallete},
         space_after_task_markers::on_space_after_task_markers,
         tabbing_in_list::{on_shift_tab_inside_list, on_tab_inside_list},
+        tabbing_in_table::{on_shift_tab_inside_table, on_tab_inside_table},
+        toggle_callout::toggle_callout,
         toggle_code_block::toggle_code_block,
-        toggle_md_headings::toggle_md_heading,
+        toggle_md_headings::{HeadingLevelChange, change_md_heading_level, toggle_md_heading},
         toggle_simple_md_annotations::toggle_simple_md_annotations,
+        transpose_chars::transpose_chars,
+        wrap_in_details::{set_all_details_open, wrap_in_details},
     },
     feedback::FeedbackData,
-    persistent_state::{DataToSave, LoadKind, NoteFile, RestoredData},
-    scripting::settings_eval::Scripts,
-    settings_parsing::LlmSettings,
+    focus_session::FocusSession,
+    local_links::{LinkMarker, RemoteImageStatus},
+    note_switcher::{NoteSwitcher, RecentNotes, adjacent_note},
+    note_template::{DEFAULT_TIMESTAMP_FORMAT, format_timestamp},
+    persistent_state::{DataToSave, LoadKind, NoteFile, PersistenceWarning, RestoredData},
+    recovery_bin::RecoveryBin,
+    scripting::{
+        js_snippets::JsSnippets,
+        js_task::{JsTaskProgress, JsTaskUpdate},
+        note_eval_context::BlockId,
+        note_storage::ScriptStorage,
+        settings_eval::{GlobalHotkeyWarning, Scripts},
+    },
+    settings_parsing::{
+        CloseButtonBehavior, DEFAULT_LARGE_NOTE_KB, DevSettings, EditorSettings, FocusLostBehavior,
+        LlmSettings, MIN_WINDOW_OPACITY_PERCENT, QuietHoursSettings, SlashPaletteSettings,
+        WindowSettings,
+    },
+    tags::TagFilter,
     text_structure::{
-        CodeBlockMeta, SpanIndex, SpanKind, SpanMeta, TextDiffPart, TextHash, TextStructure,
+        CalloutKind, CodeBlockMeta, MarkerVisibility, SpanIndex, SpanKind, SpanMeta, TaskState,
+        TextDiffPart, TextHash, TextStructure,
     },
     theme::AppTheme,
+    tutorial::TutorialState,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Hash, Eq)]
@@ -89,6 +139,26 @@ pub enum VersionState {
     RequiredUpdateAvailable(Version),
 }
 
+impl VersionState {
+    /// Optional updates are hidden during quiet hours (and focus sessions if opted in),
+    /// required ones are always shown
+    pub fn respecting_quiet_hours(
+        &self,
+        quiet_hours: Option<&QuietHoursSettings>,
+        in_focus_session: bool,
+    ) -> &Self {
+        static UP_TO_DATE: VersionState = VersionState::UpToDate;
+
+        let is_quiet_time = quiet_hours
+            .is_some_and(|quiet_hours| quiet_hours.is_quiet(Local::now().time(), in_focus_session));
+
+        match self {
+            VersionState::UpdateAvailable(_) if is_quiet_time => &UP_TO_DATE,
+            _ => self,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FeedbackState {
     pub is_feedback_open: bool,
@@ -109,6 +179,7 @@ impl Default for FeedbackState {
 #[derive(Debug)]
 pub enum CodeBlockAnnotation {
     RunButton,
+    Progress(JsTaskProgress),
     Applied { message: String },
     Error { title: String, message: String },
 }
@@ -118,6 +189,9 @@ pub enum CodeBlockAnnotation {
 #[derive(Debug)]
 pub struct NoteDerivedState {
     pub code_block_annotations: Vec<(SpanIndex, CodeBlockAnnotation)>,
+    /// images and links pointing to local files that don't exist and remote images
+    /// that are loading or failed to load, see `local_links`
+    pub link_markers: Vec<LinkMarker>,
     pub structure: TextStructure,
 }
 
@@ -127,6 +201,7 @@ impl NoteDerivedState {
         // TODO: add code block annotations at the start somehow
         Self {
             code_block_annotations: Vec::new(),
+            link_markers: Vec::new(),
             structure,
         }
     }
@@ -165,6 +240,13 @@ pub enum UnsavedChange {
     SelectionChanged,
     LastUpdated,
     PinStateChanged,
+    CrashReportingChanged,
+    StartAtLoginPrompted,
+    LastLLMPromptsChanged,
+    LockedNotesChanged,
+    CommandLogChanged,
+    CommandUsageChanged,
+    ScriptStorageChanged,
 }
 
 /// Actions specific to a render update, that is, what needs to happen during this render
@@ -177,12 +259,87 @@ pub enum RenderAction {
 pub struct SlashPalette {
     pub note_file: NoteFile,
     pub slash_byte_pos: usize,
+    /// false if the palette was opened via a shortcut, meaning that there is no "/" at `slash_byte_pos`
+    pub with_slash: bool,
     pub search_term: String,
     pub options: Vec<SlashPaletteCmd>,
+    /// the first `most_used_count` options are the most used commands, only for the empty search term
+    pub most_used_count: usize,
     pub selected: usize,
     pub update_count: u32,
 }
 
+#[derive(Debug, Clone)]
+pub struct TablePicker {
+    pub note_file: NoteFile,
+    pub byte_pos: usize,
+    /// including the header row
+    pub rows: usize,
+    pub cols: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct RenamePrompt {
+    pub note_file: NoteFile,
+    /// code of the block, the renaming doesn't go outside of it
+    pub block: ByteSpan,
+    /// where to show the prompt
+    pub byte_pos: usize,
+    pub token: String,
+    pub new_name: String,
+}
+
+/// Find and replace bar, see `show_find_replace`
+#[derive(Debug)]
+pub struct FindReplace {
+    pub note_file: NoteFile,
+    pub query: String,
+    pub replacement: String,
+    pub is_regex: bool,
+    /// multi line selection at the moment the bar was opened
+    pub selection: Option<ByteSpan>,
+    pub in_selection: bool,
+}
+
+impl FindReplace {
+    pub fn scope(&self) -> Option<ByteSpan> {
+        self.selection.filter(|_| self.in_selection)
+    }
+}
+
+/// Additional selections added by `SelectNextOccurrence`, the primary one is the note cursor
+#[derive(Debug, Clone)]
+pub struct MultiCursor {
+    pub note_file: NoteFile,
+    pub selections: SmallVec<[ByteSpan; 4]>,
+}
+
+/// Evaluating a large note on every keystroke would make typing lag
+pub const LARGE_NOTE_EVAL_DELAY: Duration = Duration::from_millis(1000);
+
+/// Typing has to pause this long before a continuation of the note is requested from the LLM
+pub const INLINE_SUGGESTION_DELAY: Duration = Duration::from_millis(800);
+
+/// How much of the text before the cursor is sent along with the suggestion request
+pub const INLINE_SUGGESTION_CONTEXT_BYTES: usize = 4096;
+
+/// Continuation of the note suggested by the LLM, see `EditorSettings::inline_suggestions`
+#[derive(Debug)]
+pub struct InlineSuggestion {
+    /// the cursor at the end of the note, any edit or cursor move drops the suggestion
+    pub address: TextSelectionAddress,
+    pub status: InlineSuggestionStatus,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum InlineSuggestionStatus {
+    /// last edit, the request is sent once `INLINE_SUGGESTION_DELAY` passes without edits
+    Waiting(Instant),
+    Requested,
+    /// shown as dimmed text after the cursor, nothing is inserted until it is accepted with Tab
+    Ready(String),
+}
+
 pub struct AppState {
     // -----this is persistent model-------
     pub notes: BTreeMap<NoteFile, Note>,
@@ -191,10 +348,33 @@ pub struct AppState {
     // -------- emphemeral state ----------
     pub last_saved: u128,
     unsaved_changes: SmallVec<[UnsavedChange; 2]>,
+    /// A note was edited since the panic backup was synced, see `take_unsaved_notes_edited`
+    unsaved_notes_edited: bool,
     pub scheduled_script_run_version: Option<u64>,
 
     // ------------------------------------
     pub is_pinned: bool,
+    pub crash_reporting: bool,
+    /// Shelv is registered as a login item, read from the system at launch
+    pub start_at_login: bool,
+    pub start_at_login_prompted: bool,
+    /// the one time prompt is shown, starting from the second launch
+    pub show_start_at_login_prompt: bool,
+    pub command_usage: CommandUsage,
+    /// Notes that are stored encrypted, see `note_encryption`
+    pub encrypted_notes: BTreeSet<NoteFile>,
+    /// the prompt that was last executed in a note, pre-fills the next one in that note
+    pub last_llm_prompts: BTreeMap<NoteFile, String>,
+    /// Files the notes are copied to on every save, configured with `mirror` in settings
+    pub note_mirrors: BTreeMap<NoteFile, PathBuf>,
+    /// Text empty notes are filled with, configured with `template` in settings
+    pub note_templates: BTreeMap<NoteFile, String>,
+    /// notes that can't be edited until unlocked, rendered as in reading mode
+    pub locked_notes: BTreeSet<NoteFile>,
+    /// encrypted notes that failed to decrypt on launch, read only and never saved
+    pub undecryptable_notes: BTreeSet<NoteFile>,
+    /// remote images of all notes by url, each one is fetched once per launch
+    pub remote_images: BTreeMap<String, RemoteImageStatus>,
 
     pub theme: AppTheme,
     pub syntax_set: SyntaxSet,
@@ -202,13 +382,64 @@ pub struct AppState {
     pub msg_queue: Receiver<MsgToApp>,
     pub hidden: bool,
     pub prev_focused: bool,
+    /// set while waiting for `focusLostDelayMs` to pass before applying `onFocusLost`
+    pub focus_lost_at: Option<Instant>,
+    /// started (and restarted) with `/focus`, not persisted
+    pub focus_session: Option<FocusSession>,
     pub commands: CommandList,
     pub llm_settings: Option<LlmSettings>,
+    pub command_log: CommandLog,
+    /// code blocks copied during the session, pasted via the slash palette
+    pub clipboard_history: ClipboardHistory,
+    pub script_storage: ScriptStorage,
+    pub quiet_hours: Option<QuietHoursSettings>,
+    pub slash_palette_settings: Option<SlashPaletteSettings>,
+    pub dev_settings: Option<DevSettings>,
+    pub window_settings: Option<WindowSettings>,
+    pub editor_settings: Option<EditorSettings>,
+    /// the window is dimmed on focus lost, see `FocusLostBehavior::Dim`
+    pub is_window_dimmed: bool,
+    /// the last opacity set on the window, to apply changes only once
+    pub applied_window_opacity: f32,
+    pub js_snippets: Option<JsSnippets>,
+    pub persistence_warning: Option<PersistenceWarning>,
+    /// set when the OS refused a `global` binding from settings
+    pub global_hotkey_warning: Option<GlobalHotkeyWarning>,
+    /// where the note files are, e.g. for `/insert-path`
+    pub persistence_folder: PathBuf,
 
     pub inline_llm_prompt: Option<InlineLLMPromptState>,
+    pub inline_suggestion: Option<InlineSuggestion>,
     pub slash_palette: Option<SlashPalette>,
+    pub table_picker: Option<TablePicker>,
+    pub multi_cursor: Option<MultiCursor>,
+    pub rename_prompt: Option<RenamePrompt>,
+    pub find_replace: Option<FindReplace>,
+    /// the note that is about to be cleared, waiting for confirmation
+    pub clear_note_confirmation: Option<NoteFile>,
+    /// text kept visible in a floating overlay above the editor, see `/pin`
+    pub pinned_snippet: Option<String>,
+    pub recent_notes: RecentNotes,
+    pub note_switcher: Option<NoteSwitcher>,
+    pub tag_filter: Option<TagFilter>,
+    pub recovery_bin: Option<RecoveryBin>,
+    /// js tasks running in the background, with their latest progress
+    pub js_tasks: BTreeMap<(NoteFile, BlockId), JsTaskProgress>,
+    /// notes rendered as read only markdown, without the markers
+    pub reading_mode_notes: BTreeSet<NoteFile>,
+    /// notes with soft wrap turned off, long lines overflow and the note scrolls horizontally
+    pub unwrapped_notes: BTreeSet<NoteFile>,
+    /// notes shown in their own windows (next to the main one), with the layout of that window
+    pub popped_out_notes: BTreeMap<NoteFile, Option<ComputedLayout>>,
+    /// last edit of large notes, they are evaluated once `LARGE_NOTE_EVAL_DELAY` passes without edits
+    pub large_note_edits: BTreeMap<NoteFile, Instant>,
+    /// the rendered markdown of the selected note is shown next to the editor
+    pub show_preview_panel: bool,
+    pub tutorial: Option<TutorialState>,
 
     pub computed_layout: Option<ComputedLayout>,
+    pub preview_layout: Option<ComputedLayout>,
+    pub highlight_cache: CodeHighlightCache,
     pub settings_scripts: Option<Scripts>,
     pub deferred_actions: Vec<AppAction>,
     pub render_actions: Vec<RenderAction>,
@@ -218,6 +449,11 @@ pub struct AppState {
 
 impl AppState {
     pub fn add_unsaved_change(&mut self, change: UnsavedChange) {
+        // the text keeps changing while the change is pending
+        if let UnsavedChange::NoteContentChanged(_) = change {
+            self.unsaved_notes_edited = true;
+        }
+
         if self.unsaved_changes.iter().any(|c| c == &change) {
             // if we already have a change pending do nothing
             return;
@@ -225,6 +461,208 @@ impl AppState {
 
         self.unsaved_changes.push(change);
     }
+
+    pub fn record_executed_command(
+        &mut self,
+        instruction: &CommandInstruction,
+        action_count: usize,
+    ) {
+        let outcome = match action_count {
+            0 => CommandOutcome::Skipped,
+            n => CommandOutcome::Applied(n),
+        };
+
+        if self.command_log.record(instruction, outcome) {
+            self.add_unsaved_change(UnsavedChange::CommandLogChanged);
+        }
+    }
+
+    pub fn record_slash_command_usage(&mut self, prefix: &str) {
+        self.command_usage.record(prefix);
+        self.add_unsaved_change(UnsavedChange::CommandUsageChanged);
+    }
+
+    /// Palettes, prompts, pickers or modals that Escape should close before anything else
+    pub fn has_open_overlay(&self) -> bool {
+        self.inline_llm_prompt.is_some()
+            || self.slash_palette.is_some()
+            || self.table_picker.is_some()
+            || self.rename_prompt.is_some()
+            || self.find_replace.is_some()
+            || self.clear_note_confirmation.is_some()
+            || self.note_switcher.is_some()
+            || self.tag_filter.is_some()
+            || self.recovery_bin.is_some()
+            || self
+                .feedback
+                .as_ref()
+                .is_some_and(|feedback| feedback.is_feedback_open)
+    }
+
+    pub fn on_focus_lost(&self) -> FocusLostBehavior {
+        self.window_settings
+            .as_ref()
+            .map(|s| s.on_focus_lost)
+            .unwrap_or_default()
+    }
+
+    pub fn cycle_through_settings(&self) -> bool {
+        self.window_settings
+            .as_ref()
+            .is_some_and(|s| s.cycle_through_settings)
+    }
+
+    pub fn close_button(&self) -> CloseButtonBehavior {
+        self.window_settings
+            .as_ref()
+            .map(|s| s.close_button)
+            .unwrap_or_default()
+    }
+
+    /// From the `window` settings, 1.0 is fully opaque
+    pub fn window_opacity(&self) -> f32 {
+        let percent = self
+            .window_settings
+            .as_ref()
+            .map(|s| s.opacity_percent)
+            .unwrap_or(100)
+            .clamp(MIN_WINDOW_OPACITY_PERCENT, 100);
+
+        percent as f32 / 100.0
+    }
+
+    pub fn live_preview(&self) -> bool {
+        self.editor_settings
+            .as_ref()
+            .is_some_and(|editor| editor.live_preview)
+    }
+
+    pub fn checkbox_tasks(&self) -> bool {
+        self.editor_settings
+            .as_ref()
+            .is_some_and(|editor| editor.checkbox_tasks)
+    }
+
+    pub fn scroll_past_end(&self) -> bool {
+        self.editor_settings
+            .as_ref()
+            .is_some_and(|editor| editor.scroll_past_end)
+    }
+
+    pub fn wrap_code_blocks(&self) -> bool {
+        self.editor_settings
+            .as_ref()
+            .is_none_or(|editor| editor.wrap_code_blocks)
+    }
+
+    pub fn code_auto_indent(&self) -> bool {
+        self.editor_settings
+            .as_ref()
+            .is_none_or(|editor| editor.code_auto_indent)
+    }
+
+    pub fn auto_links(&self) -> bool {
+        self.editor_settings
+            .as_ref()
+            .is_none_or(|editor| editor.auto_links)
+    }
+
+    /// Notes of at least this many bytes are rendered without code highlighting and bare urls
+    pub fn large_note_bytes(&self) -> usize {
+        let kb = self
+            .editor_settings
+            .as_ref()
+            .map(|editor| editor.large_note_kb)
+            .unwrap_or(DEFAULT_LARGE_NOTE_KB);
+
+        kb as usize * 1024
+    }
+
+    pub fn is_large_note(&self, note_file: NoteFile) -> bool {
+        self.notes
+            .get(&note_file)
+            .is_some_and(|note| note.text.len() >= self.large_note_bytes())
+    }
+
+    pub fn inline_suggestions(&self) -> bool {
+        self.editor_settings
+            .as_ref()
+            .is_some_and(|editor| editor.inline_suggestions)
+    }
+
+    /// Locked by the user or an encrypted note that couldn't be decrypted
+    pub fn is_read_only(&self, note_file: NoteFile) -> bool {
+        self.locked_notes.contains(&note_file) || self.undecryptable_notes.contains(&note_file)
+    }
+
+    /// Where a continuation of the selected note can be suggested, that is, the cursor at its end.
+    /// The ghost text is appended to the layout, so it can't go in the middle of the text
+    pub fn inline_suggestion_address(&self) -> Option<TextSelectionAddress> {
+        let note_file = self.selected_note;
+        let is_available = self.inline_suggestions()
+            && matches!(note_file, NoteFile::Note(_))
+            && !self.is_read_only(note_file)
+            && !self.encrypted_notes.contains(&note_file)
+            && !self.reading_mode_notes.contains(&note_file)
+            && !self.is_large_note(note_file)
+            && self.inline_llm_prompt.is_none()
+            && self.slash_palette.is_none()
+            && self.multi_cursor.is_none();
+
+        if !is_available {
+            return None;
+        }
+
+        let note = self.notes.get(&note_file)?;
+        let cursor = note.cursor()?.ordered();
+
+        let is_at_the_end = cursor.is_empty() && cursor.start == note.text.len();
+
+        (is_at_the_end && !note.text.trim().is_empty()).then(|| TextSelectionAddress {
+            span: cursor,
+            note_file,
+            text_version: note.derived_state.structure.opaque_version(),
+        })
+    }
+
+    /// Ghost text after the cursor of the selected note
+    pub fn ready_inline_suggestion(&self) -> Option<&str> {
+        match self.inline_suggestion.as_ref()? {
+            InlineSuggestion {
+                address,
+                status: InlineSuggestionStatus::Ready(text),
+            } if address.note_file == self.selected_note => Some(text.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn timestamp_format(&self) -> &str {
+        self.editor_settings
+            .as_ref()
+            .and_then(|editor| editor.timestamp_format.as_deref())
+            .unwrap_or(DEFAULT_TIMESTAMP_FORMAT)
+    }
+
+    pub fn normalize_pasted_lists(&self) -> bool {
+        self.editor_settings
+            .as_ref()
+            .is_none_or(|editor| editor.normalize_pasted_lists)
+    }
+
+    pub fn collapse_blank_lines(&self) -> bool {
+        self.editor_settings
+            .as_ref()
+            .is_some_and(|editor| editor.collapse_blank_lines)
+    }
+
+    pub fn focus_lost_delay(&self) -> Duration {
+        Duration::from_millis(
+            self.window_settings
+                .as_ref()
+                .map(|s| s.focus_lost_delay_ms)
+                .unwrap_or(0),
+        )
+    }
 }
 #[derive(Debug)]
 pub struct CodeArea {
@@ -234,28 +672,87 @@ pub struct CodeArea {
     pub code_block_span_index: SpanIndex,
 }
 
+/// Geometry of a task marker, used to draw it as a checkbox
+#[derive(Debug)]
+pub struct TaskArea {
+    pub rect: Rect,
+    pub byte_range: ByteSpan,
+    pub state: TaskState,
+}
+
+/// Geometry of an image or a link, used to mark missing local files and remote images that failed to load
+#[derive(Debug)]
+pub struct LinkArea {
+    /// the end of the image or the link, on its last row
+    pub rect: Rect,
+    pub url: String,
+    pub is_image: bool,
+}
+
+/// Geometry of a `> [!NOTE]` callout, painted as a tinted box behind the text
+#[derive(Debug)]
+pub struct CalloutArea {
+    pub rect: Rect,
+    pub kind: CalloutKind,
+}
+
 #[derive(Debug)]
 pub struct ComputedLayout {
     pub galley: Arc<Galley>,
+    /// width of the note, rows can be wider if they are not wrapped
+    pub wrap_width: f32,
     pub layout_params_hash: u64,
     pub code_areas: SmallVec<[CodeArea; 6]>,
+    pub task_areas: SmallVec<[TaskArea; 8]>,
+    pub callout_areas: SmallVec<[CalloutArea; 2]>,
+    pub link_areas: SmallVec<[LinkArea; 4]>,
 }
 
 #[derive(Debug)]
 pub struct LayoutParams<'a> {
     text: &'a str,
     wrap_width: f32,
+    marker_visibility: MarkerVisibility,
+    soft_wrap: bool,
+    wrap_code_blocks: bool,
+    auto_links: bool,
+    /// code is not highlighted and bare urls are plain text, see `AppState::large_note_bytes`
+    is_large_note: bool,
+    /// dimmed text appended after the note, see `InlineSuggestion`
+    ghost_text: Option<&'a str>,
     hash: u64,
 }
 
 impl<'a> LayoutParams<'a> {
-    pub fn new(text: &'a str, wrap_width: f32, dpi: f32) -> Self {
+    pub fn new(
+        text: &'a str,
+        wrap_width: f32,
+        dpi: f32,
+        marker_visibility: MarkerVisibility,
+        soft_wrap: bool,
+        wrap_code_blocks: bool,
+        auto_links: bool,
+        is_large_note: bool,
+        ghost_text: Option<&'a str>,
+    ) -> Self {
         Self {
             text,
             wrap_width,
+            marker_visibility,
+            soft_wrap,
+            wrap_code_blocks,
+            auto_links,
+            is_large_note,
+            ghost_text,
             hash: {
                 let mut hasher = fxhash::FxHasher::default();
                 text.hash(&mut hasher);
+                marker_visibility.hash(&mut hasher);
+                soft_wrap.hash(&mut hasher);
+                wrap_code_blocks.hash(&mut hasher);
+                auto_links.hash(&mut hasher);
+                is_large_note.hash(&mut hasher);
+                ghost_text.hash(&mut hasher);
                 // note that it is OK to round it up
                 ((wrap_width * 100.0) as i64).hash(&mut hasher);
                 ((dpi * 100.0) as i64).hash(&mut hasher);
@@ -278,18 +775,69 @@ impl ComputedLayout {
         theme: &AppTheme,
         syntax_set: &SyntaxSet,
         theme_set: &ThemeSet,
+        highlight_cache: &mut CodeHighlightCache,
     ) -> Self {
         // let text_structure = TextStructure::create_from(text);
 
-        let mut job =
-            text_structure.create_layout_job(layout_params.text, theme, syntax_set, theme_set);
+        let mut job = text_structure.create_layout_job(
+            layout_params.text,
+            theme,
+            syntax_set,
+            theme_set,
+            highlight_cache,
+            layout_params.marker_visibility,
+            layout_params.auto_links && !layout_params.is_large_note,
+            !layout_params.is_large_note,
+        );
 
-        job.wrap.max_width = layout_params.wrap_width;
+        // the cursor is at the end of the note, so the text before it keeps its char positions
+        if let Some(ghost_text) = layout_params.ghost_text {
+            job.append(
+                ghost_text,
+                0.0,
+                TextFormat::simple(
+                    FontId::new(theme.fonts.size.normal, theme.fonts.family.normal.clone()),
+                    theme.colors.subtle_text_color,
+                ),
+            );
+        }
 
-        let galley = ui.fonts(|f| f.layout_job(job));
+        // without soft wrap every line is as long as it is, and the note scrolls horizontally
+        job.wrap.max_width = match layout_params.soft_wrap {
+            true => layout_params.wrap_width,
+            false => f32::INFINITY,
+        };
+
+        let no_wrap_ranges: SmallVec<[Range<usize>; 4]> = match layout_params.wrap_code_blocks
+            || !layout_params.soft_wrap
+        {
+            true => SmallVec::new(),
+            false => text_structure
+                .iter()
+                .filter(|(_, desc)| desc.kind == SpanKind::CodeBlock)
+                .map(|(_, desc)| {
+                    let [start, end] = [desc.byte_pos.start, desc.byte_pos.end]
+                        .map(|byte_pos| char_index_from_byte_index(layout_params.text, byte_pos));
+                    start..end
+                })
+                .collect(),
+        };
+
+        let galley = match no_wrap_ranges.is_empty() {
+            true => ui.fonts(|f| f.layout_job(job)),
+            false => {
+                let mut unwrapped_job = job.clone();
+                unwrapped_job.wrap.max_width = f32::INFINITY;
+
+                let (wrapped, unwrapped) =
+                    ui.fonts(|f| (f.layout_job(job), f.layout_job(unwrapped_job)));
+                Arc::new(merge_unwrapped_rows(&wrapped, &unwrapped, &no_wrap_ranges))
+            }
+        };
 
         let code_areas: SmallVec<[CodeArea; 6]> = text_structure
             .iter()
+            .filter(|(_, desc)| !text_structure.is_in_collapsed_details(desc.byte_pos))
             .filter_map(|(index, desc)| match desc.kind {
                 SpanKind::CodeBlock => {
                     text_structure.find_meta(index).and_then(|meta| match meta {
@@ -311,7 +859,15 @@ impl ComputedLayout {
                 // TODO make a prettier math
                 r_start.extend_with(r_end.min);
                 r_start.extend_with(r_end.max);
-                r_start.set_right(r_start.right().max(layout_params.wrap_width));
+
+                // unwrapped code lines can be wider than the note
+                let widest_row = galley
+                    .rows
+                    .iter()
+                    .filter(|row| r_start.y_range().contains(row.rect.center().y))
+                    .map(|row| row.rect.right())
+                    .fold(layout_params.wrap_width, f32::max);
+                r_start.set_right(r_start.right().max(widest_row));
                 CodeArea {
                     rect: r_start,
                     lang,
@@ -320,6 +876,75 @@ impl ComputedLayout {
             })
             .collect();
 
+        let task_areas: SmallVec<[TaskArea; 8]> = text_structure
+            .iter()
+            .filter(|(_, desc)| !text_structure.is_in_collapsed_details(desc.byte_pos))
+            .filter_map(
+                |(index, desc)| match (desc.kind, text_structure.find_meta(index)) {
+                    (SpanKind::TaskMarker, Some(SpanMeta::TaskMarker(state))) => {
+                        Some((desc.byte_pos, *state))
+                    }
+                    _ => None,
+                },
+            )
+            .filter_map(|(byte_range, state)| {
+                let [start, end] = [byte_range.start, byte_range.end].map(|byte_pos| {
+                    let char_pos = char_index_from_byte_index(layout_params.text, byte_pos);
+                    galley.pos_from_ccursor(CCursor::new(char_pos))
+                });
+
+                let rect = start.union(end);
+
+                // hidden (shrunk) or wrapped markers stay as text
+                let is_drawable = rect.width() >= rect.height() / 2.0 && start.top() == end.top();
+                is_drawable.then_some(TaskArea {
+                    rect,
+                    byte_range,
+                    state,
+                })
+            })
+            .collect();
+
+        let callout_areas: SmallVec<[CalloutArea; 2]> = text_structure
+            .iter()
+            .filter(|(_, desc)| !text_structure.is_in_collapsed_details(desc.byte_pos))
+            .filter_map(|(_, desc)| match desc.kind {
+                SpanKind::Callout(kind) => Some((desc.byte_pos, kind)),
+                _ => None,
+            })
+            .map(|(byte_span, kind)| {
+                let [start, end] = [byte_span.start, byte_span.end].map(|byte_pos| {
+                    let char_pos = char_index_from_byte_index(layout_params.text, byte_pos);
+                    galley.pos_from_ccursor(CCursor::new(char_pos))
+                });
+
+                // the box spans the whole width, even if the lines are short
+                let mut rect = start.union(end);
+                rect.set_right(rect.right().max(layout_params.wrap_width));
+
+                CalloutArea { rect, kind }
+            })
+            .collect();
+
+        let link_areas: SmallVec<[LinkArea; 4]> = text_structure
+            .iter()
+            .filter(|(_, desc)| !text_structure.is_in_collapsed_details(desc.byte_pos))
+            .filter_map(|(index, desc)| match text_structure.find_meta(index)? {
+                SpanMeta::Image { url } => Some((desc.byte_pos, url, true)),
+                SpanMeta::Link { url } => Some((desc.byte_pos, url, false)),
+                _ => None,
+            })
+            .map(|(byte_span, url, is_image)| {
+                let char_pos = char_index_from_byte_index(layout_params.text, byte_span.end);
+
+                LinkArea {
+                    rect: galley.pos_from_ccursor(CCursor::new(char_pos)),
+                    url: url.clone(),
+                    is_image,
+                }
+            })
+            .collect();
+
         // println!("^^^^ compute layout, code_areas = {code_areas:#?}");
         // println!(
         //     "^^^^ galley rect={:#?}, mesh_rect={:#?}",
@@ -328,7 +953,11 @@ impl ComputedLayout {
 
         Self {
             galley,
+            wrap_width: layout_params.wrap_width,
             code_areas,
+            task_areas,
+            callout_areas,
+            link_areas,
             layout_params_hash: layout_params.hash,
         }
     }
@@ -359,8 +988,23 @@ pub enum MsgToApp {
         response: InlineLLMResponseChunk,
         address: TextSelectionAddress,
     },
+    /// Continuation of the note for `InlineSuggestion`, errors are only logged
+    InlineSuggestionResponse {
+        response: Result<String, String>,
+        address: TextSelectionAddress,
+    },
+    /// Result of `AppIO::fetch_remote_image`, the error is shown next to the image
+    RemoteImageFetched {
+        url: String,
+        result: Result<(), String>,
+    },
     UpdateRequired(Version),
     UpdateAvailable(Version),
+    JsTaskUpdate {
+        note_file: NoteFile,
+        block_id: BlockId,
+        update: JsTaskUpdate,
+    },
 }
 
 // struct MdAnnotationShortcut {
@@ -377,12 +1021,18 @@ impl AppState {
             persistent_state,
             last_saved,
             load_kind,
+            persistence_warning,
+            persistence_folder,
+            start_at_login,
         } = init_data;
 
         let RestoredData {
             state: saved_state,
             notes,
             settings,
+            script_storage,
+            command_log,
+            undecryptable_notes,
         } = persistent_state;
 
         let shelf_count = notes.len();
@@ -418,6 +1068,24 @@ impl AppState {
 
         let selected_note = saved_state.selected;
         let is_window_pinned = saved_state.is_pinned;
+        let crash_reporting = saved_state.crash_reporting;
+        let start_at_login_prompted = saved_state.start_at_login_prompted;
+        let show_start_at_login_prompt = !start_at_login_prompted
+            && !start_at_login
+            && !matches!(load_kind, LoadKind::FreshInstall);
+        let command_usage = saved_state.command_usage;
+        let encrypted_notes = saved_state.encrypted_notes;
+        let last_llm_prompts = saved_state.last_llm_prompts.into_iter().collect();
+        let locked_notes = saved_state.locked_notes;
+        // the folder warnings are more important, they concern all the notes
+        let persistence_warning = persistence_warning.or_else(|| {
+            (!undecryptable_notes.is_empty()).then(|| {
+                PersistenceWarning::EncryptedNotesNotDecrypted(
+                    undecryptable_notes.iter().copied().collect(),
+                )
+            })
+        });
+        let recent_notes = RecentNotes::new(notes.keys().copied(), selected_note);
 
         let keybord_instructions: Vec<(CommandInstruction, CommandScope)> = Vec::from_iter(
             [
@@ -425,6 +1093,8 @@ impl AppState {
                 CommandInstruction::IndentListItem,
                 CommandInstruction::UnindentListItem,
                 CommandInstruction::SplitListItem,
+                CommandInstruction::NextTableCell,
+                CommandInstruction::PrevTableCell,
                 CommandInstruction::MarkdownCodeBlock(None),
                 CommandInstruction::MarkdownBold,
                 CommandInstruction::MarkdownItalic,
@@ -432,10 +1102,29 @@ impl AppState {
                 CommandInstruction::MarkdownH1,
                 CommandInstruction::MarkdownH2,
                 CommandInstruction::MarkdownH3,
+                CommandInstruction::IncreaseHeadingLevel,
+                CommandInstruction::DecreaseHeadingLevel,
+                CommandInstruction::CollapseAllDetails,
+                CommandInstruction::ExpandAllDetails,
+                CommandInstruction::InsertHorizontalRule,
+                CommandInstruction::InsertTimestamp,
+                CommandInstruction::CycleTaskState,
                 CommandInstruction::EnterInsideKDL,
+                CommandInstruction::EnterInsideCodeBlock,
                 // CommandInstruction::RunLLMBlock,
                 CommandInstruction::ShowPrompt,
                 CommandInstruction::ShowSlashPallete,
+                CommandInstruction::ToggleSlashPallete,
+                CommandInstruction::SelectNextOccurrence,
+                CommandInstruction::RenameInCodeBlock,
+                CommandInstruction::FindReplace,
+                CommandInstruction::NextSiblingHeading,
+                CommandInstruction::PrevSiblingHeading,
+                CommandInstruction::ParentHeading,
+                CommandInstruction::NextCodeBlock,
+                CommandInstruction::PrevCodeBlock,
+                CommandInstruction::TransposeChars,
+                CommandInstruction::JoinLines,
                 // CommandInstruction::HideSlashPallete,
                 // CommandInstruction::NextSlashPalleteCmd,
                 // CommandInstruction::PrevSlashPalleteCmd,
@@ -448,6 +1137,54 @@ impl AppState {
                     CommandInstruction::SwitchToSettings,
                     CommandScope::UiState(UiState::Editing),
                 ),
+                (
+                    CommandInstruction::SwitchToNextNote,
+                    CommandScope::UiState(UiState::Editing),
+                ),
+                (
+                    CommandInstruction::SwitchToPrevNote,
+                    CommandScope::UiState(UiState::Editing),
+                ),
+                (
+                    CommandInstruction::CycleRecentNotes,
+                    CommandScope::UiState(UiState::Editing),
+                ),
+                (
+                    CommandInstruction::CycleRecentNotesBack,
+                    CommandScope::UiState(UiState::Editing),
+                ),
+                (
+                    CommandInstruction::FilterNotesByTag,
+                    CommandScope::UiState(UiState::Editing),
+                ),
+                (
+                    CommandInstruction::ToggleReadingMode,
+                    CommandScope::UiState(UiState::Editing),
+                ),
+                (
+                    CommandInstruction::TogglePreviewPanel,
+                    CommandScope::UiState(UiState::Editing),
+                ),
+                (
+                    CommandInstruction::ToggleSoftWrap,
+                    CommandScope::UiState(UiState::Editing),
+                ),
+                (
+                    CommandInstruction::ExportNote,
+                    CommandScope::UiState(UiState::Editing),
+                ),
+                (
+                    CommandInstruction::ToggleNoteEncryption,
+                    CommandScope::UiState(UiState::Editing),
+                ),
+                (
+                    CommandInstruction::ToggleNoteLock,
+                    CommandScope::UiState(UiState::Editing),
+                ),
+                (
+                    CommandInstruction::ToggleFocusSession,
+                    CommandScope::UiState(UiState::Editing),
+                ),
                 (CommandInstruction::PinWindow, CommandScope::Global),
                 (CommandInstruction::HideApp, CommandScope::Global),
             ])
@@ -473,6 +1210,116 @@ impl AppState {
                     ("h1", CommandInstruction::MarkdownH1, P::TEXT_H_ONE),
                     ("h2", CommandInstruction::MarkdownH2, P::TEXT_H_TWO),
                     ("h3", CommandInstruction::MarkdownH3, P::TEXT_H_THREE),
+                    ("task", CommandInstruction::CycleTaskState, P::CHECK_SQUARE),
+                    ("tasks", CommandInstruction::ConvertToTasks, P::LIST_CHECKS),
+                    ("table", CommandInstruction::InsertTable, P::TABLE),
+                    ("hr", CommandInstruction::InsertHorizontalRule, P::MINUS),
+                    ("now", CommandInstruction::InsertTimestamp, P::CLOCK),
+                    ("join", CommandInstruction::JoinLines, P::ARROWS_MERGE),
+                    ("quote-paste", CommandInstruction::PasteAsQuote, P::QUOTES),
+                    (
+                        "replace",
+                        CommandInstruction::FindReplace,
+                        P::MAGNIFYING_GLASS,
+                    ),
+                    ("image", CommandInstruction::CopyNoteAsImage, P::IMAGE),
+                    (
+                        "extract-block",
+                        CommandInstruction::ExtractCodeBlock,
+                        P::EXPORT,
+                    ),
+                    (
+                        NOTE_LINK_PREFIX,
+                        CommandInstruction::InsertNoteLink,
+                        P::LINK,
+                    ),
+                    (
+                        "insert-path",
+                        CommandInstruction::InsertNotePath,
+                        P::FILE_TEXT,
+                    ),
+                    (
+                        "copy-with-path",
+                        CommandInstruction::CopyWithHeadingPath,
+                        P::TREE_STRUCTURE,
+                    ),
+                    ("pin", CommandInstruction::PinSelection, P::PUSH_PIN),
+                    (
+                        "details",
+                        CommandInstruction::WrapInDetails,
+                        P::CARET_CIRCLE_DOWN,
+                    ),
+                    (
+                        "collapse-all",
+                        CommandInstruction::CollapseAllDetails,
+                        P::ARROWS_IN_LINE_VERTICAL,
+                    ),
+                    (
+                        "expand-all",
+                        CommandInstruction::ExpandAllDetails,
+                        P::ARROWS_OUT_LINE_VERTICAL,
+                    ),
+                    ("escape", CommandInstruction::EscapeMarkdown, P::TEXT_T),
+                    ("unescape", CommandInstruction::UnescapeMarkdown, P::TEXT_AA),
+                    ("format", CommandInstruction::FormatNote, P::BROOM),
+                    (
+                        "note",
+                        CommandInstruction::ToggleCallout(CalloutKind::Note),
+                        P::INFO,
+                    ),
+                    (
+                        "tip",
+                        CommandInstruction::ToggleCallout(CalloutKind::Tip),
+                        P::LIGHTBULB,
+                    ),
+                    (
+                        "important",
+                        CommandInstruction::ToggleCallout(CalloutKind::Important),
+                        P::WARNING_DIAMOND,
+                    ),
+                    (
+                        "warning",
+                        CommandInstruction::ToggleCallout(CalloutKind::Warning),
+                        P::WARNING,
+                    ),
+                    (
+                        "caution",
+                        CommandInstruction::ToggleCallout(CalloutKind::Caution),
+                        P::WARNING_OCTAGON,
+                    ),
+                    (
+                        "external-editor",
+                        CommandInstruction::OpenInExternalEditor,
+                        P::ARROW_SQUARE_OUT,
+                    ),
+                    ("export", CommandInstruction::ExportNote, P::EXPORT),
+                    ("tags", CommandInstruction::FilterNotesByTag, P::HASH),
+                    (
+                        "reading-mode",
+                        CommandInstruction::ToggleReadingMode,
+                        P::BOOK_OPEN,
+                    ),
+                    (
+                        "preview",
+                        CommandInstruction::TogglePreviewPanel,
+                        P::COLUMNS,
+                    ),
+                    (
+                        "wrap",
+                        CommandInstruction::ToggleSoftWrap,
+                        P::ARROWS_LEFT_RIGHT,
+                    ),
+                    ("encrypt", CommandInstruction::ToggleNoteEncryption, P::LOCK),
+                    ("lock", CommandInstruction::ToggleNoteLock, P::LOCK_SIMPLE),
+                    ("focus", CommandInstruction::ToggleFocusSession, P::TIMER),
+                    ("duplicate-note", CommandInstruction::DuplicateNote, P::COPY),
+                    ("pop-out", CommandInstruction::PopOutNote, P::APP_WINDOW),
+                    ("clear-note", CommandInstruction::ClearNote, P::ERASER),
+                    (
+                        "recover",
+                        CommandInstruction::ShowRecoveryBin,
+                        P::ARROW_COUNTER_CLOCKWISE,
+                    ),
                     ("bold", CommandInstruction::MarkdownBold, P::TEXT_BOLDER),
                     ("italic", CommandInstruction::MarkdownItalic, P::TEXT_ITALIC),
                     (
@@ -515,23 +1362,72 @@ impl AppState {
 
         Self {
             is_pinned: is_window_pinned,
+            crash_reporting,
+            start_at_login,
+            start_at_login_prompted,
+            show_start_at_login_prompt,
+            command_usage,
+            encrypted_notes,
+            last_llm_prompts,
+            note_mirrors: BTreeMap::new(),
+            note_templates: BTreeMap::new(),
+            locked_notes,
+            undecryptable_notes,
+            remote_images: BTreeMap::new(),
             unsaved_changes: Default::default(),
+            unsaved_notes_edited: false,
             scheduled_script_run_version: None,
             theme,
             notes,
             computed_layout: None,
+            preview_layout: None,
+            highlight_cache: CodeHighlightCache::default(),
             syntax_set: SyntaxSet::load_defaults_newlines(),
             theme_set: ThemeSet::load_defaults(),
             msg_queue,
             selected_note,
             hidden: false,
             prev_focused: false,
+            focus_lost_at: None,
+            focus_session: None,
             last_saved,
             commands: editor_commands,
             llm_settings: None,
+            command_log,
+            clipboard_history: ClipboardHistory::default(),
+            script_storage,
+            quiet_hours: None,
+            slash_palette_settings: None,
+            dev_settings: None,
+            window_settings: None,
+            editor_settings: None,
+            is_window_dimmed: false,
+            applied_window_opacity: 1.0,
+            js_snippets: None,
+            persistence_warning,
+            global_hotkey_warning: None,
+            persistence_folder,
             deferred_actions,
             inline_llm_prompt: None,
+            inline_suggestion: None,
             slash_palette: None,
+            table_picker: None,
+            multi_cursor: None,
+            rename_prompt: None,
+            find_replace: None,
+            clear_note_confirmation: None,
+            pinned_snippet: None,
+            recent_notes,
+            note_switcher: None,
+            tag_filter: None,
+            recovery_bin: None,
+            js_tasks: BTreeMap::new(),
+            reading_mode_notes: BTreeSet::new(),
+            unwrapped_notes: BTreeSet::new(),
+            popped_out_notes: BTreeMap::new(),
+            large_note_edits: BTreeMap::new(),
+            show_preview_panel: false,
+            tutorial: None,
             settings_scripts: None,
             render_actions: vec![],
             feedback: None,
@@ -539,13 +1435,48 @@ impl AppState {
         }
     }
 
+    /// Notes that were edited since the last save.
+    /// Encrypted notes are left out, so their text is never written in plain
+    pub fn unsaved_notes(&self) -> impl Iterator<Item = (NoteFile, &str)> {
+        self.unsaved_changes
+            .iter()
+            .filter_map(|change| match change {
+                UnsavedChange::NoteContentChanged(note_file)
+                    if !self.encrypted_notes.contains(note_file)
+                        && !self.undecryptable_notes.contains(note_file) =>
+                {
+                    self.notes
+                        .get(note_file)
+                        .map(|n| (*note_file, n.text.as_str()))
+                }
+                _ => None,
+            })
+    }
+
+    /// True once after the notes were edited, so the panic backup isn't copied on every frame
+    pub fn take_unsaved_notes_edited(&mut self) -> bool {
+        std::mem::take(&mut self.unsaved_notes_edited)
+    }
+
     pub fn should_persist(&mut self) -> Option<DataToSave> {
         if !self.unsaved_changes.is_empty() {
             let changes: SmallVec<[_; 4]> = self.unsaved_changes.drain(..).unique().collect();
+            let command_log = changes
+                .contains(&UnsavedChange::CommandLogChanged)
+                .then(|| self.command_log.to_markdown());
+            let script_storage = changes
+                .contains(&UnsavedChange::ScriptStorageChanged)
+                .then(|| self.script_storage.to_json());
             Some(DataToSave {
                 files: changes
                     .into_iter()
                     .filter_map(|change| match change {
+                        // the ciphertext of an undecryptable note is never written over
+                        UnsavedChange::NoteContentChanged(note_file)
+                            if self.undecryptable_notes.contains(&note_file) =>
+                        {
+                            None
+                        }
                         UnsavedChange::NoteContentChanged(note_file) => self
                             .notes
                             .get(&note_file)
@@ -555,6 +1486,15 @@ impl AppState {
                     .collect(),
                 selected: self.selected_note,
                 is_pinned: self.is_pinned,
+                crash_reporting: self.crash_reporting,
+                command_usage: self.command_usage.clone(),
+                command_log,
+                script_storage,
+                encrypted_notes: self.encrypted_notes.clone(),
+                start_at_login_prompted: self.start_at_login_prompted,
+                last_llm_prompts: self.last_llm_prompts.clone().into_iter().collect(),
+                locked_notes: self.locked_notes.clone(),
+                note_mirrors: self.note_mirrors.clone().into_iter().collect(),
             })
         } else {
             None
@@ -575,6 +1515,9 @@ pub struct AppInitData {
     pub persistent_state: RestoredData,
     pub last_saved: u128,
     pub load_kind: LoadKind,
+    pub persistence_warning: Option<PersistenceWarning>,
+    pub persistence_folder: PathBuf,
+    pub start_at_login: bool,
 }
 
 fn execute_instruction(
@@ -587,6 +1530,8 @@ fn execute_instruction(
         CI::IndentListItem => call_with_text_ctx(ctx, on_tab_inside_list),
         CI::UnindentListItem => call_with_text_ctx(ctx, on_shift_tab_inside_list),
         CI::SplitListItem => call_with_text_ctx(ctx, on_enter_inside_list_item),
+        CI::NextTableCell => call_with_text_ctx(ctx, on_tab_inside_table),
+        CI::PrevTableCell => call_with_text_ctx(ctx, on_shift_tab_inside_table),
         CI::MarkdownCodeBlock(lang) => call_with_text_ctx(ctx, |cx| {
             toggle_code_block(cx, lang.as_ref().map(|s| s.as_str()))
         }),
@@ -608,7 +1553,67 @@ fn execute_instruction(
         CI::MarkdownH3 => call_with_text_ctx(ctx, |text_context| {
             toggle_md_heading(text_context, HeadingLevel::H3)
         }),
+        CI::IncreaseHeadingLevel => call_with_text_ctx(ctx, |text_context| {
+            change_md_heading_level(text_context, HeadingLevelChange::Increase)
+        }),
+        CI::DecreaseHeadingLevel => call_with_text_ctx(ctx, |text_context| {
+            change_md_heading_level(text_context, HeadingLevelChange::Decrease)
+        }),
+        CI::CycleTaskState => call_with_text_ctx(ctx, cycle_task_state),
+        CI::ConvertToTasks => call_with_text_ctx(ctx, convert_to_tasks),
         CI::EnterInsideKDL => call_with_text_ctx(ctx, on_enter_inside_kdl_block),
+        CI::EnterInsideCodeBlock => match ctx.app_state.code_auto_indent() {
+            true => call_with_text_ctx(ctx, on_enter_inside_code_block),
+            false => SmallVec::new(),
+        },
+        CI::InsertTable => show_table_picker(ctx).unwrap_or_default(),
+        CI::InsertHorizontalRule => call_with_text_ctx(ctx, insert_horizontal_rule),
+        CI::InsertTimestamp => {
+            let timestamp = format_timestamp(Local::now(), ctx.app_state.timestamp_format());
+            call_with_text_ctx(ctx, |text_context| {
+                insert_timestamp(text_context, &timestamp)
+            })
+        }
+        CI::PasteAsQuote => [AppAction::PasteAsQuote(ctx.app_state.selected_note)].into(),
+        CI::SelectNextOccurrence => select_next_occurrence(ctx).unwrap_or_default(),
+        CI::RenameInCodeBlock => show_rename_prompt(ctx).unwrap_or_default(),
+        CI::FindReplace => show_find_replace(ctx).unwrap_or_default(),
+        CI::NextSiblingHeading => {
+            jump_to_heading(ctx, HeadingJump::NextSibling).unwrap_or_default()
+        }
+        CI::PrevSiblingHeading => {
+            jump_to_heading(ctx, HeadingJump::PrevSibling).unwrap_or_default()
+        }
+        CI::ParentHeading => jump_to_heading(ctx, HeadingJump::Parent).unwrap_or_default(),
+        CI::NextCodeBlock => jump_to_code_block(ctx, false).unwrap_or_default(),
+        CI::PrevCodeBlock => jump_to_code_block(ctx, true).unwrap_or_default(),
+        CI::TransposeChars => call_with_text_ctx(ctx, transpose_chars),
+        CI::JoinLines => call_with_text_ctx(ctx, join_lines),
+        // deferred, so the slash palette is already hidden on the screenshot
+        CI::CopyNoteAsImage => [AppAction::defer(AppAction::CopyNoteAsImage)].into(),
+        CI::ExtractCodeBlock => extract_code_block(ctx).unwrap_or_default(),
+        CI::InsertNoteLink => insert_note_link(ctx).unwrap_or_default(),
+        CI::InsertNotePath => insert_note_path(ctx).unwrap_or_default(),
+        CI::CopyWithHeadingPath => copy_with_heading_path(ctx).unwrap_or_default(),
+        CI::PinSelection => pin_selection(ctx).unwrap_or_default(),
+        CI::WrapInDetails => call_with_text_ctx(ctx, wrap_in_details),
+        CI::CollapseAllDetails => call_with_text_ctx(ctx, |text_context| {
+            set_all_details_open(text_context, false)
+        }),
+        CI::ExpandAllDetails => {
+            call_with_text_ctx(ctx, |text_context| set_all_details_open(text_context, true))
+        }
+        CI::EscapeMarkdown => call_with_text_ctx(ctx, escape_markdown),
+        CI::UnescapeMarkdown => call_with_text_ctx(ctx, unescape_markdown),
+        CI::FormatNote => call_with_text_ctx(ctx, format_note),
+        CI::ToggleCallout(kind) => {
+            call_with_text_ctx(ctx, |text_context| toggle_callout(text_context, *kind))
+        }
+        CI::OpenInExternalEditor => [AppAction::OpenNoteInExternalEditor(
+            ctx.app_state.selected_note,
+        )]
+        .into(),
+        CI::ExportNote => [AppAction::ExportNoteToPath(ctx.app_state.selected_note)].into(),
 
         CI::SwitchToNote(note_index) => SmallVec::from([AppAction::SwitchToNote {
             note_file: NoteFile::Note(*note_index as u32),
@@ -621,14 +1626,75 @@ fn execute_instruction(
         }]
         .into(),
 
+        CI::SwitchToNextNote | CI::SwitchToPrevNote => {
+            let app_state = ctx.app_state;
+            // the settings note is among the notes
+            let shelf_count = app_state.notes.len() as u32 - 1;
+
+            [AppAction::SwitchToNote {
+                note_file: adjacent_note(
+                    app_state.selected_note,
+                    shelf_count,
+                    matches!(instruction, CI::SwitchToPrevNote),
+                    app_state.cycle_through_settings(),
+                ),
+                via_shortcut: true,
+            }]
+            .into()
+        }
+
+        CI::CycleRecentNotes => [AppAction::NoteSwitcher(NoteSwitcherAction::Cycle {
+            backwards: false,
+        })]
+        .into(),
+
+        CI::CycleRecentNotesBack => [AppAction::NoteSwitcher(NoteSwitcherAction::Cycle {
+            backwards: true,
+        })]
+        .into(),
+
+        CI::FilterNotesByTag => [AppAction::TagFilter(TagFilterAction::Show)].into(),
+        CI::ToggleReadingMode => [AppAction::ToggleReadingMode(ctx.app_state.selected_note)].into(),
+        CI::TogglePreviewPanel => [AppAction::TogglePreviewPanel].into(),
+        CI::ToggleSoftWrap => [AppAction::ToggleSoftWrap(ctx.app_state.selected_note)].into(),
+        CI::ToggleNoteEncryption => {
+            [AppAction::ToggleNoteEncryption(ctx.app_state.selected_note)].into()
+        }
+        CI::ToggleNoteLock => [AppAction::ToggleNoteLock(ctx.app_state.selected_note)].into(),
+        CI::ToggleFocusSession => [AppAction::ToggleFocusSession].into(),
+        CI::DuplicateNote => [AppAction::DuplicateNote(ctx.app_state.selected_note)].into(),
+        CI::PopOutNote => {
+            let note_file = ctx.app_state.selected_note;
+            match ctx.app_state.popped_out_notes.contains_key(&note_file) {
+                true => [AppAction::ClosePoppedOutNote(note_file)].into(),
+                false => [AppAction::PopOutNote(note_file)].into(),
+            }
+        }
+
+        CI::ClearNote => [AppAction::ClearNote(ClearNoteAction::Ask(
+            ctx.app_state.selected_note,
+        ))]
+        .into(),
+        CI::ShowRecoveryBin => [AppAction::RecoveryBin(RecoveryBinAction::Show)].into(),
+
         CI::PinWindow => [AppAction::SetWindowPinned(!ctx.app_state.is_pinned)].into(),
 
+        CI::ToggleCrashReporting => {
+            [AppAction::SetCrashReporting(!ctx.app_state.crash_reporting)].into()
+        }
+
+        CI::ToggleStartAtLogin => {
+            [AppAction::SetStartAtLogin(!ctx.app_state.start_at_login)].into()
+        }
+
+        // keyboard commands run after the frame hotkeys of overlays,
+        // so Escape closes any of them first and only then hides the window
         CI::HideApp => match (
             ctx.app_focus.is_menu_opened,
-            &ctx.app_state.slash_palette,
+            ctx.app_state.has_open_overlay(),
             ctx.app_focus.internal_focus,
         ) {
-            (false, None, None | Some(AppFocus::NoteEditor)) => {
+            (false, false, None | Some(AppFocus::NoteEditor)) => {
                 [AppAction::HandleMsgToApp(MsgToApp::ToggleVisibility)].into()
             }
             _ => SmallVec::new(),
@@ -640,6 +1706,8 @@ fn execute_instruction(
 
         CI::ShowSlashPallete => show_slash_pallete(ctx).unwrap_or_default(),
 
+        CI::ToggleSlashPallete => toggle_slash_pallete(ctx).unwrap_or_default(),
+
         // CI::HideSlashPallete => hide_slash_pallete(ctx).unwrap_or_default(),
 
         // CI::NextSlashPalleteCmd => next_slash_cmd(ctx).unwrap_or_default(),
@@ -717,3 +1785,68 @@ mod prompt_response_tests {
         assert!(response.explanation.is_none());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_harness::{IOCall, TestHarness};
+
+    use super::*;
+
+    #[test]
+    pub fn test_inline_suggestion_is_accepted_with_tab() {
+        let mut harness = TestHarness::new("Groceries: milk,{||}");
+
+        let address = TextSelectionAddress {
+            span: ByteSpan::point(16),
+            note_file: NoteFile::Note(0),
+            text_version: harness.state.notes[&NoteFile::Note(0)]
+                .derived_state
+                .structure
+                .opaque_version(),
+        };
+        harness.state.inline_suggestion = Some(InlineSuggestion {
+            address,
+            status: InlineSuggestionStatus::Requested,
+        });
+
+        harness.dispatch(AppAction::RequestInlineSuggestion(address));
+        assert_eq!(
+            harness.io.take_calls(),
+            vec![IOCall::RequestLlmSuggestion("Groceries: milk,".to_string())]
+        );
+
+        harness.dispatch(AppAction::HandleMsgToApp(
+            MsgToApp::InlineSuggestionResponse {
+                response: Ok(" eggs".to_string()),
+                address,
+            },
+        ));
+        assert_eq!(harness.state.ready_inline_suggestion(), Some(" eggs"));
+        assert_eq!(harness.text_with_cursor(), "Groceries: milk,{||}");
+
+        harness.dispatch(AppAction::AcceptInlineSuggestion { accept: true });
+        assert_eq!(harness.text_with_cursor(), "Groceries: milk, eggs{||}");
+
+        // a late response for the text before the edit is dropped
+        harness.dispatch(AppAction::HandleMsgToApp(
+            MsgToApp::InlineSuggestionResponse {
+                response: Ok(" bread".to_string()),
+                address,
+            },
+        ));
+        assert_eq!(harness.state.ready_inline_suggestion(), None);
+    }
+
+    #[test]
+    pub fn test_inline_suggestions_skip_encrypted_notes() {
+        let mut harness = TestHarness::new("Groceries: milk,{||}");
+        assert!(harness.state.inline_suggestion_address().is_none());
+
+        harness.with_settings("editor { inlineSuggestions true; }");
+        assert!(harness.state.inline_suggestion_address().is_some());
+
+        // the text of an encrypted note is never sent to the LLM
+        harness.state.encrypted_notes.insert(NoteFile::Note(0));
+        assert!(harness.state.inline_suggestion_address().is_none());
+    }
+}
@@ -1,9 +1,10 @@
 use eframe::{
     egui::{
         self, Context, CursorIcon, FontFamily, FontSelection, Frame, Id, Key, KeyboardShortcut,
-        Label, LayerId, Layout, Margin, Modal, Modifiers, Order, Painter, Response, RichText,
-        ScrollArea, Sense, Shadow, StrokeKind, TextEdit, TextFormat, TextStyle, TextWrapMode,
-        TopBottomPanel, Ui, UiBuilder, UiStackInfo, Vec2, WidgetText,
+        Label, LayerId, Layout, Margin, Modal, Modifiers, Order, Painter, ProgressBar,
+        ResizeDirection, Response, RichText, ScrollArea, Sense, Shadow, StrokeKind, TextEdit,
+        TextFormat, TextStyle, TextWrapMode, TopBottomPanel, Ui, UiBuilder, UiStackInfo, Vec2,
+        ViewportCommand, WidgetInfo, WidgetText, WidgetType,
         debug_text::print,
         scroll_area::ScrollBarVisibility,
         text::{CCursor, CCursorRange},
@@ -27,31 +28,67 @@ use smallvec::SmallVec;
 use syntect::{highlighting::ThemeSet, parsing::SyntaxSet};
 
 use crate::{
-    app_actions::{AppAction, FocusTarget, SlashPaletteAction},
+    app_actions::{
+        AppAction, ClearNoteAction, FindReplaceAction, FocusTarget, RecoveryBinAction,
+        RenamePromptAction, SlashPaletteAction, TablePickerAction, TagFilterAction, TutorialAction,
+    },
     app_state::{
-        CodeBlockAnnotation, ComputedLayout, FeedbackState, InlineLLMPromptState,
-        InlinePromptStatus, LayoutParams, RenderAction, SlashPalette, VersionState,
+        CalloutArea, CodeBlockAnnotation, ComputedLayout, FeedbackState, FindReplace,
+        InlineLLMPromptState, InlinePromptStatus, LayoutParams, RenamePrompt, RenderAction,
+        SlashPalette, TablePicker, VersionState,
     },
-    byte_span::UnOrderedByteSpan,
+    byte_span::{ByteSpan, UnOrderedByteSpan},
+    code_highlight::CodeHighlightCache,
     command::{
-        CommandInstruction, CommandList, EditorCommandOutput, FrameHotkeys, PROMOTED_COMMANDS,
-        SlashPaletteCmd,
+        CommandInstruction, CommandList, EditorCommandOutput, FrameHotkey, FrameHotkeyLayer,
+        FrameHotkeys, SlashPaletteCmd,
+    },
+    commands::{
+        find_replace::{Finder, find_query_input_id, replacement_input_id},
+        inline_llm_prompt::compute_inline_prompt_text_input_id,
+        insert_table::{TABLE_PICKER_MAX_COLS, TABLE_PICKER_MAX_ROWS},
+        rename_in_code_block::rename_prompt_input_id,
+        run_llm::LLM_LANG,
     },
-    commands::{inline_llm_prompt::compute_inline_prompt_text_input_id, run_llm::LLM_LANG},
     effects::text_change_effect::TextChange,
     feedback::{Feedback, FeedbackResult},
-    persistent_state::NoteFile,
+    local_links::{LinkMarker, LinkMarkerKind},
+    nord::Nord,
+    note_snapshot,
+    note_switcher::NoteSwitcherItem,
+    persistent_state::{NoteFile, PersistenceWarning},
     picker::{Picker, PickerItem, PickerItemKind},
-    settings_parsing::format_mac_shortcut_with_symbols,
+    recovery_bin::RecoveryBin,
+    scripting::{
+        js_snippets::SNIPPETS_BLOCK_LANG,
+        note_eval::JSBlockLang,
+        settings_eval::{GlobalHotkeyWarning, SETTINGS_BLOCK_LANG},
+    },
+    settings_parsing::{CloseButtonBehavior, format_mac_shortcut_with_symbols},
     taffy_styles::{StyleBuilder, flex_column, flex_row, style},
-    text_structure::{InteractiveTextPart, SpanIndex, TextStructure},
-    theme::{AppIcon, AppTheme},
+    tags::{TagFilter, tag_filter_input_id},
+    text_structure::{
+        CalloutKind, InteractiveTextPart, MarkerVisibility, SpanIndex, SpanKind, TaskState,
+        TextStructure, toggle_details_open_attribute,
+    },
+    theme::{AppIcon, AppTheme, ColorManipulation},
+    tutorial::{TUTORIAL_STEPS, TutorialState, TutorialTarget},
     ui_components::{IconButton, IconButtonSize, apply_icon_btn_styling, rich_text_tooltip},
+    word_goal::WordGoalProgress,
 };
 
+const WORD_GOAL_ANIMATION_SECS: f32 = 0.6;
+
+/// Window edge (in points) that starts a resize when dragged
+const RESIZE_EDGE: f32 = 4.0;
+/// Corners are larger, so they are easier to grab
+const RESIZE_CORNER: f32 = 12.0;
+
 pub struct AppRenderData<'a> {
     pub selected_note: NoteFile,
     pub code_block_annotations: &'a [(SpanIndex, CodeBlockAnnotation)],
+    /// see `NoteDerivedState::link_markers`
+    pub link_markers: &'a [LinkMarker],
     pub note_count: usize,
     pub text_edit_id: Id,
     pub byte_cursor: Option<UnOrderedByteSpan>,
@@ -59,13 +96,59 @@ pub struct AppRenderData<'a> {
     pub syntax_set: &'a SyntaxSet,
     pub theme_set: &'a ThemeSet,
     pub computed_layout: Option<ComputedLayout>,
+    pub highlight_cache: &'a mut CodeHighlightCache,
     pub inline_llm_prompt: Option<&'a mut InlineLLMPromptState>,
     pub slash_palette: Option<&'a SlashPalette>,
+    pub table_picker: Option<&'a TablePicker>,
+    pub multi_cursor_selections: &'a [ByteSpan],
+    pub rename_prompt: Option<&'a mut RenamePrompt>,
+    pub find_replace: Option<&'a mut FindReplace>,
+    pub tag_filter: Option<&'a mut TagFilter>,
+    /// text pinned with `/pin`, floats above the editor
+    pub pinned_snippet: Option<&'a str>,
+    pub recovery_bin: Option<&'a mut RecoveryBin>,
+    pub note_switcher_items: Option<SmallVec<[NoteSwitcherItem; 5]>>,
+    pub tutorial: Option<&'a TutorialState>,
     pub is_window_pinned: bool,
+    /// the selected note is rendered as read only markdown
+    pub is_reading_mode: bool,
+    /// the selected note can't be edited until unlocked, shown in the header
+    pub is_locked: bool,
+    /// markdown markers are shown only on the line of the cursor
+    pub live_preview: bool,
+    /// task markers are drawn as checkboxes
+    pub checkbox_tasks: bool,
+    /// toggled per note, long lines overflow and the note scrolls horizontally when off
+    pub soft_wrap: bool,
+    /// otherwise long code lines overflow and the note scrolls horizontally
+    pub wrap_code_blocks: bool,
+    /// the last line can be scrolled up to the middle of the viewport
+    pub scroll_past_end: bool,
+    /// bare urls are styled and clickable
+    pub auto_links: bool,
+    /// the note is above `largeNoteKb`, rendered without code highlighting and bare urls
+    pub is_large_note: bool,
+    /// continuation of the note suggested by the LLM, shown as ghost text after the cursor
+    pub inline_suggestion: Option<&'a str>,
+    /// the rendered markdown is shown in a panel next to the editor
+    pub show_preview_panel: bool,
+    pub preview_layout: Option<ComputedLayout>,
+    pub close_button: CloseButtonBehavior,
+    pub crash_reporting: bool,
+    pub start_at_login: bool,
+    /// asks once whether Shelv should start at login
+    pub show_start_at_login_prompt: bool,
     pub render_actions: SmallVec<[RenderAction; 2]>,
     pub feedback: Option<&'a mut FeedbackState>,
     pub frame_hotkeys: &'a mut FrameHotkeys,
     pub version_state: &'a VersionState,
+    pub persistence_warning: Option<&'a PersistenceWarning>,
+    pub global_hotkey_warning: Option<&'a GlobalHotkeyWarning>,
+    pub show_cursor_context: bool,
+    /// title of the note that is about to be cleared
+    pub clear_note_title: Option<String>,
+    /// elapsed time and context switches of the focus session, shown in the footer
+    pub focus_session_label: Option<String>,
 }
 
 pub struct RenderAppResult {
@@ -73,6 +156,7 @@ pub struct RenderAppResult {
     pub updated_text_structure: TextStructure,
     pub latest_cursor: Option<UnOrderedByteSpan>,
     pub latest_layout: Option<ComputedLayout>,
+    pub latest_preview_layout: Option<ComputedLayout>,
     pub text_changed: bool,
 }
 
@@ -90,21 +174,68 @@ pub fn render_app(
         byte_cursor,
         command_list,
         computed_layout,
+        highlight_cache,
         syntax_set,
         theme_set,
         is_window_pinned,
+        is_reading_mode,
+        is_locked,
+        live_preview,
+        checkbox_tasks,
+        soft_wrap,
+        wrap_code_blocks,
+        scroll_past_end,
+        auto_links,
+        is_large_note,
+        inline_suggestion,
+        show_preview_panel,
+        preview_layout,
+        close_button,
+        crash_reporting,
+        start_at_login,
+        show_start_at_login_prompt,
         inline_llm_prompt,
         slash_palette,
+        table_picker,
+        multi_cursor_selections,
+        rename_prompt,
+        find_replace,
+        tag_filter,
+        pinned_snippet,
+        recovery_bin,
+        note_switcher_items,
+        tutorial,
         mut render_actions,
         feedback,
         frame_hotkeys,
         code_block_annotations,
+        link_markers,
         version_state,
+        persistence_warning,
+        global_hotkey_warning,
+        show_cursor_context,
+        clear_note_title,
+        focus_session_label,
     } = visual_state;
 
     let mut output_actions: SmallVec<[AppAction; 4]> = Default::default();
 
-    let footer_actions = render_footer_panel(selected_note, note_count, command_list, ctx, &theme);
+    let cursor_context = byte_cursor
+        .filter(|_| show_cursor_context)
+        .map(|cursor| cursor_context_label(&text_structure, cursor.ordered()));
+
+    let word_goal = WordGoalProgress::of_note(editor_text, &text_structure);
+
+    let footer_actions = render_footer_panel(
+        selected_note,
+        note_count,
+        command_list,
+        cursor_context,
+        word_goal,
+        focus_session_label,
+        ctx,
+        &theme,
+    );
     output_actions.extend(footer_actions);
 
     let header_actions = render_header_panel(
@@ -112,12 +243,29 @@ pub fn render_app(
         theme,
         command_list,
         selected_note,
+        is_locked,
         is_window_pinned,
+        close_button,
+        crash_reporting,
+        start_at_login,
         feedback.as_ref().map(|f| f.is_sent).unwrap_or(false),
         version_state,
+        persistence_warning,
+        global_hotkey_warning,
+        is_large_note,
     );
     output_actions.extend(header_actions);
 
+    if let Some(find_replace) = find_replace.filter(|f| f.note_file == selected_note) {
+        output_actions.extend(render_find_replace_panel(
+            find_replace,
+            editor_text,
+            theme,
+            frame_hotkeys,
+            ctx,
+        ));
+    }
+
     restore_cursor_from_note_state(&editor_text, byte_cursor, ctx, text_edit_id);
 
     if let Some(feedback) = feedback {
@@ -172,18 +320,57 @@ pub fn render_app(
         //     });
     }
 
+    if let Some(title) = clear_note_title {
+        output_actions.extend(render_clear_note_modal(&title, theme, frame_hotkeys, ctx));
+    }
+
+    if show_start_at_login_prompt {
+        output_actions.extend(render_start_at_login_modal(theme, frame_hotkeys, ctx));
+    }
+
+    if let Some(recovery_bin) = recovery_bin {
+        output_actions.extend(render_recovery_bin_modal(
+            recovery_bin,
+            theme,
+            frame_hotkeys,
+            ctx,
+        ));
+    }
+
+    // laid out from the structure of the previous frame, edits show up on the next one
+    let preview_layout = match show_preview_panel {
+        true => Some(render_preview_panel(
+            &text_structure,
+            editor_text,
+            preview_layout,
+            auto_links,
+            is_large_note,
+            theme,
+            syntax_set,
+            theme_set,
+            highlight_cache,
+            ctx,
+        )),
+        false => None,
+    };
+
     let (text_has_changed, text_structure, computed_layout, updated_cursor, editor_actions) =
         egui::CentralPanel::default()
             .frame(Frame::central_panel(&ctx.style()).inner_margin(Margin::ZERO))
             .show(ctx, |ui| {
                 {
                     let avail_space = ui.available_rect_before_wrap();
+                    TutorialTarget::Editor.register_rect(ctx, avail_space);
+                    // registered again by the palette if it is still opened
+                    TutorialTarget::SlashPalette.clear_rect(ctx);
+                    note_snapshot::register_editor_rect(ctx, avail_space);
 
                     let hints: Option<SmallVec<[(CowStr<'static>, KeyboardShortcut); 8]>> =
                         editor_text.is_empty().then(|| {
-                            PROMOTED_COMMANDS
-                                .into_iter()
-                                .filter_map(|builtin| command_list.find(builtin))
+                            command_list
+                                .promoted_commands()
+                                .iter()
+                                .filter_map(|promoted| command_list.find(promoted.clone()))
                                 .filter_map(|cmd| {
                                     Some(cmd.instruction.human_description()).zip(cmd.shortcut)
                                 })
@@ -199,7 +386,8 @@ pub fn render_app(
                     );
                 }
 
-                egui::ScrollArea::vertical()
+                // horizontal scrolling is only needed for unwrapped code blocks
+                let scroll_output = egui::ScrollArea::new([!wrap_code_blocks || !soft_wrap, true])
                     .id_salt(text_edit_id)
                     .show(ui, |ui| {
                         ui.spacing_mut().item_spacing = vec2(0.0, 0.0);
@@ -215,25 +403,51 @@ pub fn render_app(
                             editor_text,
                             text_structure,
                             computed_layout,
-                            inline_llm_prompt,
-                            slash_palette,
-                            &mut render_actions,
+                            highlight_cache,
+                            EditorOverlays {
+                                inline_llm_prompt,
+                                slash_palette,
+                                table_picker,
+                                multi_cursor_selections,
+                                rename_prompt,
+                                render_actions: Some(&mut render_actions),
+                            },
+                            EditorRenderOptions {
+                                note_file: selected_note,
+                                is_reading_mode,
+                                live_preview_cursor: byte_cursor
+                                    .filter(|_| live_preview)
+                                    .map(|cursor| cursor.ordered()),
+                                checkbox_tasks,
+                                soft_wrap,
+                                wrap_code_blocks,
+                                auto_links,
+                                is_large_note,
+                                inline_suggestion,
+                                code_block_annotations,
+                                link_markers,
+                            },
                             theme,
                             syntax_set,
                             theme_set,
                             text_edit_id,
-                            selected_note,
                             command_list,
                             frame_hotkeys,
-                            code_block_annotations,
                             ctx,
                         );
 
-                        if changed {
+                        // large notes are evaluated once the typing pauses, see `LARGE_NOTE_EVAL_DELAY`
+                        if changed && !is_large_note {
                             output_actions.push(AppAction::EvalNote(selected_note))
                         }
 
-                        let space_below = ui.available_rect_before_wrap();
+                        let mut space_below = ui.available_rect_before_wrap();
+
+                        if scroll_past_end {
+                            let viewport_height = ui.clip_rect().height();
+                            space_below.set_height(space_below.height().max(viewport_height / 2.0));
+                            ui.allocate_rect(space_below, Sense::hover());
+                        }
 
                         // ---- CLICKING ON EMPTY AREA FOCUSES ON TEXT EDIT ----
                         // TODO migrate to use app actions
@@ -259,8 +473,22 @@ pub fn render_app(
                                 .text()
                                 .byte_index_from_char_index(cursor.ccursor.index);
 
-                            if let Some(interactive) =
-                                text_structure.find_interactive_text_part(byte_cursor)
+                            // task markers drawn as checkboxes handle clicks themselves
+                            let is_drawn_as_checkbox = |part: &InteractiveTextPart| match part {
+                                InteractiveTextPart::TaskMarker { byte_range, .. } => {
+                                    checkbox_tasks
+                                        && layout
+                                            .task_areas
+                                            .iter()
+                                            .any(|area| area.byte_range == *byte_range)
+                                }
+                                InteractiveTextPart::Link(_)
+                                | InteractiveTextPart::DetailsSummary(_) => false,
+                            };
+
+                            if let Some(interactive) = text_structure
+                                .find_interactive_text_part(byte_cursor, auto_links)
+                                .filter(|part| !is_drawn_as_checkbox(part))
                             {
                                 // if ui.input(|i| i.modifiers.command)
                                 {
@@ -269,14 +497,13 @@ pub fn render_app(
                                         match interactive {
                                             InteractiveTextPart::TaskMarker {
                                                 byte_range,
-                                                checked,
+                                                state,
                                             } => {
                                                 output_actions.push(AppAction::apply_text_changes(
                                                     selected_note,
                                                     [TextChange::Insert(
                                                         byte_range,
-                                                        (if checked { "[ ]" } else { "[x]" })
-                                                            .to_string(),
+                                                        state.next().marker().to_string(),
                                                     )]
                                                     .into(),
                                                 ));
@@ -324,6 +551,22 @@ pub fn render_app(
                                                 };
                                                 output_actions.push(action)
                                             }
+                                            InteractiveTextPart::DetailsSummary(details) => {
+                                                let open_tag = details.open_tag;
+                                                output_actions.push(AppAction::apply_text_changes(
+                                                    selected_note,
+                                                    [TextChange::Insert(
+                                                        open_tag,
+                                                        toggle_details_open_attribute(
+                                                            &editor_text[open_tag.range()],
+                                                        ),
+                                                    )]
+                                                    .into(),
+                                                ));
+
+                                                text_structure =
+                                                    text_structure.recycle(&editor_text);
+                                            }
                                         }
                                     }
                                 }
@@ -337,37 +580,313 @@ pub fn render_app(
                             updated_cursor,
                             editor_actions,
                         )
-                    })
-                    .inner
+                    });
+
+                // the preview panel follows the scroll position of the editor
+                let max_offset = scroll_output.content_size.y - scroll_output.inner_rect.height();
+                let fraction = match max_offset > 0.0 {
+                    true => scroll_output.state.offset.y / max_offset,
+                    false => 0.0,
+                };
+                ctx.data_mut(|d| d.insert_temp(editor_scroll_fraction_id(), fraction));
+
+                scroll_output.inner
             })
             .inner;
 
     output_actions.extend(editor_actions);
+
+    if let Some(tutorial) = tutorial {
+        output_actions.extend(render_tutorial_callout(tutorial, theme, ctx));
+    }
+
+    if let Some(items) = note_switcher_items {
+        render_note_switcher(&items, theme, ctx);
+    }
+
+    if let Some(tag_filter) = tag_filter {
+        output_actions.extend(render_tag_filter(tag_filter, theme, frame_hotkeys, ctx));
+    }
+
+    if let Some(snippet) = pinned_snippet {
+        output_actions.extend(render_pinned_snippet(snippet, theme, ctx));
+    }
+
+    // the titlebar is hidden on macOS, which leaves (almost) no native resize edges
+    if cfg!(target_os = "macos") {
+        render_resize_handles(theme, ctx);
+    }
+
     RenderAppResult {
         requested_actions: output_actions,
         updated_text_structure: text_structure,
         latest_cursor: updated_cursor,
         latest_layout: computed_layout,
+        latest_preview_layout: preview_layout,
         text_changed: text_has_changed,
     }
 }
 
+pub struct PoppedOutRenderData<'a> {
+    pub note_file: NoteFile,
+    pub title: String,
+    pub code_block_annotations: &'a [(SpanIndex, CodeBlockAnnotation)],
+    pub link_markers: &'a [LinkMarker],
+    pub command_list: &'a CommandList,
+    pub syntax_set: &'a SyntaxSet,
+    pub theme_set: &'a ThemeSet,
+    pub computed_layout: Option<ComputedLayout>,
+    pub highlight_cache: &'a mut CodeHighlightCache,
+    pub is_reading_mode: bool,
+    pub checkbox_tasks: bool,
+    pub soft_wrap: bool,
+    pub wrap_code_blocks: bool,
+    pub auto_links: bool,
+    pub is_large_note: bool,
+}
+
+/// The note in its own window, just the editor without the header and the footer.
+/// The text is shared with the main window, so edits show up in both
+pub fn render_popped_out_note(
+    text_structure: TextStructure,
+    editor_text: &mut String,
+    render_data: PoppedOutRenderData,
+    theme: &AppTheme,
+    ctx: &egui::Context,
+) -> RenderAppResult {
+    let PoppedOutRenderData {
+        note_file,
+        title,
+        code_block_annotations,
+        link_markers,
+        command_list,
+        syntax_set,
+        theme_set,
+        computed_layout,
+        highlight_cache,
+        is_reading_mode,
+        checkbox_tasks,
+        soft_wrap,
+        wrap_code_blocks,
+        auto_links,
+        is_large_note,
+    } = render_data;
+
+    let viewport = egui::ViewportBuilder::default()
+        .with_title(title)
+        .with_resizable(true)
+        .with_always_on_top()
+        .with_min_inner_size(vec2(250.0, 200.0))
+        .with_inner_size(vec2(350.0, 450.0));
+
+    // the callback of an immediate viewport is called once, right away
+    let mut inputs = Some((text_structure, computed_layout));
+
+    ctx.show_viewport_immediate(
+        egui::ViewportId::from_hash_of(("popped_out_note", note_file)),
+        viewport,
+        |ctx, _| {
+            let (text_structure, computed_layout) = inputs.take().unwrap();
+            let mut output_actions: SmallVec<[AppAction; 4]> = Default::default();
+
+            if ctx.input(|i| i.viewport().close_requested()) {
+                output_actions.push(AppAction::ClosePoppedOutNote(note_file));
+            }
+
+            let text_edit_id = Id::new(("popped_out_text_edit", note_file));
+            // shortcuts are handled only in the main window, so the ones of code blocks are dropped
+            let mut frame_hotkeys = FrameHotkeys::default();
+
+            let (changed, computed_layout, text_structure, editor_actions) =
+                egui::CentralPanel::default()
+                    .frame(Frame::central_panel(&ctx.style()).inner_margin(Margin::ZERO))
+                    .show(ctx, |ui| {
+                        egui::ScrollArea::new([!wrap_code_blocks || !soft_wrap, true])
+                            .id_salt(text_edit_id)
+                            .show(ui, |ui| {
+                                ui.spacing_mut().item_spacing = vec2(0.0, 0.0);
+                                let (changed, layout, text_structure, _, _, editor_actions) =
+                                    render_editor(
+                                        ui,
+                                        editor_text,
+                                        text_structure,
+                                        computed_layout,
+                                        highlight_cache,
+                                        EditorOverlays::default(),
+                                        EditorRenderOptions {
+                                            note_file,
+                                            is_reading_mode,
+                                            live_preview_cursor: None,
+                                            checkbox_tasks,
+                                            soft_wrap,
+                                            wrap_code_blocks,
+                                            auto_links,
+                                            is_large_note,
+                                            inline_suggestion: None,
+                                            code_block_annotations,
+                                            link_markers,
+                                        },
+                                        theme,
+                                        syntax_set,
+                                        theme_set,
+                                        text_edit_id,
+                                        command_list,
+                                        &mut frame_hotkeys,
+                                        ctx,
+                                    );
+
+                                (changed, layout, text_structure, editor_actions)
+                            })
+                            .inner
+                    })
+                    .inner;
+
+            if changed && !is_large_note {
+                output_actions.push(AppAction::EvalNote(note_file));
+            }
+            output_actions.extend(editor_actions);
+
+            RenderAppResult {
+                requested_actions: output_actions,
+                updated_text_structure: text_structure,
+                latest_cursor: None,
+                latest_layout: computed_layout,
+                latest_preview_layout: None,
+                text_changed: changed,
+            }
+        },
+    )
+}
+
+fn editor_scroll_fraction_id() -> Id {
+    Id::new("editor_scroll_fraction")
+}
+
+/// Read only rendered markdown of the note, scrolled along with the editor
+fn render_preview_panel(
+    text_structure: &TextStructure,
+    text: &str,
+    preview_layout: Option<ComputedLayout>,
+    auto_links: bool,
+    is_large_note: bool,
+    theme: &AppTheme,
+    syntax_set: &SyntaxSet,
+    theme_set: &ThemeSet,
+    highlight_cache: &mut CodeHighlightCache,
+    ctx: &Context,
+) -> ComputedLayout {
+    egui::SidePanel::right("preview_panel")
+        .frame(Frame::central_panel(&ctx.style()).inner_margin(Margin::ZERO))
+        .default_width(ctx.available_rect().width() / 2.0)
+        .show(ctx, |ui| {
+            let margin = vec2(theme.sizes.l, theme.sizes.l);
+
+            let layout_params = LayoutParams::new(
+                text,
+                ui.available_width() - 2.0 * margin.x,
+                ctx.pixels_per_point(),
+                MarkerVisibility::Hidden,
+                true,
+                true,
+                auto_links,
+                is_large_note,
+                None,
+            );
+
+            let layout = match preview_layout {
+                Some(layout) if !layout.should_recompute(&layout_params) => layout,
+                _ => ComputedLayout::compute(
+                    text_structure,
+                    &layout_params,
+                    ui,
+                    theme,
+                    syntax_set,
+                    theme_set,
+                    highlight_cache,
+                ),
+            };
+
+            let content_size = layout.galley.size() + 2.0 * margin;
+
+            // follow the editor only when it was scrolled, so the preview can be scrolled on its own
+            let synced_fraction_id = Id::new("preview_synced_scroll_fraction");
+            let editor_fraction = ctx.data(|d| d.get_temp::<f32>(editor_scroll_fraction_id()));
+            let synced_fraction = ctx.data(|d| d.get_temp::<f32>(synced_fraction_id));
+
+            let mut scroll_area = ScrollArea::vertical().id_salt("preview_panel_scroll");
+            if let Some(fraction) = editor_fraction.filter(|f| Some(*f) != synced_fraction) {
+                let max_offset = (content_size.y - ui.available_height()).max(0.0);
+                scroll_area = scroll_area.vertical_scroll_offset(fraction * max_offset);
+                ctx.data_mut(|d| d.insert_temp(synced_fraction_id, fraction));
+            }
+
+            scroll_area.show(ui, |ui| {
+                let (rect, _) = ui.allocate_exact_size(content_size, Sense::hover());
+                let text_pos = rect.min + margin;
+
+                paint_callouts(ui, &layout.callout_areas, text_pos, theme);
+
+                let code_bg_rounding = ui.visuals().widgets.inactive.corner_radius;
+                for area in layout.code_areas.iter() {
+                    ui.painter().rect_filled(
+                        area.rect.expand(1.).translate(text_pos.to_vec2()),
+                        code_bg_rounding,
+                        code_block_bg_color(&area.lang, theme),
+                    );
+                }
+
+                ui.painter().galley(
+                    text_pos,
+                    layout.galley.clone(),
+                    theme.colors.normal_text_color,
+                );
+            });
+
+            layout
+        })
+        .inner
+}
+
+/// How a note is rendered by `render_editor`, both in the main and the popped out windows
+struct EditorRenderOptions<'a> {
+    note_file: NoteFile,
+    is_reading_mode: bool,
+    live_preview_cursor: Option<ByteSpan>,
+    checkbox_tasks: bool,
+    soft_wrap: bool,
+    wrap_code_blocks: bool,
+    auto_links: bool,
+    is_large_note: bool,
+    inline_suggestion: Option<&'a str>,
+    code_block_annotations: &'a [(SpanIndex, CodeBlockAnnotation)],
+    link_markers: &'a [LinkMarker],
+}
+
+/// Prompts and popups on top of the editor, only the main window has them
+#[derive(Default)]
+struct EditorOverlays<'a> {
+    inline_llm_prompt: Option<&'a mut InlineLLMPromptState>,
+    slash_palette: Option<&'a SlashPalette>,
+    table_picker: Option<&'a TablePicker>,
+    multi_cursor_selections: &'a [ByteSpan],
+    rename_prompt: Option<&'a mut RenamePrompt>,
+    render_actions: Option<&'a mut SmallVec<[RenderAction; 2]>>,
+}
+
 fn render_editor(
     ui: &mut Ui,
     editor_text: &mut String,
     text_structure: TextStructure,
     mut computed_layout: Option<ComputedLayout>,
-    inline_llm_prompt: Option<&mut InlineLLMPromptState>,
-    slash_palette: Option<&SlashPalette>,
-    render_actions: &mut SmallVec<[RenderAction; 2]>,
+    highlight_cache: &mut CodeHighlightCache,
+    overlays: EditorOverlays,
+    options: EditorRenderOptions,
     theme: &AppTheme,
     syntax_set: &SyntaxSet,
     theme_set: &ThemeSet,
     text_edit_id: Id,
-    note_file: NoteFile,
     command_list: &CommandList,
     frame_hotkeys: &mut FrameHotkeys,
-    code_block_annotations: &[(SpanIndex, CodeBlockAnnotation)],
     ctx: &egui::Context,
 ) -> (
     bool, // if the text was changed, TODO rework this mess
@@ -377,9 +896,42 @@ fn render_editor(
     egui::Pos2,
     SmallVec<[AppAction; 1]>,
 ) {
+    let EditorOverlays {
+        inline_llm_prompt,
+        slash_palette,
+        table_picker,
+        multi_cursor_selections,
+        rename_prompt,
+        render_actions,
+    } = overlays;
+
+    let EditorRenderOptions {
+        note_file,
+        is_reading_mode,
+        live_preview_cursor,
+        checkbox_tasks,
+        soft_wrap,
+        wrap_code_blocks,
+        auto_links,
+        is_large_note,
+        inline_suggestion,
+        code_block_annotations,
+        link_markers,
+    } = options;
+
     let mut resulting_actions: SmallVec<[AppAction; 1]> = SmallVec::new();
     let mut structure_wrapper = Some(text_structure);
 
+    // registered before the commands, so Tab accepts the suggestion instead of indenting
+    if inline_suggestion.is_some() && !is_reading_mode {
+        frame_hotkeys.add_key(Key::Tab, |_ctx| {
+            [AppAction::AcceptInlineSuggestion { accept: true }].into()
+        });
+        frame_hotkeys.add_key(Key::Escape, |_ctx| {
+            [AppAction::AcceptInlineSuggestion { accept: false }].into()
+        });
+    }
+
     let text_edit_margin = Margin {
         left: (theme.sizes.l) as i8,
         right: (theme.sizes.l) as i8,
@@ -389,9 +941,15 @@ fn render_editor(
 
     let estimated_text_pos = ui.next_widget_position() + text_edit_margin.left_top();
 
-    let code_bg = ui.visuals().code_bg_color;
     let code_bg_rounding = ui.visuals().widgets.inactive.corner_radius;
     if let Some(computed_layout) = &computed_layout {
+        paint_callouts(
+            ui,
+            &computed_layout.callout_areas,
+            estimated_text_pos,
+            theme,
+        );
+
         for area in computed_layout.code_areas.iter() {
             let background_rect = area
                 .rect
@@ -399,19 +957,53 @@ fn render_editor(
                 .expand(1.)
                 .translate(estimated_text_pos.to_vec2());
 
-            ui.painter()
-                .rect_filled(background_rect, code_bg_rounding, code_bg);
+            ui.painter().rect_filled(
+                background_rect,
+                code_bg_rounding,
+                code_block_bg_color(&area.lang, theme),
+            );
+        }
+
+        // painted under the text, the same way as the primary selection of the text edit
+        for selection in multi_cursor_selections {
+            paint_extra_selection(
+                ui,
+                editor_text,
+                &computed_layout.galley,
+                *selection,
+                estimated_text_pos,
+            );
         }
     }
 
     let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
-        let layout_cache_params = LayoutParams::new(text, wrap_width, ctx.pixels_per_point());
+        let marker_visibility = match (is_reading_mode, live_preview_cursor) {
+            (true, _) => MarkerVisibility::Hidden,
+            (false, Some(cursor)) => MarkerVisibility::around_cursor(text, cursor),
+            (false, None) => MarkerVisibility::Shown,
+        };
+
+        let layout_cache_params = LayoutParams::new(
+            text,
+            wrap_width,
+            ctx.pixels_per_point(),
+            marker_visibility,
+            soft_wrap,
+            wrap_code_blocks,
+            auto_links,
+            is_large_note,
+            inline_suggestion,
+        );
 
         let layout = match computed_layout.take() {
             Some(layout) if !layout.should_recompute(&layout_cache_params) => layout,
 
             _ => {
-                let structure = structure_wrapper.take().unwrap().recycle(text);
+                let structure = structure_wrapper.take().unwrap();
+                let structure = match is_large_note {
+                    true => structure.recycle_without_raw_links(text),
+                    false => structure.recycle(text),
+                };
 
                 // println!("### updated structure w={avail_w} {layout_cache_params:#?}");
                 //println!("### rerender with {text}");
@@ -422,6 +1014,7 @@ fn render_editor(
                     theme,
                     syntax_set,
                     theme_set,
+                    highlight_cache,
                 );
 
                 structure_wrapper = Some(structure);
@@ -446,17 +1039,26 @@ fn render_editor(
         .code_editor()
         .id(text_edit_id)
         .lock_focus(true)
+        .interactive(!is_reading_mode)
         .desired_width(f32::INFINITY)
         .frame(false)
         .margin(text_edit_margin)
         .layouter(&mut layouter)
         .show(ui);
 
-    let prev_actions_count = render_actions.len();
-    render_actions.retain(|action| !matches!(action, RenderAction::ScrollToEditorCursorPos));
+    let should_scroll_to_cursor = match render_actions {
+        Some(render_actions) => {
+            let prev_actions_count = render_actions.len();
+            render_actions
+                .retain(|action| !matches!(action, RenderAction::ScrollToEditorCursorPos));
+
+            // that verifies that we indeed removed some actions, that is, there was at least one scroll action
+            prev_actions_count != render_actions.len()
+        }
+        None => false,
+    };
 
-    // that verifies that we indeed removed some actions, that is, there was at least one scroll action
-    if let (Some(cursor_range), true) = (cursor_range, prev_actions_count != render_actions.len()) {
+    if let (Some(cursor_range), true) = (cursor_range, should_scroll_to_cursor) {
         let font_id = FontSelection::Style(TextStyle::Monospace).resolve(ui.style());
         let row_height = ui.fonts(|f| f.row_height(&font_id));
         let primary_cursor_pos = cursor_rect(&galley, &cursor_range.primary, row_height);
@@ -471,9 +1073,15 @@ fn render_editor(
     let text_structure = structure_wrapper.unwrap();
 
     // ------- FLOATING BUTTONS -------
-    if let Some(computed_layout) = &computed_layout {
+    if let Some(computed_layout) = computed_layout.as_ref().filter(|_| !is_reading_mode) {
         for area in computed_layout.code_areas.iter() {
-            let code_area = area.rect.translate(estimated_text_pos.to_vec2());
+            // buttons stay within the note width, even if the code overflows it
+            let mut code_area = area.rect.translate(estimated_text_pos.to_vec2());
+            code_area.set_right(
+                code_area
+                    .right()
+                    .min(estimated_text_pos.x + computed_layout.wrap_width),
+            );
 
             // that mambo jambo check if the cursor is inside the code area of interest, if yes:
             //   shortcuts become avalable, such as copy block or run the bloc etc
@@ -491,6 +1099,7 @@ fn render_editor(
                 ui,
                 theme,
                 code_area,
+                &area.lang,
                 code_block_annotations
                     .iter()
                     .find(|(idx, _)| *idx == area.code_block_span_index)
@@ -504,7 +1113,30 @@ fn render_editor(
         }
     }
 
-    let overlay_layer_width = galley.job.wrap.max_width - 2. * estimated_text_pos.x;
+    // ------- TASK CHECKBOXES -------
+    if let Some(computed_layout) = computed_layout.as_ref().filter(|_| checkbox_tasks) {
+        resulting_actions.extend(render_task_checkboxes(
+            ui,
+            computed_layout,
+            galley_pos,
+            note_file,
+            theme,
+        ));
+    }
+
+    // ------- MISSING FILES AND REMOTE IMAGES -------
+    if let Some(computed_layout) = computed_layout
+        .as_ref()
+        .filter(|_| !link_markers.is_empty())
+    {
+        render_link_markers(ui, computed_layout, galley_pos, link_markers, theme);
+    }
+
+    // the galley can be unwrapped, overlays stay within the note width anyway
+    let note_width = computed_layout
+        .as_ref()
+        .map_or(galley.job.wrap.max_width, |layout| layout.wrap_width);
+    let overlay_layer_width = note_width - 2. * estimated_text_pos.x;
 
     // ------- LLM PROMPT -------
     match inline_llm_prompt {
@@ -570,6 +1202,8 @@ fn render_editor(
         let (palette_rect, palette_actions) =
             render_slash_palette(palette, frame_rect, theme, frame_hotkeys, ui);
 
+        TutorialTarget::SlashPalette.register_rect(ui.ctx(), palette_rect);
+
         // TODO hack, only scroll to it on post render
         // Possibly makt it an app action maybe?
         if palette.update_count == 1 {
@@ -588,6 +1222,61 @@ fn render_editor(
         resulting_actions.extend(palette_actions);
     }
 
+    // ------- TABLE PICKER -------
+    if let Some(picker) = table_picker.filter(|p| p.note_file == note_file) {
+        let char_pos = char_index_from_byte_index(editor_text, picker.byte_pos);
+        let relative_pos = galley.pos_from_ccursor(CCursor::new(char_pos));
+
+        let frame_width = theme.sizes.menu_width;
+
+        let frame_right =
+            estimated_text_pos.x + (overlay_layer_width).min(relative_pos.left() + frame_width);
+
+        let frame_rect = Rect::from_min_size(
+            pos2(
+                frame_right - frame_width,
+                estimated_text_pos.y + relative_pos.bottom(),
+            ),
+            vec2(frame_width, theme.sizes.menu_height),
+        );
+
+        let (picker_rect, picker_actions) =
+            render_table_picker(picker, frame_rect, theme, frame_hotkeys, ui);
+
+        let delta = picker_rect.bottom() - text_edit_response.rect.bottom();
+        if delta > 0. {
+            ui.add_space(delta + theme.sizes.s);
+        }
+
+        resulting_actions.extend(picker_actions);
+    }
+
+    // ------- RENAME PROMPT -------
+    if let Some(prompt) = rename_prompt.filter(|p| p.note_file == note_file) {
+        let char_pos = char_index_from_byte_index(editor_text, prompt.byte_pos);
+        let relative_pos = galley.pos_from_ccursor(CCursor::new(char_pos));
+
+        let frame_width = theme.sizes.menu_width;
+
+        let frame_right =
+            estimated_text_pos.x + (overlay_layer_width).min(relative_pos.left() + frame_width);
+
+        let frame_rect = Rect::from_min_size(
+            pos2(
+                frame_right - frame_width,
+                estimated_text_pos.y + relative_pos.bottom(),
+            ),
+            vec2(frame_width, theme.sizes.menu_height),
+        );
+
+        let prompt_rect = render_rename_prompt(prompt, frame_rect, theme, frame_hotkeys, ui);
+
+        let delta = prompt_rect.bottom() - text_edit_response.rect.bottom();
+        if delta > 0. {
+            ui.add_space(delta + theme.sizes.s);
+        }
+    }
+
     use egui::TextBuffer;
 
     let byte_cursor = cursor_range.map(|range| {
@@ -597,6 +1286,16 @@ fn render_editor(
         UnOrderedByteSpan::new(start, end)
     });
 
+    // the layout was computed with the cursor of the previous frame,
+    // so if the cursor moved to another line markers need to be shown there instead
+    if let (Some(prev_cursor), Some(cursor)) = (live_preview_cursor, byte_cursor) {
+        if MarkerVisibility::around_cursor(editor_text, prev_cursor)
+            != MarkerVisibility::around_cursor(editor_text, cursor.ordered())
+        {
+            ctx.request_repaint();
+        }
+    }
+
     (
         text_edit_response.changed(),
         computed_layout,
@@ -607,6 +1306,51 @@ fn render_editor(
     )
 }
 
+fn render_rename_prompt(
+    prompt: &mut RenamePrompt,
+    allocated_frame: Rect,
+    theme: &AppTheme,
+    frame_hotkeys: &mut FrameHotkeys,
+    ui: &mut Ui,
+) -> Rect {
+    frame_hotkeys.add_key(Key::Enter, |_ctx| {
+        [AppAction::RenamePrompt(RenamePromptAction::Confirm)].into()
+    });
+    frame_hotkeys.add_key(Key::Escape, |_ctx| {
+        [AppAction::RenamePrompt(RenamePromptAction::Hide)].into()
+    });
+
+    let mut prompt_ui = ui.new_child(
+        UiBuilder::new()
+            .max_rect(allocated_frame.translate(vec2(0., theme.sizes.xs)))
+            .id_salt("rename_prompt_ui")
+            .layout(Layout::top_down(Align::LEFT))
+            .ui_stack_info(UiStackInfo::new(egui::UiKind::GenericArea)),
+    );
+
+    egui::Frame::none()
+        .fill(theme.colors.code_bg_color)
+        .inner_margin(theme.sizes.s)
+        .stroke(prompt_ui.visuals().window_stroke)
+        .shadow(prompt_ui.visuals().window_shadow)
+        .corner_radius(prompt_ui.visuals().window_corner_radius)
+        .show(&mut prompt_ui, |ui| {
+            ui.label(
+                RichText::new(format!("Rename \"{}\" in this block", prompt.token))
+                    .color(theme.colors.subtle_text_color),
+            );
+
+            ui.add(
+                TextEdit::singleline(&mut prompt.new_name)
+                    .id(rename_prompt_input_id())
+                    .font(TextStyle::Monospace)
+                    .desired_width(f32::INFINITY),
+            );
+        })
+        .response
+        .rect
+}
+
 fn render_inline_prompt(
     inline_llm_prompt: &mut InlineLLMPromptState,
     editor_text: &str,
@@ -775,7 +1519,11 @@ fn render_inline_prompt(
                 }
 
                 InlinePromptStatus::Streaming { .. } => {
-                    ui.spinner();
+                    AppIcon::render_spinner(
+                        ui,
+                        theme.fonts.size.normal,
+                        theme.colors.normal_text_color,
+                    );
                 }
 
                 InlinePromptStatus::Done { prompt } => {
@@ -943,6 +1691,24 @@ fn render_slash_palette(
                         ) {
                             match item {
                                 Some((i, cmd)) => {
+                                    if slash_palette.most_used_count > 0 {
+                                        let section = match i {
+                                            0 => Some("Frequently used"),
+                                            i if i == slash_palette.most_used_count => {
+                                                Some("All commands")
+                                            }
+                                            _ => None,
+                                        };
+
+                                        if let Some(section) = section {
+                                            ui.label(
+                                                RichText::new(section)
+                                                    .small()
+                                                    .color(theme.colors.subtle_text_color),
+                                            );
+                                        }
+                                    }
+
                                     let selected = i == slash_palette.selected;
                                     let resp = render_slash_cmd(ui, theme, cmd, selected)
                                         .interact(Sense::CLICK)
@@ -1009,39 +1775,665 @@ fn render_slash_palette(
     (frame_resp.rect, resulting_actions)
 }
 
-fn render_code_actions(
-    ui: &mut Ui,
+fn render_table_picker(
+    table_picker: &TablePicker,
+    allocated_frame: Rect,
     theme: &AppTheme,
-    code_area: Rect,
-    annotation: Option<&CodeBlockAnnotation>,
-    span_index: SpanIndex,
-    note_file: NoteFile,
     frame_hotkeys: &mut FrameHotkeys,
-    is_cursor_inside: bool,
-) -> EditorCommandOutput {
-    let id = ui.id().with("code_annotations").with(span_index);
-    let is_hovered = ui.rect_contains_pointer(code_area);
-    let alpha = ui.ctx().animate_bool_with_time_and_easing(
-        id,
-        is_hovered,
-        0.2,
-        emath::easing::cubic_in_out,
-    );
+    ui: &mut Ui,
+) -> (Rect, SmallVec<[AppAction; 1]>) {
+    let mut resulting_actions = SmallVec::new();
 
-    let monospace = &theme.fonts.family.code;
-    let mut resulting_actions: SmallVec<[AppAction; 1]> = SmallVec::new();
+    let &TablePicker { rows, cols, .. } = table_picker;
 
-    let buttons_anim_id = ui.id().with("buttons_overlay_anim").with(span_index);
-    let buttons_visible = ui
-        .ctx()
-        .animate_bool_with_time(buttons_anim_id, is_hovered, 0.20);
+    let resize = |rows: usize, cols: usize| {
+        SmallVec::from_buf([AppAction::TablePicker(TablePickerAction::Resize {
+            rows,
+            cols,
+        })])
+    };
 
-    // TODO: add a setting setup of these
-    let run_hotkey = KeyboardShortcut::new(Modifiers::MAC_CMD, Key::Enter);
-    // TODO figure out how to bind cmd + c derivitave in egui, it doesn't currently work :(
-    // let copy_hotkey = KeyboardShortcut::new(Modifiers::MAC_CMD.plus(Modifiers::SHIFT), Key::Copy);
+    frame_hotkeys.add_key(Key::ArrowRight, move |_ctx| resize(rows, cols + 1));
+    frame_hotkeys.add_key(Key::ArrowLeft, move |_ctx| {
+        resize(rows, cols.saturating_sub(1))
+    });
+    frame_hotkeys.add_key(Key::ArrowDown, move |_ctx| resize(rows + 1, cols));
+    frame_hotkeys.add_key(Key::ArrowUp, move |_ctx| {
+        resize(rows.saturating_sub(1), cols)
+    });
+    frame_hotkeys.add_key(Key::Enter, |_ctx| {
+        [AppAction::TablePicker(TablePickerAction::Confirm)].into()
+    });
+    frame_hotkeys.add_key(Key::Escape, |_ctx| {
+        [AppAction::TablePicker(TablePickerAction::Hide)].into()
+    });
 
-    // Collect dimensions for logging
+    let mut picker_ui = ui.new_child(
+        UiBuilder::new()
+            .max_rect(allocated_frame.translate(vec2(0., theme.sizes.xs)))
+            .id_salt("table_picker_ui")
+            .layout(Layout::top_down(Align::LEFT))
+            .ui_stack_info(UiStackInfo::new(egui::UiKind::GenericArea)),
+    );
+
+    let cell_size = vec2(theme.sizes.l, theme.sizes.l);
+
+    let frame_resp = egui::Frame::none()
+        .fill(theme.colors.code_bg_color)
+        .inner_margin(theme.sizes.s)
+        .stroke(picker_ui.visuals().window_stroke)
+        .shadow(picker_ui.visuals().window_shadow)
+        .corner_radius(picker_ui.visuals().window_corner_radius)
+        .show(&mut picker_ui, |ui| {
+            ui.label(
+                RichText::new(format!("{cols} × {rows}")).color(theme.colors.subtle_text_color),
+            );
+
+            ui.spacing_mut().item_spacing = vec2(theme.sizes.xs, theme.sizes.xs);
+
+            for row in 1..=TABLE_PICKER_MAX_ROWS {
+                ui.horizontal(|ui| {
+                    for col in 1..=TABLE_PICKER_MAX_COLS {
+                        let (rect, resp) = ui.allocate_exact_size(cell_size, Sense::click());
+                        let resp = resp.on_hover_cursor(CursorIcon::PointingHand);
+
+                        let is_selected = row <= rows && col <= cols;
+                        ui.painter().rect(
+                            rect,
+                            theme.colors.rounding_controls,
+                            if is_selected {
+                                theme.colors.selection_bg
+                            } else {
+                                theme.colors.faint_bg_color
+                            },
+                            Stroke::new(1., theme.colors.outline_fg),
+                            StrokeKind::Inside,
+                        );
+
+                        if resp.hovered() && (row, col) != (rows, cols) {
+                            resulting_actions.push(AppAction::TablePicker(
+                                TablePickerAction::Resize {
+                                    rows: row,
+                                    cols: col,
+                                },
+                            ));
+                        }
+
+                        if resp.clicked() {
+                            resulting_actions
+                                .push(AppAction::FocusRequest(FocusTarget::CurrentNote));
+                            // same as with the slash palette, it takes a few renders to restore the cursor
+                            resulting_actions.push(AppAction::defer(AppAction::defer(
+                                AppAction::TablePicker(TablePickerAction::Confirm),
+                            )));
+                        }
+                    }
+                });
+            }
+        })
+        .response;
+
+    (frame_resp.rect, resulting_actions)
+}
+
+fn render_tutorial_callout(
+    tutorial: &TutorialState,
+    theme: &AppTheme,
+    ctx: &Context,
+) -> SmallVec<[AppAction; 1]> {
+    let mut resulting_actions = SmallVec::new();
+
+    let Some(step) = tutorial.current_step() else {
+        return resulting_actions;
+    };
+
+    let screen_rect = ctx.screen_rect();
+    // rects are registered during rendering, so it might be missing on the first frame
+    let target_rect = step.target.rect_or_fallback(ctx).unwrap_or(screen_rect);
+
+    ctx.layer_painter(LayerId::new(
+        Order::Foreground,
+        Id::new("tutorial_highlight"),
+    ))
+    .rect_stroke(
+        target_rect.expand(theme.sizes.xs).intersect(screen_rect),
+        theme.colors.rounding_controls,
+        Stroke::new(2., theme.colors.hyperlink_color),
+        StrokeKind::Inside,
+    );
+
+    let gap = theme.sizes.s;
+    let (pivot, pos) = if target_rect.height() > screen_rect.height() / 2. {
+        // large targets (the editor) get the callout in the middle
+        (Align2::CENTER_CENTER, target_rect.center())
+    } else if target_rect.center().y < screen_rect.center().y {
+        (
+            Align2::CENTER_TOP,
+            target_rect.center_bottom() + vec2(0., gap),
+        )
+    } else {
+        (
+            Align2::CENTER_BOTTOM,
+            target_rect.center_top() - vec2(0., gap),
+        )
+    };
+
+    egui::Area::new(Id::new("tutorial_callout"))
+        .order(Order::Foreground)
+        .pivot(pivot)
+        .fixed_pos(pos)
+        .constrain(true)
+        .show(ctx, |ui| {
+            Frame::popup(ui.style())
+                .fill(theme.colors.code_bg_color)
+                .inner_margin(theme.sizes.m)
+                .stroke(Stroke::new(1., theme.colors.hyperlink_color))
+                .show(ui, |ui| {
+                    ui.set_width(260.);
+
+                    ui.label(
+                        RichText::new(format!(
+                            "{}/{}  {}",
+                            tutorial.step_index() + 1,
+                            TUTORIAL_STEPS.len(),
+                            step.title
+                        ))
+                        .color(theme.colors.normal_text_color)
+                        .font(FontId {
+                            size: theme.fonts.size.normal,
+                            family: theme.fonts.family.bold.clone(),
+                        }),
+                    );
+
+                    ui.add_space(theme.sizes.xs);
+                    ui.label(RichText::new(step.text).color(theme.colors.subtle_text_color));
+                    ui.add_space(theme.sizes.s);
+
+                    ui.horizontal(|ui| {
+                        if ui.button("Skip").clicked() {
+                            resulting_actions.push(AppAction::Tutorial(TutorialAction::Finish));
+                        }
+
+                        if tutorial.step_index() > 0 && ui.button("Back").clicked() {
+                            resulting_actions.push(AppAction::Tutorial(TutorialAction::Prev));
+                        }
+
+                        let next_label = if tutorial.is_last_step() {
+                            "Done"
+                        } else {
+                            "Next"
+                        };
+                        if ui.button(next_label).clicked() {
+                            resulting_actions.push(AppAction::Tutorial(TutorialAction::Next));
+                        }
+                    });
+                });
+        });
+
+    resulting_actions
+}
+
+fn render_note_switcher(items: &[NoteSwitcherItem], theme: &AppTheme, ctx: &Context) {
+    egui::Area::new(Id::new("note_switcher"))
+        .order(Order::Foreground)
+        .pivot(Align2::CENTER_CENTER)
+        .fixed_pos(ctx.screen_rect().center())
+        .constrain(true)
+        .interactable(false)
+        .show(ctx, |ui| {
+            Frame::popup(ui.style())
+                .fill(theme.colors.code_bg_color)
+                .inner_margin(theme.sizes.s)
+                .show(ui, |ui| {
+                    ui.set_width(theme.sizes.menu_width);
+
+                    for item in items {
+                        let color = match item.is_selected {
+                            true => theme.colors.normal_text_color,
+                            false => theme.colors.subtle_text_color,
+                        };
+
+                        let text = match item.is_locked {
+                            true => AppIcon::Lock.render_with_text(
+                                theme.fonts.size.normal,
+                                color,
+                                &item.title,
+                            ),
+                            false => RichText::new(&item.title).color(color).into(),
+                        };
+
+                        Frame::new()
+                            .fill(match item.is_selected {
+                                true => theme.colors.selection_bg,
+                                false => Color32::TRANSPARENT,
+                            })
+                            .corner_radius(theme.colors.rounding_controls)
+                            .inner_margin(theme.sizes.xs)
+                            .show(ui, |ui| {
+                                ui.set_width(ui.available_width());
+                                ui.add(Label::new(text).truncate());
+                            });
+                    }
+                });
+        });
+}
+
+/// Dragging the window edges and corners starts a native resize,
+/// the bottom right corner also gets a subtle grip
+fn render_resize_handles(theme: &AppTheme, ctx: &egui::Context) {
+    if ctx.input(|i| i.viewport().fullscreen.unwrap_or(false)) {
+        return;
+    }
+
+    let screen_rect = ctx.screen_rect();
+    let hovered_direction = ctx
+        .input(|i| i.pointer.hover_pos())
+        .and_then(|pos| resize_direction_at(screen_rect, pos))
+        // e.g. the scroll bar is being dragged
+        .filter(|_| ctx.dragged_id().is_none());
+
+    if let Some(direction) = hovered_direction {
+        ctx.set_cursor_icon(match direction {
+            ResizeDirection::North | ResizeDirection::South => CursorIcon::ResizeVertical,
+            ResizeDirection::East | ResizeDirection::West => CursorIcon::ResizeHorizontal,
+            ResizeDirection::NorthWest | ResizeDirection::SouthEast => CursorIcon::ResizeNwSe,
+            ResizeDirection::NorthEast | ResizeDirection::SouthWest => CursorIcon::ResizeNeSw,
+        });
+
+        if ctx.input(|i| i.pointer.primary_pressed()) {
+            ctx.send_viewport_cmd(ViewportCommand::BeginResize(direction));
+        }
+    }
+
+    let grip_color = match hovered_direction {
+        Some(ResizeDirection::SouthEast) => theme.colors.normal_text_color,
+        _ => theme.colors.outline_fg,
+    };
+
+    let painter = ctx.layer_painter(LayerId::new(Order::Foreground, Id::new("resize_grip")));
+    let corner = screen_rect.right_bottom() - Vec2::splat(theme.sizes.xs);
+    for offset in [RESIZE_CORNER / 3.0, RESIZE_CORNER * 2.0 / 3.0] {
+        painter.line_segment(
+            [corner - vec2(offset, 0.0), corner - vec2(0.0, offset)],
+            Stroke::new(1.0, grip_color),
+        );
+    }
+}
+
+fn resize_direction_at(screen_rect: Rect, pos: egui::Pos2) -> Option<ResizeDirection> {
+    if !screen_rect.contains(pos) {
+        return None;
+    }
+
+    let left = pos.x - screen_rect.left();
+    let right = screen_rect.right() - pos.x;
+    let top = pos.y - screen_rect.top();
+    let bottom = screen_rect.bottom() - pos.y;
+
+    let is_corner = |a: f32, b: f32| a <= RESIZE_CORNER && b <= RESIZE_CORNER;
+
+    let direction = match () {
+        _ if is_corner(bottom, right) => ResizeDirection::SouthEast,
+        _ if is_corner(bottom, left) => ResizeDirection::SouthWest,
+        _ if is_corner(top, right) => ResizeDirection::NorthEast,
+        _ if is_corner(top, left) => ResizeDirection::NorthWest,
+        _ if bottom <= RESIZE_EDGE => ResizeDirection::South,
+        _ if top <= RESIZE_EDGE => ResizeDirection::North,
+        _ if right <= RESIZE_EDGE => ResizeDirection::East,
+        _ if left <= RESIZE_EDGE => ResizeDirection::West,
+        _ => return None,
+    };
+
+    Some(direction)
+}
+
+fn render_tag_filter(
+    tag_filter: &mut TagFilter,
+    theme: &AppTheme,
+    frame_hotkeys: &mut FrameHotkeys,
+    ctx: &Context,
+) -> SmallVec<[AppAction; 1]> {
+    let mut resulting_actions = SmallVec::new();
+
+    frame_hotkeys.add_key(Key::ArrowDown, |_ctx| {
+        [AppAction::TagFilter(TagFilterAction::MoveSelection {
+            backwards: false,
+        })]
+        .into()
+    });
+    frame_hotkeys.add_key(Key::ArrowUp, |_ctx| {
+        [AppAction::TagFilter(TagFilterAction::MoveSelection {
+            backwards: true,
+        })]
+        .into()
+    });
+    frame_hotkeys.add_key(Key::Enter, |_ctx| {
+        [AppAction::TagFilter(TagFilterAction::Confirm)].into()
+    });
+    frame_hotkeys.add_key(Key::Escape, |_ctx| {
+        [AppAction::TagFilter(TagFilterAction::Hide)].into()
+    });
+
+    egui::Area::new(Id::new("tag_filter"))
+        .order(Order::Foreground)
+        .pivot(Align2::CENTER_TOP)
+        .fixed_pos(pos2(
+            ctx.screen_rect().center().x,
+            theme.sizes.header_footer * 2.,
+        ))
+        .constrain(true)
+        .show(ctx, |ui| {
+            Frame::popup(ui.style())
+                .fill(theme.colors.code_bg_color)
+                .inner_margin(theme.sizes.s)
+                .show(ui, |ui| {
+                    ui.set_width(theme.sizes.menu_width);
+
+                    let query_resp = ui.add(
+                        TextEdit::singleline(&mut tag_filter.query)
+                            .id(tag_filter_input_id())
+                            .hint_text("#tag")
+                            .font(TextStyle::Monospace)
+                            .desired_width(f32::INFINITY),
+                    );
+
+                    if query_resp.changed() {
+                        tag_filter.selected = 0;
+                    }
+
+                    ui.add_space(theme.sizes.xs);
+
+                    let mut clicked = None;
+                    let mut is_empty = true;
+
+                    ScrollArea::vertical()
+                        .max_height(ctx.screen_rect().height() / 2.)
+                        .id_salt("tag_filter_scroll")
+                        .show(ui, |ui| {
+                            for (i, entry) in tag_filter.matching().enumerate() {
+                                is_empty = false;
+                                let is_selected = i == tag_filter.selected;
+
+                                let resp = Frame::new()
+                                    .fill(match is_selected {
+                                        true => theme.colors.selection_bg,
+                                        false => Color32::TRANSPARENT,
+                                    })
+                                    .corner_radius(theme.colors.rounding_controls)
+                                    .inner_margin(theme.sizes.xs)
+                                    .show(ui, |ui| {
+                                        ui.set_width(ui.available_width());
+                                        ui.horizontal(|ui| {
+                                            ui.label(
+                                                RichText::new(format!("#{}", entry.tag))
+                                                    .color(theme.colors.normal_text_color),
+                                            );
+                                            ui.add(
+                                                Label::new(
+                                                    RichText::new(&entry.note_title)
+                                                        .color(theme.colors.subtle_text_color),
+                                                )
+                                                .truncate(),
+                                            );
+                                        });
+                                    })
+                                    .response
+                                    .interact(Sense::CLICK)
+                                    .on_hover_cursor(CursorIcon::PointingHand);
+
+                                if is_selected && !ui.is_rect_visible(resp.rect) {
+                                    resp.scroll_to_me(Some(Align::Center));
+                                }
+
+                                if resp.clicked() {
+                                    clicked = Some(i);
+                                }
+                            }
+                        });
+
+                    if is_empty {
+                        ui.label(
+                            RichText::new("No tagged shelves found")
+                                .color(theme.colors.subtle_text_color),
+                        );
+                    }
+
+                    if let Some(i) = clicked {
+                        tag_filter.selected = i;
+                        resulting_actions.push(AppAction::TagFilter(TagFilterAction::Confirm));
+                    }
+                });
+        });
+
+    resulting_actions
+}
+
+/// A small window on top of the editor, it stays in place while the note scrolls.
+/// Translucent, so the text below it is not completely hidden
+fn render_pinned_snippet(snippet: &str, theme: &AppTheme, ctx: &Context) -> Option<AppAction> {
+    let mut is_closed = false;
+    let screen_rect = ctx.screen_rect();
+
+    egui::Window::new("Pinned")
+        .id(Id::new("pinned_snippet"))
+        .title_bar(false)
+        .collapsible(false)
+        .resizable(true)
+        .default_pos(pos2(
+            screen_rect.right() - theme.sizes.menu_width - theme.sizes.m,
+            theme.sizes.header_footer * 2.,
+        ))
+        .default_width(theme.sizes.menu_width)
+        .max_height(screen_rect.height() / 2.)
+        .frame(
+            Frame::popup(&ctx.style())
+                .fill(theme.colors.code_bg_color.gamma_multiply(0.85))
+                .inner_margin(theme.sizes.s),
+        )
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(
+                    RichText::new("Pinned")
+                        .size(theme.fonts.size.small)
+                        .color(theme.colors.subtle_text_color),
+                );
+
+                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                    let close_resp = ui
+                        .add(
+                            egui::Button::new(
+                                AppIcon::Close.render(
+                                    theme.fonts.size.normal,
+                                    theme.colors.subtle_text_color,
+                                ),
+                            )
+                            .frame(false),
+                        )
+                        .on_hover_text("Unpin");
+
+                    close_resp.widget_info(|| {
+                        WidgetInfo::labeled(WidgetType::Button, true, "Unpin the snippet")
+                    });
+
+                    is_closed = close_resp.clicked();
+                });
+            });
+
+            ui.add_space(theme.sizes.xs);
+
+            ScrollArea::vertical()
+                .id_salt("pinned_snippet_scroll")
+                .show(ui, |ui| {
+                    ui.add(
+                        Label::new(RichText::new(snippet).color(theme.colors.normal_text_color))
+                            .selectable(true),
+                    );
+                });
+        });
+
+    is_closed.then_some(AppAction::SetPinnedSnippet(None))
+}
+
+/// `missing image: <path>` at the end of the row of an image (or a link) to a local file that doesn't exist,
+/// `loading image` or `broken image: <url>` for remote images. Painted over the text if the row is long
+fn render_link_markers(
+    ui: &Ui,
+    computed_layout: &ComputedLayout,
+    galley_pos: egui::Pos2,
+    link_markers: &[LinkMarker],
+    theme: &AppTheme,
+) {
+    let font_id = FontId::new(theme.fonts.size.small, theme.fonts.family.normal.clone());
+    let right_edge = galley_pos.x + computed_layout.wrap_width;
+
+    for (area, marker) in computed_layout.link_areas.iter().filter_map(|area| {
+        link_markers
+            .iter()
+            .find(|marker| marker.url == area.url)
+            .map(|marker| (area, marker))
+    }) {
+        let end_rect = area.rect.translate(galley_pos.to_vec2());
+
+        if !ui.is_rect_visible(end_rect) {
+            continue;
+        }
+
+        let (label, color) = match (&marker.kind, area.is_image) {
+            (LinkMarkerKind::Missing, true) => (
+                format!("missing image: {}", area.url),
+                theme.colors.error_fg_color,
+            ),
+            (LinkMarkerKind::Missing, false) => (
+                format!("missing file: {}", area.url),
+                theme.colors.error_fg_color,
+            ),
+            (LinkMarkerKind::Loading, _) => {
+                ("loading image…".to_string(), theme.colors.subtle_text_color)
+            }
+            (LinkMarkerKind::FetchFailed(reason), _) => (
+                format!("broken image: {} ({reason})", area.url),
+                theme.colors.error_fg_color,
+            ),
+        };
+
+        let galley = ui.painter().layout_no_wrap(label, font_id.clone(), color);
+
+        let marker_rect = Rect::from_min_size(
+            pos2(
+                right_edge - galley.size().x,
+                end_rect.center().y - galley.size().y / 2.,
+            ),
+            galley.size(),
+        );
+
+        ui.painter().rect_filled(
+            marker_rect.expand(theme.sizes.xs),
+            theme.colors.rounding_controls,
+            ui.visuals().panel_fill,
+        );
+        ui.painter().galley(marker_rect.min, galley, color);
+    }
+}
+
+/// Checkboxes are painted over the `[ ]` text, the markdown itself stays as is
+fn render_task_checkboxes(
+    ui: &mut Ui,
+    computed_layout: &ComputedLayout,
+    galley_pos: egui::Pos2,
+    note_file: NoteFile,
+    theme: &AppTheme,
+) -> SmallVec<[AppAction; 1]> {
+    let mut resulting_actions = SmallVec::new();
+
+    for area in computed_layout.task_areas.iter() {
+        let marker_rect = area.rect.translate(galley_pos.to_vec2());
+
+        if !ui.is_rect_visible(marker_rect) {
+            continue;
+        }
+
+        let resp = ui.interact(
+            marker_rect.expand(theme.sizes.xs),
+            Id::new("task_checkbox")
+                .with(note_file)
+                .with(area.byte_range.start),
+            Sense::click(),
+        );
+
+        let (icon, color) = match area.state {
+            TaskState::Todo => (AppIcon::TaskTodo, theme.colors.md_link),
+            TaskState::InProgress => (AppIcon::TaskInProgress, theme.colors.md_task_in_progress),
+            TaskState::Done => (AppIcon::TaskDone, theme.colors.md_link),
+        };
+
+        let color = match resp.hovered() {
+            true => color.gamma_multiply(0.7),
+            false => color,
+        };
+
+        ui.painter()
+            .rect_filled(marker_rect, 0.0, ui.visuals().panel_fill);
+        ui.painter().text(
+            marker_rect.center(),
+            Align2::CENTER_CENTER,
+            icon.to_icon_str(),
+            FontId::new(marker_rect.height(), FontFamily::Name("phosphor".into())),
+            color,
+        );
+
+        if resp.hovered() {
+            ui.ctx().set_cursor_icon(CursorIcon::PointingHand);
+        }
+
+        if resp.clicked() {
+            resulting_actions.push(AppAction::apply_text_changes(
+                note_file,
+                [TextChange::Insert(
+                    area.byte_range,
+                    area.state.next().marker().to_string(),
+                )]
+                .into(),
+            ));
+        }
+    }
+
+    resulting_actions
+}
+
+fn render_code_actions(
+    ui: &mut Ui,
+    theme: &AppTheme,
+    code_area: Rect,
+    lang: &str,
+    annotation: Option<&CodeBlockAnnotation>,
+    span_index: SpanIndex,
+    note_file: NoteFile,
+    frame_hotkeys: &mut FrameHotkeys,
+    is_cursor_inside: bool,
+) -> EditorCommandOutput {
+    let id = ui.id().with("code_annotations").with(span_index);
+    let is_hovered = ui.rect_contains_pointer(code_area);
+    let alpha = ui.ctx().animate_bool_with_time_and_easing(
+        id,
+        is_hovered,
+        0.2,
+        emath::easing::cubic_in_out,
+    );
+
+    let monospace = &theme.fonts.family.code;
+    let mut resulting_actions: SmallVec<[AppAction; 1]> = SmallVec::new();
+
+    let buttons_anim_id = ui.id().with("buttons_overlay_anim").with(span_index);
+    let buttons_visible = ui
+        .ctx()
+        .animate_bool_with_time(buttons_anim_id, is_hovered, 0.20);
+
+    // TODO: add a setting setup of these
+    let run_hotkey = KeyboardShortcut::new(Modifiers::MAC_CMD, Key::Enter);
+    // TODO figure out how to bind cmd + c derivitave in egui, it doesn't currently work :(
+    // let copy_hotkey = KeyboardShortcut::new(Modifiers::MAC_CMD.plus(Modifiers::SHIFT), Key::Copy);
+
+    // Collect dimensions for logging
     let mut dimensions = Vec::new();
     dimensions.push(format!("Code area: {:?}", code_area));
     dimensions.push(format!(
@@ -1071,6 +2463,31 @@ fn render_code_actions(
                 .gap(theme.sizes.xs),
         )
         .show(|tui| {
+            // the language badge is always visible, it only gets brighter on hover
+            if !lang.is_empty() {
+                let badge = egui::Button::new(
+                    RichText::new(lang)
+                        .size(theme.fonts.size.small)
+                        .family(monospace.clone())
+                        .color(
+                            theme
+                                .colors
+                                .subtle_text_color
+                                .gamma_multiply(0.5 + 0.5 * alpha),
+                        ),
+                )
+                .frame(false);
+
+                if tui
+                    .ui_add(badge)
+                    .on_hover_text("Change language")
+                    .on_hover_cursor(CursorIcon::PointingHand)
+                    .clicked()
+                {
+                    resulting_actions.push(AppAction::SelectCodeBlockLang(note_file, span_index));
+                }
+            }
+
             // Only show the copy button if the mouse is over the code area
             if buttons_visible > 0.0 {
                 if tui
@@ -1164,6 +2581,26 @@ fn render_code_actions(
                     }
                 }
 
+                CodeBlockAnnotation::Progress(progress) => {
+                    let spinner = tui.ui_add(
+                        egui::Spinner::new()
+                            .size(theme.fonts.size.normal)
+                            .color(theme.colors.subtle_text_color),
+                    );
+
+                    if let Some(fraction) = progress.fraction {
+                        tui.label(
+                            RichText::new(format!("{:.0}%", fraction * 100.))
+                                .size(theme.fonts.size.small)
+                                .color(theme.colors.subtle_text_color),
+                        );
+                    }
+
+                    if !progress.message.is_empty() {
+                        spinner.on_hover_text(&progress.message);
+                    }
+                }
+
                 CodeBlockAnnotation::Applied { message } => {
                     tui.label(
                         AppIcon::Check
@@ -1228,84 +2665,499 @@ fn render_slash_cmd(
         theme.colors.normal_text_color,
     );
 
-    let header_font = TextFormat::simple(
-        FontId::new(theme.fonts.size.normal, theme.fonts.family.normal.clone()),
-        theme.colors.md_code,
-    );
+    let header_font = TextFormat::simple(
+        FontId::new(theme.fonts.size.normal, theme.fonts.family.normal.clone()),
+        theme.colors.md_code,
+    );
+
+    let shortcut_font = TextFormat::simple(
+        FontId::new(theme.fonts.size.small, theme.fonts.family.italic.clone()),
+        theme.colors.subtle_text_color,
+    );
+
+    let description_font = TextFormat::simple(
+        FontId::new(theme.fonts.size.small, theme.fonts.family.normal.clone()),
+        theme.colors.subtle_text_color,
+    );
+
+    let available_width = ui.available_width();
+
+    tui(ui, ui.id().with(cmd))
+        .reserve_available_width()
+        .show(|tui| {
+            tui.style(
+                flex_column()
+                    .align_content(AlignContent::Stretch)
+                    .width(available_width)
+                    .auto_height()
+                    .padding(theme.sizes.xs)
+                    .gap(theme.sizes.xs),
+            )
+            .selectable(selected, |tui| {
+                tui.style(flex_row().justify_content(JustifyContent::SpaceBetween))
+                    .add(|tui| {
+                        tui.style(flex_row().gap(theme.sizes.s)).add(|tui| {
+                            tui.ui_add(
+                                Label::new(
+                                    RichText::new(if let Some(icon) = &cmd.phosphor_icon {
+                                        icon.symbol()
+                                    } else {
+                                        egui_phosphor::light::GEAR
+                                    })
+                                    .font(phosphor_icon_font.font_id)
+                                    .color(phosphor_icon_font.color),
+                                )
+                                .wrap_mode(TextWrapMode::Extend),
+                            );
+                            tui.ui_add(
+                                Label::new(
+                                    RichText::new(&cmd.prefix)
+                                        .font(header_font.font_id)
+                                        .color(header_font.color),
+                                )
+                                .wrap_mode(TextWrapMode::Extend),
+                            );
+                        });
+
+                        tui.ui_add(
+                            Label::new(
+                                RichText::new(format!(
+                                    "{}",
+                                    cmd.instance
+                                        .shortcut
+                                        .map(format_mac_shortcut_with_symbols)
+                                        .unwrap_or_default()
+                                ))
+                                .font(shortcut_font.font_id)
+                                .color(shortcut_font.color),
+                            )
+                            .wrap_mode(egui::TextWrapMode::Extend),
+                        );
+                    });
+
+                tui.label(
+                    RichText::new(&cmd.description)
+                        .font(description_font.font_id)
+                        .color(description_font.color),
+                );
+            })
+        })
+        .response
+}
+
+fn render_find_replace_panel(
+    find_replace: &mut FindReplace,
+    editor_text: &str,
+    theme: &AppTheme,
+    frame_hotkeys: &mut FrameHotkeys,
+    ctx: &egui::Context,
+) -> SmallVec<[AppAction; 1]> {
+    let mut resulting_actions = SmallVec::new();
+
+    frame_hotkeys.add_key(Key::Escape, |_ctx| {
+        [AppAction::FindReplace(FindReplaceAction::Hide)].into()
+    });
+
+    // otherwise Enter would be intercepted while editing the note
+    let is_bar_focused = ctx.memory(|m| {
+        m.focused()
+            .is_some_and(|id| id == find_query_input_id() || id == replacement_input_id())
+    });
+
+    if is_bar_focused {
+        frame_hotkeys.add_key(Key::Enter, |_ctx| {
+            [AppAction::FindReplace(FindReplaceAction::ReplaceNext)].into()
+        });
+        frame_hotkeys.add_key_with_modifier(Modifiers::COMMAND, Key::Enter, |_ctx| {
+            [AppAction::FindReplace(FindReplaceAction::ReplaceAll)].into()
+        });
+    }
+
+    let matches = Finder::new(&find_replace.query, find_replace.is_regex).map(|finder| {
+        finder
+            .map(|finder| finder.matches(editor_text, find_replace.scope()).len())
+            .unwrap_or(0)
+    });
+
+    TopBottomPanel::top("find_replace_panel")
+        .show_separator_line(false)
+        .frame(
+            Frame::new()
+                .fill(theme.colors.code_bg_color)
+                .inner_margin(theme.sizes.s),
+        )
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.add(
+                    TextEdit::singleline(&mut find_replace.query)
+                        .id(find_query_input_id())
+                        .hint_text("Find")
+                        .font(TextStyle::Monospace)
+                        .desired_width(ui.available_width() * 0.6),
+                );
+
+                ui.checkbox(&mut find_replace.is_regex, ".*")
+                    .on_hover_text("Regular expression");
+
+                if find_replace.selection.is_some() {
+                    ui.checkbox(&mut find_replace.in_selection, "In selection");
+                }
+
+                let (status, color) = match &matches {
+                    Ok(1) => ("1 match".to_string(), theme.colors.subtle_text_color),
+                    Ok(count) => (format!("{count} matches"), theme.colors.subtle_text_color),
+                    Err(_) => ("Invalid regex".to_string(), theme.colors.error_fg_color),
+                };
+
+                let status = ui.label(
+                    RichText::new(status)
+                        .size(theme.fonts.size.small)
+                        .color(color),
+                );
+
+                if let Err(err) = &matches {
+                    status.on_hover_text(err.as_str());
+                }
+            });
+
+            ui.horizontal(|ui| {
+                let hint = match find_replace.is_regex {
+                    true => "Replace ($1 for a capture group)",
+                    false => "Replace",
+                };
+
+                ui.add(
+                    TextEdit::singleline(&mut find_replace.replacement)
+                        .id(replacement_input_id())
+                        .hint_text(hint)
+                        .font(TextStyle::Monospace)
+                        .desired_width(ui.available_width() * 0.6),
+                );
+
+                let has_matches = matches!(matches, Ok(count) if count > 0);
+
+                if ui
+                    .add_enabled(has_matches, egui::Button::new("Replace"))
+                    .on_hover_text("Enter")
+                    .clicked()
+                {
+                    resulting_actions.push(AppAction::FindReplace(FindReplaceAction::ReplaceNext));
+                }
+
+                if ui
+                    .add_enabled(has_matches, egui::Button::new("All"))
+                    .on_hover_text(format_mac_shortcut_with_symbols(KeyboardShortcut::new(
+                        Modifiers::COMMAND,
+                        Key::Enter,
+                    )))
+                    .clicked()
+                {
+                    resulting_actions.push(AppAction::FindReplace(FindReplaceAction::ReplaceAll));
+                }
+
+                let close_resp = ui
+                    .add(
+                        egui::Button::new(
+                            AppIcon::Close
+                                .render(theme.fonts.size.normal, theme.colors.subtle_text_color),
+                        )
+                        .frame(false),
+                    )
+                    .on_hover_text("Escape");
+
+                close_resp.widget_info(|| {
+                    WidgetInfo::labeled(WidgetType::Button, true, "Close find and replace")
+                });
+
+                if close_resp.clicked() {
+                    resulting_actions.push(AppAction::FindReplace(FindReplaceAction::Hide));
+                }
+            });
+        });
+
+    resulting_actions
+}
+
+fn render_clear_note_modal(
+    title: &str,
+    theme: &AppTheme,
+    frame_hotkeys: &mut FrameHotkeys,
+    ctx: &egui::Context,
+) -> Option<AppAction> {
+    let confirm = || AppAction::ClearNote(ClearNoteAction::Confirm);
+    let cancel = || AppAction::ClearNote(ClearNoteAction::Cancel);
+
+    frame_hotkeys.add_with_layer(
+        FrameHotkey::new(
+            KeyboardShortcut::new(Modifiers::NONE, Key::Enter),
+            move |_ctx| SmallVec::from_iter([confirm()]),
+        ),
+        FrameHotkeyLayer::Modal,
+    );
+
+    frame_hotkeys.add_with_layer(
+        FrameHotkey::new(
+            KeyboardShortcut::new(Modifiers::NONE, Key::Escape),
+            move |_ctx| SmallVec::from_iter([cancel()]),
+        ),
+        FrameHotkeyLayer::Modal,
+    );
+
+    let mut action = None;
+
+    let modal = Modal::new(Id::new("Clear Note Modal")).show(ctx, |ui| {
+        ui.set_width(300.);
+        ui.label(
+            RichText::new(format!("Clear \"{title}\"?"))
+                .size(theme.fonts.size.normal)
+                .color(theme.colors.normal_text_color)
+                .strong(),
+        );
+        ui.label(
+            RichText::new("A copy is saved to the backups folder")
+                .size(theme.fonts.size.small)
+                .color(theme.colors.subtle_text_color),
+        );
+
+        ui.add_space(theme.sizes.s);
+
+        ui.horizontal(|ui| {
+            if ui.button("Clear").clicked() {
+                action = Some(confirm());
+            }
+            if ui.button("Cancel").clicked() {
+                action = Some(cancel());
+            }
+        });
+    });
+
+    match modal.should_close() {
+        true => Some(cancel()),
+        false => action,
+    }
+}
+
+fn render_recovery_bin_modal(
+    recovery_bin: &mut RecoveryBin,
+    theme: &AppTheme,
+    frame_hotkeys: &mut FrameHotkeys,
+    ctx: &egui::Context,
+) -> Option<AppAction> {
+    let hotkeys: [(Key, fn() -> RecoveryBinAction); 4] = [
+        (Key::ArrowDown, || RecoveryBinAction::MoveSelection {
+            backwards: false,
+        }),
+        (Key::ArrowUp, || RecoveryBinAction::MoveSelection {
+            backwards: true,
+        }),
+        (Key::Enter, || RecoveryBinAction::Confirm),
+        (Key::Escape, || RecoveryBinAction::Hide),
+    ];
+
+    for (key, action) in hotkeys {
+        frame_hotkeys.add_with_layer(
+            FrameHotkey::new(KeyboardShortcut::new(Modifiers::NONE, key), move |_ctx| {
+                SmallVec::from_iter([AppAction::RecoveryBin(action())])
+            }),
+            FrameHotkeyLayer::Modal,
+        );
+    }
+
+    let mut action = None;
+
+    let modal = Modal::new(Id::new("Recovery Bin Modal")).show(ctx, |ui| {
+        ui.set_width(theme.sizes.menu_width);
+        ui.label(
+            RichText::new("Restore a cleared shelf")
+                .size(theme.fonts.size.normal)
+                .color(theme.colors.normal_text_color)
+                .strong(),
+        );
+        ui.label(
+            RichText::new("Replaces the current shelf, its content is backed up first")
+                .size(theme.fonts.size.small)
+                .color(theme.colors.subtle_text_color),
+        );
+
+        ui.add_space(theme.sizes.s);
+
+        if recovery_bin.entries.is_empty() {
+            ui.label(
+                RichText::new("No cleared shelves found").color(theme.colors.subtle_text_color),
+            );
+        }
+
+        let mut clicked = None;
+
+        ScrollArea::vertical()
+            .max_height(ctx.screen_rect().height() / 2.)
+            .id_salt("recovery_bin_scroll")
+            .show(ui, |ui| {
+                for (i, entry) in recovery_bin.entries.iter().enumerate() {
+                    let is_selected = i == recovery_bin.selected;
+
+                    let resp = Frame::new()
+                        .fill(match is_selected {
+                            true => theme.colors.selection_bg,
+                            false => Color32::TRANSPARENT,
+                        })
+                        .corner_radius(theme.colors.rounding_controls)
+                        .inner_margin(theme.sizes.xs)
+                        .show(ui, |ui| {
+                            ui.set_width(ui.available_width());
+                            ui.horizontal(|ui| {
+                                ui.add(
+                                    Label::new(
+                                        RichText::new(&entry.note_title)
+                                            .color(theme.colors.normal_text_color),
+                                    )
+                                    .truncate(),
+                                );
+                                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                                    ui.label(
+                                        RichText::new(&entry.age)
+                                            .color(theme.colors.subtle_text_color),
+                                    );
+                                });
+                            });
+                        })
+                        .response
+                        .interact(Sense::CLICK)
+                        .on_hover_cursor(CursorIcon::PointingHand);
+
+                    if is_selected && !ui.is_rect_visible(resp.rect) {
+                        resp.scroll_to_me(Some(Align::Center));
+                    }
+
+                    if resp.clicked() {
+                        clicked = Some(i);
+                    }
+                }
+            });
+
+        if let Some(i) = clicked {
+            recovery_bin.selected = i;
+            action = Some(AppAction::RecoveryBin(RecoveryBinAction::Confirm));
+        }
+    });
 
-    let shortcut_font = TextFormat::simple(
-        FontId::new(theme.fonts.size.small, theme.fonts.family.italic.clone()),
-        theme.colors.subtle_text_color,
+    match modal.should_close() {
+        true => Some(AppAction::RecoveryBin(RecoveryBinAction::Hide)),
+        false => action,
+    }
+}
+
+fn render_start_at_login_modal(
+    theme: &AppTheme,
+    frame_hotkeys: &mut FrameHotkeys,
+    ctx: &egui::Context,
+) -> Option<AppAction> {
+    let accept = || AppAction::SetStartAtLogin(true);
+    let decline = || AppAction::SetStartAtLogin(false);
+
+    frame_hotkeys.add_with_layer(
+        FrameHotkey::new(
+            KeyboardShortcut::new(Modifiers::NONE, Key::Enter),
+            move |_ctx| SmallVec::from_iter([accept()]),
+        ),
+        FrameHotkeyLayer::Modal,
     );
 
-    let description_font = TextFormat::simple(
-        FontId::new(theme.fonts.size.small, theme.fonts.family.normal.clone()),
-        theme.colors.subtle_text_color,
+    frame_hotkeys.add_with_layer(
+        FrameHotkey::new(
+            KeyboardShortcut::new(Modifiers::NONE, Key::Escape),
+            move |_ctx| SmallVec::from_iter([decline()]),
+        ),
+        FrameHotkeyLayer::Modal,
     );
 
-    let available_width = ui.available_width();
+    let mut action = None;
 
-    tui(ui, ui.id().with(cmd))
-        .reserve_available_width()
-        .show(|tui| {
-            tui.style(
-                flex_column()
-                    .align_content(AlignContent::Stretch)
-                    .width(available_width)
-                    .auto_height()
-                    .padding(theme.sizes.xs)
-                    .gap(theme.sizes.xs),
+    let modal = Modal::new(Id::new("Start at Login Modal")).show(ctx, |ui| {
+        ui.set_width(300.);
+        ui.label(
+            RichText::new("Start Shelv at login?")
+                .size(theme.fonts.size.normal)
+                .color(theme.colors.normal_text_color)
+                .strong(),
+        );
+        ui.label(
+            RichText::new(
+                "Keeps the show/hide shortcut working right away, can be changed in the menu",
             )
-            .selectable(selected, |tui| {
-                tui.style(flex_row().justify_content(JustifyContent::SpaceBetween))
-                    .add(|tui| {
-                        tui.style(flex_row().gap(theme.sizes.s)).add(|tui| {
-                            tui.ui_add(
-                                Label::new(
-                                    RichText::new(if let Some(icon) = &cmd.phosphor_icon {
-                                        icon.symbol()
-                                    } else {
-                                        egui_phosphor::light::GEAR
-                                    })
-                                    .font(phosphor_icon_font.font_id)
-                                    .color(phosphor_icon_font.color),
-                                )
-                                .wrap_mode(TextWrapMode::Extend),
-                            );
-                            tui.ui_add(
-                                Label::new(
-                                    RichText::new(&cmd.prefix)
-                                        .font(header_font.font_id)
-                                        .color(header_font.color),
-                                )
-                                .wrap_mode(TextWrapMode::Extend),
-                            );
-                        });
+            .size(theme.fonts.size.small)
+            .color(theme.colors.subtle_text_color),
+        );
 
-                        tui.ui_add(
-                            Label::new(
-                                RichText::new(format!(
-                                    "{}",
-                                    cmd.instance
-                                        .shortcut
-                                        .map(format_mac_shortcut_with_symbols)
-                                        .unwrap_or_default()
-                                ))
-                                .font(shortcut_font.font_id)
-                                .color(shortcut_font.color),
-                            )
-                            .wrap_mode(egui::TextWrapMode::Extend),
-                        );
-                    });
+        ui.add_space(theme.sizes.s);
 
-                tui.label(
-                    RichText::new(&cmd.description)
-                        .font(description_font.font_id)
-                        .color(description_font.color),
-                );
-            })
-        })
-        .response
+        ui.horizontal(|ui| {
+            if ui.button("Start at Login").clicked() {
+                action = Some(accept());
+            }
+            if ui.button("Not Now").clicked() {
+                action = Some(decline());
+            }
+        });
+    });
+
+    match modal.should_close() {
+        true => Some(decline()),
+        false => action,
+    }
+}
+
+/// Settings and script output blocks are tinted, so they are visually separable from regular code
+fn code_block_bg_color(lang: &str, theme: &AppTheme) -> Color32 {
+    let colors = &theme.colors;
+    match JSBlockLang::parse(lang) {
+        Some(JSBlockLang::Output(..)) => colors.md_output_code_bg,
+        _ if lang == SETTINGS_BLOCK_LANG || lang == SNIPPETS_BLOCK_LANG => {
+            colors.md_settings_code_bg
+        }
+        _ => colors.code_bg_color,
+    }
+}
+
+/// GitHub alert types are mapped to the Nord accent colors
+fn callout_accent_color(kind: CalloutKind) -> Color32 {
+    match kind {
+        CalloutKind::Note => Nord::NORD8,
+        CalloutKind::Tip => Nord::NORD14,
+        CalloutKind::Important => Nord::NORD15,
+        CalloutKind::Warning => Nord::NORD13,
+        CalloutKind::Caution => Nord::NORD11,
+    }
+}
+
+/// Tinted box with an accent stripe on the left and the icon of the type in the corner,
+/// painted under the text, the markdown itself stays as is
+fn paint_callouts(ui: &Ui, areas: &[CalloutArea], text_pos: egui::Pos2, theme: &AppTheme) {
+    let rounding = ui.visuals().widgets.inactive.corner_radius;
+
+    for area in areas {
+        let rect = area
+            .rect
+            .expand2(vec2(theme.sizes.s, 1.))
+            .translate(text_pos.to_vec2());
+
+        if !ui.is_rect_visible(rect) {
+            continue;
+        }
+
+        let accent = callout_accent_color(area.kind);
+        let stripe = Rect::from_min_size(rect.min, vec2(theme.sizes.xs, rect.height()));
+
+        ui.painter().rect_filled(rect, rounding, accent.shade(0.25));
+        ui.painter().rect_filled(stripe, rounding, accent);
+        ui.painter().text(
+            rect.right_top() + vec2(-theme.sizes.s, theme.sizes.xs),
+            Align2::RIGHT_TOP,
+            AppIcon::Callout(area.kind).to_icon_str(),
+            FontId::new(theme.fonts.size.normal, FontFamily::Name("phosphor".into())),
+            accent,
+        );
+    }
 }
 
 fn restore_cursor_from_note_state(
@@ -1329,10 +3181,25 @@ fn restore_cursor_from_note_state(
     }
 }
 
+/// e.g. "List > ListItem > Paragraph > Bold"
+fn cursor_context_label(text_structure: &TextStructure, cursor: ByteSpan) -> String {
+    text_structure
+        .span_stack_at(cursor)
+        .into_iter()
+        .map(|kind| match kind {
+            SpanKind::Heading(level) => format!("Heading{}", level as usize),
+            kind => format!("{kind:?}"),
+        })
+        .join(" > ")
+}
+
 fn render_footer_panel(
     selected: NoteFile,
     note_count: usize,
     command_list: &CommandList,
+    cursor_context: Option<String>,
+    word_goal: Option<WordGoalProgress>,
+    focus_session_label: Option<String>,
     ctx: &Context,
     theme: &AppTheme,
 ) -> SmallVec<[AppAction; 1]> {
@@ -1405,6 +3272,7 @@ fn render_footer_panel(
                             NoteFile::Settings => note_count,
                         },
                         items: &items,
+                        label: "Shelves",
                         gap: sizes.s,
                         // TODO why the button icons are rendered with h3 font size?
                         item_size: theme.sizes.toolbar_icon,
@@ -1417,12 +3285,43 @@ fn render_footer_panel(
                         tooltip_text_color: theme.colors.subtle_text_color,
                     };
 
-                    if let Some(&note_file) = picker.show(ui).inner {
+                    let picker_resp = picker.show(ui);
+                    TutorialTarget::NotePicker.register_rect(ctx, picker_resp.response.rect);
+
+                    if let Some(&note_file) = picker_resp.inner {
                         actions.push(AppAction::SwitchToNote {
                             note_file,
                             via_shortcut: false,
                         });
                     }
+
+                    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                        if let Some(word_goal) = word_goal {
+                            render_word_goal(ui, word_goal, selected, theme);
+                        }
+
+                        if let Some(focus_session_label) = focus_session_label {
+                            ui.label(
+                                RichText::new(focus_session_label)
+                                    .small()
+                                    .color(theme.colors.subtle_text_color),
+                            )
+                            .on_hover_text(
+                                "Focus session: time since it started and how many times Shelv lost focus",
+                            );
+                        }
+
+                        if let Some(cursor_context) = cursor_context {
+                            ui.add(
+                                Label::new(
+                                    RichText::new(cursor_context)
+                                        .small()
+                                        .color(theme.colors.subtle_text_color),
+                                )
+                                .truncate(),
+                            );
+                        }
+                    });
                 });
             });
         });
@@ -1430,6 +3329,48 @@ fn render_footer_panel(
     actions
 }
 
+/// "120 / 500 words" with a progress bar, reaching the goal fades in a check mark
+fn render_word_goal(
+    ui: &mut Ui,
+    progress: WordGoalProgress,
+    note_file: NoteFile,
+    theme: &AppTheme,
+) {
+    let colors = &theme.colors;
+
+    // the first frame of a note starts at the end of the animation,
+    // so it is played only when the goal is reached while typing
+    let reached_t = ui.ctx().animate_bool_with_time(
+        Id::new("word_goal_reached").with(note_file),
+        progress.is_reached(),
+        WORD_GOAL_ANIMATION_SECS,
+    );
+
+    let color = colors
+        .subtle_text_color
+        .lerp_to_gamma(colors.success_fg_color, reached_t);
+
+    if reached_t > 0.0 {
+        ui.label(AppIcon::Check.render(
+            theme.fonts.size.normal,
+            colors.success_fg_color.gamma_multiply(reached_t),
+        ));
+    }
+
+    ui.add(
+        ProgressBar::new(progress.fraction())
+            .desired_width(theme.sizes.xl * 2.0)
+            .desired_height(theme.sizes.xs)
+            .fill(color),
+    );
+
+    ui.label(
+        RichText::new(format!("{} / {} words", progress.words, progress.goal))
+            .small()
+            .color(color),
+    );
+}
+
 fn set_menu_bar_style(ui: &mut egui::Ui) {
     let style = ui.style_mut();
     // TODO 2 seems better (more square, but we need to take the value from theme or soemthing)
@@ -1446,9 +3387,16 @@ fn render_header_panel(
     theme: &AppTheme,
     command_list: &CommandList,
     selected_note: NoteFile,
+    is_note_locked: bool,
     is_window_pinned: bool,
+    close_button: CloseButtonBehavior,
+    crash_reporting: bool,
+    start_at_login: bool,
     feedback_sent: bool,
     version_state: &VersionState,
+    persistence_warning: Option<&PersistenceWarning>,
+    global_hotkey_warning: Option<&GlobalHotkeyWarning>,
+    is_large_note: bool,
 ) -> SmallVec<[AppAction; 1]> {
     TopBottomPanel::top("top_panel")
         .show_separator_line(false)
@@ -1486,15 +3434,34 @@ fn render_header_panel(
                     // Left section: Close button and title
                     t.style(flex_row().align_items(AlignItems::Center).gap(sizes.m))
                         .add(|t| {
-                            // Close button
+                            // Close button, hiding keeps Shelv running in the tray
+                            let (close_tooltip, close_action) = match close_button {
+                                CloseButtonBehavior::Quit => ("Quit Shelv", AppAction::QuitApp),
+                                CloseButtonBehavior::Hide | CloseButtonBehavior::HideAndQuit => (
+                                    "Hide Shelv, it stays in the menu bar tray",
+                                    AppAction::HideApp,
+                                ),
+                            };
+
                             if t.ui_add(
                                 IconButton::new(AppIcon::Close, theme)
                                     .size(IconButtonSize::Large)
-                                    .tooltip("Hide Shelv", None),
+                                    .tooltip(close_tooltip, None),
                             )
                             .clicked()
                             {
-                                resulting_actions.push(AppAction::HideApp);
+                                resulting_actions.push(close_action);
+                            }
+
+                            if close_button == CloseButtonBehavior::HideAndQuit
+                                && t.ui_add(
+                                    IconButton::new(AppIcon::Quit, theme)
+                                        .size(IconButtonSize::Large)
+                                        .tooltip("Quit Shelv", None),
+                                )
+                                .clicked()
+                            {
+                                resulting_actions.push(AppAction::QuitApp);
                             }
 
                             // Title
@@ -1515,6 +3482,37 @@ fn render_header_panel(
                                 )
                                 .extend(),
                             );
+
+                            if is_note_locked
+                                && t.ui_add(
+                                    IconButton::new(AppIcon::Lock, theme)
+                                        .size(IconButtonSize::Large)
+                                        .tooltip(
+                                            "The shelf is read only, click to unlock",
+                                            command_list
+                                                .find(CommandInstruction::ToggleNoteLock)
+                                                .and_then(|cmd| cmd.shortcut),
+                                        ),
+                                )
+                                .clicked()
+                            {
+                                resulting_actions.push(AppAction::ToggleNoteLock(selected_note));
+                            }
+
+                            if is_large_note {
+                                t.ui_add(
+                                    Label::new(
+                                        RichText::new("large note")
+                                            .color(theme.colors.subtle_text_color)
+                                            .size(theme.fonts.size.small),
+                                    )
+                                    .extend(),
+                                )
+                                .on_hover_text(
+                                    "Code highlighting and bare links are off to keep typing fast, \
+                                     see largeNoteKb in settings",
+                                );
+                            }
                         });
 
                     // Right section: Feedback button, pin button, separator, and menu
@@ -1546,22 +3544,54 @@ fn render_header_panel(
                                 };
                             }
 
-                            // Feedback button
-                            if t.ui_add(
+                            // Persistence warning
+                            if let Some(warning) = persistence_warning {
+                                t.ui_add(
+                                    IconButton::new(AppIcon::Error, theme)
+                                        .color(theme.colors.warn_fg_color)
+                                        .size(IconButtonSize::Large)
+                                        .text(warning.title())
+                                        .tooltip(warning.description(), None),
+                                );
+                            }
+
+                            // Global hotkey warning, stays until the binding is fixed in settings
+                            if let Some(warning) = global_hotkey_warning {
+                                if t.ui_add(
+                                    IconButton::new(AppIcon::Error, theme)
+                                        .color(theme.colors.warn_fg_color)
+                                        .size(IconButtonSize::Large)
+                                        .text(warning.title())
+                                        .tooltip(warning.description(), None),
+                                )
+                                .clicked()
+                                {
+                                    resulting_actions.push(AppAction::SwitchToNote {
+                                        note_file: NoteFile::Settings,
+                                        via_shortcut: false,
+                                    });
+                                }
+                            }
+
+                            // Feedback button, feedback is delivered by the crash reporting client
+                            let feedback_tooltip = match crash_reporting {
+                                true => "Send this note to report a bug or share feedback.",
+                                false => "Feedback is sent along with crash reports, turn on \"Send crash reports\" in the menu and restart Shelv.",
+                            };
+                            let feedback_resp = t.ui_add(
                                 IconButton::new(AppIcon::Feedback, theme)
                                     .size(IconButtonSize::Large)
-                                    .tooltip(
-                                        "Send this note to report a bug or share feedback.",
-                                        None,
-                                    ),
-                            )
-                            .clicked()
-                            {
+                                    .fade(if crash_reporting { 1.0 } else { 0.3 })
+                                    .tooltip(feedback_tooltip, None),
+                            );
+                            TutorialTarget::FeedbackButton.register_rect(ctx, feedback_resp.rect);
+
+                            if feedback_resp.clicked() && crash_reporting {
                                 resulting_actions.push(AppAction::OpenFeedbackWindow);
                             }
 
                             // Pin button with tooltip and keyboard shortcut
-                            if t.ui_add(
+                            let pin_resp = t.ui_add(
                                 IconButton::new(AppIcon::Pin, theme)
                                     .size(IconButtonSize::Large)
                                     .toggled(is_window_pinned)
@@ -1575,9 +3605,10 @@ fn render_header_panel(
                                             .find(CommandInstruction::PinWindow)
                                             .and_then(|cmd| cmd.shortcut),
                                     ),
-                            )
-                            .clicked()
-                            {
+                            );
+                            TutorialTarget::PinButton.register_rect(ctx, pin_resp.rect);
+
+                            if pin_resp.clicked() {
                                 resulting_actions
                                     .push(AppAction::SetWindowPinned(!is_window_pinned));
                             }
@@ -1592,7 +3623,7 @@ fn render_header_panel(
                             t.ui_add_manual(
                                 |ui| {
                                     apply_icon_btn_styling(ui.style_mut());
-                                    ui.menu_button(
+                                    let menu_response = ui.menu_button(
                                         AppIcon::Menu.render(
                                             sizes.toolbar_icon,
                                             theme.colors.subtle_text_color,
@@ -1649,6 +3680,38 @@ fn render_header_panel(
 
                                             ui.separator();
 
+                                            if let NoteFile::Note(_) = selected_note {
+                                                if ui
+                                                    .button(AppIcon::Copy.render_with_text(
+                                                        theme.fonts.size.normal,
+                                                        theme.colors.normal_text_color,
+                                                        "Duplicate shelf",
+                                                    ))
+                                                    .clicked()
+                                                {
+                                                    ui.close_menu();
+                                                    resulting_actions.push(
+                                                        AppAction::DuplicateNote(selected_note),
+                                                    );
+                                                }
+
+                                                if ui
+                                                    .button(AppIcon::Close.render_with_text(
+                                                        theme.fonts.size.normal,
+                                                        theme.colors.normal_text_color,
+                                                        "Clear shelf…",
+                                                    ))
+                                                    .clicked()
+                                                {
+                                                    ui.close_menu();
+                                                    resulting_actions.push(AppAction::ClearNote(
+                                                        ClearNoteAction::Ask(selected_note),
+                                                    ));
+                                                }
+
+                                                ui.separator();
+                                            }
+
                                             if ui
                                                 .button(AppIcon::Folder.render_with_text(
                                                     theme.fonts.size.normal,
@@ -1661,9 +3724,64 @@ fn render_header_panel(
                                                 resulting_actions
                                                     .push(AppAction::OpenNotesInFinder);
                                             }
+
+                                            let crash_reporting_tooltip = if crash_reporting {
+                                                "Helps to fix bugs, notes are not sent"
+                                            } else {
+                                                "Takes effect after restarting Shelv"
+                                            };
+
+                                            let crash_reporting_btn = ui
+                                                .button(
+                                                    match crash_reporting {
+                                                        true => AppIcon::Check,
+                                                        false => AppIcon::Close,
+                                                    }
+                                                    .render_with_text(
+                                                        theme.fonts.size.normal,
+                                                        theme.colors.normal_text_color,
+                                                        "Send crash reports",
+                                                    ),
+                                                )
+                                                .on_hover_text(crash_reporting_tooltip);
+
+                                            if crash_reporting_btn.clicked() {
+                                                ui.close_menu();
+                                                resulting_actions.push(
+                                                    AppAction::SetCrashReporting(!crash_reporting),
+                                                );
+                                            }
+
+                                            let start_at_login_btn = ui
+                                                .button(
+                                                    match start_at_login {
+                                                        true => AppIcon::Check,
+                                                        false => AppIcon::Close,
+                                                    }
+                                                    .render_with_text(
+                                                        theme.fonts.size.normal,
+                                                        theme.colors.normal_text_color,
+                                                        "Start at login",
+                                                    ),
+                                                )
+                                                .on_hover_text(
+                                                    "Shelv is launched in the background when you log in",
+                                                );
+
+                                            if start_at_login_btn.clicked() {
+                                                ui.close_menu();
+                                                resulting_actions.push(
+                                                    AppAction::SetStartAtLogin(!start_at_login),
+                                                );
+                                            }
                                         },
                                     )
-                                    .response
+                                    .response;
+
+                                    menu_response.widget_info(|| {
+                                        WidgetInfo::labeled(WidgetType::Button, true, "Menu")
+                                    });
+                                    menu_response
                                 },
                                 |mut val, _ui| {
                                     // Menu button can grow minimally
@@ -1776,6 +3894,52 @@ pub fn is_shortcut_match(input: &egui::InputState, shortcut: &KeyboardShortcut)
     })
 }
 
+/// Selections of the multi cursor besides the primary one, see `SelectNextOccurrence`
+fn paint_extra_selection(
+    ui: &Ui,
+    text: &str,
+    galley: &egui::Galley,
+    selection: ByteSpan,
+    galley_pos: egui::Pos2,
+) {
+    let visuals = ui.visuals();
+    let to_rect = |byte_pos: usize| {
+        galley
+            .pos_from_ccursor(CCursor::new(char_index_from_byte_index(text, byte_pos)))
+            .translate(galley_pos.to_vec2())
+    };
+
+    let (start, end) = (to_rect(selection.start), to_rect(selection.end));
+
+    if selection.is_empty() {
+        ui.painter().line_segment(
+            [start.center_top(), start.center_bottom()],
+            visuals.text_cursor.stroke,
+        );
+        return;
+    }
+
+    let (left, right) = (
+        galley_pos.x + galley.rect.left(),
+        galley_pos.x + galley.rect.right(),
+    );
+
+    let rects = match start.top() == end.top() {
+        true => SmallVec::<[Rect; 3]>::from_iter([Rect::from_min_max(start.min, end.max)]),
+        // the first row till the end, rows in between fully, the last row from the start
+        false => SmallVec::from_iter([
+            Rect::from_min_max(start.min, pos2(right, start.bottom())),
+            Rect::from_min_max(pos2(left, start.bottom()), pos2(right, end.top())),
+            Rect::from_min_max(pos2(left, end.top()), end.max),
+        ]),
+    };
+
+    for rect in rects {
+        ui.painter()
+            .rect_filled(rect, 0., visuals.selection.bg_fill);
+    }
+}
+
 pub fn char_index_from_byte_index(s: &str, byte_index: usize) -> usize {
     for (ci, (bi, _)) in s.char_indices().enumerate() {
         if bi == byte_index {
@@ -1800,3 +3964,67 @@ fn interpolate_color(from: Color32, to: Color32, progress: f32) -> Color32 {
         ((fa + ta) * 255.) as u8,
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_harness::TestHarness;
+
+    use super::*;
+
+    #[test]
+    pub fn test_lang_badge_selects_the_lang() {
+        let mut harness = TestHarness::new("```rust\nlet a = 1;{||}\n```");
+
+        let code_block = harness.state.notes[&NoteFile::Note(0)]
+            .derived_state
+            .structure
+            .iter()
+            .find(|(_, desc)| desc.kind == SpanKind::CodeBlock)
+            .map(|(index, _)| index)
+            .unwrap();
+
+        harness.dispatch(AppAction::SelectCodeBlockLang(
+            NoteFile::Note(0),
+            code_block,
+        ));
+        assert_eq!(harness.text_with_cursor(), "```{|}rust{|}\nlet a = 1;\n```");
+    }
+
+    #[test]
+    pub fn test_popped_out_note_stays_open_across_note_switches() {
+        let mut harness = TestHarness::new("first{||}");
+
+        harness.run_command(CommandInstruction::PopOutNote);
+        harness.dispatch(AppAction::SwitchToNote {
+            note_file: NoteFile::Note(1),
+            via_shortcut: true,
+        });
+        harness.run_command(CommandInstruction::PopOutNote);
+
+        assert_eq!(
+            harness.state.popped_out_notes.keys().collect::<Vec<_>>(),
+            [&NoteFile::Note(0), &NoteFile::Note(1)]
+        );
+
+        // running it again closes the window of the selected note
+        harness.run_command(CommandInstruction::PopOutNote);
+        harness.dispatch(AppAction::ClosePoppedOutNote(NoteFile::Note(0)));
+        assert!(harness.state.popped_out_notes.is_empty());
+    }
+
+    #[test]
+    pub fn test_soft_wrap_is_toggled_per_note() {
+        let mut harness = TestHarness::new("a very long line{||}");
+
+        harness.run_command(CommandInstruction::ToggleSoftWrap);
+        harness.dispatch(AppAction::SwitchToNote {
+            note_file: NoteFile::Note(1),
+            via_shortcut: true,
+        });
+        assert!(harness.state.unwrapped_notes.contains(&NoteFile::Note(0)));
+        assert!(!harness.state.unwrapped_notes.contains(&NoteFile::Note(1)));
+
+        harness.dispatch(AppAction::ToggleSoftWrap(NoteFile::Note(0)));
+        assert!(harness.state.unwrapped_notes.is_empty());
+    }
+}
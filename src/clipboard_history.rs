@@ -0,0 +1,77 @@
+use std::collections::VecDeque;
+
+/// How many of the recent copies are kept
+const CLIPBOARD_HISTORY_SIZE: usize = 10;
+
+/// Max number of chars of an entry shown in the slash palette
+const PREVIEW_LEN: usize = 40;
+
+/// Recently copied code blocks, the most recent first. Kept in memory only
+#[derive(Debug, Default)]
+pub struct ClipboardHistory(VecDeque<String>);
+
+impl ClipboardHistory {
+    /// Copying the same text again moves it to the top
+    pub fn record(&mut self, text: String) {
+        if text.trim().is_empty() {
+            return;
+        }
+
+        self.0.retain(|entry| *entry != text);
+        self.0.push_front(text);
+        self.0.truncate(CLIPBOARD_HISTORY_SIZE);
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+}
+
+/// Single line preview of the entry, e.g. "let a = 1; (+2 lines)"
+pub fn clipboard_entry_preview(entry: &str) -> String {
+    let mut lines = entry.lines().filter(|line| !line.trim().is_empty());
+    let first_line = lines.next().unwrap_or_default().trim();
+
+    let mut preview: String = first_line.chars().take(PREVIEW_LEN).collect();
+    if first_line.chars().count() > PREVIEW_LEN {
+        preview.push('…');
+    }
+
+    match lines.count() {
+        0 => preview,
+        rest => format!("{preview} (+{rest} lines)"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_clipboard_history() {
+        let mut history = ClipboardHistory::default();
+
+        history.record("a".to_string());
+        history.record("b".to_string());
+        history.record("a".to_string());
+        history.record("  \n".to_string());
+
+        assert_eq!(history.entries().collect::<Vec<_>>(), vec!["a", "b"]);
+
+        for i in 0..CLIPBOARD_HISTORY_SIZE {
+            history.record(i.to_string());
+        }
+
+        assert_eq!(history.entries().count(), CLIPBOARD_HISTORY_SIZE);
+        assert_eq!(history.entries().next(), Some("9"));
+
+        assert_eq!(
+            clipboard_entry_preview("\nlet a = 1;\nlet b = 2;\n\nlet c = 3;\n"),
+            "let a = 1; (+2 lines)"
+        );
+        assert_eq!(
+            clipboard_entry_preview(&"a".repeat(PREVIEW_LEN + 1)),
+            format!("{}…", "a".repeat(PREVIEW_LEN))
+        );
+    }
+}
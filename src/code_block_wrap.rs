@@ -0,0 +1,129 @@
+use std::ops::Range;
+
+use eframe::epaint::{Galley, Rect, Vec2, text::Row, vec2};
+
+/// Takes the rows of the paragraphs that start inside `no_wrap` (char ranges) from `unwrapped`
+/// and the rest from `wrapped`, the rows below are shifted accordingly.
+/// Both galleys must be laid out from the same job, with different `wrap.max_width`
+pub fn merge_unwrapped_rows(
+    wrapped: &Galley,
+    unwrapped: &Galley,
+    no_wrap: &[Range<usize>],
+) -> Galley {
+    let mut rows: Vec<Row> = Vec::with_capacity(wrapped.rows.len());
+    let mut char_pos = 0;
+    let mut y = 0.0;
+
+    // the same text has the same paragraphs, only wrapped into a different number of rows
+    let paragraphs = wrapped
+        .rows
+        .split_inclusive(|row| row.ends_with_newline)
+        .zip(unwrapped.rows.split_inclusive(|row| row.ends_with_newline));
+
+    for (wrapped_rows, unwrapped_rows) in paragraphs {
+        let source = match no_wrap.iter().any(|range| range.contains(&char_pos)) {
+            true => unwrapped_rows,
+            false => wrapped_rows,
+        };
+
+        char_pos += source
+            .iter()
+            .map(|row| row.char_count_including_newline())
+            .sum::<usize>();
+
+        let (Some(first), Some(last)) = (source.first(), source.last()) else {
+            continue;
+        };
+
+        let delta = vec2(0.0, y - first.rect.top());
+        y = last.rect.bottom() + delta.y;
+
+        rows.extend(source.iter().cloned().map(|row| translate_row(row, delta)));
+    }
+
+    let rect = rows
+        .iter()
+        .fold(Rect::NOTHING, |rect, row| rect.union(row.rect));
+    let mesh_bounds = rows.iter().fold(Rect::NOTHING, |rect, row| {
+        rect.union(row.visuals.mesh_bounds)
+    });
+
+    Galley {
+        job: wrapped.job.clone(),
+        num_vertices: rows.iter().map(|row| row.visuals.mesh.vertices.len()).sum(),
+        num_indices: rows.iter().map(|row| row.visuals.mesh.indices.len()).sum(),
+        rows,
+        elided: wrapped.elided,
+        rect,
+        mesh_bounds,
+        pixels_per_point: wrapped.pixels_per_point,
+    }
+}
+
+fn translate_row(mut row: Row, delta: Vec2) -> Row {
+    row.rect = row.rect.translate(delta);
+    row.visuals.mesh.translate(delta);
+    row.visuals.mesh_bounds = row.visuals.mesh_bounds.translate(delta);
+    for glyph in row.glyphs.iter_mut() {
+        glyph.pos += delta;
+    }
+    row
+}
+
+#[cfg(test)]
+mod tests {
+    use eframe::epaint::{
+        Color32, FontId,
+        text::{Fonts, LayoutJob},
+    };
+
+    use crate::theme::get_font_definitions;
+
+    use super::*;
+
+    #[test]
+    pub fn test_merge_unwrapped_rows() {
+        let fonts = Fonts::new(1.0, 1024, get_font_definitions());
+
+        let long_line = "word ".repeat(20);
+        let text = format!("{long_line}\n{long_line}\n{long_line}");
+        let second_line = long_line.chars().count() + 1..2 * (long_line.chars().count() + 1);
+
+        let job = LayoutJob::simple(text, FontId::monospace(12.0), Color32::WHITE, 100.0);
+        let mut unwrapped_job = job.clone();
+        unwrapped_job.wrap.max_width = f32::INFINITY;
+
+        let wrapped = fonts.layout_job(job);
+        let unwrapped = fonts.layout_job(unwrapped_job);
+        let merged = merge_unwrapped_rows(&wrapped, &unwrapped, &[second_line]);
+
+        let rows_per_line = wrapped.rows.len() / 3;
+        assert!(rows_per_line > 1);
+        assert_eq!(merged.rows.len(), 2 * rows_per_line + 1);
+
+        // the middle line is a single row that overflows the wrap width
+        let middle = &merged.rows[rows_per_line];
+        assert!(middle.rect.width() > 100.0);
+        assert_eq!(
+            middle.rect.top(),
+            merged.rows[rows_per_line - 1].rect.bottom()
+        );
+        assert_eq!(
+            merged.rows[rows_per_line + 1].rect.top(),
+            middle.rect.bottom()
+        );
+
+        assert_eq!(
+            merged
+                .rows
+                .iter()
+                .map(|row| row.char_count_including_newline())
+                .sum::<usize>(),
+            wrapped
+                .rows
+                .iter()
+                .map(|row| row.char_count_including_newline())
+                .sum::<usize>()
+        );
+    }
+}
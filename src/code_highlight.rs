@@ -0,0 +1,341 @@
+use std::ops::Range;
+
+use eframe::epaint::Color32;
+use fxhash::{FxHashMap, FxHashSet, hash64};
+use syntect::{
+    easy::HighlightLines, highlighting::ThemeSet, parsing::SyntaxSet, util::LinesWithEndings,
+};
+use tree_sitter::Language;
+use tree_sitter_highlight::{HighlightConfiguration, HighlightEvent};
+
+use crate::{nord::Nord, theme::ColorManipulation};
+
+/// Syntect theme of the code blocks
+const SYNTAX_THEME: &str = "base16-ocean.dark";
+
+/// Colors of the byte ranges of a piece of code
+pub type HighlightedCode = Vec<(Range<usize>, Color32)>;
+
+/// Which engine highlights the code of a language
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Highlighter {
+    Syntect,
+    TreeSitter,
+}
+
+struct TreeSitterGrammar {
+    /// Code block languages (info strings) the grammar is used for
+    langs: &'static [&'static str],
+    language: fn() -> Language,
+    highlights_query: &'static str,
+    injections_query: &'static str,
+    locals_query: &'static str,
+}
+
+/// Grammars that can be chosen over syntect in the editor settings,
+/// a grammar is used regardless of the settings if syntect doesn't know the language (e.g. kdl)
+const TREE_SITTER_GRAMMARS: [TreeSitterGrammar; 3] = [
+    TreeSitterGrammar {
+        langs: &["kdl"],
+        language: tree_sitter_kdl::language,
+        highlights_query: tree_sitter_kdl::HIGHLIGHTS_QUERY,
+        injections_query: tree_sitter_kdl::INJECTIONS_QUERY,
+        locals_query: tree_sitter_kdl::LOCALS_QUERY,
+    },
+    TreeSitterGrammar {
+        langs: &["rs", "rust"],
+        language: tree_sitter_rust::language,
+        highlights_query: tree_sitter_rust::HIGHLIGHT_QUERY,
+        injections_query: tree_sitter_rust::INJECTIONS_QUERY,
+        locals_query: "",
+    },
+    TreeSitterGrammar {
+        langs: &["js", "javascript", "jsx"],
+        language: tree_sitter_javascript::language,
+        highlights_query: tree_sitter_javascript::HIGHLIGHT_QUERY,
+        injections_query: tree_sitter_javascript::INJECTION_QUERY,
+        locals_query: tree_sitter_javascript::LOCALS_QUERY,
+    },
+];
+
+fn find_grammar(lang: &str) -> Option<&'static TreeSitterGrammar> {
+    TREE_SITTER_GRAMMARS
+        .iter()
+        .find(|grammar| grammar.langs.contains(&lang))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct HighlightKey {
+    code_hash: u64,
+    lang: String,
+    highlighter: Highlighter,
+    theme: &'static str,
+}
+
+/// Keeps the highlighting of the code blocks from the previous layout,
+/// so only the changed ones are highlighted again
+#[derive(Debug, Default)]
+pub struct CodeHighlightCache {
+    entries: FxHashMap<HighlightKey, HighlightedCode>,
+    used: FxHashSet<HighlightKey>,
+    /// Languages that opted into tree-sitter via the editor settings
+    tree_sitter_langs: Vec<String>,
+}
+
+impl CodeHighlightCache {
+    /// Returns true if the list has changed, that is, the code blocks need to be laid out again
+    pub fn set_tree_sitter_langs(&mut self, langs: Vec<String>) -> bool {
+        let changed = self.tree_sitter_langs != langs;
+        self.tree_sitter_langs = langs;
+        changed
+    }
+
+    /// Tree-sitter if the language opted into it (with any of its names) or syntect doesn't support it
+    pub fn highlighter_for(&self, lang: &str, syntax_set: &SyntaxSet) -> Highlighter {
+        match find_grammar(lang) {
+            Some(grammar)
+                if syntax_set.find_syntax_by_extension(lang).is_none()
+                    || grammar
+                        .langs
+                        .iter()
+                        .any(|name| self.tree_sitter_langs.iter().any(|l| l == name)) =>
+            {
+                Highlighter::TreeSitter
+            }
+            _ => Highlighter::Syntect,
+        }
+    }
+
+    /// `None` if the language is not supported, the code is then rendered as is
+    pub fn highlight(
+        &mut self,
+        code: &str,
+        lang: &str,
+        syntax_set: &SyntaxSet,
+        theme_set: &ThemeSet,
+    ) -> Option<&HighlightedCode> {
+        let key = HighlightKey {
+            code_hash: hash64(code),
+            lang: lang.to_string(),
+            highlighter: self.highlighter_for(lang, syntax_set),
+            theme: SYNTAX_THEME,
+        };
+
+        if !self.entries.contains_key(&key) {
+            let highlighted = match key.highlighter {
+                Highlighter::Syntect => highlight_with_syntect(code, lang, syntax_set, theme_set)?,
+                Highlighter::TreeSitter => highlight_with_tree_sitter(code, find_grammar(lang)?)?,
+            };
+            self.entries.insert(key.clone(), highlighted);
+        }
+
+        self.used.insert(key.clone());
+        self.entries.get(&key)
+    }
+
+    /// Drops the code that wasn't highlighted since the previous call, e.g. the edited versions of a block
+    pub fn retain_used(&mut self) {
+        let used = std::mem::take(&mut self.used);
+        self.entries.retain(|key, _| used.contains(key));
+    }
+}
+
+fn highlight_with_syntect(
+    code: &str,
+    lang: &str,
+    syntax_set: &SyntaxSet,
+    theme_set: &ThemeSet,
+) -> Option<HighlightedCode> {
+    let syntax = syntax_set.find_syntax_by_extension(lang)?;
+    let mut h = HighlightLines::new(syntax, &theme_set.themes[SYNTAX_THEME]);
+    let mut highlighted = vec![];
+    let mut start = 0;
+
+    for line in LinesWithEndings::from(code) {
+        let ranges = h.highlight_line(line, syntax_set).unwrap();
+        for (style, part) in ranges {
+            let front = style.foreground;
+            highlighted.push((
+                start..start + part.len(),
+                Color32::from_rgb(front.r, front.g, front.b),
+            ));
+            start += part.len();
+        }
+    }
+
+    Some(highlighted)
+}
+
+/// Colors of the tree-sitter captures shared by all grammars,
+/// a capture such as "function.method" falls back to "function" if it isn't listed
+fn tree_sitter_colors() -> [(&'static str, Color32); 28] {
+    [
+        ("type", Nord::NORD6),
+        ("type.builtin", Nord::NORD9),
+        ("property", Nord::NORD8),
+        ("variable", Nord::NORD4),
+        ("variable.builtin", Nord::NORD9),
+        ("variable.parameter", Nord::NORD4),
+        ("string", Nord::NORD14),
+        ("string.escape", Nord::NORD13),
+        ("string.special", Nord::NORD13),
+        ("escape", Nord::NORD13),
+        ("number", Nord::NORD15),
+        ("float", Nord::NORD15),
+        ("boolean", Nord::NORD9),
+        ("constant", Nord::NORD4),
+        ("constant.builtin", Nord::NORD9),
+        ("keyword", Nord::NORD9),
+        ("operator", Nord::NORD9),
+        ("function", Nord::NORD8),
+        ("function.macro", Nord::NORD8),
+        ("constructor", Nord::NORD7),
+        ("attribute", Nord::NORD12),
+        ("label", Nord::NORD12),
+        ("embedded", Nord::NORD4),
+        ("punctuation.bracket", Nord::NORD10),
+        ("punctuation.delimiter", Nord::NORD9),
+        ("punctuation.special", Nord::NORD9),
+        ("comment", Nord::NORD3.shade(1.2)),
+        ("spell", Nord::NORD3.shade(1.2)),
+    ]
+}
+
+/// `None` if the grammar queries are invalid or the code couldn't be parsed
+fn highlight_with_tree_sitter(code: &str, grammar: &TreeSitterGrammar) -> Option<HighlightedCode> {
+    let mut config = HighlightConfiguration::new(
+        (grammar.language)(),
+        grammar.highlights_query,
+        grammar.injections_query,
+        grammar.locals_query,
+    )
+    .ok()?;
+
+    let colors = tree_sitter_colors();
+    config.configure(&colors.map(|(name, _)| name));
+
+    let mut highlighter = tree_sitter_highlight::Highlighter::new();
+    let highlights = highlighter
+        .highlight(&config, code.as_bytes(), None, |_| None)
+        .ok()?;
+
+    let mut highlighted = vec![];
+    let mut color_stack = vec![Nord::NORD4];
+
+    for event in highlights {
+        match event.ok()? {
+            HighlightEvent::Source { start, end } => {
+                highlighted.push((start..end, *color_stack.last().unwrap()))
+            }
+            HighlightEvent::HighlightStart(s) => color_stack.push(colors[s.0].1),
+            HighlightEvent::HighlightEnd => {
+                color_stack.pop();
+            }
+        }
+    }
+
+    Some(highlighted)
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+
+    #[test]
+    pub fn test_unchanged_code_is_highlighted_once() {
+        let syntax_set = SyntaxSet::load_defaults_newlines();
+        let theme_set = ThemeSet::load_defaults();
+        let mut cache = CodeHighlightCache::default();
+
+        let code = "fn main() {\n    let a = 1;\n}\n";
+        let highlighted = cache
+            .highlight(code, "rs", &syntax_set, &theme_set)
+            .cloned()
+            .unwrap();
+
+        let covered: String = highlighted
+            .iter()
+            .map(|(range, _)| &code[range.clone()])
+            .collect();
+        assert_eq!(covered, code);
+        assert!(highlighted.iter().map(|(_, color)| color).unique().count() > 1);
+
+        cache.retain_used();
+        cache.highlight(code, "rs", &syntax_set, &theme_set);
+        cache.highlight("a = 1", "kdl", &syntax_set, &theme_set);
+        assert_eq!(cache.entries.len(), 2);
+
+        assert_eq!(
+            cache.highlight(code, "unknown", &syntax_set, &theme_set),
+            None
+        );
+
+        // only the kdl block is left after the next layout
+        cache.retain_used();
+        cache.highlight("a = 1", "kdl", &syntax_set, &theme_set);
+        cache.retain_used();
+        assert_eq!(cache.entries.len(), 1);
+    }
+
+    #[test]
+    pub fn test_tree_sitter_is_opted_in_per_language() {
+        let syntax_set = SyntaxSet::load_defaults_newlines();
+        let theme_set = ThemeSet::load_defaults();
+        let mut cache = CodeHighlightCache::default();
+
+        assert_eq!(
+            cache.highlighter_for("rs", &syntax_set),
+            Highlighter::Syntect
+        );
+        assert_eq!(
+            cache.highlighter_for("js", &syntax_set),
+            Highlighter::Syntect
+        );
+        // syntect doesn't know kdl
+        assert_eq!(
+            cache.highlighter_for("kdl", &syntax_set),
+            Highlighter::TreeSitter
+        );
+
+        // any name of the language opts it in
+        assert!(cache.set_tree_sitter_langs(vec!["rust".to_string()]));
+        assert!(!cache.set_tree_sitter_langs(vec!["rust".to_string()]));
+        assert_eq!(
+            cache.highlighter_for("rs", &syntax_set),
+            Highlighter::TreeSitter
+        );
+        assert_eq!(
+            cache.highlighter_for("js", &syntax_set),
+            Highlighter::Syntect
+        );
+        assert_eq!(
+            cache.highlighter_for("py", &syntax_set),
+            Highlighter::Syntect
+        );
+
+        for (code, lang) in [
+            ("fn main() {\n    let a = \"b\";\n}\n", "rs"),
+            ("const a = () => console.log(\"b\");\n", "javascript"),
+        ] {
+            cache.set_tree_sitter_langs(vec![lang.to_string()]);
+            assert_eq!(
+                cache.highlighter_for(lang, &syntax_set),
+                Highlighter::TreeSitter
+            );
+
+            let highlighted = cache
+                .highlight(code, lang, &syntax_set, &theme_set)
+                .cloned()
+                .unwrap();
+
+            let covered: String = highlighted
+                .iter()
+                .map(|(range, _)| &code[range.clone()])
+                .collect();
+            assert_eq!(covered, code);
+            assert!(highlighted.iter().map(|(_, color)| color).unique().count() > 1);
+        }
+    }
+}
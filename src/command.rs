@@ -13,7 +13,7 @@ use crate::{
     persistent_state::NoteFile,
     scripting::settings_eval::Scripts,
     settings_parsing::{format_mac_shortcut_with_names, format_mac_shortcut_with_symbols},
-    text_structure::TextStructure,
+    text_structure::{CalloutKind, TextStructure},
 };
 
 #[derive(Debug, Clone, Copy)]
@@ -203,6 +203,10 @@ pub enum CommandInstruction {
     UnindentListItem,
     #[knus(skip)]
     SplitListItem,
+    #[knus(skip)]
+    NextTableCell,
+    #[knus(skip)]
+    PrevTableCell,
 
     // Markdown
     #[knus(name = "MarkdownBold")]
@@ -226,6 +230,105 @@ pub enum CommandInstruction {
     #[knus(name = "MarkdownH3")]
     MarkdownH3,
 
+    #[knus(name = "IncreaseHeadingLevel")]
+    IncreaseHeadingLevel,
+
+    #[knus(name = "DecreaseHeadingLevel")]
+    DecreaseHeadingLevel,
+
+    #[knus(name = "CycleTaskState")]
+    CycleTaskState,
+
+    #[knus(name = "ConvertToTasks")]
+    ConvertToTasks,
+
+    #[knus(name = "InsertTable")]
+    InsertTable,
+
+    #[knus(name = "InsertHorizontalRule")]
+    InsertHorizontalRule,
+
+    #[knus(name = "InsertTimestamp")]
+    InsertTimestamp,
+
+    #[knus(name = "PasteAsQuote")]
+    PasteAsQuote,
+
+    #[knus(name = "CopyNoteAsImage")]
+    CopyNoteAsImage,
+
+    #[knus(name = "SelectNextOccurrence")]
+    SelectNextOccurrence,
+
+    #[knus(name = "RenameInCodeBlock")]
+    RenameInCodeBlock,
+
+    #[knus(name = "FindReplace")]
+    FindReplace,
+
+    #[knus(name = "ExtractCodeBlock")]
+    ExtractCodeBlock,
+
+    #[knus(name = "InsertNoteLink")]
+    InsertNoteLink,
+
+    #[knus(name = "InsertNotePath")]
+    InsertNotePath,
+
+    #[knus(name = "CopyWithHeadingPath")]
+    CopyWithHeadingPath,
+
+    #[knus(name = "PinSelection")]
+    PinSelection,
+
+    #[knus(name = "WrapInDetails")]
+    WrapInDetails,
+
+    #[knus(name = "CollapseAllDetails")]
+    CollapseAllDetails,
+
+    #[knus(name = "ExpandAllDetails")]
+    ExpandAllDetails,
+
+    #[knus(name = "EscapeMarkdown")]
+    EscapeMarkdown,
+
+    #[knus(name = "UnescapeMarkdown")]
+    UnescapeMarkdown,
+
+    #[knus(name = "FormatNote")]
+    FormatNote,
+
+    #[knus(name = "ToggleCallout")]
+    ToggleCallout(#[knus(argument)] CalloutKind),
+
+    #[knus(name = "OpenInExternalEditor")]
+    OpenInExternalEditor,
+
+    #[knus(name = "ExportNote")]
+    ExportNote,
+
+    #[knus(name = "NextSiblingHeading")]
+    NextSiblingHeading,
+
+    #[knus(name = "PrevSiblingHeading")]
+    PrevSiblingHeading,
+
+    #[knus(name = "ParentHeading")]
+    ParentHeading,
+
+    #[knus(name = "NextCodeBlock")]
+    NextCodeBlock,
+
+    #[knus(name = "PrevCodeBlock")]
+    PrevCodeBlock,
+
+    #[knus(name = "TransposeChars")]
+    TransposeChars,
+
+    #[knus(name = "JoinLines")]
+    JoinLines,
+
     // Others
     #[knus(name = "SwitchToNote")]
     SwitchToNote(#[knus(argument)] u8),
@@ -233,20 +336,77 @@ pub enum CommandInstruction {
     #[knus(name = "SwitchToSettings")]
     SwitchToSettings,
 
+    #[knus(name = "SwitchToNextNote")]
+    SwitchToNextNote,
+
+    #[knus(name = "SwitchToPrevNote")]
+    SwitchToPrevNote,
+
+    #[knus(name = "CycleRecentNotes")]
+    CycleRecentNotes,
+
+    #[knus(name = "CycleRecentNotesBack")]
+    CycleRecentNotesBack,
+
+    #[knus(name = "FilterNotesByTag")]
+    FilterNotesByTag,
+
+    #[knus(name = "ToggleReadingMode")]
+    ToggleReadingMode,
+
+    #[knus(name = "TogglePreviewPanel")]
+    TogglePreviewPanel,
+
+    #[knus(name = "ToggleSoftWrap")]
+    ToggleSoftWrap,
+
+    #[knus(name = "ToggleNoteEncryption")]
+    ToggleNoteEncryption,
+
+    #[knus(name = "ToggleNoteLock")]
+    ToggleNoteLock,
+
+    #[knus(name = "ToggleFocusSession")]
+    ToggleFocusSession,
+
+    #[knus(name = "DuplicateNote")]
+    DuplicateNote,
+
+    #[knus(name = "PopOutNote")]
+    PopOutNote,
+
+    #[knus(name = "ClearNote")]
+    ClearNote,
+
+    #[knus(name = "ShowRecoveryBin")]
+    ShowRecoveryBin,
+
     #[knus(name = "PinWindow")]
     PinWindow,
 
     #[knus(name = "HideApp")]
     HideApp,
 
+    #[knus(name = "ToggleCrashReporting")]
+    ToggleCrashReporting,
+
+    #[knus(name = "ToggleStartAtLogin")]
+    ToggleStartAtLogin,
+
     // SlashPallete
     #[knus(skip)]
     ShowSlashPallete,
 
+    #[knus(name = "ToggleSlashPallete")]
+    ToggleSlashPallete,
+
     // Lang specific
     #[knus(skip)]
     EnterInsideKDL,
 
+    #[knus(skip)]
+    EnterInsideCodeBlock,
+
     #[knus(skip)]
     BracketAutoclosingInsideKDL,
 
@@ -284,6 +444,8 @@ impl CommandInstruction {
             Self::IndentListItem => "Increase List Item identation".into(),
             Self::UnindentListItem => "Decrease List Item identation".into(),
             Self::SplitListItem => "Split List item at cursor position".into(),
+            Self::NextTableCell => "Move to the next Table cell".into(),
+            Self::PrevTableCell => "Move to the previous Table cell".into(),
             Self::MarkdownBold => "Toggle Bold".into(),
             Self::MarkdownItalic => "Toggle Italic".into(),
             Self::MarkdownStrikethrough => "Toggle Strikethrough".into(),
@@ -294,6 +456,39 @@ impl CommandInstruction {
             Self::MarkdownH1 => "Heading 1".into(),
             Self::MarkdownH2 => "Heading 2".into(),
             Self::MarkdownH3 => "Heading 3".into(),
+            Self::IncreaseHeadingLevel => "Increase Heading Level".into(),
+            Self::DecreaseHeadingLevel => "Decrease Heading Level".into(),
+            Self::CycleTaskState => "Cycle Task State".into(),
+            Self::ConvertToTasks => "Convert Lines to Tasks".into(),
+            Self::InsertTable => "Insert Table".into(),
+            Self::InsertHorizontalRule => "Insert Horizontal Rule".into(),
+            Self::InsertTimestamp => "Insert Current Date and Time".into(),
+            Self::PasteAsQuote => "Paste Clipboard as Quote".into(),
+            Self::CopyNoteAsImage => "Copy Note as Image".into(),
+            Self::ExtractCodeBlock => "Move Code Block to Another Shelf".into(),
+            Self::InsertNoteLink => "Insert Link to the Shelf".into(),
+            Self::InsertNotePath => "Insert Path of the Shelf File".into(),
+            Self::CopyWithHeadingPath => "Copy with Heading Path".into(),
+            Self::PinSelection => "Pin Selection as Reference".into(),
+            Self::WrapInDetails => "Wrap in Collapsible Details".into(),
+            Self::CollapseAllDetails => "Collapse All Details".into(),
+            Self::ExpandAllDetails => "Expand All Details".into(),
+            Self::EscapeMarkdown => "Escape Markdown Characters".into(),
+            Self::UnescapeMarkdown => "Unescape Markdown Characters".into(),
+            Self::FormatNote => "Format Shelf".into(),
+            Self::ToggleCallout(kind) => format!("{} Callout", kind.title()).into(),
+            Self::OpenInExternalEditor => "Open Shelf in External Editor".into(),
+            Self::ExportNote => "Export Shelf to a Markdown File".into(),
+            Self::SelectNextOccurrence => "Select Next Occurrence".into(),
+            Self::RenameInCodeBlock => "Rename in Code Block".into(),
+            Self::FindReplace => "Find and Replace".into(),
+            Self::NextSiblingHeading => "Go to Next Heading of the Same Level".into(),
+            Self::PrevSiblingHeading => "Go to Previous Heading of the Same Level".into(),
+            Self::ParentHeading => "Go to Parent Heading".into(),
+            Self::NextCodeBlock => "Go to Next Code Block".into(),
+            Self::PrevCodeBlock => "Go to Previous Code Block".into(),
+            Self::TransposeChars => "Transpose Characters".into(),
+            Self::JoinLines => "Join Lines".into(),
             Self::SwitchToNote(n) => {
                 let note_index = *n;
                 match note_index {
@@ -305,15 +500,34 @@ impl CommandInstruction {
                 }
             }
             Self::SwitchToSettings => "Open Settings".into(),
+            Self::SwitchToNextNote => "Switch to Next Shelf".into(),
+            Self::SwitchToPrevNote => "Switch to Previous Shelf".into(),
+            Self::CycleRecentNotes => "Switch to Recent Shelf".into(),
+            Self::CycleRecentNotesBack => "Switch to Recent Shelf (backwards)".into(),
+            Self::FilterNotesByTag => "Find Shelves by Tag".into(),
+            Self::ToggleReadingMode => "Toggle Reading Mode".into(),
+            Self::TogglePreviewPanel => "Toggle Preview Panel".into(),
+            Self::ToggleSoftWrap => "Toggle Soft Wrap".into(),
+            Self::ToggleNoteEncryption => "Toggle Shelf Encryption".into(),
+            Self::ToggleNoteLock => "Toggle Shelf Lock".into(),
+            Self::ToggleFocusSession => "Start/Stop Focus Session".into(),
+            Self::DuplicateNote => "Duplicate Shelf into an Empty One".into(),
+            Self::PopOutNote => "Open Shelf in a Separate Window".into(),
+            Self::ClearNote => "Clear Shelf".into(),
+            Self::ShowRecoveryBin => "Restore a Cleared Shelf".into(),
             Self::PinWindow => "Toggle Always on Top".into(),
             Self::HideApp => "Hide Window".into(),
+            Self::ToggleCrashReporting => "Toggle Crash Reporting".into(),
+            Self::ToggleStartAtLogin => "Toggle Start at Login".into(),
             // Self::RunLLMBlock => "Execute AI Block".into(),
             CommandInstruction::ShowPrompt => "Show AI Prompt".into(),
             CommandInstruction::EnterInsideKDL => "Auto indent KDL".into(),
+            CommandInstruction::EnterInsideCodeBlock => "Auto indent code".into(),
             CommandInstruction::BracketAutoclosingInsideKDL => {
                 "Auto closing of '{' inside KDL".into()
             }
             CommandInstruction::ShowSlashPallete => "Show slash command palette".into(),
+            CommandInstruction::ToggleSlashPallete => "Toggle command palette".into(),
             CommandInstruction::InsertText(ForwardToChild(source)) => match source {
                 TextSource::Str(str) => format!("Insert: {}", str).into(),
                 TextSource::Script(script_call) => {
@@ -332,6 +546,8 @@ impl CommandInstruction {
             C::IndentListItem => shortcut(Modifiers::NONE, Key::Tab),
             C::UnindentListItem => shortcut(Modifiers::SHIFT, Key::Tab),
             C::SplitListItem => shortcut(Modifiers::NONE, Key::Enter),
+            C::NextTableCell => shortcut(Modifiers::NONE, Key::Tab),
+            C::PrevTableCell => shortcut(Modifiers::SHIFT, Key::Tab),
             C::MarkdownCodeBlock(None) => shortcut(Modifiers::COMMAND.plus(Modifiers::ALT), Key::B),
             C::MarkdownBold => shortcut(Modifiers::COMMAND, Key::B),
             C::MarkdownItalic => shortcut(Modifiers::COMMAND, Key::I),
@@ -339,6 +555,15 @@ impl CommandInstruction {
             C::MarkdownH1 => shortcut(Modifiers::COMMAND.plus(Modifiers::ALT), Key::Num1),
             C::MarkdownH2 => shortcut(Modifiers::COMMAND.plus(Modifiers::ALT), Key::Num2),
             C::MarkdownH3 => shortcut(Modifiers::COMMAND.plus(Modifiers::ALT), Key::Num3),
+            C::IncreaseHeadingLevel => shortcut(Modifiers::COMMAND, Key::CloseBracket),
+            C::DecreaseHeadingLevel => shortcut(Modifiers::COMMAND, Key::OpenBracket),
+            C::CollapseAllDetails => shortcut(Modifiers::COMMAND.plus(Modifiers::ALT), Key::Minus),
+            C::ExpandAllDetails => shortcut(Modifiers::COMMAND.plus(Modifiers::ALT), Key::Equals),
+            C::InsertHorizontalRule => {
+                shortcut(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::Minus)
+            }
+            C::InsertTimestamp => shortcut(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::D),
+            C::CycleTaskState => shortcut(Modifiers::COMMAND, Key::Enter),
             C::SwitchToNote(0) => shortcut(Modifiers::COMMAND, Key::Num1),
             C::SwitchToNote(1) => shortcut(Modifiers::COMMAND, Key::Num2),
             C::SwitchToNote(2) => shortcut(Modifiers::COMMAND, Key::Num3),
@@ -346,14 +571,68 @@ impl CommandInstruction {
             // TODO figure out how to make it more bulletproof, option maybe?
             C::SwitchToNote(_) => shortcut(Modifiers::COMMAND, Key::Num0),
             C::SwitchToSettings => shortcut(Modifiers::COMMAND, Key::Comma),
+            C::SwitchToNextNote => {
+                shortcut(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::CloseBracket)
+            }
+            C::SwitchToPrevNote => {
+                shortcut(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::OpenBracket)
+            }
+            C::CycleRecentNotes => shortcut(Modifiers::CTRL, Key::Tab),
+            C::CycleRecentNotesBack => shortcut(Modifiers::CTRL.plus(Modifiers::SHIFT), Key::Tab),
+            C::FilterNotesByTag => shortcut(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::T),
+            C::ToggleReadingMode => shortcut(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::R),
+            C::ToggleSoftWrap => shortcut(Modifiers::ALT, Key::Z),
+            C::ExportNote => shortcut(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::S),
             C::PinWindow => shortcut(Modifiers::COMMAND, Key::P),
+            C::SelectNextOccurrence => shortcut(Modifiers::COMMAND, Key::D),
+            C::RenameInCodeBlock => shortcut(Modifiers::NONE, Key::F2),
+            C::FindReplace => shortcut(Modifiers::COMMAND.plus(Modifiers::ALT), Key::F),
+            C::NextSiblingHeading => {
+                shortcut(Modifiers::COMMAND.plus(Modifiers::ALT), Key::ArrowDown)
+            }
+            C::PrevSiblingHeading => {
+                shortcut(Modifiers::COMMAND.plus(Modifiers::ALT), Key::ArrowUp)
+            }
+            C::ParentHeading => shortcut(Modifiers::COMMAND.plus(Modifiers::ALT), Key::ArrowLeft),
+            C::NextCodeBlock => shortcut(Modifiers::COMMAND.plus(Modifiers::CTRL), Key::ArrowDown),
+            C::PrevCodeBlock => shortcut(Modifiers::COMMAND.plus(Modifiers::CTRL), Key::ArrowUp),
+            C::TransposeChars => shortcut(Modifiers::CTRL, Key::T),
+            C::JoinLines => shortcut(Modifiers::COMMAND, Key::J),
             // C::RunLLMBlock => shortcut(Modifiers::COMMAND, Key::Enter),
             C::ShowPrompt => shortcut(Modifiers::CTRL, Key::Enter),
             C::EnterInsideKDL => shortcut(Modifiers::NONE, Key::Enter),
+            C::EnterInsideCodeBlock => shortcut(Modifiers::NONE, Key::Enter),
             C::BracketAutoclosingInsideKDL => shortcut(Modifiers::SHIFT, Key::OpenBracket),
             C::HideApp => shortcut(Modifiers::NONE, Key::Escape),
             C::ShowSlashPallete => shortcut(Modifiers::NONE, Key::Slash),
-            C::InsertText(_) | C::MarkdownCodeBlock(_) => None,
+            C::ToggleSlashPallete => shortcut(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::P),
+            C::InsertText(_)
+            | C::MarkdownCodeBlock(_)
+            | C::InsertTable
+            | C::PasteAsQuote
+            | C::CopyNoteAsImage
+            | C::ExtractCodeBlock
+            | C::InsertNoteLink
+            | C::InsertNotePath
+            | C::CopyWithHeadingPath
+            | C::PinSelection
+            | C::WrapInDetails
+            | C::EscapeMarkdown
+            | C::UnescapeMarkdown
+            | C::FormatNote
+            | C::ConvertToTasks
+            | C::ToggleCallout(_)
+            | C::OpenInExternalEditor
+            | C::DuplicateNote
+            | C::PopOutNote
+            | C::ClearNote
+            | C::ShowRecoveryBin
+            | C::ToggleNoteEncryption
+            | C::ToggleNoteLock
+            | C::ToggleFocusSession
+            | C::TogglePreviewPanel
+            | C::ToggleCrashReporting
+            | C::ToggleStartAtLogin => None,
         }
     }
 
@@ -363,8 +642,11 @@ impl CommandInstruction {
             | Self::IndentListItem
             | Self::UnindentListItem
             | Self::SplitListItem
+            | Self::NextTableCell
+            | Self::PrevTableCell
             | Self::ShowSlashPallete
             | Self::EnterInsideKDL
+            | Self::EnterInsideCodeBlock
             | Self::BracketAutoclosingInsideKDL => None,
 
             Self::MarkdownBold => Some("MarkdownBold;".into()),
@@ -377,10 +659,63 @@ impl CommandInstruction {
             Self::MarkdownH1 => Some("MarkdownH1;".into()),
             Self::MarkdownH2 => Some("MarkdownH2;".into()),
             Self::MarkdownH3 => Some("MarkdownH3;".into()),
+            Self::IncreaseHeadingLevel => Some("IncreaseHeadingLevel;".into()),
+            Self::DecreaseHeadingLevel => Some("DecreaseHeadingLevel;".into()),
+            Self::CycleTaskState => Some("CycleTaskState;".into()),
+            Self::ConvertToTasks => Some("ConvertToTasks;".into()),
+            Self::InsertTable => Some("InsertTable;".into()),
+            Self::InsertHorizontalRule => Some("InsertHorizontalRule;".into()),
+            Self::InsertTimestamp => Some("InsertTimestamp;".into()),
+            Self::PasteAsQuote => Some("PasteAsQuote;".into()),
+            Self::CopyNoteAsImage => Some("CopyNoteAsImage;".into()),
+            Self::ExtractCodeBlock => Some("ExtractCodeBlock;".into()),
+            Self::InsertNoteLink => Some("InsertNoteLink;".into()),
+            Self::InsertNotePath => Some("InsertNotePath;".into()),
+            Self::CopyWithHeadingPath => Some("CopyWithHeadingPath;".into()),
+            Self::PinSelection => Some("PinSelection;".into()),
+            Self::WrapInDetails => Some("WrapInDetails;".into()),
+            Self::CollapseAllDetails => Some("CollapseAllDetails;".into()),
+            Self::ExpandAllDetails => Some("ExpandAllDetails;".into()),
+            Self::EscapeMarkdown => Some("EscapeMarkdown;".into()),
+            Self::UnescapeMarkdown => Some("UnescapeMarkdown;".into()),
+            Self::FormatNote => Some("FormatNote;".into()),
+            Self::ToggleCallout(kind) => {
+                Some(format!("ToggleCallout \"{}\";", kind.title().to_lowercase()).into())
+            }
+            Self::OpenInExternalEditor => Some("OpenInExternalEditor;".into()),
+            Self::ExportNote => Some("ExportNote;".into()),
+            Self::SelectNextOccurrence => Some("SelectNextOccurrence;".into()),
+            Self::RenameInCodeBlock => Some("RenameInCodeBlock;".into()),
+            Self::FindReplace => Some("FindReplace;".into()),
+            Self::NextSiblingHeading => Some("NextSiblingHeading;".into()),
+            Self::PrevSiblingHeading => Some("PrevSiblingHeading;".into()),
+            Self::ParentHeading => Some("ParentHeading;".into()),
+            Self::NextCodeBlock => Some("NextCodeBlock;".into()),
+            Self::PrevCodeBlock => Some("PrevCodeBlock;".into()),
+            Self::TransposeChars => Some("TransposeChars;".into()),
+            Self::JoinLines => Some("JoinLines;".into()),
             Self::SwitchToNote(n) => Some(format!("SwitchToNote {};", n).into()),
             Self::SwitchToSettings => Some("SwitchToSettings;".into()),
+            Self::SwitchToNextNote => Some("SwitchToNextNote;".into()),
+            Self::SwitchToPrevNote => Some("SwitchToPrevNote;".into()),
+            Self::CycleRecentNotes => Some("CycleRecentNotes;".into()),
+            Self::CycleRecentNotesBack => Some("CycleRecentNotesBack;".into()),
+            Self::FilterNotesByTag => Some("FilterNotesByTag;".into()),
+            Self::ToggleReadingMode => Some("ToggleReadingMode;".into()),
+            Self::TogglePreviewPanel => Some("TogglePreviewPanel;".into()),
+            Self::ToggleSoftWrap => Some("ToggleSoftWrap;".into()),
+            Self::ToggleNoteEncryption => Some("ToggleNoteEncryption;".into()),
+            Self::ToggleNoteLock => Some("ToggleNoteLock;".into()),
+            Self::ToggleFocusSession => Some("ToggleFocusSession;".into()),
+            Self::DuplicateNote => Some("DuplicateNote;".into()),
+            Self::PopOutNote => Some("PopOutNote;".into()),
+            Self::ClearNote => Some("ClearNote;".into()),
+            Self::ShowRecoveryBin => Some("ShowRecoveryBin;".into()),
             Self::PinWindow => Some("PinWindow;".into()),
             Self::HideApp => Some("HideApp;".into()),
+            Self::ToggleCrashReporting => Some("ToggleCrashReporting;".into()),
+            Self::ToggleStartAtLogin => Some("ToggleStartAtLogin;".into()),
+            Self::ToggleSlashPallete => Some("ToggleSlashPallete;".into()),
             // Self::RunLLMBlock => Some("ExecutePrompt;".into()),
             Self::ShowPrompt => Some("ShowPrompt;".into()),
             Self::InsertText(ForwardToChild(source)) => match source {
@@ -511,6 +846,7 @@ impl FrameHotkey {
 /// Hotkeys that are only valid until the next render, that is, after the frame fills them in
 /// they can be triggered at the begining of the next one, and then cleared
 /// useful for stuff like modal dialog shortcuts and such
+#[derive(Default)]
 pub struct FrameHotkeys(Vec<FrameHotkey>);
 
 impl FrameHotkeys {
@@ -549,6 +885,8 @@ pub struct CommandList {
     keyboard_commands: Vec<CommandInstance>,
     slash_commands: Vec<SlashPaletteCmd>,
     frame_hotkeys: Vec<FrameHotkey>,
+    /// shown as hints on an empty note, `PROMOTED_COMMANDS` unless configured with `hints`
+    promoted_commands: Vec<CommandInstruction>,
 }
 
 impl Debug for CommandList {
@@ -557,6 +895,7 @@ impl Debug for CommandList {
             .field("defaults", &self.defaults)
             .field("keyboard_commands", &self.keyboard_commands)
             .field("slash_commands", &self.slash_commands)
+            .field("promoted_commands", &self.promoted_commands)
             .finish()
     }
 }
@@ -586,6 +925,7 @@ impl CommandList {
             execute_instruction: Box::new(execute),
             keyboard_commands,
             slash_commands: slash_palette_commands,
+            promoted_commands: PROMOTED_COMMANDS.to_vec(),
         }
     }
 
@@ -617,6 +957,14 @@ impl CommandList {
         self.slash_commands.iter()
     }
 
+    pub fn promoted_commands(&self) -> &[CommandInstruction] {
+        &self.promoted_commands
+    }
+
+    pub fn set_promoted_commands(&mut self, commands: Vec<CommandInstruction>) {
+        self.promoted_commands = commands;
+    }
+
     pub fn find(&self, cmd: CommandInstruction) -> Option<&CommandInstance> {
         self.keyboard_commands
             .iter()
@@ -638,6 +986,40 @@ impl CommandList {
         self.keyboard_commands.push(cmd);
     }
 
+    /// Binding from the settings note. Rebinding a built-in command replaces its default shortcut
+    /// and keeps its scope, e.g. `PinWindow` stays global and `Cmd P` stops pinning the window
+    pub fn bind_user_cmd(
+        &mut self,
+        instruction: CommandInstruction,
+        shortcut: Option<KeyboardShortcut>,
+    ) {
+        let built_in = self
+            .defaults
+            .0
+            .iter()
+            .find(|cmd| cmd.instruction == instruction)
+            .cloned();
+
+        let scope = match built_in {
+            Some(built_in) => {
+                // other user bindings of the same command stay
+                self.keyboard_commands.retain(|cmd| *cmd != built_in);
+                built_in.scope
+            }
+            None => CommandScope::Focus(AppFocus::NoteEditor),
+        };
+
+        self.add_editor_cmd(CommandInstance::user_defined(instruction, shortcut, scope));
+    }
+
+    /// Drops the default binding of a built-in command, user bindings of it stay
+    pub fn unbind_built_in(&mut self, instruction: &CommandInstruction) {
+        let defaults = &self.defaults.0;
+        self.keyboard_commands.retain(|cmd| {
+            cmd.instruction != *instruction || !defaults.iter().any(|default| default == cmd)
+        });
+    }
+
     pub fn add_slash_command(&mut self, cmd: SlashPaletteCmd) {
         // Check for existing command with same prefix
         if let Some(existing_pos) = self
@@ -660,6 +1042,8 @@ impl CommandList {
 
         self.slash_commands.clear();
         self.slash_commands.extend_from_slice(&self.defaults.1);
+
+        self.promoted_commands = PROMOTED_COMMANDS.to_vec();
     }
 
     pub fn run(
@@ -808,3 +1192,47 @@ bind "Cmd I" { MarkdownItalic; }"#;
 
     assert_eq!(docs, expected_docs);
 }
+
+#[test]
+fn test_rebinding_built_in_command_keeps_its_scope() {
+    use eframe::egui::{Key, Modifiers};
+
+    let mut cmd_list = CommandList::new(
+        |_, _| SmallVec::new(),
+        vec![
+            (CommandInstruction::PinWindow, CommandScope::Global),
+            (
+                CommandInstruction::MarkdownBold,
+                CommandScope::Focus(AppFocus::NoteEditor),
+            ),
+        ],
+        vec![],
+    );
+
+    let pin_shortcut = KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::P);
+    cmd_list.bind_user_cmd(CommandInstruction::PinWindow, Some(pin_shortcut));
+
+    let pin_cmds: Vec<_> = cmd_list
+        .keyboard_commands
+        .iter()
+        .filter(|cmd| cmd.instruction == CommandInstruction::PinWindow)
+        .collect();
+
+    assert_eq!(pin_cmds.len(), 1);
+    assert_eq!(pin_cmds[0].shortcut, Some(pin_shortcut));
+    assert_eq!(pin_cmds[0].scope, CommandScope::Global);
+    assert_eq!(
+        cmd_list
+            .find(CommandInstruction::PinWindow)
+            .and_then(|cmd| cmd.shortcut),
+        Some(pin_shortcut)
+    );
+
+    cmd_list.reset_to_defaults();
+    assert_eq!(
+        cmd_list
+            .find(CommandInstruction::PinWindow)
+            .and_then(|cmd| cmd.shortcut),
+        Some(KeyboardShortcut::new(Modifiers::COMMAND, Key::P))
+    );
+}
@@ -0,0 +1,245 @@
+use std::{collections::VecDeque, fs, path::Path};
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+
+use crate::{command::CommandInstruction, settings_parsing::CommandLogSettings};
+
+pub const COMMAND_LOG_FILE_NAME: &str = "command-log.md";
+const DEFAULT_MAX_ENTRIES: usize = 200;
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandOutcome {
+    /// Command produced `n` actions
+    Applied(usize),
+    /// Command was matched but decided not to do anything
+    Skipped,
+}
+
+#[derive(Debug, Clone)]
+pub struct CommandLogEntry {
+    pub timestamp: DateTime<Local>,
+    pub description: String,
+    pub kdl: Option<String>,
+    pub outcome: CommandOutcome,
+}
+
+/// Opt-in, bounded log of executed commands, enabled via `commandLog` in settings
+#[derive(Debug, Default)]
+pub struct CommandLog {
+    settings: Option<CommandLogSettings>,
+    entries: VecDeque<CommandLogEntry>,
+}
+
+impl CommandLog {
+    /// Entries of the previous sessions, so they aren't lost on the first save.
+    /// They are kept (and trimmed) only if the log is enabled once the settings are applied
+    pub fn load(folder: &Path) -> Self {
+        fs::read_to_string(folder.join(COMMAND_LOG_FILE_NAME))
+            .map(|markdown| Self::from_markdown(&markdown))
+            .unwrap_or_default()
+    }
+
+    /// Reverse of `to_markdown`, lines that aren't entries are ignored
+    fn from_markdown(markdown: &str) -> Self {
+        Self {
+            settings: None,
+            entries: markdown.lines().filter_map(parse_markdown_entry).collect(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.settings.as_ref().is_some_and(|s| s.enabled)
+    }
+
+    pub fn max_entries(&self) -> usize {
+        self.settings
+            .as_ref()
+            .and_then(|s| s.max_entries)
+            .map(|n| n as usize)
+            .unwrap_or(DEFAULT_MAX_ENTRIES)
+    }
+
+    pub fn apply_settings(&mut self, settings: Option<CommandLogSettings>) {
+        self.settings = settings;
+        if !self.is_enabled() {
+            self.entries.clear();
+        }
+        self.truncate();
+    }
+
+    /// Returns true if the entry was recorded
+    pub fn record(&mut self, instruction: &CommandInstruction, outcome: CommandOutcome) -> bool {
+        self.record_at(Local::now(), instruction, outcome)
+    }
+
+    fn record_at(
+        &mut self,
+        timestamp: DateTime<Local>,
+        instruction: &CommandInstruction,
+        outcome: CommandOutcome,
+    ) -> bool {
+        if !self.is_enabled() {
+            return false;
+        }
+
+        self.entries.push_back(CommandLogEntry {
+            timestamp,
+            description: instruction.human_description().to_string(),
+            kdl: instruction.serialize_to_kdl().map(|kdl| kdl.to_string()),
+            outcome,
+        });
+
+        self.truncate();
+        true
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &CommandLogEntry> {
+        self.entries.iter()
+    }
+
+    fn truncate(&mut self) {
+        let max = self.max_entries();
+        while self.entries.len() > max {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Markdown representation of the log, kdl snippets can be copied into `bind` blocks to replay a command
+    pub fn to_markdown(&self) -> String {
+        let mut result = "# Command log\n\n".to_string();
+
+        for entry in self.entries.iter() {
+            let outcome = match entry.outcome {
+                CommandOutcome::Applied(count) => format!("applied ({count} actions)"),
+                CommandOutcome::Skipped => "skipped".to_string(),
+            };
+
+            result.push_str(&format!(
+                "- {} **{}** {}",
+                entry.timestamp.format(TIMESTAMP_FORMAT),
+                entry.description,
+                outcome
+            ));
+
+            if let Some(kdl) = &entry.kdl {
+                result.push_str(&format!(" `{}`", kdl.replace('\n', " ")));
+            }
+
+            result.push('\n');
+        }
+
+        result
+    }
+}
+
+/// e.g. `- 2024-05-01 10:30:00 **Toggle Bold** applied (1 actions) `MarkdownBold;``
+fn parse_markdown_entry(line: &str) -> Option<CommandLogEntry> {
+    let (timestamp, rest) = line.strip_prefix("- ")?.split_once(" **")?;
+    let timestamp = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()?;
+    let timestamp = Local.from_local_datetime(&timestamp).earliest()?;
+
+    let (description, rest) = rest.split_once("** ")?;
+
+    let (outcome, kdl) = match rest.split_once(" `") {
+        Some((outcome, kdl)) => (outcome, Some(kdl.strip_suffix('`')?.to_string())),
+        None => (rest, None),
+    };
+
+    let outcome = match outcome {
+        "skipped" => CommandOutcome::Skipped,
+        applied => CommandOutcome::Applied(
+            applied
+                .strip_prefix("applied (")?
+                .strip_suffix(" actions)")?
+                .parse()
+                .ok()?,
+        ),
+    };
+
+    Some(CommandLogEntry {
+        timestamp,
+        description: description.to_string(),
+        kdl,
+        outcome,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn enabled(max_entries: Option<u32>) -> Option<CommandLogSettings> {
+        Some(CommandLogSettings {
+            enabled: true,
+            max_entries,
+        })
+    }
+
+    #[test]
+    pub fn test_command_log_is_opt_in() {
+        let mut log = CommandLog::default();
+        assert!(!log.record(
+            &CommandInstruction::MarkdownBold,
+            CommandOutcome::Applied(1)
+        ));
+        assert_eq!(log.entries().count(), 0);
+
+        log.apply_settings(enabled(None));
+        assert!(log.record(
+            &CommandInstruction::MarkdownBold,
+            CommandOutcome::Applied(1)
+        ));
+        assert_eq!(log.entries().count(), 1);
+
+        log.apply_settings(None);
+        assert_eq!(log.entries().count(), 0);
+    }
+
+    #[test]
+    pub fn test_command_log_is_bounded() {
+        let mut log = CommandLog::default();
+        log.apply_settings(enabled(Some(2)));
+
+        log.record(
+            &CommandInstruction::MarkdownBold,
+            CommandOutcome::Applied(1),
+        );
+        log.record(&CommandInstruction::MarkdownItalic, CommandOutcome::Skipped);
+        log.record(&CommandInstruction::MarkdownH1, CommandOutcome::Applied(1));
+
+        let descriptions: Vec<_> = log.entries().map(|e| e.description.as_str()).collect();
+        assert_eq!(descriptions, ["Toggle Italic", "Heading 1"]);
+    }
+
+    #[test]
+    pub fn test_command_log_markdown() {
+        let mut log = CommandLog::default();
+        log.apply_settings(enabled(None));
+
+        let timestamp = Local.with_ymd_and_hms(2024, 5, 1, 10, 30, 0).unwrap();
+        log.record_at(
+            timestamp,
+            &CommandInstruction::MarkdownBold,
+            CommandOutcome::Applied(1),
+        );
+        log.record_at(
+            timestamp,
+            &CommandInstruction::SplitListItem,
+            CommandOutcome::Skipped,
+        );
+
+        assert_eq!(
+            log.to_markdown(),
+            "# Command log\n\n\
+             - 2024-05-01 10:30:00 **Toggle Bold** applied (1 actions) `MarkdownBold;`\n\
+             - 2024-05-01 10:30:00 **Split List item at cursor position** skipped\n"
+        );
+
+        // the previous sessions are read back from the file
+        let restored = CommandLog::from_markdown(&log.to_markdown());
+        assert_eq!(restored.to_markdown(), log.to_markdown());
+    }
+}
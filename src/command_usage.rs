@@ -0,0 +1,96 @@
+use std::{cmp::Reverse, collections::BTreeMap};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::command::SlashPaletteCmd;
+
+/// How many of the most used commands are surfaced at the top of the slash palette
+const MOST_USED_COUNT: usize = 3;
+
+/// How many times each slash command was executed, keyed by the command prefix.
+/// Persisted in `state.json`
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct CommandUsage(BTreeMap<String, u32>);
+
+impl CommandUsage {
+    pub fn record(&mut self, prefix: &str) {
+        let count = self.0.entry(prefix.to_string()).or_default();
+        *count = count.saturating_add(1);
+    }
+
+    pub fn count(&self, prefix: &str) -> u32 {
+        self.0.get(prefix).copied().unwrap_or(0)
+    }
+
+    /// Moves the most used commands to the front (the rest keep their order),
+    /// returns how many commands were moved
+    pub fn promote_most_used(
+        &self,
+        options: Vec<SlashPaletteCmd>,
+    ) -> (Vec<SlashPaletteCmd>, usize) {
+        let most_used: Vec<usize> = options
+            .iter()
+            .enumerate()
+            .map(|(index, cmd)| (index, self.count(&cmd.prefix)))
+            .filter(|(_, count)| *count > 0)
+            .sorted_by_key(|(index, count)| (Reverse(*count), *index))
+            .take(MOST_USED_COUNT)
+            .map(|(index, _)| index)
+            .collect();
+
+        let (mut promoted, rest): (Vec<_>, Vec<_>) = options
+            .into_iter()
+            .enumerate()
+            .partition(|(index, _)| most_used.contains(index));
+
+        promoted.sort_by_key(|(index, _)| most_used.iter().position(|i| i == index));
+
+        let promoted_count = promoted.len();
+        let options = promoted
+            .into_iter()
+            .chain(rest)
+            .map(|(_, cmd)| cmd)
+            .collect();
+
+        (options, promoted_count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command::{AppFocus, CommandInstruction, CommandScope};
+
+    use super::*;
+
+    fn cmd(prefix: &str) -> SlashPaletteCmd {
+        SlashPaletteCmd::from_instruction(
+            prefix,
+            CommandInstruction::MarkdownBold,
+            CommandScope::Focus(AppFocus::NoteEditor),
+        )
+    }
+
+    #[test]
+    pub fn test_promote_most_used() {
+        let mut usage = CommandUsage::default();
+        for prefix in ["h1", "code", "code", "table", "code", "h1", "todo"] {
+            usage.record(prefix);
+        }
+
+        let options = ["bold", "h1", "table", "todo", "code", "italic"].map(cmd);
+        let (options, promoted_count) = usage.promote_most_used(options.into());
+
+        assert_eq!(promoted_count, 3);
+        assert_eq!(
+            options.iter().map(|cmd| cmd.prefix.as_str()).collect_vec(),
+            ["code", "h1", "table", "bold", "todo", "italic"],
+            "ties are resolved by the original order"
+        );
+
+        let (options, promoted_count) = CommandUsage::default().promote_most_used(options);
+        assert_eq!(promoted_count, 0);
+        assert_eq!(options[0].prefix, "code");
+    }
+}
@@ -0,0 +1,111 @@
+use smallvec::SmallVec;
+
+use crate::{
+    app_actions::AppAction,
+    byte_span::ByteSpan,
+    command::{CommandContext, EditorCommandOutput, try_extract_text_command_context},
+    effects::text_change_effect::TextChange,
+    text_structure::{SpanKind, TextStructure},
+};
+
+/// Moves the cursor inside the next (or previous) code block, it is scrolled into view then
+pub fn jump_to_code_block(
+    CommandContext { app_state, .. }: CommandContext,
+    backwards: bool,
+) -> Option<EditorCommandOutput> {
+    let context = try_extract_text_command_context(app_state)?;
+
+    let target = find_code_block_target(
+        context.text_structure,
+        context.text,
+        context.byte_cursor,
+        backwards,
+    )?;
+
+    Some(SmallVec::from_buf([AppAction::ApplyTextChanges {
+        target: app_state.selected_note,
+        changes: vec![TextChange::Insert(
+            ByteSpan::point(target),
+            TextChange::CURSOR.to_string(),
+        )],
+        should_trigger_eval: false,
+    }]))
+}
+
+/// Byte position at the start of the code inside the target block.
+/// The block the cursor is in is skipped in both directions
+pub fn find_code_block_target(
+    structure: &TextStructure,
+    text: &str,
+    cursor: ByteSpan,
+    backwards: bool,
+) -> Option<usize> {
+    let mut blocks: Vec<(ByteSpan, usize)> = structure
+        .iter()
+        .filter(|(_, desc)| desc.kind == SpanKind::CodeBlock)
+        .map(|(index, desc)| {
+            let code_start = structure
+                .iterate_immediate_children_of(index)
+                .find(|(_, child)| child.kind == SpanKind::Text)
+                .map(|(_, child)| child.byte_pos.start)
+                // empty block, right after the line with the opening fence
+                .unwrap_or_else(|| {
+                    text[desc.byte_pos.range()]
+                        .find('\n')
+                        .map(|newline| desc.byte_pos.start + newline + 1)
+                        .unwrap_or(desc.byte_pos.end)
+                });
+
+            (desc.byte_pos, code_start)
+        })
+        .collect();
+
+    blocks.sort_by_key(|(block, _)| block.start);
+
+    match backwards {
+        true => blocks
+            .iter()
+            .rev()
+            .find(|(block, _)| block.end <= cursor.start)
+            .map(|(_, code_start)| *code_start),
+
+        false => blocks
+            .iter()
+            .find(|(block, _)| block.start > cursor.start)
+            .map(|(_, code_start)| *code_start),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_find_code_block_target() {
+        let text = "intro\n```js\nfirst\n```\nmiddle\n```\n```\n```rs\nlast\n```\noutro";
+        let pos = |needle: &str| text.find(needle).unwrap();
+
+        let test_cases = [
+            ("intro", false, Some(pos("first"))),
+            ("intro", true, None),
+            ("first", false, Some(pos("```\n```\n```rs") + 4)),
+            ("first", true, None),
+            ("middle", true, Some(pos("first"))),
+            ("middle", false, Some(pos("```\n```\n```rs") + 4)),
+            ("last", true, Some(pos("```\n```\n```rs") + 4)),
+            ("last", false, None),
+            ("outro", true, Some(pos("last"))),
+        ];
+
+        let structure = TextStructure::new(text);
+
+        for (cursor_at, backwards, expected) in test_cases {
+            let cursor = ByteSpan::point(pos(cursor_at));
+            assert_eq!(
+                find_code_block_target(&structure, text, cursor, backwards),
+                expected,
+                "cursor at {cursor_at:?}, backwards={backwards}"
+            );
+        }
+    }
+}
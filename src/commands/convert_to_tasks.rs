@@ -0,0 +1,197 @@
+use crate::{
+    byte_span::ByteSpan,
+    command::TextCommandContext,
+    effects::text_change_effect::TextChange,
+    text_structure::{SpanKind, SpanMeta, TaskState, TextStructure},
+};
+
+use super::select_unordered_list_marker;
+
+enum TaskLine {
+    Task(ByteSpan, TaskState),
+    /// position right after the list item marker
+    ListItem(usize),
+    /// position right after the indentation
+    Plain(usize),
+}
+
+/// Turns every line of the selection into a `- [ ]` task, list items keep their markers.
+/// If all of them are tasks already, they are all checked (or unchecked if all are done)
+pub fn convert_to_tasks(context: TextCommandContext) -> Option<Vec<TextChange>> {
+    let TextCommandContext {
+        text_structure: structure,
+        text,
+        byte_cursor: cursor,
+    } = context;
+
+    let lines: Vec<TaskLine> = structure
+        .lines_of(cursor)
+        .iter()
+        .filter(|line| !text[line.range()].trim().is_empty())
+        .filter(|line| {
+            structure
+                .find_span_at(SpanKind::CodeBlock, **line)
+                .is_none()
+        })
+        .filter_map(|line| task_line(structure, text, *line))
+        .collect();
+
+    let task_states: Vec<TaskState> = lines
+        .iter()
+        .filter_map(|line| match line {
+            TaskLine::Task(_, state) => Some(*state),
+            _ => None,
+        })
+        .collect();
+
+    let changes: Vec<TextChange> = match task_states.len() == lines.len() {
+        true => {
+            let target = match task_states.iter().all(|state| *state == TaskState::Done) {
+                true => TaskState::Todo,
+                false => TaskState::Done,
+            };
+
+            lines
+                .iter()
+                .filter_map(|line| match line {
+                    TaskLine::Task(marker, state) if *state != target => {
+                        Some(TextChange::Insert(*marker, target.marker().to_string()))
+                    }
+                    _ => None,
+                })
+                .collect()
+        }
+        false => lines
+            .iter()
+            .filter_map(|line| match line {
+                TaskLine::Task(..) => None,
+                TaskLine::ListItem(pos) => Some(TextChange::Insert(
+                    ByteSpan::point(*pos),
+                    format!("{} ", TaskState::Todo.marker()),
+                )),
+                TaskLine::Plain(pos) => Some(TextChange::Insert(
+                    ByteSpan::point(*pos),
+                    format!(
+                        "{} {} ",
+                        select_unordered_list_marker(0),
+                        TaskState::Todo.marker()
+                    ),
+                )),
+            })
+            .collect(),
+    };
+
+    (!changes.is_empty()).then_some(changes)
+}
+
+/// `None` for the lines that continue a list item started above
+fn task_line(structure: &TextStructure, text: &str, line: ByteSpan) -> Option<TaskLine> {
+    let task_marker = structure
+        .iter()
+        .filter(|(_, desc)| desc.kind == SpanKind::TaskMarker)
+        .filter(|(_, desc)| line.contains_pos(desc.byte_pos.start))
+        .find_map(|(index, desc)| match structure.find_meta(index) {
+            Some(SpanMeta::TaskMarker(state)) => Some((desc.byte_pos, *state)),
+            _ => None,
+        });
+
+    if let Some((marker, state)) = task_marker {
+        return Some(TaskLine::Task(marker, state));
+    }
+
+    // nested items might start at the indentation of the parent item (or even before the line break)
+    let item_start = |desc_start: usize| {
+        text[desc_start..]
+            .find(|ch: char| !ch.is_whitespace())
+            .map_or(text.len(), |offset| desc_start + offset)
+    };
+
+    let list_item = structure
+        .iter()
+        .filter(|(_, desc)| desc.kind == SpanKind::ListItem)
+        .filter(|(_, desc)| line.contains_pos(item_start(desc.byte_pos.start)))
+        .last();
+
+    if let Some((index, desc)) = list_item {
+        let content = structure.get_span_inner_content(index);
+
+        return match content.start > desc.byte_pos.start {
+            true => Some(TaskLine::ListItem(content.start)),
+            // e.g. `- ` without any text
+            false => Some(TaskLine::ListItem(line.end)),
+        };
+    }
+
+    let is_inside_list_item = structure
+        .iter()
+        .any(|(_, desc)| desc.kind == SpanKind::ListItem && desc.byte_pos.contains(line));
+
+    if is_inside_list_item {
+        return None;
+    }
+
+    let line_text = &text[line.range()];
+    let indent = line_text.len() - line_text.trim_start().len();
+    Some(TaskLine::Plain(line.start + indent))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::effects::text_change_effect::apply_text_changes;
+
+    use super::*;
+
+    #[test]
+    pub fn test_convert_to_tasks() {
+        let test_cases = [
+            (
+                "## plain lines ##",
+                "{|}buy milk\neggs{|}",
+                Some("- [ ] {|}buy milk\n- [ ] eggs{|}"),
+            ),
+            (
+                "## list items and blank lines ##",
+                "- a{|}\n- [ ] b\n\n\nplain{|}",
+                Some("- [ ] a{|}\n- [ ] b\n\n\n- [ ] plain{|}"),
+            ),
+            (
+                "## continuation lines are skipped ##",
+                "{|}- a\n  continued{|}",
+                Some("{|}- [ ] a\n  continued{|}"),
+            ),
+            (
+                "## nested item ##",
+                "- a\n\t- b{||}",
+                Some("- a\n\t- [ ] b{||}"),
+            ),
+            (
+                "## all tasks get checked ##",
+                "{|}- [ ] a\n- [x] b{|}",
+                Some("{|}- [x] a\n- [x] b{|}"),
+            ),
+            (
+                "## all done tasks get unchecked ##",
+                "- [x] a\n- [x] b{||}",
+                Some("- [x] a\n- [ ] b{||}"),
+            ),
+            ("## code blocks are skipped ##", "```\nco{||}de\n```", None),
+        ];
+
+        for (desc, input, expected) in test_cases {
+            let (mut text, cursor) = TextChange::try_extract_cursor(input.to_string());
+            let cursor = cursor.unwrap();
+
+            let structure = TextStructure::new(&text);
+
+            let res = convert_to_tasks(TextCommandContext::new(&structure, &text, cursor)).map(
+                |changes| {
+                    let cursor =
+                        apply_text_changes(&mut text, Some(cursor.unordered()), changes).unwrap();
+                    TextChange::encode_cursor(&text, cursor.unwrap())
+                },
+            );
+
+            assert_eq!(res.as_deref(), expected, "test case: {desc}");
+        }
+    }
+}
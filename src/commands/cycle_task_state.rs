@@ -0,0 +1,112 @@
+use crate::{
+    byte_span::ByteSpan,
+    command::TextCommandContext,
+    effects::text_change_effect::TextChange,
+    text_structure::{SpanKind, SpanMeta, TaskState},
+};
+
+use super::select_unordered_list_marker;
+
+/// Cycles the task marker on the cursor line `[ ]` -> `[/]` -> `[x]` -> `[ ]`.
+/// A list item without a marker becomes a task, and so does a regular line (as a new list item)
+pub fn cycle_task_state(context: TextCommandContext) -> Option<Vec<TextChange>> {
+    let TextCommandContext {
+        text_structure: structure,
+        text,
+        byte_cursor: cursor,
+    } = context;
+
+    if structure
+        .find_span_at(SpanKind::CodeBlock, cursor)
+        .is_some()
+    {
+        return None;
+    }
+
+    let (line_loc, line_span, _) = structure.find_line_location(cursor)?;
+
+    let task_marker = structure
+        .find_span_on_the_line(SpanKind::TaskMarker, line_loc.line_start)
+        .and_then(|(range, index, _)| match structure.find_meta(index) {
+            Some(SpanMeta::TaskMarker(state)) => Some((range, *state)),
+            _ => None,
+        });
+
+    if let Some((marker_range, state)) = task_marker {
+        return Some(vec![TextChange::Insert(
+            marker_range,
+            state.next().marker().to_string(),
+        )]);
+    }
+
+    let list_item_content = structure
+        .find_span_on_the_line(SpanKind::ListItem, line_loc.line_start)
+        .map(|(_, index, _)| structure.get_span_inner_content(index));
+
+    let (insert_at, task) = match list_item_content {
+        Some(content) => (content.start, format!("{} ", TaskState::Todo.marker())),
+        None => {
+            let line = &text[line_span.range()];
+            let indent = line.len() - line.trim_start().len();
+            (
+                line_span.start + indent,
+                format!(
+                    "{} {} ",
+                    select_unordered_list_marker(0),
+                    TaskState::Todo.marker()
+                ),
+            )
+        }
+    };
+
+    Some(vec![TextChange::Insert(ByteSpan::point(insert_at), task)])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{effects::text_change_effect::apply_text_changes, text_structure::TextStructure};
+
+    use super::*;
+
+    #[test]
+    pub fn test_cycle_task_state() {
+        let test_cases = [
+            ("buy milk{||}", "- [ ] buy milk{||}"),
+            ("{||}", "- [ ] {||}"),
+            ("- buy{||} milk", "- [ ] buy{||} milk"),
+            ("- [ ] buy{||} milk", "- [/] buy{||} milk"),
+            ("- [/] buy{||} milk", "- [x] buy{||} milk"),
+            ("- [x] buy{||} milk", "- [ ] buy{||} milk"),
+            ("- a\n\t- [/] b{||}", "- a\n\t- [x] b{||}"),
+        ];
+
+        for (input, output) in test_cases {
+            let (mut text, cursor) = TextChange::try_extract_cursor(input.to_string());
+            let cursor = cursor.unwrap();
+
+            let changes = cycle_task_state(TextCommandContext::new(
+                &TextStructure::new(&text),
+                &text,
+                cursor,
+            ))
+            .unwrap();
+
+            let cursor = apply_text_changes(&mut text, Some(cursor.unordered()), changes).unwrap();
+            assert_eq!(
+                TextChange::encode_cursor(&text, cursor.unwrap()),
+                output,
+                "input: {input:?}"
+            );
+        }
+
+        let (text, cursor) = TextChange::try_extract_cursor("```\ncode{||}\n```".to_string());
+        assert!(
+            cycle_task_state(TextCommandContext::new(
+                &TextStructure::new(&text),
+                &text,
+                cursor.unwrap(),
+            ))
+            .is_none()
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{
+    byte_span::ByteSpan,
+    command::TextCommandContext,
+    effects::text_change_effect::TextChange,
+    text_structure::{SpanKind, TextStructure},
+};
+
+/// Code lines are not markdown, so nothing is continued, only the indentation of the line is kept
+pub fn on_enter_inside_code_block(context: TextCommandContext) -> Option<Vec<TextChange>> {
+    let TextCommandContext {
+        text_structure: structure,
+        text,
+        byte_cursor: cursor,
+    } = context;
+
+    if !is_inside_code(structure, cursor) {
+        return None;
+    }
+
+    let (_, line_span, _) = structure.find_line_location(cursor)?;
+
+    let indent_len = text[line_span.start..cursor.start]
+        .find(|c: char| c != ' ' && c != '\t')
+        .unwrap_or(cursor.start - line_span.start);
+
+    if indent_len == 0 {
+        // a plain newline is inserted by the editor itself
+        return None;
+    }
+
+    let indent = &text[line_span.start..line_span.start + indent_len];
+
+    Some(vec![TextChange::Insert(
+        cursor,
+        format!("\n{indent}{}", TextChange::CURSOR),
+    )])
+}
+
+/// Inside the code of a code block, that is, not on its fences
+pub fn is_inside_code(structure: &TextStructure, cursor: ByteSpan) -> bool {
+    structure
+        .find_span_at(SpanKind::CodeBlock, cursor)
+        .is_some_and(|(_, index)| {
+            structure
+                .iterate_immediate_children_of(index)
+                .any(|(_, desc)| desc.kind == SpanKind::Text && desc.byte_pos.contains(cursor))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::effects::text_change_effect::apply_text_changes;
+
+    use super::*;
+
+    #[test]
+    pub fn test_enter_inside_code_block() {
+        let test_cases = [
+            (
+                "## keeps the indentation ##",
+                "```rust\nfn main() {\n    let a = 1;{||}\n}\n```",
+                Some("```rust\nfn main() {\n    let a = 1;\n    {||}\n}\n```"),
+            ),
+            (
+                "## tabs are kept too ##",
+                "```\n\t- not a list{||}\n```",
+                Some("```\n\t- not a list\n\t{||}\n```"),
+            ),
+            (
+                "## selection is replaced ##",
+                "```\n  a {|}b{|}\n```",
+                Some("```\n  a \n  {||}\n```"),
+            ),
+            ("## no indentation ##", "```\nlet a = 1;{||}\n```", None),
+            ("## outside of code ##", "  text{||}", None),
+        ];
+
+        for (desc, input, expected) in test_cases {
+            let (mut text, cursor) = TextChange::try_extract_cursor(input.to_string());
+            let cursor = cursor.unwrap();
+
+            let structure = TextStructure::new(&text);
+            let changes =
+                on_enter_inside_code_block(TextCommandContext::new(&structure, &text, cursor));
+
+            let res = changes.map(|changes| {
+                let cursor =
+                    apply_text_changes(&mut text, Some(cursor.unordered()), changes).unwrap();
+                TextChange::encode_cursor(&text, cursor.unwrap())
+            });
+
+            assert_eq!(res.as_deref(), expected, "test case: {desc}");
+        }
+    }
+}
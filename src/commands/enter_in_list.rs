@@ -7,7 +7,7 @@ use crate::{
     text_structure::{ListDesc, SpanKind, SpanMeta},
 };
 
-use super::select_unordered_list_marker;
+use super::{enter_in_code_block::is_inside_code, select_unordered_list_marker};
 
 // handler on ENTER
 pub fn on_enter_inside_list_item(context: TextCommandContext) -> Option<Vec<TextChange>> {
@@ -17,6 +17,11 @@ pub fn on_enter_inside_list_item(context: TextCommandContext) -> Option<Vec<Text
         byte_cursor: cursor,
     } = context;
 
+    // code inside a list item is not a list
+    if is_inside_code(structure, cursor) {
+        return None;
+    }
+
     let (line_loc, _, _) = structure.find_line_location(cursor)?;
 
     let (span_range, item_index, _) =
@@ -194,7 +199,10 @@ pub fn on_enter_inside_list_item(context: TextCommandContext) -> Option<Vec<Text
 
 #[cfg(test)]
 mod tests {
-    use crate::{effects::text_change_effect::apply_text_changes, text_structure::TextStructure};
+    use crate::{
+        command::CommandInstruction, effects::text_change_effect::apply_text_changes,
+        test_harness::TestHarness, text_structure::TextStructure,
+    };
 
     use super::*;
 
@@ -348,4 +356,56 @@ mod tests {
         ));
         assert!(changes.is_none());
     }
+
+    #[test]
+    pub fn test_skips_handling_enter_inside_code_block() {
+        for input in ["```md\n- a{||}\n```", "- item\n\n  ```\n  - a{||}\n  ```"] {
+            let (text, cursor) = TextChange::try_extract_cursor(input.to_string());
+            let changes = on_enter_inside_list_item(TextCommandContext::new(
+                &TextStructure::new(&text),
+                &text,
+                cursor.unwrap(),
+            ));
+            assert!(changes.is_none(), "{input}");
+        }
+    }
+
+    #[test]
+    pub fn test_list_continuation_via_command() {
+        let test_cases = [
+            ("## end of a bullet ##", "- a{||}", "- a\n- {||}"),
+            ("## middle of a bullet ##", "- ab{||}c", "- ab\n- {||}c"),
+            (
+                "## empty bullet exits the list ##",
+                "- a\n- {||}",
+                "- a\n{||}",
+            ),
+            (
+                "## nested bullet keeps the depth ##",
+                "- a\n\t* b{||}\n- c",
+                "- a\n\t* b\n\t* {||}\n- c",
+            ),
+            (
+                "## numbered item increments ##",
+                "1. a{||}",
+                "1. a\n2. {||}",
+            ),
+            (
+                "## numbered items below are renumbered ##",
+                "1. a{||}\n2. b",
+                "1. a\n2. {||}\n3. b",
+            ),
+            (
+                "## nested numbered item ##",
+                "- a\n\t1. b{||}",
+                "- a\n\t1. b\n\t2. {||}",
+            ),
+        ];
+
+        for (desc, input, expected) in test_cases {
+            let mut harness = TestHarness::new(input);
+            harness.run_command(CommandInstruction::SplitListItem);
+            assert_eq!(harness.text_with_cursor(), expected, "test case: {}", desc);
+        }
+    }
 }
@@ -0,0 +1,287 @@
+use crate::{
+    byte_span::ByteSpan,
+    command::TextCommandContext,
+    effects::text_change_effect::TextChange,
+    text_structure::{SpanKind, TextStructure},
+};
+
+/// Special anywhere in a line, with strikethrough (`~`) and tables (`|`) enabled in the parser
+const INLINE_SPECIALS: &[char] = &['\\', '`', '*', '_', '[', ']', '<', '~', '|'];
+
+/// Backslash-escapes the markdown specials of the selection (or the lines of the cursor),
+/// code is left as is, backslashes are literal there
+pub fn escape_markdown(context: TextCommandContext) -> Option<Vec<TextChange>> {
+    let TextCommandContext {
+        text_structure: structure,
+        text,
+        byte_cursor: cursor,
+    } = context;
+
+    let target = target_span(structure, cursor)?;
+    let code = code_spans(structure);
+    let is_plain = |pos: usize| !code.iter().any(|span| span.contains_pos(pos));
+
+    let inline = text[target.range()]
+        .match_indices(INLINE_SPECIALS)
+        .map(|(offset, _)| target.start + offset);
+
+    let line_starts = structure
+        .lines_of(target)
+        .iter()
+        .filter_map(|line| {
+            let line_text = &text[line.range()];
+            let indent = line_text.len() - line_text.trim_start_matches(' ').len();
+
+            // 4 spaces make an indented code block
+            if indent > 3 {
+                return None;
+            }
+
+            line_start_special(&line_text[indent..]).map(|offset| line.start + indent + offset)
+        })
+        .filter(|pos| target.start <= *pos && *pos < target.end);
+
+    let mut edits: Vec<Edit> = inline
+        .chain(line_starts)
+        .filter(|pos| is_plain(*pos))
+        .map(Edit::AddBackslash)
+        .collect();
+    edits.sort_by_key(Edit::pos);
+
+    rewrite(text, target, cursor, &edits)
+}
+
+/// Removes the backslashes in front of ascii punctuation, the same ones that pulldown-cmark treats as escapes
+pub fn unescape_markdown(context: TextCommandContext) -> Option<Vec<TextChange>> {
+    let TextCommandContext {
+        text_structure: structure,
+        text,
+        byte_cursor: cursor,
+    } = context;
+
+    let target = target_span(structure, cursor)?;
+    let code = code_spans(structure);
+
+    let mut edits = vec![];
+    let mut chars = text[target.range()].char_indices().peekable();
+
+    while let Some((offset, ch)) = chars.next() {
+        let pos = target.start + offset;
+        let is_escape = ch == '\\'
+            && chars
+                .peek()
+                .is_some_and(|(_, next)| next.is_ascii_punctuation());
+
+        if is_escape && !code.iter().any(|span| span.contains_pos(pos)) {
+            edits.push(Edit::RemoveBackslash(pos));
+            // the escaped char stays as is, even if it is a backslash
+            chars.next();
+        }
+    }
+
+    rewrite(text, target, cursor, &edits)
+}
+
+enum Edit {
+    AddBackslash(usize),
+    RemoveBackslash(usize),
+}
+
+impl Edit {
+    fn pos(&self) -> usize {
+        match self {
+            Edit::AddBackslash(pos) | Edit::RemoveBackslash(pos) => *pos,
+        }
+    }
+}
+
+/// Replaces the target with the edits applied, the cursor (or the selection) stays on the same text.
+/// Edits at the position of the cursor end up after it, so escaping a selection keeps the escapes inside
+fn rewrite(
+    text: &str,
+    target: ByteSpan,
+    cursor: ByteSpan,
+    edits: &[Edit],
+) -> Option<Vec<TextChange>> {
+    if edits.is_empty() {
+        return None;
+    }
+
+    let mut rewritten = String::with_capacity(target.range().len() + edits.len());
+    let mut copied_until = target.start;
+
+    for edit in edits {
+        rewritten.push_str(&text[copied_until..edit.pos()]);
+        copied_until = edit.pos();
+
+        match edit {
+            Edit::AddBackslash(_) => rewritten.push('\\'),
+            Edit::RemoveBackslash(pos) => copied_until = pos + 1,
+        }
+    }
+    rewritten.push_str(&text[copied_until..target.end]);
+
+    let shifted = |pos: usize| -> usize {
+        edits.iter().filter(|edit| edit.pos() < pos).fold(
+            pos - target.start,
+            |pos, edit| match edit {
+                Edit::AddBackslash(_) => pos + 1,
+                Edit::RemoveBackslash(_) => pos - 1,
+            },
+        )
+    };
+
+    let (start, end) = (shifted(cursor.start), shifted(cursor.end));
+    let with_cursor = match cursor.is_empty() {
+        true => format!(
+            "{}{}{}",
+            &rewritten[..start],
+            TextChange::CURSOR,
+            &rewritten[start..]
+        ),
+        false => format!(
+            "{}{}{}{}{}",
+            &rewritten[..start],
+            TextChange::CURSOR_EDGE,
+            &rewritten[start..end],
+            TextChange::CURSOR_EDGE,
+            &rewritten[end..]
+        ),
+    };
+
+    Some(vec![TextChange::Insert(target, with_cursor)])
+}
+
+/// The selection, or the lines of the cursor if nothing is selected
+fn target_span(structure: &TextStructure, cursor: ByteSpan) -> Option<ByteSpan> {
+    if !cursor.is_empty() {
+        return Some(cursor);
+    }
+
+    let lines = structure.lines_of(cursor);
+    Some(ByteSpan::new(lines.first()?.start, lines.last()?.end))
+}
+
+fn code_spans(structure: &TextStructure) -> Vec<ByteSpan> {
+    structure
+        .iter()
+        .filter(|(_, desc)| {
+            matches!(
+                desc.kind,
+                SpanKind::CodeBlock | SpanKind::InlineCode | SpanKind::FrontMatter
+            )
+        })
+        .map(|(_, desc)| desc.byte_pos)
+        .collect()
+}
+
+/// Offset of the char that would start a block, e.g. `# heading`, `> quote`, `- item`, `1. item` or `---`
+fn line_start_special(line: &str) -> Option<usize> {
+    let mut chars = line.chars();
+    let first = chars.next()?;
+    let next = chars.next();
+    let is_marker_end = |ch: Option<char>| ch.is_none_or(|ch| ch == ' ' || ch == '\t');
+
+    match first {
+        '#' | '>' => Some(0),
+        '-' | '+' | ':' if is_marker_end(next) => Some(0),
+        // thematic breaks and setext heading underlines
+        '-' | '=' if line.trim_end().chars().all(|ch| ch == first) => Some(0),
+        '0'..='9' => {
+            let digits = line.len()
+                - line
+                    .trim_start_matches(|ch: char| ch.is_ascii_digit())
+                    .len();
+            let rest = &line[digits..];
+
+            (digits <= 9 && rest.starts_with(['.', ')']) && is_marker_end(rest[1..].chars().next()))
+                .then_some(digits)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::effects::text_change_effect::apply_text_changes;
+
+    use super::*;
+
+    #[test]
+    pub fn test_escape_markdown() {
+        let test_cases = [
+            (
+                "## inline specials of the selection ##",
+                "{|}a *b* _c_ [d](e) ~f~ <g> `h` \\{|}",
+                Some("{|}a \\*b\\* \\_c\\_ \\[d\\](e) \\~f\\~ \\<g> `h` \\\\{|}"),
+            ),
+            (
+                "## line starts of the cursor line ##",
+                "# not a heading{||}",
+                Some("\\# not a heading{||}"),
+            ),
+            (
+                "## list markers, quotes and breaks ##",
+                "{|}- a\n1. b\n> c\n---\n2024 was fine{|}",
+                Some("{|}\\- a\n1\\. b\n\\> c\n\\---\n2024 was fine{|}"),
+            ),
+            (
+                "## code is left as is ##",
+                "{|}`a*b` and\n```\n*c*\n```\n*d*{|}",
+                Some("{|}`a*b` and\n```\n*c*\n```\n\\*d\\*{|}"),
+            ),
+            ("## nothing to escape ##", "plain text{||}", None),
+        ];
+
+        for (desc, input, expected) in test_cases {
+            let (mut text, cursor) = TextChange::try_extract_cursor(input.to_string());
+            let cursor = cursor.unwrap();
+
+            let structure = TextStructure::new(&text);
+
+            let res = escape_markdown(TextCommandContext::new(&structure, &text, cursor)).map(
+                |changes| {
+                    let cursor =
+                        apply_text_changes(&mut text, Some(cursor.unordered()), changes).unwrap();
+                    TextChange::encode_cursor(&text, cursor.unwrap())
+                },
+            );
+
+            assert_eq!(res.as_deref(), expected, "test case: {desc}");
+        }
+    }
+
+    #[test]
+    pub fn test_unescape_markdown() {
+        let test_cases = [
+            (
+                "## reverses escaping ##",
+                "{|}a \\*b\\* \\# \\\\ c\\d{|}",
+                Some("{|}a *b* # \\ c\\d{|}"),
+            ),
+            (
+                "## code is left as is ##",
+                "{|}`\\*` \\*{|}",
+                Some("{|}`\\*` *{|}"),
+            ),
+            ("## nothing to unescape ##", "C:\\path{||}", None),
+        ];
+
+        for (desc, input, expected) in test_cases {
+            let (mut text, cursor) = TextChange::try_extract_cursor(input.to_string());
+            let cursor = cursor.unwrap();
+
+            let structure = TextStructure::new(&text);
+
+            let res = unescape_markdown(TextCommandContext::new(&structure, &text, cursor)).map(
+                |changes| {
+                    let cursor =
+                        apply_text_changes(&mut text, Some(cursor.unordered()), changes).unwrap();
+                    TextChange::encode_cursor(&text, cursor.unwrap())
+                },
+            );
+
+            assert_eq!(res.as_deref(), expected, "test case: {desc}");
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use smallvec::SmallVec;
+
+use crate::{
+    app_actions::AppAction,
+    byte_span::ByteSpan,
+    command::{
+        CommandContext, EditorCommandOutput, TextCommandContext, try_extract_text_command_context,
+    },
+    effects::text_change_effect::TextChange,
+    persistent_state::NoteFile,
+    text_structure::{SpanKind, SpanMeta},
+};
+
+/// Moves the code block under the cursor to another shelf,
+/// the block is replaced in place with a link to that shelf
+pub fn extract_code_block(
+    CommandContext { app_state, .. }: CommandContext,
+) -> Option<EditorCommandOutput> {
+    let source = app_state.selected_note;
+    // settings blocks are only evaluated inside the settings note
+    let NoteFile::Note(_) = source else {
+        return None;
+    };
+
+    let TextCommandContext {
+        text,
+        text_structure,
+        byte_cursor,
+    } = try_extract_text_command_context(app_state)?;
+
+    let (_, block, SpanMeta::CodeBlock(meta)) =
+        text_structure.find_surrounding_span_with_meta(SpanKind::CodeBlock, byte_cursor)?
+    else {
+        return None;
+    };
+
+    let target = extraction_target(
+        source,
+        app_state
+            .notes
+            .iter()
+            .map(|(note_file, note)| (*note_file, note.text.as_str())),
+    )?;
+
+    let target_text = &app_state.notes.get(&target)?.text;
+
+    let (source_change, target_change) =
+        prepare_extraction(text, block.byte_pos, &meta.lang, target, target_text)?;
+
+    Some(SmallVec::from_iter([
+        AppAction::ApplyTextChanges {
+            target,
+            changes: vec![target_change],
+            should_trigger_eval: true,
+        },
+        AppAction::ApplyTextChanges {
+            target: source,
+            changes: vec![source_change],
+            should_trigger_eval: true,
+        },
+    ]))
+}
+
+/// The first empty shelf, otherwise the last one, never the settings or the source note itself
+fn extraction_target<'a>(
+    source: NoteFile,
+    notes: impl Iterator<Item = (NoteFile, &'a str)>,
+) -> Option<NoteFile> {
+    let candidates: SmallVec<[(NoteFile, &str); 4]> = notes
+        .filter(|(note_file, _)| matches!(note_file, NoteFile::Note(_)) && *note_file != source)
+        .collect();
+
+    candidates
+        .iter()
+        .find(|(_, text)| text.trim().is_empty())
+        .or(candidates.last())
+        .map(|(note_file, _)| *note_file)
+}
+
+/// Changes for the source note (block -> link) and for the target note (block is appended)
+fn prepare_extraction(
+    text: &str,
+    block: ByteSpan,
+    lang: &str,
+    target: NoteFile,
+    target_text: &str,
+) -> Option<(TextChange, TextChange)> {
+    let NoteFile::Note(target_index) = target else {
+        return None;
+    };
+
+    let block_text = text.get(block.range())?.trim_end_matches('\n');
+    let block = ByteSpan::new(block.start, block.start + block_text.len());
+
+    let description = match lang.split_whitespace().next() {
+        Some(lang) => format!("{lang} block"),
+        None => "code block".to_string(),
+    };
+
+    let link = format!(
+        "[{description} moved to Shelf {shelf}](shelv://note{shelf}){cursor}",
+        shelf = target_index + 1,
+        cursor = TextChange::CURSOR
+    );
+
+    let target_change = match target_text {
+        t if t.trim().is_empty() => {
+            TextChange::Insert(ByteSpan::new(0, t.len()), format!("{block_text}\n"))
+        }
+        t => {
+            let separator = match t {
+                t if t.ends_with("\n\n") => "",
+                t if t.ends_with('\n') => "\n",
+                _ => "\n\n",
+            };
+            TextChange::Insert(
+                ByteSpan::point(t.len()),
+                format!("{separator}{block_text}\n"),
+            )
+        }
+    };
+
+    Some((TextChange::Insert(block, link), target_change))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{effects::text_change_effect::apply_text_changes, text_structure::TextStructure};
+
+    use super::*;
+
+    #[test]
+    pub fn test_extraction_target() {
+        let notes = [
+            (NoteFile::Note(0), "# a"),
+            (NoteFile::Note(1), "# b"),
+            (NoteFile::Note(2), " \n"),
+            (NoteFile::Note(3), "# d"),
+            (NoteFile::Settings, ""),
+        ];
+
+        assert_eq!(
+            extraction_target(NoteFile::Note(0), notes.into_iter()),
+            Some(NoteFile::Note(2)),
+            "empty shelf goes first"
+        );
+        assert_eq!(
+            extraction_target(NoteFile::Note(2), notes.into_iter()),
+            Some(NoteFile::Note(3)),
+            "otherwise the last one"
+        );
+        assert_eq!(
+            extraction_target(NoteFile::Settings, notes.into_iter()),
+            Some(NoteFile::Note(2))
+        );
+    }
+
+    #[test]
+    pub fn test_prepare_extraction() {
+        let mut text = "before\n\n```js\nlet a = 1;\n```\n\nafter".to_string();
+        let mut target_text = "# Scripts".to_string();
+
+        let structure = TextStructure::new(&text);
+        let (block, _) = structure
+            .find_span_at(
+                SpanKind::CodeBlock,
+                ByteSpan::point(text.find("let").unwrap()),
+            )
+            .unwrap();
+
+        let (source_change, target_change) =
+            prepare_extraction(&text, block, "js", NoteFile::Note(3), &target_text).unwrap();
+
+        apply_text_changes(&mut text, None, [source_change]).unwrap();
+        apply_text_changes(&mut target_text, None, [target_change]).unwrap();
+
+        assert_eq!(
+            text,
+            "before\n\n[js block moved to Shelf 4](shelv://note4)\n\nafter"
+        );
+        assert_eq!(target_text, "# Scripts\n\n```js\nlet a = 1;\n```\n");
+    }
+}
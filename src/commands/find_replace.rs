@@ -0,0 +1,213 @@
+use eframe::egui::Id;
+use regex::{Regex, RegexBuilder};
+use smallvec::SmallVec;
+
+use crate::{
+    app_actions::{AppAction, FindReplaceAction},
+    app_state::FindReplace,
+    byte_span::ByteSpan,
+    command::{
+        CommandContext, EditorCommandOutput, TextCommandContext, try_extract_text_command_context,
+    },
+    effects::text_change_effect::TextChange,
+};
+
+pub fn find_query_input_id() -> Id {
+    Id::new("find_query_input")
+}
+
+pub fn replacement_input_id() -> Id {
+    Id::new("find_replacement_input")
+}
+
+pub fn show_find_replace(
+    CommandContext { app_state, .. }: CommandContext,
+) -> Option<EditorCommandOutput> {
+    let TextCommandContext {
+        text, byte_cursor, ..
+    } = try_extract_text_command_context(app_state)?;
+
+    let selected = &text[byte_cursor.range()];
+
+    // a single line selection is what to look for, a multi line one is where to look
+    let (query, selection) = match selected {
+        "" => (String::new(), None),
+        s if s.contains('\n') => (String::new(), Some(byte_cursor)),
+        s => (s.to_string(), None),
+    };
+
+    Some(SmallVec::from_buf([AppAction::FindReplace(
+        FindReplaceAction::Show(FindReplace {
+            note_file: app_state.selected_note,
+            query,
+            replacement: String::new(),
+            is_regex: false,
+            in_selection: selection.is_some(),
+            selection,
+        }),
+    )]))
+}
+
+/// Compiled query, a literal one is escaped, so both are matched the same way
+pub struct Finder(Regex);
+
+impl Finder {
+    pub fn new(query: &str, is_regex: bool) -> Result<Option<Self>, String> {
+        if query.is_empty() {
+            return Ok(None);
+        }
+
+        let pattern = match is_regex {
+            true => query.to_string(),
+            false => regex::escape(query),
+        };
+
+        RegexBuilder::new(&pattern)
+            .multi_line(true)
+            .build()
+            .map(|regex| Some(Self(regex)))
+            .map_err(|err| err.to_string())
+    }
+
+    /// Searched in the whole text, so `^` and `\b` see the text outside of the scope
+    pub fn matches(&self, text: &str, scope: Option<ByteSpan>) -> Vec<ByteSpan> {
+        let scope = scope.unwrap_or(ByteSpan::new(0, text.len()));
+        self.0
+            .find_iter(text)
+            .filter(|m| !m.is_empty())
+            .map(|m| ByteSpan::new(m.start(), m.end()))
+            .filter(|occurrence| scope.contains(*occurrence))
+            .collect()
+    }
+
+    /// The replacement can reference capture groups in regex mode, e.g. `$1` or `${name}`
+    fn replace(
+        &self,
+        text: &str,
+        occurrence: ByteSpan,
+        replacement: &str,
+        is_regex: bool,
+    ) -> String {
+        if !is_regex {
+            return replacement.to_string();
+        }
+
+        let mut result = String::new();
+        if let Some(captures) = self.0.captures_at(text, occurrence.start) {
+            captures.expand(replacement, &mut result);
+        }
+        result
+    }
+
+    /// Replaces every match in the scope
+    pub fn replace_all(
+        &self,
+        text: &str,
+        scope: Option<ByteSpan>,
+        replacement: &str,
+        is_regex: bool,
+    ) -> Vec<TextChange> {
+        self.matches(text, scope)
+            .into_iter()
+            .map(|occurrence| {
+                let replaced = self.replace(text, occurrence, replacement, is_regex);
+                TextChange::Insert(occurrence, replaced)
+            })
+            .collect()
+    }
+
+    /// Replaces the first match after the cursor (wrapping around),
+    /// the cursor is placed after the replacement, so the next call moves on to the next match
+    pub fn replace_next(
+        &self,
+        text: &str,
+        scope: Option<ByteSpan>,
+        cursor: usize,
+        replacement: &str,
+        is_regex: bool,
+    ) -> Option<TextChange> {
+        let matches = self.matches(text, scope);
+        let occurrence = matches
+            .iter()
+            .find(|m| m.start >= cursor)
+            .or(matches.first())?;
+
+        let replaced = self.replace(text, *occurrence, replacement, is_regex);
+        Some(TextChange::Insert(
+            *occurrence,
+            format!("{replaced}{}", TextChange::CURSOR),
+        ))
+    }
+}
+
+/// How much the text length changes after applying the replacements, used to keep the selection scope in sync
+pub fn length_delta(changes: &[TextChange]) -> isize {
+    changes
+        .iter()
+        .map(|TextChange::Insert(span, text)| {
+            let text_len = text
+                .replace(TextChange::CURSOR, "")
+                .replace(TextChange::CURSOR_EDGE, "")
+                .len();
+            text_len as isize - span.range().len() as isize
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::effects::text_change_effect::apply_text_changes;
+
+    use super::*;
+
+    #[test]
+    pub fn test_find_matches() {
+        let text = "a.b a.b axb";
+
+        let literal = Finder::new("a.b", false).unwrap().unwrap();
+        assert_eq!(literal.matches(text, None).len(), 2);
+
+        let regex = Finder::new("a.b", true).unwrap().unwrap();
+        assert_eq!(regex.matches(text, None).len(), 3);
+        assert_eq!(
+            regex.matches(text, Some(ByteSpan::new(4, text.len()))),
+            [ByteSpan::new(4, 7), ByteSpan::new(8, 11)]
+        );
+
+        assert!(Finder::new("(", true).is_err());
+        assert!(Finder::new("", true).unwrap().is_none());
+    }
+
+    #[test]
+    pub fn test_replace_all_with_captures() {
+        let mut text = "- [ ] buy milk\n- [ ] call mom\nnot a task [ ] x".to_string();
+
+        let finder = Finder::new(r"^- \[ \] (?<task>.*)$", true)
+            .unwrap()
+            .unwrap();
+        let changes = finder.replace_all(&text, None, "- [x] ${task}!", true);
+
+        assert_eq!(changes.len(), 2);
+        apply_text_changes(&mut text, None, changes).unwrap();
+        assert_eq!(text, "- [x] buy milk!\n- [x] call mom!\nnot a task [ ] x");
+    }
+
+    #[test]
+    pub fn test_replace_next_wraps_around() {
+        let text = "cat dog cat";
+        let finder = Finder::new("cat", false).unwrap().unwrap();
+
+        let Some(TextChange::Insert(span, _)) = finder.replace_next(text, None, 5, "cow", false)
+        else {
+            panic!("expected a replacement");
+        };
+        assert_eq!(span, ByteSpan::new(8, 11));
+
+        let Some(TextChange::Insert(span, _)) =
+            finder.replace_next(text, Some(ByteSpan::new(0, 7)), 5, "cow", false)
+        else {
+            panic!("expected a replacement");
+        };
+        assert_eq!(span, ByteSpan::new(0, 3), "wraps around inside the scope");
+    }
+}
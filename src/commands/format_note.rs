@@ -0,0 +1,140 @@
+use crate::{
+    byte_span::ByteSpan,
+    command::TextCommandContext,
+    effects::text_change_effect::TextChange,
+    text_structure::{SpanKind, TextStructure},
+};
+
+/// Tidies up the markdown source of the whole note without changing how it renders,
+/// for now that is collapsing runs of blank lines
+pub fn format_note(
+    TextCommandContext {
+        text_structure,
+        text,
+        ..
+    }: TextCommandContext,
+) -> Option<Vec<TextChange>> {
+    let changes = collapse_blank_lines(text, text_structure);
+
+    (!changes.is_empty()).then_some(changes)
+}
+
+/// Removes the blank lines that follow another blank line, markdown renders a run of them as one.
+/// Code blocks are kept verbatim
+pub fn collapse_blank_lines(text: &str, structure: &TextStructure) -> Vec<TextChange> {
+    let code_blocks: Vec<ByteSpan> = structure
+        .iter()
+        .filter(|(_, desc)| desc.kind == SpanKind::CodeBlock)
+        .map(|(_, desc)| desc.byte_pos)
+        .collect();
+
+    let mut changes = vec![];
+
+    // blank lines of the current run after the first one
+    let mut extra_lines: Option<ByteSpan> = None;
+    let mut prev_is_blank = false;
+    let mut line_start = 0;
+
+    for line in text.split_inclusive('\n') {
+        let line_end = line_start + line.len();
+        let is_blank =
+            line.trim().is_empty() && !code_blocks.iter().any(|span| span.contains_pos(line_start));
+
+        match (prev_is_blank, is_blank) {
+            (true, true) => {
+                let start = extra_lines.map_or(line_start, |lines| lines.start);
+                extra_lines = Some(ByteSpan::new(start, line_end));
+            }
+            _ => changes.extend(
+                extra_lines
+                    .take()
+                    .map(|lines| TextChange::Insert(lines, String::new())),
+            ),
+        }
+
+        prev_is_blank = is_blank;
+        line_start = line_end;
+    }
+
+    changes.extend(extra_lines.map(|lines| TextChange::Insert(lines, String::new())));
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        command::CommandInstruction, effects::text_change_effect::apply_text_changes,
+        persistent_state::NoteFile, test_harness::TestHarness,
+    };
+
+    use super::*;
+
+    #[test]
+    pub fn test_format_note() {
+        let test_cases = [
+            (
+                "## runs of blank lines become one ##",
+                "first\n\n\n\nsecond\n\n\nthird{||}",
+                Some("first\n\nsecond\n\nthird{||}"),
+            ),
+            (
+                "## whitespace only lines are blank ##",
+                "first\n\n  \n\t\nsecond{||}",
+                Some("first\n\nsecond{||}"),
+            ),
+            (
+                "## trailing blank lines ##",
+                "first{||}\n\n\n\n",
+                Some("first{||}\n\n"),
+            ),
+            (
+                "## code blocks are kept verbatim ##",
+                "```js\na\n\n\n\nb\n```\n\n\n\nafter{||}",
+                Some("```js\na\n\n\n\nb\n```\n\nafter{||}"),
+            ),
+            (
+                "## cursor inside the removed lines ##",
+                "first\n\n\n{||}\nsecond",
+                Some("first\n\n{||}second"),
+            ),
+            ("## nothing to collapse ##", "first\n\nsecond{||}", None),
+        ];
+
+        for (desc, input, expected) in test_cases {
+            let (mut text, cursor) = TextChange::try_extract_cursor(input.to_string());
+            let cursor = cursor.unwrap();
+
+            let structure = TextStructure::new(&text);
+
+            let res =
+                format_note(TextCommandContext::new(&structure, &text, cursor)).map(|changes| {
+                    let cursor =
+                        apply_text_changes(&mut text, Some(cursor.unordered()), changes).unwrap();
+                    TextChange::encode_cursor(&text, cursor.unwrap())
+                });
+
+            assert_eq!(res.as_deref(), expected, "test case: {desc}");
+        }
+    }
+
+    #[test]
+    pub fn test_blank_lines_are_collapsed_when_switching_away() {
+        let mut harness = TestHarness::new("first\n\n\n\nsecond{||}");
+
+        harness.run_command(CommandInstruction::SwitchToNextNote);
+        assert_eq!(
+            harness.state.notes[&NoteFile::Note(0)].text,
+            "first\n\n\n\nsecond"
+        );
+
+        harness.with_settings("editor {\n    collapseBlankLines true\n}");
+
+        harness.run_command(CommandInstruction::SwitchToPrevNote);
+        harness.run_command(CommandInstruction::SwitchToNextNote);
+        assert_eq!(
+            harness.state.notes[&NoteFile::Note(0)].text,
+            "first\n\nsecond"
+        );
+    }
+}
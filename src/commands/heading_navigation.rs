@@ -0,0 +1,234 @@
+use pulldown_cmark::HeadingLevel;
+use smallvec::SmallVec;
+
+use crate::{
+    app_actions::AppAction,
+    byte_span::ByteSpan,
+    command::{
+        CommandContext, EditorCommandOutput, TextCommandContext, try_extract_text_command_context,
+    },
+    effects::text_change_effect::TextChange,
+    text_structure::{SpanIndex, SpanKind, TextStructure},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HeadingJump {
+    NextSibling,
+    PrevSibling,
+    Parent,
+}
+
+/// Moves the cursor to the start of the target heading, relative to the section the cursor is in
+pub fn jump_to_heading(
+    CommandContext { app_state, .. }: CommandContext,
+    jump: HeadingJump,
+) -> Option<EditorCommandOutput> {
+    let context = try_extract_text_command_context(app_state)?;
+
+    let target = find_heading_target(context.text_structure, context.byte_cursor, jump)?;
+
+    Some(SmallVec::from_buf([AppAction::ApplyTextChanges {
+        target: app_state.selected_note,
+        changes: vec![TextChange::Insert(
+            ByteSpan::point(target),
+            TextChange::CURSOR.to_string(),
+        )],
+        should_trigger_eval: false,
+    }]))
+}
+
+/// Byte position of the heading to jump to.
+/// Siblings are headings of the same level within the same parent section
+pub fn find_heading_target(
+    structure: &TextStructure,
+    cursor: ByteSpan,
+    jump: HeadingJump,
+) -> Option<usize> {
+    let mut headings: Vec<(usize, HeadingLevel)> = structure
+        .iter()
+        .filter_map(|(_, desc)| match desc.kind {
+            SpanKind::Heading(level) => Some((desc.byte_pos.start, level)),
+            _ => None,
+        })
+        .collect();
+
+    headings.sort_by_key(|(pos, _)| *pos);
+
+    // the heading of the section the cursor is in
+    let current = headings.iter().rposition(|(pos, _)| *pos <= cursor.start);
+
+    let Some(current) = current else {
+        // before the first heading, so the first one is the "next" one
+        return match jump {
+            HeadingJump::NextSibling => headings.first().map(|(pos, _)| *pos),
+            HeadingJump::PrevSibling | HeadingJump::Parent => None,
+        };
+    };
+
+    let (_, level) = headings[current];
+
+    // a heading of a higher level ends the parent section
+    let sibling_or_parent = |(pos, other_level): &(usize, HeadingLevel)| {
+        (*other_level <= level).then_some((*pos, *other_level == level))
+    };
+
+    match jump {
+        HeadingJump::NextSibling => headings[current + 1..]
+            .iter()
+            .find_map(sibling_or_parent)
+            .and_then(|(pos, is_sibling)| is_sibling.then_some(pos)),
+
+        HeadingJump::PrevSibling => headings[..current]
+            .iter()
+            .rev()
+            .find_map(sibling_or_parent)
+            .and_then(|(pos, is_sibling)| is_sibling.then_some(pos)),
+
+        HeadingJump::Parent => headings[..current]
+            .iter()
+            .rev()
+            .find(|(_, other_level)| *other_level < level)
+            .map(|(pos, _)| *pos),
+    }
+}
+
+/// Titles of the headings of the sections the cursor is in, starting from the outermost one.
+/// Headings don't contain their sections in the structure, so the sections are found by levels
+pub fn heading_path<'t>(
+    structure: &TextStructure,
+    text: &'t str,
+    cursor: ByteSpan,
+) -> Vec<&'t str> {
+    let mut headings: Vec<(usize, HeadingLevel, SpanIndex)> = structure
+        .iter()
+        .filter_map(|(index, desc)| match desc.kind {
+            SpanKind::Heading(level) => Some((desc.byte_pos.start, level, index)),
+            _ => None,
+        })
+        .collect();
+
+    headings.sort_by_key(|(pos, ..)| *pos);
+
+    let mut path = vec![];
+    let mut outer_level: Option<HeadingLevel> = None;
+
+    for (_, level, index) in headings
+        .iter()
+        .rev()
+        .filter(|(pos, ..)| *pos <= cursor.start)
+    {
+        if outer_level.is_none_or(|outer| *level < outer) {
+            let title = text[structure.get_span_inner_content(*index).range()].trim();
+            if !title.is_empty() {
+                path.push(title);
+            }
+            outer_level = Some(*level);
+        }
+    }
+
+    path.reverse();
+    path
+}
+
+/// Copies the selection (or the line of the cursor) with its heading path on top, e.g. `Project > Tasks`
+pub fn copy_with_heading_path(
+    CommandContext { app_state, .. }: CommandContext,
+) -> Option<EditorCommandOutput> {
+    let TextCommandContext {
+        text_structure,
+        text,
+        byte_cursor,
+    } = try_extract_text_command_context(app_state)?;
+
+    let snippet_span = match byte_cursor.is_empty() {
+        true => text_structure.find_line_location(byte_cursor)?.1,
+        false => byte_cursor,
+    };
+
+    let snippet = text[snippet_span.range()].trim_end();
+    let path = heading_path(text_structure, text, snippet_span);
+
+    let copied = match path.is_empty() {
+        true => snippet.to_string(),
+        false => format!("{}\n{snippet}", path.join(" > ")),
+    };
+
+    Some(SmallVec::from_buf([AppAction::CopyText(copied)]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        command::CommandInstruction,
+        test_harness::{IOCall, TestHarness},
+    };
+
+    use super::*;
+
+    #[test]
+    pub fn test_find_heading_target() {
+        let text = "intro\n# A\n## A1\ntext\n### A1a\n## A2\n# B\n## B1\n";
+        let pos = |needle: &str| text.find(needle).unwrap();
+
+        let test_cases = [
+            ("intro", HeadingJump::NextSibling, Some(pos("# A"))),
+            ("intro", HeadingJump::PrevSibling, None),
+            ("# A", HeadingJump::NextSibling, Some(pos("# B"))),
+            ("text", HeadingJump::NextSibling, Some(pos("## A2"))),
+            ("text", HeadingJump::PrevSibling, None),
+            ("text", HeadingJump::Parent, Some(pos("# A"))),
+            ("A1a", HeadingJump::Parent, Some(pos("## A1"))),
+            ("A1a", HeadingJump::NextSibling, None),
+            ("## A2", HeadingJump::PrevSibling, Some(pos("## A1"))),
+            ("## A2", HeadingJump::NextSibling, None),
+            ("B1", HeadingJump::PrevSibling, None),
+            ("# B", HeadingJump::PrevSibling, Some(pos("# A"))),
+            ("# B", HeadingJump::Parent, None),
+        ];
+
+        let structure = TextStructure::new(text);
+
+        for (cursor_at, jump, expected) in test_cases {
+            let cursor = ByteSpan::point(pos(cursor_at));
+            assert_eq!(
+                find_heading_target(&structure, cursor, jump),
+                expected,
+                "cursor at {cursor_at:?}, {jump:?}"
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_heading_path() {
+        let text = "intro\n# Project\n## Notes\n## Tasks\n### Today\n## Done\n- shipped\n";
+        let structure = TextStructure::new(text);
+        let path_at = |needle: &str| {
+            heading_path(
+                &structure,
+                text,
+                ByteSpan::point(text.find(needle).unwrap()),
+            )
+        };
+
+        assert_eq!(path_at("intro"), Vec::<&str>::new());
+        assert_eq!(path_at("## Notes"), ["Project", "Notes"]);
+        assert_eq!(path_at("Today"), ["Project", "Tasks", "Today"]);
+        assert_eq!(path_at("shipped"), ["Project", "Done"]);
+
+        let mut harness = TestHarness::new("# Project\n## Tasks\n- [ ] write {||}docs\n");
+        harness.run_command(CommandInstruction::CopyWithHeadingPath);
+        assert_eq!(
+            harness.io.take_calls(),
+            vec![IOCall::CopyToClipboard(
+                "Project > Tasks\n- [ ] write docs".to_string()
+            )]
+        );
+
+        let mut harness = TestHarness::new("no headings {|}here{|}");
+        harness.run_command(CommandInstruction::CopyWithHeadingPath);
+        assert_eq!(
+            harness.io.take_calls(),
+            vec![IOCall::CopyToClipboard("here".to_string())]
+        );
+    }
+}
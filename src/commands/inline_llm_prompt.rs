@@ -24,3 +24,49 @@ pub fn inline_llm_prompt_command_handler(
 pub fn compute_inline_prompt_text_input_id(inline_prompt_address: TextSelectionAddress) -> Id {
     Id::new(inline_prompt_address)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        byte_span::ByteSpan,
+        command::CommandInstruction,
+        persistent_state::NoteFile,
+        test_harness::{IOCall, TestHarness},
+    };
+
+    use super::*;
+
+    #[test]
+    pub fn test_last_prompt_is_remembered_per_note() {
+        let mut harness = TestHarness::new("{|}selected{|}");
+
+        harness.run_command(CommandInstruction::ShowPrompt);
+        let prompt = harness.state.inline_llm_prompt.as_mut().unwrap();
+        assert_eq!(prompt.prompt, "");
+
+        prompt.prompt = "fix grammar".to_string();
+        harness.dispatch(AppAction::ExecutePrompt);
+        assert_eq!(
+            harness.io.take_calls(),
+            vec![IOCall::ExecuteLlmPrompt("fix grammar".to_string())]
+        );
+        assert!(harness.state.should_persist().is_some_and(
+            |data| data.last_llm_prompts == [(NoteFile::Note(0), "fix grammar".to_string())]
+        ));
+
+        harness.dispatch(AppAction::AcceptPromptSuggestion { accept: false });
+        harness.run_command(CommandInstruction::ShowPrompt);
+        assert_eq!(
+            harness.state.inline_llm_prompt.as_ref().unwrap().prompt,
+            "fix grammar"
+        );
+
+        // other notes have their own prompts
+        harness.dispatch(AppAction::AcceptPromptSuggestion { accept: false });
+        harness.state.selected_note = NoteFile::Note(1);
+        let note = harness.state.notes.get_mut(&NoteFile::Note(1)).unwrap();
+        note.update_cursor(ByteSpan::new(0, 0).unordered());
+        harness.run_command(CommandInstruction::ShowPrompt);
+        assert_eq!(harness.state.inline_llm_prompt.as_ref().unwrap().prompt, "");
+    }
+}
@@ -0,0 +1,71 @@
+use crate::{command::TextCommandContext, effects::text_change_effect::TextChange};
+
+use super::block_separators;
+
+/// Inserts a `---` thematic break, the cursor ends up after it.
+/// The empty line before keeps it from turning the paragraph above into a setext heading
+pub fn insert_horizontal_rule(
+    TextCommandContext {
+        text, byte_cursor, ..
+    }: TextCommandContext,
+) -> Option<Vec<TextChange>> {
+    let (prefix, suffix) = block_separators(text, byte_cursor);
+
+    Some(vec![TextChange::Insert(
+        byte_cursor,
+        format!("{prefix}---{suffix}{}", TextChange::CURSOR),
+    )])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{effects::text_change_effect::apply_text_changes, text_structure::TextStructure};
+
+    use super::*;
+
+    #[test]
+    pub fn test_insert_horizontal_rule() {
+        let test_cases = [
+            ("## empty note ##", "{||}", "---\n{||}"),
+            (
+                "## separated from the paragraph above ##",
+                "some text{||}",
+                "some text\n\n---\n{||}",
+            ),
+            (
+                "## separated from the previous line ##",
+                "some text\n{||}",
+                "some text\n\n---\n{||}",
+            ),
+            (
+                "## separated from the text after ##",
+                "before\n\n{||}after",
+                "before\n\n---\n\n{||}after",
+            ),
+            (
+                "## replaces selection ##",
+                "before\n\n{|}selected{|}\n\nafter",
+                "before\n\n---{||}\n\nafter",
+            ),
+        ];
+
+        for (desc, input, expected_output) in test_cases {
+            let (mut text, cursor) = TextChange::try_extract_cursor(input.to_string());
+            let cursor = cursor.unwrap();
+
+            let structure = TextStructure::new(&text);
+
+            let changes =
+                insert_horizontal_rule(TextCommandContext::new(&structure, &text, cursor)).unwrap();
+
+            let cursor = apply_text_changes(&mut text, Some(cursor.unordered()), changes).unwrap();
+
+            assert_eq!(
+                TextChange::encode_cursor(&text, cursor.unwrap()),
+                expected_output,
+                "test case: {}",
+                desc
+            );
+        }
+    }
+}
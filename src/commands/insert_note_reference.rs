@@ -0,0 +1,112 @@
+use smallvec::SmallVec;
+
+use crate::{
+    app_actions::AppAction,
+    app_state::AppState,
+    command::{
+        AppFocus, CommandContext, CommandInstruction, CommandScope, EditorCommandOutput,
+        ForwardToChild, SlashPaletteCmd, TextSource, try_extract_text_command_context,
+    },
+    effects::text_change_effect::TextChange,
+    persistent_state::{NoteFile, note_file_name},
+};
+
+/// Prefix of the slash commands that insert a link to a note
+pub const NOTE_LINK_PREFIX: &str = "insert-note-link";
+
+/// Markdown link that opens the note when clicked, e.g. `[Shelf 2](shelv://note2)`
+pub fn note_link(note_file: NoteFile) -> String {
+    match note_file {
+        NoteFile::Note(index) => format!("[Shelf {shelf}](shelv://note{shelf})", shelf = index + 1),
+        NoteFile::Settings => "[Settings](shelv://settings)".to_string(),
+    }
+}
+
+pub fn insert_note_link(ctx: CommandContext) -> Option<EditorCommandOutput> {
+    let note_file = ctx.app_state.selected_note;
+    insert_at_cursor(ctx, note_link(note_file))
+}
+
+/// Absolute path of the current note file, e.g. to read it from a script
+pub fn insert_note_path(ctx: CommandContext) -> Option<EditorCommandOutput> {
+    let path = ctx
+        .app_state
+        .persistence_folder
+        .join(note_file_name(ctx.app_state.selected_note));
+
+    insert_at_cursor(ctx, path.to_string_lossy().to_string())
+}
+
+fn insert_at_cursor(
+    CommandContext { app_state, .. }: CommandContext,
+    text: String,
+) -> Option<EditorCommandOutput> {
+    let context = try_extract_text_command_context(app_state)?;
+
+    Some(SmallVec::from_buf([AppAction::ApplyTextChanges {
+        target: app_state.selected_note,
+        changes: vec![TextChange::Insert(
+            context.byte_cursor,
+            format!("{text}{}", TextChange::CURSOR),
+        )],
+        should_trigger_eval: false,
+    }]))
+}
+
+/// Links to the other notes, listed in the palette next to the link to the current one
+pub fn note_link_options(app_state: &AppState) -> impl Iterator<Item = SlashPaletteCmd> {
+    app_state
+        .notes
+        .keys()
+        .copied()
+        .filter(|note_file| {
+            matches!(note_file, NoteFile::Note(_)) && *note_file != app_state.selected_note
+        })
+        .map(|note_file| {
+            SlashPaletteCmd::from_instruction(
+                NOTE_LINK_PREFIX,
+                CommandInstruction::InsertText(ForwardToChild(TextSource::Str(note_link(
+                    note_file,
+                )))),
+                CommandScope::Focus(AppFocus::NoteEditor),
+            )
+            .icon(egui_phosphor::light::LINK.to_string())
+            .description(match note_file {
+                NoteFile::Note(index) => format!("Link to Shelf {}", index + 1),
+                NoteFile::Settings => "Link to Settings".to_string(),
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_harness::TestHarness;
+
+    use super::*;
+
+    #[test]
+    pub fn test_insert_note_link_and_path() {
+        assert_eq!(note_link(NoteFile::Note(1)), "[Shelf 2](shelv://note2)");
+
+        let mut harness = TestHarness::new("see {||}");
+        harness.run_command(CommandInstruction::InsertNoteLink);
+        assert_eq!(
+            harness.text_with_cursor(),
+            "see [Shelf 1](shelv://note1){||}"
+        );
+
+        let mut harness = TestHarness::new("{|}replaced{|}");
+        harness.run_command(CommandInstruction::InsertNotePath);
+        assert_eq!(
+            harness.text_with_cursor(),
+            format!(
+                "{}{{||}}",
+                harness
+                    .state
+                    .persistence_folder
+                    .join("note-1.md")
+                    .to_string_lossy()
+            )
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use smallvec::SmallVec;
+
+use crate::{
+    app_actions::{AppAction, TablePickerAction},
+    app_state::TablePicker,
+    command::{
+        CommandContext, EditorCommandOutput, TextCommandContext, try_extract_text_command_context,
+    },
+    effects::text_change_effect::TextChange,
+};
+
+use super::block_separators;
+
+pub const TABLE_PICKER_MAX_ROWS: usize = 8;
+pub const TABLE_PICKER_MAX_COLS: usize = 8;
+
+pub fn show_table_picker(
+    CommandContext { app_state, .. }: CommandContext,
+) -> Option<EditorCommandOutput> {
+    let TextCommandContext { byte_cursor, .. } = try_extract_text_command_context(app_state)?;
+
+    Some(SmallVec::from_buf([AppAction::TablePicker(
+        TablePickerAction::Show(TablePicker {
+            note_file: app_state.selected_note,
+            byte_pos: byte_cursor.start,
+            rows: 2,
+            cols: 2,
+        }),
+    )]))
+}
+
+/// Inserts a skeleton GFM table, `rows` includes the header row,
+/// the cursor is placed inside the first header cell
+pub fn insert_table(
+    TextCommandContext {
+        text, byte_cursor, ..
+    }: TextCommandContext,
+    rows: usize,
+    cols: usize,
+) -> Option<Vec<TextChange>> {
+    if rows == 0 || cols == 0 {
+        return None;
+    }
+
+    let (prefix, suffix) = block_separators(text, byte_cursor);
+
+    let header = table_row(cols, TextChange::CURSOR);
+    let delimiter = format!("|{}", " --- |".repeat(cols));
+    let body = std::iter::repeat(table_row(cols, "")).take(rows - 1);
+
+    let table = [header, delimiter]
+        .into_iter()
+        .chain(body)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Some(vec![TextChange::Insert(
+        byte_cursor,
+        format!("{prefix}{table}{suffix}"),
+    )])
+}
+
+/// Row of empty cells, except the first one, e.g. `| first |  |  |`
+pub fn table_row(cols: usize, first_cell: &str) -> String {
+    format!("| {first_cell} |{}", "  |".repeat(cols.saturating_sub(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{effects::text_change_effect::apply_text_changes, text_structure::TextStructure};
+
+    use super::*;
+
+    #[test]
+    pub fn tests_for_insert_table() {
+        let test_cases = [
+            (
+                "## inserts a table into an empty note ##",
+                "{||}",
+                "| {||} |  |\n| --- | --- |\n|  |  |\n",
+                2,
+                2,
+            ),
+            (
+                "## header only table ##",
+                "{||}",
+                "| {||} |  |  |\n| --- | --- | --- |\n",
+                1,
+                3,
+            ),
+            (
+                "## separates the table from the paragraph on the same line ##",
+                "some text{||}",
+                "some text\n\n| {||} |\n| --- |\n|  |\n",
+                2,
+                1,
+            ),
+            (
+                "## separates the table from the previous line ##",
+                "some text\n{||}",
+                "some text\n\n| {||} |\n| --- |\n",
+                1,
+                1,
+            ),
+            (
+                "## separates the table from the text after ##",
+                "before\n\n{||}after",
+                "before\n\n| {||} |\n| --- |\n\nafter",
+                1,
+                1,
+            ),
+            (
+                "## replaces selection ##",
+                "{|}selected{|}\n\nafter",
+                "| {||} |\n| --- |\n\nafter",
+                1,
+                1,
+            ),
+        ];
+
+        for (desc, input, expected_output, rows, cols) in test_cases {
+            let (mut text, cursor) = TextChange::try_extract_cursor(input.to_string());
+            let cursor = cursor.unwrap();
+
+            let structure = TextStructure::new(&text);
+
+            let changes = insert_table(
+                TextCommandContext::new(&structure, &text, cursor),
+                rows,
+                cols,
+            )
+            .unwrap();
+
+            let cursor = apply_text_changes(&mut text, Some(cursor.unordered()), changes).unwrap();
+
+            assert_eq!(
+                TextChange::encode_cursor(&text, cursor.unwrap()),
+                expected_output,
+                "test case: {}",
+                desc
+            );
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use crate::{command::TextCommandContext, effects::text_change_effect::TextChange};
+
+/// Inserts the formatted timestamp at the cursor (or instead of the selection), the cursor ends up after it
+pub fn insert_timestamp(
+    TextCommandContext { byte_cursor, .. }: TextCommandContext,
+    timestamp: &str,
+) -> Option<Vec<TextChange>> {
+    Some(vec![TextChange::Insert(
+        byte_cursor,
+        format!("{timestamp}{}", TextChange::CURSOR),
+    )])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{effects::text_change_effect::apply_text_changes, text_structure::TextStructure};
+
+    use super::*;
+
+    #[test]
+    pub fn test_insert_timestamp() {
+        let test_cases = [
+            ("## at the cursor ##", "- {||}", "- 2025-03-14 09:30{||}"),
+            (
+                "## replaces selection ##",
+                "logged at {|}yesterday{|}.",
+                "logged at 2025-03-14 09:30{||}.",
+            ),
+        ];
+
+        for (desc, input, expected_output) in test_cases {
+            let (mut text, cursor) = TextChange::try_extract_cursor(input.to_string());
+            let cursor = cursor.unwrap();
+
+            let structure = TextStructure::new(&text);
+
+            let changes = insert_timestamp(
+                TextCommandContext::new(&structure, &text, cursor),
+                "2025-03-14 09:30",
+            )
+            .unwrap();
+
+            let cursor = apply_text_changes(&mut text, Some(cursor.unordered()), changes).unwrap();
+
+            assert_eq!(
+                TextChange::encode_cursor(&text, cursor.unwrap()),
+                expected_output,
+                "test case: {}",
+                desc
+            );
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use crate::{
+    byte_span::ByteSpan,
+    command::TextCommandContext,
+    effects::text_change_effect::TextChange,
+    text_structure::{SpanKind, TextStructure},
+};
+
+/// Joins the line of the cursor with the next one (or all the lines of the selection) into one,
+/// separated by a space. If both lines are list items, the marker of the second one is removed,
+/// so they become a single item, the same for the `>` of a quote
+pub fn join_lines(context: TextCommandContext) -> Option<Vec<TextChange>> {
+    let TextCommandContext {
+        text_structure: structure,
+        text,
+        byte_cursor: cursor,
+    } = context;
+
+    let lines = structure.lines_of(cursor);
+
+    let (lines, is_single_join) = match lines {
+        [line] => {
+            // the line break is the only byte between the lines
+            let next_line_pos = line.end + 1;
+            let next = match next_line_pos <= text.len() {
+                true => structure.lines_of(ByteSpan::point(next_line_pos)).first(),
+                false => None,
+            };
+            (vec![*line, *next?], true)
+        }
+        lines => (lines.to_vec(), false),
+    };
+
+    let changes: Vec<TextChange> = lines
+        .windows(2)
+        .map(|pair| {
+            let (line, next) = (pair[0], pair[1]);
+
+            let line_text = &text[line.range()];
+            let join_start = line.start + line_text.trim_end().len();
+            let joined_start = joined_text_start(structure, text, line, next);
+
+            let separator = match line_text.trim().is_empty() || joined_start == next.end {
+                true => "",
+                false => " ",
+            };
+
+            // the cursor stays at the join, as in vim
+            let replacement = match is_single_join {
+                true => format!("{separator}{}", TextChange::CURSOR),
+                false => separator.to_string(),
+            };
+
+            TextChange::Insert(ByteSpan::new(join_start, joined_start), replacement)
+        })
+        .collect();
+
+    (!changes.is_empty()).then_some(changes)
+}
+
+/// Where the text of the `next` line starts, after its indentation, quote and list markers.
+/// Code blocks are joined as is, only the indentation goes away
+fn joined_text_start(
+    structure: &TextStructure,
+    text: &str,
+    line: ByteSpan,
+    next: ByteSpan,
+) -> usize {
+    let next_text = &text[next.range()];
+    let indented = next.start + next_text.len() - next_text.trim_start().len();
+
+    if structure.find_span_at(SpanKind::CodeBlock, next).is_some() {
+        return indented;
+    }
+
+    let unquoted = next.start + next_text.len()
+        - next_text
+            .trim_start_matches(|ch: char| ch == '>' || ch.is_whitespace())
+            .len();
+
+    match is_in_list_item(structure, line) {
+        true => list_item_content_start(structure, text, next).unwrap_or(unquoted),
+        false => unquoted,
+    }
+}
+
+fn is_in_list_item(structure: &TextStructure, line: ByteSpan) -> bool {
+    structure.iter().any(|(_, desc)| {
+        desc.kind == SpanKind::ListItem
+            && desc.byte_pos.start <= line.start
+            && line.end <= desc.byte_pos.end
+    })
+}
+
+/// `None` if the line doesn't start a list item, otherwise the position after its marker
+/// (and the task marker), e.g. `buy milk` of `- [ ] buy milk`
+fn list_item_content_start(structure: &TextStructure, text: &str, line: ByteSpan) -> Option<usize> {
+    // nested items might start at the indentation of the parent item (or even before the line break)
+    let item_start = |desc_start: usize| {
+        text[desc_start..]
+            .find(|ch: char| !ch.is_whitespace())
+            .map_or(text.len(), |offset| desc_start + offset)
+    };
+
+    let (index, _) = structure
+        .iter()
+        .filter(|(_, desc)| desc.kind == SpanKind::ListItem)
+        .filter(|(_, desc)| line.contains_pos(item_start(desc.byte_pos.start)))
+        .last()?;
+
+    let content = structure.get_span_inner_content(index);
+    let content_start = match content.start > line.start && content.start <= line.end {
+        true => content.start,
+        // e.g. `- ` without any text
+        false => return Some(line.end),
+    };
+
+    let after_task_marker = structure
+        .iter()
+        .find(|(_, desc)| desc.kind == SpanKind::TaskMarker && desc.byte_pos.start == content_start)
+        .map_or(content_start, |(_, desc)| desc.byte_pos.end);
+
+    Some(
+        after_task_marker + text[after_task_marker..line.end].len()
+            - text[after_task_marker..line.end].trim_start().len(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::effects::text_change_effect::apply_text_changes;
+
+    use super::*;
+
+    #[test]
+    pub fn test_join_lines() {
+        let test_cases = [
+            (
+                "## joins with the next line ##",
+                "first{||} line\n  second line",
+                Some("first line {||}second line"),
+            ),
+            (
+                "## list items become one ##",
+                "- first{||}\n- second\n- third",
+                Some("- first {||}second\n- third"),
+            ),
+            (
+                "## task and ordered markers are removed ##",
+                "1. [ ] first{||}\n2. [x] second",
+                Some("1. [ ] first {||}second"),
+            ),
+            (
+                "## paragraph keeps the marker of the item below ##",
+                "text{||}\n- item",
+                Some("text {||}- item"),
+            ),
+            (
+                "## quotes ##",
+                "> first{||}\n> second",
+                Some("> first {||}second"),
+            ),
+            (
+                "## empty next line ##",
+                "first{||}\n\nthird",
+                Some("first{||}\nthird"),
+            ),
+            (
+                "## all lines of the selection ##",
+                "{|}a\nb\nc{|}\nd",
+                Some("{|}a b c{|}\nd"),
+            ),
+            ("## last line ##", "first\nlast{||}", None),
+        ];
+
+        for (desc, input, expected) in test_cases {
+            let (mut text, cursor) = TextChange::try_extract_cursor(input.to_string());
+            let cursor = cursor.unwrap();
+
+            let structure = TextStructure::new(&text);
+
+            let res =
+                join_lines(TextCommandContext::new(&structure, &text, cursor)).map(|changes| {
+                    let cursor =
+                        apply_text_changes(&mut text, Some(cursor.unordered()), changes).unwrap();
+                    TextChange::encode_cursor(&text, cursor.unwrap())
+                });
+
+            assert_eq!(res.as_deref(), expected, "test case: {desc}");
+        }
+    }
+}
@@ -1,14 +1,40 @@
+pub mod code_block_navigation;
+pub mod convert_to_tasks;
+pub mod cycle_task_state;
+pub mod enter_in_code_block;
 pub mod enter_in_list;
+pub mod escape_markdown;
+pub mod extract_code_block;
+pub mod find_replace;
+pub mod format_note;
+pub mod heading_navigation;
 pub mod inline_llm_prompt;
+pub mod insert_horizontal_rule;
+pub mod insert_note_reference;
+pub mod insert_table;
 pub mod insert_text;
+pub mod insert_timestamp;
+pub mod join_lines;
 pub mod kdl_lang;
+pub mod multi_cursor;
+pub mod paste_list;
+pub mod paste_quote;
+pub mod pin_selection;
+pub mod rename_in_code_block;
 pub mod run_llm;
 pub mod slash_pallete;
+pub mod smart_typography;
 pub mod space_after_task_markers;
 pub mod tabbing_in_list;
+pub mod tabbing_in_table;
+pub mod toggle_callout;
 pub mod toggle_code_block;
 pub mod toggle_md_headings;
 pub mod toggle_simple_md_annotations;
+pub mod transpose_chars;
+pub mod wrap_in_details;
+
+use crate::byte_span::ByteSpan;
 
 pub fn select_unordered_list_marker(depth: usize) -> &'static str {
     match depth {
@@ -16,3 +42,23 @@ pub fn select_unordered_list_marker(depth: usize) -> &'static str {
         _ => "*",
     }
 }
+
+/// Line breaks to put around a block inserted at `span`,
+/// so it is separated from the surrounding blocks by an empty line
+pub fn block_separators(text: &str, span: ByteSpan) -> (&'static str, &'static str) {
+    let prefix = match &text[..span.start] {
+        "" => "",
+        before if before.ends_with("\n\n") => "",
+        before if before.ends_with('\n') => "\n",
+        _ => "\n\n",
+    };
+
+    let suffix = match &text[span.end..] {
+        "" => "\n",
+        after if after.starts_with("\n\n") => "",
+        after if after.starts_with('\n') => "\n",
+        _ => "\n\n",
+    };
+
+    (prefix, suffix)
+}
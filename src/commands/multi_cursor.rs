@@ -0,0 +1,283 @@
+use eframe::egui::{Event, InputState, Key, Modifiers};
+use itertools::Itertools;
+use smallvec::SmallVec;
+
+use crate::{
+    app_actions::{AppAction, MultiCursorAction},
+    byte_span::ByteSpan,
+    command::{
+        CommandContext, EditorCommandOutput, TextCommandContext, try_extract_text_command_context,
+    },
+    effects::text_change_effect::TextChange,
+};
+
+/// Edit that is applied at every selection of the multi cursor
+#[derive(Debug, Clone, PartialEq)]
+pub enum MultiCursorEdit {
+    Insert(String),
+    DeleteBackward,
+}
+
+/// Cmd+D: selects the word under the cursor first,
+/// then each press adds the next occurrence of the selected text as another selection
+pub fn select_next_occurrence(
+    CommandContext { app_state, .. }: CommandContext,
+) -> Option<EditorCommandOutput> {
+    let TextCommandContext {
+        text, byte_cursor, ..
+    } = try_extract_text_command_context(app_state)?;
+
+    let note_file = app_state.selected_note;
+
+    if byte_cursor.is_empty() {
+        let word = word_at(text, byte_cursor.start)?;
+        return Some(SmallVec::from_buf([AppAction::ApplyTextChanges {
+            target: note_file,
+            changes: vec![select_span(text, word)],
+            should_trigger_eval: false,
+        }]));
+    }
+
+    let existing: SmallVec<[ByteSpan; 8]> = app_state
+        .multi_cursor
+        .as_ref()
+        .filter(|multi_cursor| multi_cursor.note_file == note_file)
+        .map(|multi_cursor| multi_cursor.selections.clone())
+        .unwrap_or_default()
+        .into_iter()
+        .chain([byte_cursor])
+        .collect();
+
+    let next = find_next_occurrence(text, byte_cursor, &existing)?;
+
+    Some(
+        [
+            AppAction::MultiCursor(MultiCursorAction::AddSelection(byte_cursor)),
+            AppAction::ApplyTextChanges {
+                target: note_file,
+                changes: vec![select_span(text, next)],
+                should_trigger_eval: false,
+            },
+        ]
+        .into_iter()
+        .collect(),
+    )
+}
+
+/// Takes the typing out of the input, so the text edit doesn't apply it to the primary cursor only
+pub fn consume_multi_cursor_input(input: &mut InputState) -> SmallVec<[MultiCursorAction; 2]> {
+    let mut actions = SmallVec::new();
+
+    input.events.retain(|event| {
+        let action = match event {
+            Event::Text(text) | Event::Paste(text) => {
+                MultiCursorAction::Edit(MultiCursorEdit::Insert(text.clone()))
+            }
+            Event::Key {
+                key,
+                pressed: true,
+                modifiers: Modifiers::NONE,
+                ..
+            } => match key {
+                Key::Backspace => MultiCursorAction::Edit(MultiCursorEdit::DeleteBackward),
+                Key::Enter => MultiCursorAction::Edit(MultiCursorEdit::Insert("\n".to_string())),
+                Key::Escape => MultiCursorAction::Clear,
+                _ => return true,
+            },
+            _ => return true,
+        };
+
+        actions.push(action);
+        false
+    });
+
+    actions
+}
+
+/// Applies the edit at every selection, returns the updated selections in the same order
+pub fn edit_at_selections(
+    text: &mut String,
+    selections: &[ByteSpan],
+    edit: &MultiCursorEdit,
+) -> SmallVec<[ByteSpan; 8]> {
+    let mut order: SmallVec<[usize; 8]> = (0..selections.len()).collect();
+    order.sort_by_key(|&i| selections[i].start);
+
+    let mut updated: SmallVec<[ByteSpan; 8]> = selections.iter().copied().collect();
+
+    // how much the text before the current selection grew or shrank
+    let mut delta: isize = 0;
+
+    for i in order {
+        let selection = selections[i].move_by(delta);
+
+        let (replaced, inserted) = match edit {
+            MultiCursorEdit::Insert(insertion) => (selection, insertion.as_str()),
+            MultiCursorEdit::DeleteBackward if !selection.is_empty() => (selection, ""),
+            MultiCursorEdit::DeleteBackward => {
+                let prev_char_start = text[..selection.start]
+                    .char_indices()
+                    .next_back()
+                    .map_or(selection.start, |(pos, _)| pos);
+                (ByteSpan::new(prev_char_start, selection.start), "")
+            }
+        };
+
+        text.replace_range(replaced.range(), inserted);
+
+        updated[i] = ByteSpan::point(replaced.start + inserted.len());
+        delta += inserted.len() as isize - replaced.range().len() as isize;
+    }
+
+    updated
+}
+
+/// Selections can become stale if the text was changed by something else
+pub fn are_valid_selections(text: &str, selections: &[ByteSpan]) -> bool {
+    let in_bounds = selections.iter().all(|s| {
+        s.end <= text.len() && text.is_char_boundary(s.start) && text.is_char_boundary(s.end)
+    });
+
+    let sorted: SmallVec<[ByteSpan; 8]> = selections
+        .iter()
+        .copied()
+        .sorted_by_key(|s| s.start)
+        .collect();
+
+    in_bounds && sorted.windows(2).all(|pair| pair[0].end <= pair[1].start)
+}
+
+/// Next occurrence of the selected text after `selection` that is not selected yet,
+/// wraps around to the beginning of the note
+fn find_next_occurrence(
+    text: &str,
+    selection: ByteSpan,
+    existing: &[ByteSpan],
+) -> Option<ByteSpan> {
+    let needle = &text[selection.range()];
+
+    let is_selected = |candidate: &ByteSpan| {
+        existing
+            .iter()
+            .any(|s| s.start < candidate.end && candidate.start < s.end)
+    };
+
+    let candidates: SmallVec<[ByteSpan; 8]> = text
+        .match_indices(needle)
+        .map(|(pos, _)| ByteSpan::new(pos, pos + needle.len()))
+        .filter(|candidate| !is_selected(candidate))
+        .collect();
+
+    candidates
+        .iter()
+        .find(|candidate| candidate.start >= selection.end)
+        .or(candidates.first())
+        .copied()
+}
+
+pub fn word_at(text: &str, pos: usize) -> Option<ByteSpan> {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+
+    let start = text[..pos]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word_char(*c))
+        .last()
+        .map_or(pos, |(i, _)| i);
+
+    let end = text[pos..]
+        .char_indices()
+        .find(|(_, c)| !is_word_char(*c))
+        .map_or(text.len(), |(i, _)| pos + i);
+
+    (start < end).then(|| ByteSpan::new(start, end))
+}
+
+fn select_span(text: &str, span: ByteSpan) -> TextChange {
+    TextChange::Insert(
+        span,
+        format!(
+            "{edge}{}{edge}",
+            &text[span.range()],
+            edge = TextChange::CURSOR_EDGE
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_find_next_occurrence() {
+        let text = "foo bar foo baz foo";
+        let first = ByteSpan::new(0, 3);
+
+        let second = find_next_occurrence(text, first, &[first]).unwrap();
+        assert_eq!(second, ByteSpan::new(8, 11));
+
+        let third = find_next_occurrence(text, second, &[first, second]).unwrap();
+        assert_eq!(third, ByteSpan::new(16, 19));
+
+        // wraps around and skips the selected ones
+        assert_eq!(
+            find_next_occurrence(text, ByteSpan::new(16, 19), &[ByteSpan::new(16, 19)]),
+            Some(first)
+        );
+        assert_eq!(
+            find_next_occurrence(text, third, &[first, second, third]),
+            None
+        );
+    }
+
+    #[test]
+    pub fn test_word_at() {
+        let text = "let some_var = 1;";
+        assert_eq!(word_at(text, 6), Some(ByteSpan::new(4, 12)));
+        assert_eq!(word_at(text, 12), Some(ByteSpan::new(4, 12)));
+        assert_eq!(word_at(text, 13), None);
+    }
+
+    #[test]
+    pub fn test_edit_at_selections() {
+        let test_cases = [
+            (
+                "-- replaces all selections --",
+                "foo bar foo",
+                vec![ByteSpan::new(8, 11), ByteSpan::new(0, 3)],
+                MultiCursorEdit::Insert("baz".to_string()),
+                "baz bar baz",
+                vec![ByteSpan::point(11), ByteSpan::point(3)],
+            ),
+            (
+                "-- deletes selected text --",
+                "foo bar foo",
+                vec![ByteSpan::new(0, 3), ByteSpan::new(8, 11)],
+                MultiCursorEdit::DeleteBackward,
+                " bar ",
+                vec![ByteSpan::point(0), ByteSpan::point(5)],
+            ),
+            (
+                "-- deletes a char before each cursor --",
+                "añb añb",
+                vec![ByteSpan::point(3), ByteSpan::point(8)],
+                MultiCursorEdit::DeleteBackward,
+                "ab ab",
+                vec![ByteSpan::point(1), ByteSpan::point(4)],
+            ),
+        ];
+
+        for (desc, input, selections, edit, expected_text, expected_selections) in test_cases {
+            let mut text = input.to_string();
+            let updated = edit_at_selections(&mut text, &selections, &edit);
+
+            assert_eq!(text, expected_text, "test case: {}", desc);
+            assert_eq!(
+                updated.as_slice(),
+                expected_selections.as_slice(),
+                "test case: {}",
+                desc
+            );
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use eframe::egui::{Event, InputState};
+use itertools::Itertools;
+
+use crate::{
+    byte_span::ByteSpan,
+    command::TextCommandContext,
+    effects::text_change_effect::TextChange,
+    text_structure::{SpanKind, TextStructure},
+};
+
+use super::{enter_in_code_block::is_inside_code, select_unordered_list_marker};
+
+/// The text pasted during the frame, if nothing else was typed along with it
+pub fn single_pasted_text(input: &InputState) -> Option<String> {
+    let event = input
+        .events
+        .iter()
+        .filter(|event| matches!(event, Event::Text(_) | Event::Paste(_)))
+        .exactly_one()
+        .ok()?;
+
+    match event {
+        Event::Paste(text) => Some(text.clone()),
+        _ => None,
+    }
+}
+
+/// `pasted` is the text that was just inserted right before the cursor.
+/// If it is a markdown list, its markers and indentation are changed to the ones Shelv uses:
+/// tabs for nesting and `-`/`*` depending on the depth, ordered items keep their numbers
+pub fn on_pasted_text(context: TextCommandContext, pasted: &str) -> Option<Vec<TextChange>> {
+    let TextCommandContext {
+        text_structure: structure,
+        text,
+        byte_cursor: cursor,
+    } = context;
+
+    if !cursor.is_empty() || !text.get(..cursor.start)?.ends_with(pasted) {
+        return None;
+    }
+
+    let paste_start = cursor.start - pasted.len();
+
+    // the depth of the pasted items is unknown if the line already has something
+    let is_line_start = paste_start == 0 || text[..paste_start].ends_with('\n');
+
+    if !is_line_start || is_inside_code(structure, ByteSpan::point(paste_start)) {
+        return None;
+    }
+
+    let changes: Vec<_> = list_item_prefixes(pasted)?
+        .into_iter()
+        .filter(|(span, prefix)| &pasted[span.range()] != prefix)
+        .map(|(span, prefix)| {
+            TextChange::Insert(
+                ByteSpan::new(paste_start + span.start, paste_start + span.end),
+                prefix,
+            )
+        })
+        .collect();
+
+    (!changes.is_empty()).then_some(changes)
+}
+
+/// Indentation + marker of every list item paired with the normalized version of them,
+/// `None` if the text is not just a list
+fn list_item_prefixes(text: &str) -> Option<Vec<(ByteSpan, String)>> {
+    let structure = TextStructure::new(text);
+
+    let top_level_kinds: Vec<_> = structure
+        .iter()
+        .filter(|(index, _)| structure.iterate_parents_of(*index).next().is_none())
+        .map(|(_, desc)| desc.kind)
+        .collect();
+
+    if top_level_kinds.is_empty() || top_level_kinds.iter().any(|kind| *kind != SpanKind::List) {
+        return None;
+    }
+
+    structure
+        .iter()
+        .filter(|(_, desc)| desc.kind == SpanKind::ListItem)
+        .map(|(index, desc)| {
+            let depth = structure
+                .iterate_parents_of(index)
+                .filter(|(_, desc)| desc.kind == SpanKind::List)
+                .count()
+                - 1;
+
+            // nested items might start at the indentation of the parent item
+            let item_start = desc.byte_pos.start
+                + text[desc.byte_pos.start..].find(|ch: char| !ch.is_whitespace())?;
+            let line_start = text[..item_start].rfind('\n').map_or(0, |i| i + 1);
+
+            // e.g. `- - nested`, there is no indentation to fix
+            if !text[line_start..item_start].trim().is_empty() {
+                return None;
+            }
+
+            let marker_end = text[item_start..]
+                .find(char::is_whitespace)
+                .map_or(text.len(), |len| item_start + len);
+
+            let marker = match &text[item_start..marker_end] {
+                "-" | "*" | "+" => select_unordered_list_marker(depth),
+                // ordered items keep their numbers
+                ordered => ordered,
+            };
+
+            Some((
+                ByteSpan::new(line_start, marker_end),
+                format!("{}{marker}", "\t".repeat(depth)),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::effects::text_change_effect::apply_text_changes;
+
+    use super::*;
+
+    #[test]
+    pub fn test_pasted_lists_are_normalized() {
+        let test_cases = [
+            (
+                "## mixed markers ##",
+                "* a\n+ b\n- c",
+                "before\n{||}",
+                Some("before\n- a\n- b\n- c{||}"),
+            ),
+            (
+                "## nested with spaces ##",
+                "* a\n    + b\n        - c\n* d\n",
+                "{||}",
+                Some("- a\n\t* b\n\t\t* c\n- d\n{||}"),
+            ),
+            (
+                "## ordered items keep numbers ##",
+                "1. a\n   * b\n2. c",
+                "{||}\nafter",
+                Some("1. a\n\t* b\n2. c{||}\nafter"),
+            ),
+            (
+                "## tasks ##",
+                "* [ ] a\n  * [x] b",
+                "{||}",
+                Some("- [ ] a\n\t* [x] b{||}"),
+            ),
+            ("## already normalized ##", "- a\n\t* b", "{||}", None),
+            ("## not only a list ##", "text\n\n* a", "{||}", None),
+            ("## not a list ##", "some text", "{||}", None),
+            ("## middle of the line ##", "* a\n* b", "- {||}", None),
+            (
+                "## inside code block ##",
+                "* a\n* b",
+                "```\n{||}\n```",
+                None,
+            ),
+        ];
+
+        for (desc, pasted, before_paste, expected) in test_cases {
+            let input = before_paste.replace(TextChange::CURSOR, &format!("{pasted}{{||}}"));
+            let (mut text, cursor) = TextChange::try_extract_cursor(input);
+            let cursor = cursor.unwrap();
+
+            let structure = TextStructure::new(&text);
+            let changes =
+                on_pasted_text(TextCommandContext::new(&structure, &text, cursor), pasted);
+
+            let res = changes.map(|changes| {
+                let cursor =
+                    apply_text_changes(&mut text, Some(cursor.unordered()), changes).unwrap();
+                TextChange::encode_cursor(&text, cursor.unwrap())
+            });
+
+            assert_eq!(res.as_deref(), expected, "test case: {desc}");
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use linkify::{LinkFinder, LinkKind};
+
+use crate::{command::TextCommandContext, effects::text_change_effect::TextChange};
+
+use super::block_separators;
+
+/// Inserts the clipboard text as a `> ` blockquote, the cursor ends up at the end of it.
+/// A lone url on the last line is treated as the source and goes after the quote, e.g. `> — https://...`
+pub fn paste_as_quote(context: TextCommandContext, clipboard: &str) -> Option<Vec<TextChange>> {
+    let TextCommandContext {
+        text, byte_cursor, ..
+    } = context;
+
+    let lines: Vec<&str> = clipboard.trim().lines().map(str::trim_end).collect();
+
+    let (lines, source) = match lines.split_last() {
+        Some((last, rest)) if !rest.is_empty() && is_url(last) => (rest, Some(*last)),
+        _ => (lines.as_slice(), None),
+    };
+
+    // the url could have been separated from the text by empty lines
+    let content_len = lines.iter().rposition(|line| !line.is_empty())? + 1;
+
+    let mut quote = lines[..content_len]
+        .iter()
+        .map(|line| match line.is_empty() {
+            true => ">".to_string(),
+            false => format!("> {line}"),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if let Some(url) = source {
+        quote.push_str(&format!("\n>\n> — {url}"));
+    }
+
+    // typing right below a quote would continue it, hence the cursor stays on its last line
+    let (prefix, suffix) = block_separators(text, byte_cursor);
+
+    Some(vec![TextChange::Insert(
+        byte_cursor,
+        format!("{prefix}{quote}{}{suffix}", TextChange::CURSOR),
+    )])
+}
+
+fn is_url(line: &str) -> bool {
+    let mut finder = LinkFinder::new();
+    finder.kinds(&[LinkKind::Url]);
+
+    let line = line.trim();
+    finder
+        .links(line)
+        .next()
+        .is_some_and(|link| link.start() == 0 && link.end() == line.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        command::CommandInstruction, effects::text_change_effect::apply_text_changes,
+        test_harness::TestHarness, text_structure::TextStructure,
+    };
+
+    use super::*;
+
+    #[test]
+    pub fn test_paste_as_quote() {
+        let test_cases = [
+            (
+                "## quotes every line ##",
+                "{||}",
+                "first line\n\nsecond line\n",
+                Some("> first line\n>\n> second line{||}\n"),
+            ),
+            (
+                "## separated from the paragraph above ##",
+                "my notes{||}",
+                "quoted",
+                Some("my notes\n\n> quoted{||}\n"),
+            ),
+            (
+                "## url on the last line is the source ##",
+                "{||}\n\nafter",
+                "quoted\n\nhttps://example.com/article",
+                Some("> quoted\n>\n> — https://example.com/article{||}\n\nafter"),
+            ),
+            (
+                "## a lone url is quoted as is ##",
+                "{||}",
+                "https://example.com",
+                Some("> https://example.com{||}\n"),
+            ),
+            ("## nothing to quote ##", "{||}", "  \n ", None),
+        ];
+
+        for (desc, input, clipboard, expected) in test_cases {
+            let (mut text, cursor) = TextChange::try_extract_cursor(input.to_string());
+            let cursor = cursor.unwrap();
+
+            let structure = TextStructure::new(&text);
+
+            let res = paste_as_quote(
+                TextCommandContext::new(&structure, &text, cursor),
+                clipboard,
+            )
+            .map(|changes| {
+                let cursor =
+                    apply_text_changes(&mut text, Some(cursor.unordered()), changes).unwrap();
+                TextChange::encode_cursor(&text, cursor.unwrap())
+            });
+
+            assert_eq!(res.as_deref(), expected, "test case: {desc}");
+        }
+    }
+
+    #[test]
+    pub fn test_clipboard_is_pasted_as_quote() {
+        let mut harness = TestHarness::new("Read today:{||}");
+
+        harness.run_command(CommandInstruction::PasteAsQuote);
+        assert_eq!(harness.text_with_cursor(), "Read today:{||}");
+
+        harness.io.clipboard_text = Some("Simple is better.\nhttps://example.com".to_string());
+        harness.run_command(CommandInstruction::PasteAsQuote);
+        assert_eq!(
+            harness.text_with_cursor(),
+            "Read today:\n\n> Simple is better.\n>\n> — https://example.com{||}\n"
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use smallvec::SmallVec;
+
+use crate::{
+    app_actions::AppAction,
+    command::{
+        CommandContext, EditorCommandOutput, TextCommandContext, try_extract_text_command_context,
+    },
+};
+
+/// Keeps the selected text visible in a floating overlay above the editor,
+/// e.g. a snippet to refer to while writing in another part of the note
+pub fn pin_selection(
+    CommandContext { app_state, .. }: CommandContext,
+) -> Option<EditorCommandOutput> {
+    let TextCommandContext {
+        text, byte_cursor, ..
+    } = try_extract_text_command_context(app_state)?;
+
+    let snippet = text[byte_cursor.range()].trim_end();
+
+    match snippet.trim().is_empty() {
+        true => None,
+        false => Some(SmallVec::from_buf([AppAction::SetPinnedSnippet(Some(
+            snippet.to_string(),
+        ))])),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{command::CommandInstruction, test_harness::TestHarness};
+
+    #[test]
+    pub fn test_pin_selection() {
+        let mut harness = TestHarness::new("intro\n{|}fn main() {}\n{|}outro");
+        harness.run_command(CommandInstruction::PinSelection);
+        assert_eq!(
+            harness.state.pinned_snippet.as_deref(),
+            Some("fn main() {}")
+        );
+
+        // the cursor alone doesn't replace the pinned snippet
+        let mut harness = TestHarness::new("intro{||}");
+        harness.state.pinned_snippet = Some("kept".to_string());
+        harness.run_command(CommandInstruction::PinSelection);
+        assert_eq!(harness.state.pinned_snippet.as_deref(), Some("kept"));
+    }
+}
@@ -0,0 +1,168 @@
+use eframe::egui::Id;
+use smallvec::SmallVec;
+
+use crate::{
+    app_actions::{AppAction, RenamePromptAction},
+    app_state::RenamePrompt,
+    byte_span::ByteSpan,
+    command::{
+        CommandContext, EditorCommandOutput, TextCommandContext, try_extract_text_command_context,
+    },
+    effects::text_change_effect::TextChange,
+    text_structure::{SpanKind, SpanMeta, TextStructure},
+};
+
+use super::multi_cursor::word_at;
+
+pub fn rename_prompt_input_id() -> Id {
+    Id::new("rename_prompt_input")
+}
+
+pub fn show_rename_prompt(
+    CommandContext { app_state, .. }: CommandContext,
+) -> Option<EditorCommandOutput> {
+    let TextCommandContext {
+        text,
+        text_structure,
+        byte_cursor,
+    } = try_extract_text_command_context(app_state)?;
+
+    let block = code_block_content(text_structure, byte_cursor)?;
+    if !block.contains(byte_cursor) {
+        return None;
+    }
+
+    let token = match byte_cursor.is_empty() {
+        true => word_at(text, byte_cursor.start)?,
+        false => byte_cursor,
+    };
+
+    let token = text[token.range()].to_string();
+    if token.trim().is_empty() || token.contains('\n') {
+        return None;
+    }
+
+    Some(SmallVec::from_buf([AppAction::RenamePrompt(
+        RenamePromptAction::Show(RenamePrompt {
+            note_file: app_state.selected_note,
+            block,
+            byte_pos: byte_cursor.start,
+            new_name: token.clone(),
+            token,
+        }),
+    )]))
+}
+
+/// Span of the code inside the block, without the fences and the language
+fn code_block_content(structure: &TextStructure, byte_cursor: ByteSpan) -> Option<ByteSpan> {
+    let (block_index, _, SpanMeta::CodeBlock(_)) =
+        structure.find_surrounding_span_with_meta(SpanKind::CodeBlock, byte_cursor)?
+    else {
+        return None;
+    };
+
+    structure
+        .iterate_immediate_children_of(block_index)
+        .find(|(_, desc)| desc.kind == SpanKind::Text)
+        .map(|(_, desc)| desc.byte_pos)
+}
+
+/// Replaces every occurrence of `token` inside the `block`,
+/// occurrences that are a part of a longer identifier are skipped
+pub fn rename_in_code_block(
+    text: &str,
+    block: ByteSpan,
+    token: &str,
+    new_name: &str,
+) -> Option<Vec<TextChange>> {
+    if token.is_empty() || new_name.is_empty() || token == new_name || block.end > text.len() {
+        return None;
+    }
+
+    let is_word_char = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+
+    let block_text = &text[block.range()];
+
+    let changes: Vec<TextChange> = block_text
+        .match_indices(token)
+        .map(|(pos, _)| ByteSpan::new(pos, pos + token.len()).move_by(block.start as isize))
+        .filter(|occurrence| {
+            let before = text[..occurrence.start].chars().next_back();
+            let after = text[occurrence.end..].chars().next();
+
+            let token_starts_with_word = is_word_char(token.chars().next());
+            let token_ends_with_word = is_word_char(token.chars().next_back());
+
+            !(token_starts_with_word && is_word_char(before))
+                && !(token_ends_with_word && is_word_char(after))
+        })
+        .map(|occurrence| TextChange::Insert(occurrence, new_name.to_string()))
+        .collect();
+
+    (!changes.is_empty()).then_some(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::effects::text_change_effect::apply_text_changes;
+
+    use super::*;
+
+    #[test]
+    pub fn test_rename_in_code_block() {
+        let test_cases = [
+            (
+                "-- renames all occurrences in the block --",
+                "```js\nlet x = 1;\nx += x;\n```",
+                "x",
+                "count",
+                Some("```js\nlet count = 1;\ncount += count;\n```"),
+            ),
+            (
+                "-- skips longer identifiers --",
+                "```js\nlet item = items[0];\n```",
+                "item",
+                "first",
+                Some("```js\nlet first = items[0];\n```"),
+            ),
+            (
+                "-- doesn't touch text outside of the block --",
+                "a = 1\n\n```js\nlet a = 2;\n```\n\na",
+                "a",
+                "b",
+                Some("a = 1\n\n```js\nlet b = 2;\n```\n\na"),
+            ),
+            (
+                "-- nothing to rename --",
+                "```js\nlet a = 2;\n```",
+                "z",
+                "b",
+                None,
+            ),
+        ];
+
+        for (desc, input, token, new_name, expected_output) in test_cases {
+            let mut text = input.to_string();
+            let structure = TextStructure::new(&text);
+
+            let block =
+                code_block_content(&structure, ByteSpan::point(text.find("let").unwrap())).unwrap();
+
+            let changes = rename_in_code_block(&text, block, token, new_name);
+
+            match (changes, expected_output) {
+                (None, None) => (),
+                (Some(changes), Some(expected_output)) => {
+                    apply_text_changes(&mut text, None, changes).unwrap();
+                    assert_eq!(text, expected_output, "test case: {}", desc);
+                }
+                (changes, expected_output) => {
+                    assert!(
+                        false,
+                        "unexpected matching, text case:{desc} \nchanges = {changes:#?}\nexpected = {expected_output:#?}",
+                    );
+                }
+            }
+        }
+    }
+}
@@ -2,20 +2,41 @@ use smallvec::SmallVec;
 
 use crate::{
     app_actions::{AppAction, SlashPaletteAction},
-    app_state::SlashPalette,
+    app_state::{AppState, SlashPalette},
+    byte_span::ByteSpan,
+    clipboard_history::clipboard_entry_preview,
     command::{
-        AppFocus, AppFocusState, CommandContext, EditorCommandOutput, TextCommandContext,
+        AppFocus, AppFocusState, CommandContext, CommandInstruction, CommandScope,
+        EditorCommandOutput, ForwardToChild, SlashPaletteCmd, TextCommandContext, TextSource,
         try_extract_text_command_context,
     },
+    commands::insert_note_reference::{NOTE_LINK_PREFIX, note_link_options},
     text_structure::{SpanKind, SpanMeta},
 };
 
-pub fn show_slash_pallete(
+pub fn show_slash_pallete(ctx: CommandContext) -> Option<EditorCommandOutput> {
+    show_palette(ctx, true)
+}
+
+/// Opens the palette at the cursor without typing "/", so the text stays untouched.
+/// Hides the palette if it is already opened
+pub fn toggle_slash_pallete(ctx: CommandContext) -> Option<EditorCommandOutput> {
+    if ctx.app_state.slash_palette.is_some() {
+        return Some(SmallVec::from_buf([AppAction::SlashPalette(
+            SlashPaletteAction::Hide,
+        )]));
+    }
+
+    show_palette(ctx, false)
+}
+
+fn show_palette(
     CommandContext {
         app_state,
         app_focus,
         ..
     }: CommandContext,
+    with_slash: bool,
 ) -> Option<EditorCommandOutput> {
     let is_focused_on_editor = matches!(
         app_focus,
@@ -37,7 +58,7 @@ pub fn show_slash_pallete(
     } = try_extract_text_command_context(app_state)?;
 
     match text_structure.find_surrounding_span_with_meta(SpanKind::CodeBlock, byte_cursor) {
-        Some((_, _, SpanMeta::CodeBlock(_))) => {
+        Some((_, _, SpanMeta::CodeBlock(_))) if with_slash => {
             // do not allow "/" palette in any code blocks
             // not 100% that this is correct but seems to be more intuitive this way
             return None;
@@ -45,6 +66,8 @@ pub fn show_slash_pallete(
         _ => (),
     }
 
+    let (options, most_used_count) = slash_palette_options(app_state, "");
+
     Some(SmallVec::from_iter(
         [
             // TODO it seems that egui will insert "/" regardless
@@ -58,12 +81,10 @@ pub fn show_slash_pallete(
                 // TODO verify + 1 thing, seems sketchy
                 // it relies that this will be done before rendering
                 slash_byte_pos: byte_cursor.start,
+                with_slash,
                 search_term: "".to_string(),
-                options: app_state
-                    .commands
-                    .available_slash_commands()
-                    .cloned()
-                    .collect(),
+                options,
+                most_used_count,
                 selected: 0,
                 update_count: 0,
             })),
@@ -77,3 +98,102 @@ pub fn show_slash_pallete(
         ),
     ))
 }
+
+/// Commands matching the search term, with an empty term the most used commands go first.
+/// Returns the options and how many of them are the most used ones
+pub fn slash_palette_options(
+    app_state: &AppState,
+    search_term: &str,
+) -> (Vec<SlashPaletteCmd>, usize) {
+    // the history would clutter the palette, so it is listed only when searched for
+    let show_clipboard = !search_term.is_empty() && CLIPBOARD_PREFIX.starts_with(search_term);
+    // same for the links to other notes, "insert-" alone would also match "insert-path"
+    let show_note_links =
+        search_term.len() > "insert-".len() && NOTE_LINK_PREFIX.starts_with(search_term);
+
+    let options: Vec<SlashPaletteCmd> = app_state
+        .commands
+        .available_slash_commands()
+        .filter(|option| option.prefix.starts_with(search_term))
+        .cloned()
+        .chain(
+            show_clipboard
+                .then(|| clipboard_options(app_state))
+                .into_iter()
+                .flatten(),
+        )
+        .chain(
+            show_note_links
+                .then(|| note_link_options(app_state))
+                .into_iter()
+                .flatten(),
+        )
+        .collect();
+
+    match search_term.is_empty() {
+        true => app_state.command_usage.promote_most_used(options),
+        false => (options, 0),
+    }
+}
+
+/// Prefix of the slash commands that paste entries of the clipboard history
+pub const CLIPBOARD_PREFIX: &str = "clipboard";
+
+fn clipboard_options(app_state: &AppState) -> impl Iterator<Item = SlashPaletteCmd> {
+    app_state.clipboard_history.entries().map(|entry| {
+        SlashPaletteCmd::from_instruction(
+            CLIPBOARD_PREFIX,
+            CommandInstruction::InsertText(ForwardToChild(TextSource::Str(entry.to_string()))),
+            CommandScope::Focus(AppFocus::NoteEditor),
+        )
+        .icon(egui_phosphor::light::CLIPBOARD_TEXT.to_string())
+        .description(clipboard_entry_preview(entry))
+    })
+}
+
+/// Span of the "/" together with the search term typed after it,
+/// `None` if the text around the palette was changed since the last update
+pub fn palette_term_span(text: &str, palette: &SlashPalette) -> Option<ByteSpan> {
+    let slash = if palette.with_slash { "/" } else { "" };
+    let term = format!("{slash}{}", palette.search_term);
+
+    text.get(palette.slash_byte_pos..)?
+        .starts_with(&term)
+        .then(|| ByteSpan::new(palette.slash_byte_pos, palette.slash_byte_pos + term.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::persistent_state::NoteFile;
+
+    use super::*;
+
+    #[test]
+    pub fn test_palette_term_span() {
+        let palette = |slash_byte_pos, with_slash, search_term: &str| SlashPalette {
+            note_file: NoteFile::Note(0),
+            slash_byte_pos,
+            with_slash,
+            search_term: search_term.to_string(),
+            options: vec![],
+            most_used_count: 0,
+            selected: 0,
+            update_count: 0,
+        };
+
+        assert_eq!(
+            palette_term_span("a /ta b", &palette(2, true, "ta")),
+            Some(ByteSpan::new(2, 5))
+        );
+        assert_eq!(
+            palette_term_span("a ta b", &palette(2, false, "ta")),
+            Some(ByteSpan::new(2, 4))
+        );
+        assert_eq!(
+            palette_term_span("a /tb", &palette(2, true, "ta")),
+            None,
+            "text was changed"
+        );
+        assert_eq!(palette_term_span("a", &palette(2, true, "")), None);
+    }
+}
@@ -0,0 +1,212 @@
+use eframe::egui::{Event, InputState};
+use itertools::Itertools;
+
+use crate::{
+    byte_span::ByteSpan, command::TextCommandContext, effects::text_change_effect::TextChange,
+    note_lang::NoteLang, settings_parsing::EditorSettings, text_structure::SpanKind,
+};
+
+/// Input time typography, `typed` is the char that was just inserted right before the cursor.
+/// Code blocks, inline code and the front matter are left as is.
+/// Quotes and capitalization follow the language of the note, see `NoteLang`
+pub fn on_typed_char(
+    context: TextCommandContext,
+    typed: char,
+    settings: &EditorSettings,
+) -> Option<Vec<TextChange>> {
+    let TextCommandContext {
+        text_structure: structure,
+        text,
+        byte_cursor: cursor,
+    } = context;
+
+    if !cursor.is_empty() || !text.get(..cursor.start)?.ends_with(typed) {
+        return None;
+    }
+
+    let typed_pos = cursor.start - typed.len_utf8();
+    let before = &text[..typed_pos];
+    let line_before = &before[before.rfind('\n').map_or(0, |i| i + 1)..];
+
+    let is_inside_code = [
+        SpanKind::CodeBlock,
+        SpanKind::InlineCode,
+        SpanKind::FrontMatter,
+    ]
+    .into_iter()
+    .any(|kind| {
+        structure
+            .find_span_at(kind, ByteSpan::point(typed_pos))
+            .is_some()
+    });
+
+    // inline code that is still being typed is not parsed as such yet
+    let is_unclosed_inline_code = line_before.matches('`').count() % 2 == 1;
+
+    if is_inside_code || is_unclosed_inline_code {
+        return None;
+    }
+
+    // replaces `replaced_before` bytes before the typed char + the char itself
+    let replace = |replaced_before: usize, with: &str| {
+        Some(vec![TextChange::Insert(
+            ByteSpan::new(typed_pos - replaced_before, cursor.start),
+            format!("{with}{}", TextChange::CURSOR),
+        )])
+    };
+
+    match typed {
+        // "--" at the start of the line is likely a rule, the front matter or a table
+        '-' if settings.smart_dashes
+            && before.ends_with('-')
+            && !before.ends_with("--")
+            && !line_before[..line_before.len() - 1].trim().is_empty() =>
+        {
+            replace(1, "—")
+        }
+
+        '.' if settings.smart_ellipsis && before.ends_with("..") && !before.ends_with("...") => {
+            replace(2, "…")
+        }
+
+        '"' | '\'' if settings.smart_quotes => {
+            let is_opening = before
+                .chars()
+                .next_back()
+                .is_none_or(|prev| prev.is_whitespace() || "([{—-".contains(prev));
+
+            let (open_double, close_double) =
+                NoteLang::of_note(text, structure, settings.lang.as_deref()).double_quotes();
+
+            let quote = match (typed, is_opening) {
+                ('"', true) => open_double,
+                ('"', false) => close_double,
+                (_, true) => "‘",
+                // closing single quote is also an apostrophe
+                (_, false) => "’",
+            };
+
+            replace(0, quote)
+        }
+
+        ch if settings.auto_capitalize && ch.is_lowercase() && is_sentence_start(line_before) => {
+            let lang = NoteLang::of_note(text, structure, settings.lang.as_deref());
+            replace(0, &lang.to_uppercase(ch))
+        }
+
+        _ => None,
+    }
+}
+
+/// The char typed during the frame, pastes and multi char (e.g. IME) input don't count
+pub fn single_typed_char(input: &InputState) -> Option<char> {
+    let event = input
+        .events
+        .iter()
+        .filter(|event| matches!(event, Event::Text(_) | Event::Paste(_)))
+        .exactly_one()
+        .ok()?;
+
+    let Event::Text(text) = event else {
+        return None;
+    };
+
+    text.chars().exactly_one().ok()
+}
+
+/// After ".", "!", "?" or at the start of a line (following heading, quote, list or task markers)
+fn is_sentence_start(line_before: &str) -> bool {
+    if !line_before.is_empty() && !line_before.ends_with(char::is_whitespace) {
+        return false;
+    }
+
+    let is_block_prefix = line_before.split_whitespace().all(|token| {
+        matches!(token, "[" | "]" | "[x]" | "[X]")
+            || token
+                .chars()
+                .all(|c| matches!(c, '#' | '>' | '-' | '*' | '+'))
+            || token
+                .strip_suffix(['.', ')'])
+                .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+    });
+
+    is_block_prefix || line_before.trim_end().ends_with(['.', '!', '?', '…'])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{effects::text_change_effect::apply_text_changes, text_structure::TextStructure};
+
+    use super::*;
+
+    #[test]
+    pub fn test_smart_typography() {
+        let settings = EditorSettings {
+            live_preview: false,
+            auto_capitalize: true,
+            smart_dashes: true,
+            smart_ellipsis: true,
+            smart_quotes: true,
+            checkbox_tasks: false,
+            wrap_code_blocks: true,
+            code_auto_indent: true,
+            normalize_pasted_lists: true,
+            collapse_blank_lines: false,
+            scroll_past_end: false,
+            auto_links: true,
+            inline_suggestions: false,
+            large_note_kb: 1024,
+            timestamp_format: None,
+            lang: None,
+            tree_sitter_langs: vec![],
+            external_editor: None,
+        };
+
+        let test_cases = [
+            ("a --{||}", Some("a —{||}")),
+            ("---{||}", None),
+            ("--{||}", None),
+            ("wait...{||}", Some("wait…{||}")),
+            ("\"{||}", Some("“{||}")),
+            ("say \"hi\"{||}", Some("say \"hi”{||}")),
+            ("it'{||}", Some("it’{||}")),
+            ("h{||}", Some("H{||}")),
+            ("- [ ] t{||}", Some("- [ ] T{||}")),
+            ("1. o{||}", Some("1. O{||}")),
+            ("Done. n{||}", Some("Done. N{||}")),
+            ("mid s{||}", None),
+            ("a\n```\nx --{||}\n```", None),
+            ("`a --{||}", None),
+            ("`x` a --{||}", Some("`x` a —{||}")),
+            (
+                "---\nlang: fr\n---\n\"{||}",
+                Some("---\nlang: fr\n---\n«{||}"),
+            ),
+            (
+                "---\nlang: tr\n---\ni{||}",
+                Some("---\nlang: tr\n---\nİ{||}"),
+            ),
+        ];
+
+        for (input, output) in test_cases {
+            let (mut text, cursor) = TextChange::try_extract_cursor(input.to_string());
+            let cursor = cursor.unwrap();
+            let typed = text[..cursor.start].chars().next_back().unwrap();
+
+            let structure = TextStructure::new(&text);
+            let changes = on_typed_char(
+                TextCommandContext::new(&structure, &text, cursor),
+                typed,
+                &settings,
+            );
+
+            let result = changes.map(|changes| {
+                let cursor =
+                    apply_text_changes(&mut text, Some(cursor.unordered()), changes).unwrap();
+                TextChange::encode_cursor(&text, cursor.unwrap())
+            });
+
+            assert_eq!(result.as_deref(), output, "input: {input:?}");
+        }
+    }
+}
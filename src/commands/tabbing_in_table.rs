@@ -0,0 +1,267 @@
+use smallvec::SmallVec;
+
+use crate::{
+    byte_span::ByteSpan,
+    command::TextCommandContext,
+    effects::text_change_effect::TextChange,
+    text_structure::{SpanKind, TextStructure},
+};
+
+use super::insert_table::table_row;
+
+/// (cell span between the pipes, trimmed cell content)
+type TableCell = (ByteSpan, ByteSpan);
+
+pub fn on_tab_inside_table(context: TextCommandContext) -> Option<Vec<TextChange>> {
+    let TextCommandContext {
+        text_structure: structure,
+        text,
+        byte_cursor: cursor,
+    } = context;
+
+    let rows = find_table_rows(structure, cursor)?;
+    let cells: SmallVec<[TableCell; 16]> = rows
+        .iter()
+        .flat_map(|row| table_row_cells(text, *row))
+        .collect();
+
+    let current = find_cell_under_cursor(&cells, cursor)?;
+
+    match cells.get(current + 1) {
+        Some((_, content)) => Some(vec![select_cell_content(text, *content)]),
+        None => {
+            // we are at the last cell => add a new row
+            let last_row = rows.last()?;
+            let cols = table_row_cells(text, *rows.first()?).len();
+
+            Some(vec![TextChange::Insert(
+                ByteSpan::point(last_row.end),
+                format!("\n{}", table_row(cols, TextChange::CURSOR)),
+            )])
+        }
+    }
+}
+
+pub fn on_shift_tab_inside_table(context: TextCommandContext) -> Option<Vec<TextChange>> {
+    let TextCommandContext {
+        text_structure: structure,
+        text,
+        byte_cursor: cursor,
+    } = context;
+
+    let rows = find_table_rows(structure, cursor)?;
+    let cells: SmallVec<[TableCell; 16]> = rows
+        .iter()
+        .flat_map(|row| table_row_cells(text, *row))
+        .collect();
+
+    let current = find_cell_under_cursor(&cells, cursor)?;
+
+    let (_, content) = cells.get(current.checked_sub(1)?)?;
+    Some(vec![select_cell_content(text, *content)])
+}
+
+/// Line spans of the header and the body rows, note that the delimiter row is not included
+fn find_table_rows(structure: &TextStructure, cursor: ByteSpan) -> Option<SmallVec<[ByteSpan; 8]>> {
+    let (line_loc, _, _) = structure.find_line_location(cursor)?;
+
+    let (_, table_index, _) =
+        structure.find_span_on_the_line(SpanKind::Table, line_loc.line_start)?;
+
+    let rows: SmallVec<[ByteSpan; 8]> = structure
+        .iterate_immediate_children_of(table_index)
+        .filter(|(_, desc)| matches!(desc.kind, SpanKind::TableHead | SpanKind::TableRow))
+        .filter_map(|(_, desc)| {
+            structure
+                .find_line_location(desc.byte_pos)
+                .map(|(_, line_span, _)| line_span)
+        })
+        .collect();
+
+    (!rows.is_empty()).then_some(rows)
+}
+
+fn find_cell_under_cursor(cells: &[TableCell], cursor: ByteSpan) -> Option<usize> {
+    cells
+        .iter()
+        .position(|(cell, _)| cell.start <= cursor.start && cursor.end <= cell.end)
+}
+
+fn table_row_cells(text: &str, line: ByteSpan) -> SmallVec<[TableCell; 8]> {
+    let line_text = &text[line.range()];
+    let bytes = line_text.as_bytes();
+
+    // escaped pipes "\|" are part of the cell content
+    let pipes = (0..bytes.len()).filter(|&i| bytes[i] == b'|' && (i == 0 || bytes[i - 1] != b'\\'));
+
+    let mut segments: SmallVec<[ByteSpan; 8]> = SmallVec::new();
+    let mut segment_start = 0;
+    for pipe in pipes.chain([bytes.len()]) {
+        segments.push(ByteSpan::new(segment_start, pipe));
+        segment_start = pipe + 1;
+    }
+
+    // leading and trailing pipes are optional
+    let is_blank = |segment: &ByteSpan| line_text[segment.range()].trim().is_empty();
+    if segments.len() > 1 {
+        if segments.first().is_some_and(is_blank) {
+            segments.remove(0);
+        }
+        if segments.last().is_some_and(is_blank) {
+            segments.pop();
+        }
+    }
+
+    segments
+        .into_iter()
+        .map(|segment| {
+            let segment_text = &line_text[segment.range()];
+            let leading_spaces = segment_text.len() - segment_text.trim_start().len();
+            let content_len = segment_text.trim().len();
+
+            let content = match content_len {
+                // place the cursor after the padding, e.g. "| {||} |"
+                0 => ByteSpan::point(segment.start + segment_text.len().min(1)),
+                _ => ByteSpan::new(
+                    segment.start + leading_spaces,
+                    segment.start + leading_spaces + content_len,
+                ),
+            };
+
+            (
+                segment.move_by(line.start as isize),
+                content.move_by(line.start as isize),
+            )
+        })
+        .collect()
+}
+
+fn select_cell_content(text: &str, content: ByteSpan) -> TextChange {
+    match content.is_empty() {
+        true => TextChange::Insert(content, TextChange::CURSOR.to_string()),
+        false => TextChange::Insert(
+            content,
+            format!(
+                "{edge}{}{edge}",
+                &text[content.range()],
+                edge = TextChange::CURSOR_EDGE
+            ),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{effects::text_change_effect::apply_text_changes, text_structure::TextStructure};
+
+    use super::*;
+
+    #[test]
+    pub fn test_tab_inside_table() {
+        let test_cases = [
+            (
+                "-- moves to the next cell in the same row --",
+                "| a{||} | b |\n| --- | --- |\n| c | d |",
+                Some("| a | {|}b{|} |\n| --- | --- |\n| c | d |"),
+            ),
+            (
+                "-- skips the delimiter row --",
+                "| a | b{||} |\n| --- | --- |\n| c | d |",
+                Some("| a | b |\n| --- | --- |\n| {|}c{|} | d |"),
+            ),
+            (
+                "-- places the cursor inside an empty cell --",
+                "| a{||} |  |\n| --- | --- |",
+                Some("| a | {||} |\n| --- | --- |"),
+            ),
+            (
+                "-- adds a new row after the last cell --",
+                "| a | b |\n| --- | --- |\n| c | d{||} |",
+                Some("| a | b |\n| --- | --- |\n| c | d |\n| {||} |  |"),
+            ),
+            (
+                "-- does nothing outside of tables --",
+                "some text{||}",
+                None,
+            ),
+        ];
+
+        for (desc, input, expected_output) in test_cases {
+            let (mut text, cursor) = TextChange::try_extract_cursor(input.to_string());
+            let cursor = cursor.unwrap();
+
+            let changes = on_tab_inside_table(TextCommandContext::new(
+                &TextStructure::new(&text),
+                &text,
+                cursor.clone(),
+            ));
+
+            match (changes, expected_output) {
+                (None, None) => (),
+                (Some(changes), Some(expected_output)) => {
+                    let cursor =
+                        apply_text_changes(&mut text, Some(cursor.unordered()), changes).unwrap();
+                    assert_eq!(
+                        TextChange::encode_cursor(&text, cursor.unwrap()),
+                        expected_output,
+                        "test case: {}",
+                        desc
+                    );
+                }
+                (changes, expected_output) => {
+                    assert!(
+                        false,
+                        "unexpected matching, text case:{desc} \nchanges = {changes:#?}\nexpected = {expected_output:#?}",
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_shift_tab_inside_table() {
+        let test_cases = [
+            (
+                "-- moves to the previous cell across rows --",
+                "| a | b |\n| --- | --- |\n| {||}c | d |",
+                Some("| a | {|}b{|} |\n| --- | --- |\n| c | d |"),
+            ),
+            (
+                "-- bails out in the first cell --",
+                "| a{||} | b |\n| --- | --- |",
+                None,
+            ),
+        ];
+
+        for (desc, input, expected_output) in test_cases {
+            let (mut text, cursor) = TextChange::try_extract_cursor(input.to_string());
+            let cursor = cursor.unwrap();
+
+            let changes = on_shift_tab_inside_table(TextCommandContext::new(
+                &TextStructure::new(&text),
+                &text,
+                cursor.clone(),
+            ));
+
+            match (changes, expected_output) {
+                (None, None) => (),
+                (Some(changes), Some(expected_output)) => {
+                    let cursor =
+                        apply_text_changes(&mut text, Some(cursor.unordered()), changes).unwrap();
+                    assert_eq!(
+                        TextChange::encode_cursor(&text, cursor.unwrap()),
+                        expected_output,
+                        "test case: {}",
+                        desc
+                    );
+                }
+                (changes, expected_output) => {
+                    assert!(
+                        false,
+                        "unexpected matching, text case:{desc} \nchanges = {changes:#?}\nexpected = {expected_output:#?}",
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{
+    byte_span::ByteSpan,
+    command::TextCommandContext,
+    effects::text_change_effect::TextChange,
+    text_structure::{CalloutKind, SpanKind},
+};
+
+use super::enter_in_code_block::is_inside_code;
+
+/// Wraps the lines of the cursor into a `> [!NOTE]` callout.
+/// Inside a callout of the same kind it is unwrapped, of a different kind the kind is changed
+pub fn toggle_callout(
+    TextCommandContext {
+        text_structure,
+        text,
+        byte_cursor,
+    }: TextCommandContext,
+    kind: CalloutKind,
+) -> Option<Vec<TextChange>> {
+    if is_inside_code(text_structure, byte_cursor) {
+        return None;
+    }
+
+    // the innermost one, nested spans come after their parents
+    let callout = text_structure
+        .iter()
+        .filter_map(|(_, desc)| match desc.kind {
+            SpanKind::Callout(kind) if desc.byte_pos.contains(byte_cursor) => {
+                Some((desc.byte_pos, kind))
+            }
+            _ => None,
+        })
+        .last();
+
+    match callout {
+        Some((span, current_kind)) if current_kind != kind => {
+            let marker_start = span.start + text[span.range()].find(current_kind.marker())?;
+
+            Some(vec![TextChange::Insert(
+                ByteSpan::new(marker_start, marker_start + current_kind.marker().len()),
+                kind.marker().to_string(),
+            )])
+        }
+        Some((span, _)) => Some(unwrap_callout(text, span)),
+        None => {
+            let (_, first_line, last_line) = text_structure.find_line_location(byte_cursor)?;
+            let lines = ByteSpan::new(first_line.start, last_line.end);
+
+            if text[lines.range()].trim().is_empty() {
+                return Some(vec![TextChange::Insert(
+                    lines,
+                    format!("> {}\n> {}", kind.marker(), TextChange::CURSOR),
+                )]);
+            }
+
+            let mut changes = vec![];
+            let mut line_start = lines.start;
+
+            for line in text[lines.range()].split('\n') {
+                let prefix = match (line_start == lines.start, line.is_empty()) {
+                    (true, _) => format!("> {}\n> ", kind.marker()),
+                    (false, true) => ">".to_string(),
+                    (false, false) => "> ".to_string(),
+                };
+
+                changes.push(TextChange::Insert(ByteSpan::point(line_start), prefix));
+                line_start += line.len() + 1;
+            }
+
+            Some(changes)
+        }
+    }
+}
+
+/// Removes the `> [!NOTE]` line and the `>` markers of the rest of the lines
+fn unwrap_callout(text: &str, span: ByteSpan) -> Vec<TextChange> {
+    let line_end_after = |pos: usize| text[pos..].find('\n').map_or(text.len(), |i| pos + i);
+
+    // nested callouts are indented, the indentation of the marker line goes away as well
+    let marker_line_start = match text[..span.start].rfind('\n').map_or(0, |i| i + 1) {
+        line_start if text[line_start..span.start].trim().is_empty() => line_start,
+        _ => span.start,
+    };
+    let marker_line_end = line_end_after(span.start);
+
+    let mut changes = vec![TextChange::Insert(
+        ByteSpan::new(marker_line_start, (marker_line_end + 1).min(text.len())),
+        "".to_string(),
+    )];
+
+    let mut line_start = marker_line_end + 1;
+    while line_start < span.end {
+        let line = &text[line_start..line_end_after(line_start)];
+        let quote_start = line_start + line.len() - line.trim_start_matches([' ', '\t']).len();
+
+        let quote_len = match &text[quote_start..] {
+            rest if rest.starts_with("> ") => 2,
+            rest if rest.starts_with('>') => 1,
+            // lazy continuation lines don't have the marker
+            _ => 0,
+        };
+
+        if quote_len > 0 {
+            changes.push(TextChange::Insert(
+                ByteSpan::new(quote_start, quote_start + quote_len),
+                "".to_string(),
+            ));
+        }
+
+        line_start += line.len() + 1;
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{effects::text_change_effect::apply_text_changes, text_structure::TextStructure};
+
+    use super::*;
+
+    #[test]
+    pub fn test_toggle_callout() {
+        use CalloutKind::*;
+
+        let test_cases = [
+            (
+                "## wraps the line of the cursor ##",
+                "fi{||}rst line",
+                Note,
+                Some("> [!NOTE]\n> fi{||}rst line"),
+            ),
+            (
+                "## wraps the lines of the selection ##",
+                "before\n\nfi{|}rst\n\nsec{|}ond\n\nafter",
+                Warning,
+                Some("before\n\n> [!WARNING]\n> fi{|}rst\n>\n> sec{|}ond\n\nafter"),
+            ),
+            (
+                "## empty line ##",
+                "a\n\n{||}",
+                Tip,
+                Some("a\n\n> [!TIP]\n> {||}"),
+            ),
+            (
+                "## the same kind unwraps the callout ##",
+                "> [!NOTE]\n> fi{||}rst\n> second\n\nafter",
+                Note,
+                Some("fi{||}rst\nsecond\n\nafter"),
+            ),
+            (
+                "## a different kind changes the marker ##",
+                "> [!NOTE]\n> te{||}xt",
+                Caution,
+                Some("> [!CAUTION]\n> te{||}xt"),
+            ),
+            (
+                "## nested in a list item ##",
+                "- item\n  > [!TIP]\n  > ne{||}sted",
+                Tip,
+                Some("- item\n  ne{||}sted"),
+            ),
+            (
+                "## doesn't do anything inside code blocks ##",
+                "```\nco{||}de\n```",
+                Note,
+                None,
+            ),
+        ];
+
+        for (desc, input, kind, expected) in test_cases {
+            let (mut text, cursor) = TextChange::try_extract_cursor(input.to_string());
+            let cursor = cursor.unwrap();
+
+            let structure = TextStructure::new(&text);
+
+            let res = toggle_callout(TextCommandContext::new(&structure, &text, cursor), kind).map(
+                |changes| {
+                    let cursor =
+                        apply_text_changes(&mut text, Some(cursor.unordered()), changes).unwrap();
+                    TextChange::encode_cursor(&text, cursor.unwrap())
+                },
+            );
+
+            assert_eq!(res.as_deref(), expected, "test case: {desc}");
+        }
+    }
+}
@@ -95,12 +95,163 @@ pub fn toggle_md_heading(
         })
 }
 
+pub enum HeadingLevelChange {
+    Increase,
+    Decrease,
+}
+
+/// Moves the current line through paragraph -> H1 -> ... -> H6 -> paragraph (or backwards)
+pub fn change_md_heading_level(
+    TextCommandContext {
+        text_structure,
+        text,
+        byte_cursor,
+    }: TextCommandContext,
+    change: HeadingLevelChange,
+) -> Option<Vec<TextChange>> {
+    let (line_loc, start_line_span, _) = text_structure.find_line_location(byte_cursor)?;
+
+    let is_paragraph = start_line_span.is_empty()
+        || text_structure
+            .find_span_on_the_line(SpanKind::Paragraph, line_loc.line_start)
+            .is_some();
+
+    let heading =
+        text_structure.find_map_span_on_the_line(line_loc.line_start, |desc| match desc.kind {
+            SpanKind::Heading(level) => Some(level),
+            _ => None,
+        });
+
+    let current = match (is_paragraph, heading) {
+        (true, _) => None,
+        (false, Some((span, _index, level)))
+            if text[span.range()].starts_with(heading_level_to_annotation(level)) =>
+        {
+            Some((span, level))
+        }
+        // setext headings or other blocks
+        _ => return None,
+    };
+
+    // 0 stands for a paragraph
+    let depth = current.map_or(0, |(_, level)| level as usize);
+    let depth = match change {
+        HeadingLevelChange::Increase => (depth + 1) % 7,
+        HeadingLevelChange::Decrease => (depth + 6) % 7,
+    };
+    let next_level = HeadingLevel::try_from(depth).ok();
+
+    let change = match (current, next_level) {
+        (None, Some(next_level)) => TextChange::Insert(
+            ByteSpan::point(start_line_span.start),
+            format!("{} ", heading_level_to_annotation(next_level)),
+        ),
+        (Some((span, level)), Some(next_level)) => TextChange::Insert(
+            ByteSpan::new(
+                span.start,
+                span.start + heading_level_to_annotation(level).len(),
+            ),
+            heading_level_to_annotation(next_level).to_string(),
+        ),
+        (Some((span, level)), None) => {
+            // removes the annotation together with the spaces after it
+            let heading = &text[span.range()];
+            let annotation_len = heading_level_to_annotation(level).len();
+            let content = heading[annotation_len..].trim_start_matches([' ', '\t']);
+
+            TextChange::Insert(
+                ByteSpan::new(span.start, span.start + heading.len() - content.len()),
+                "".to_string(),
+            )
+        }
+        (None, None) => return None,
+    };
+
+    Some(Vec::from([change]))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{effects::text_change_effect::apply_text_changes, text_structure::TextStructure};
 
     use super::*;
 
+    #[test]
+    pub fn test_change_md_heading_level() {
+        use HeadingLevelChange::*;
+
+        let test_cases = [
+            (
+                "## paragraph becomes H1 ##",
+                "some {||}text",
+                Some("# some {||}text"),
+                Increase,
+            ),
+            (
+                "## heading level goes up ##",
+                "first\n\n## **bold{||}** heading",
+                Some("first\n\n### **bold{||}** heading"),
+                Increase,
+            ),
+            (
+                "## H6 goes back to paragraph ##",
+                "###### hea{||}ding",
+                Some("hea{||}ding"),
+                Increase,
+            ),
+            (
+                "## H1 goes back to paragraph ##",
+                "#  {|}hea{|}ding",
+                Some("{|}hea{|}ding"),
+                Decrease,
+            ),
+            (
+                "## heading level goes down ##",
+                "### heading{||}",
+                Some("## heading{||}"),
+                Decrease,
+            ),
+            (
+                "## paragraph becomes H6 ##",
+                "text{||}",
+                Some("###### text{||}"),
+                Decrease,
+            ),
+            (
+                "## empty line ##",
+                "# a\n\n{||}",
+                Some("# a\n\n# {||}"),
+                Increase,
+            ),
+            ("## empty heading ##", "## {||}", Some("# {||}"), Decrease),
+            (
+                "## doesn't do anything inside code blocks ##",
+                "```js\nlog({||})\n```\n",
+                None,
+                Increase,
+            ),
+        ];
+
+        for (desc, input, expected_output, change) in test_cases {
+            let (mut text, cursor) = TextChange::try_extract_cursor(input.to_string());
+            let cursor = cursor.unwrap();
+
+            let structure = TextStructure::new(&text);
+
+            let res = change_md_heading_level(
+                TextCommandContext::new(&structure, &text, cursor.clone()),
+                change,
+            )
+            .map(|changes| {
+                let cursor =
+                    apply_text_changes(&mut text, Some(cursor.unordered()), changes).unwrap();
+                TextChange::encode_cursor(&text, cursor.unwrap())
+            });
+
+            assert_eq!(res.as_deref(), expected_output, "test case: {desc}");
+        }
+    }
+
     #[test]
     pub fn tests_for_toggle_md_headings() {
         let test_cases = [
@@ -86,4 +86,65 @@ mod tests {
             );
         }
     }
+
+    fn toggle(input: &str, target_span: SpanKind, annotation: &str) -> String {
+        let (mut text, cursor) = TextChange::try_extract_cursor(input.to_string());
+        let cursor = cursor.unwrap();
+
+        let structure = TextStructure::new(&text);
+
+        let changes = toggle_simple_md_annotations(
+            TextCommandContext::new(&structure, &text, cursor),
+            target_span,
+            annotation,
+        )
+        .unwrap();
+
+        let cursor = apply_text_changes(&mut text, Some(cursor.unordered()), changes).unwrap();
+        TextChange::encode_cursor(&text, cursor.unwrap())
+    }
+
+    #[test]
+    pub fn test_toggling_bold_italic_and_strike() {
+        for (target_span, a) in [
+            (SpanKind::Bold, "**"),
+            (SpanKind::Emphasis, "*"),
+            (SpanKind::Strike, "~~"),
+        ] {
+            let test_cases = [
+                (
+                    "## empty selection puts the cursor between the markers ##",
+                    "make {||}loud".to_string(),
+                    format!("make {a}{{||}}{a}loud"),
+                ),
+                (
+                    "## wraps the selection and keeps it over the inner text ##",
+                    "make {|}this{|} loud".to_string(),
+                    format!("make {a}{{|}}this{{|}}{a} loud"),
+                ),
+                (
+                    "## unwraps and selects the inner text ##",
+                    format!("make {a}{{|}}this{{|}}{a} loud"),
+                    "make {|}this{|} loud".to_string(),
+                ),
+                (
+                    "## unwraps with the cursor inside ##",
+                    format!("make {a}th{{||}}is{a} loud"),
+                    "make {|}this{|} loud".to_string(),
+                ),
+            ];
+
+            for (desc, input, expected) in test_cases {
+                assert_eq!(
+                    toggle(&input, target_span, a),
+                    expected,
+                    "test case: {desc} for {a}"
+                );
+            }
+
+            // toggling twice leaves the text as it was
+            let wrapped = toggle("{|}twice{|}", target_span, a);
+            assert_eq!(toggle(&wrapped, target_span, a), "{|}twice{|}");
+        }
+    }
 }
@@ -0,0 +1,87 @@
+use crate::{
+    byte_span::ByteSpan, command::TextCommandContext, effects::text_change_effect::TextChange,
+};
+
+/// Swaps the chars around the cursor and moves it past them, as Ctrl+T in Emacs and readline.
+/// At the end of a line the two chars before the cursor are swapped instead
+pub fn transpose_chars(
+    TextCommandContext {
+        text, byte_cursor, ..
+    }: TextCommandContext,
+) -> Option<Vec<TextChange>> {
+    if !byte_cursor.is_empty() {
+        return None;
+    }
+
+    let cursor = byte_cursor.start;
+    let (before, after) = text.split_at(cursor);
+
+    // chars are taken only from the line of the cursor
+    let mut before_chars = before.chars().rev().take_while(|ch| *ch != '\n');
+    let next = after.chars().next().filter(|ch| *ch != '\n');
+
+    let (first, second, start) = match next {
+        Some(next) => {
+            let prev = before_chars.next()?;
+            (prev, next, cursor - prev.len_utf8())
+        }
+        None => {
+            let last = before_chars.next()?;
+            let prev = before_chars.next()?;
+            (prev, last, cursor - last.len_utf8() - prev.len_utf8())
+        }
+    };
+
+    let end = start + first.len_utf8() + second.len_utf8();
+
+    Some(vec![TextChange::Insert(
+        ByteSpan::new(start, end),
+        format!("{second}{first}{}", TextChange::CURSOR),
+    )])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{effects::text_change_effect::apply_text_changes, text_structure::TextStructure};
+
+    use super::*;
+
+    #[test]
+    pub fn test_transpose_chars() {
+        let test_cases = [
+            (
+                "## swaps chars around the cursor ##",
+                "teh{||}s",
+                Some("tesh{||}"),
+            ),
+            ("## in the middle ##", "a{||}bc", Some("ba{||}c")),
+            (
+                "## end of the line ##",
+                "teh{||}\nnext",
+                Some("the{||}\nnext"),
+            ),
+            ("## end of the text ##", "ab{||}", Some("ba{||}")),
+            ("## multibyte chars ##", "ñ{||}é", Some("éñ{||}")),
+            ("## start of the text ##", "{||}ab", None),
+            ("## start of the line ##", "a\n{||}bc", None),
+            ("## single char on the line ##", "a\nb{||}", None),
+            ("## selection ##", "{|}ab{|}", None),
+        ];
+
+        for (desc, input, expected) in test_cases {
+            let (mut text, cursor) = TextChange::try_extract_cursor(input.to_string());
+            let cursor = cursor.unwrap();
+
+            let structure = TextStructure::new(&text);
+            let changes = transpose_chars(TextCommandContext::new(&structure, &text, cursor));
+
+            let res = changes.map(|changes| {
+                let cursor =
+                    apply_text_changes(&mut text, Some(cursor.unordered()), changes).unwrap();
+                TextChange::encode_cursor(&text, cursor.unwrap())
+            });
+
+            assert_eq!(res.as_deref(), expected, "test case: {desc}");
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{
+    byte_span::ByteSpan, command::TextCommandContext, effects::text_change_effect::TextChange,
+    text_structure::toggle_details_open_attribute,
+};
+
+/// Wraps the lines of the selection into a collapsible `<details>` html block,
+/// the cursor is placed into the empty summary
+pub fn wrap_in_details(
+    TextCommandContext {
+        text_structure,
+        text,
+        byte_cursor,
+    }: TextCommandContext,
+) -> Option<Vec<TextChange>> {
+    let (_, first_line, last_line) = text_structure.find_line_location(byte_cursor)?;
+    let lines = ByteSpan::new(first_line.start, last_line.end);
+
+    // blank lines around the content, so it is parsed as markdown and not as a part of the html block
+    let content = match text[lines.range()].trim().is_empty() {
+        true => String::new(),
+        false => format!("{}\n\n", &text[lines.range()]),
+    };
+
+    Some(vec![TextChange::Insert(
+        lines,
+        format!(
+            "<details>\n<summary>{}</summary>\n\n{content}</details>",
+            TextChange::CURSOR
+        ),
+    )])
+}
+
+/// Collapses (or expands) every `<details>` block of the note by rewriting their `open` attribute
+pub fn set_all_details_open(
+    TextCommandContext {
+        text_structure,
+        text,
+        ..
+    }: TextCommandContext,
+    open: bool,
+) -> Option<Vec<TextChange>> {
+    let changes: Vec<TextChange> = text_structure
+        .details_blocks()
+        .iter()
+        .filter(|details| details.is_open != open)
+        .map(|details| {
+            TextChange::Insert(
+                details.open_tag,
+                toggle_details_open_attribute(&text[details.open_tag.range()]),
+            )
+        })
+        .collect();
+
+    (!changes.is_empty()).then_some(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{effects::text_change_effect::apply_text_changes, text_structure::TextStructure};
+
+    use super::*;
+
+    #[test]
+    pub fn test_wrap_in_details() {
+        let test_cases = [
+            (
+                "## wraps the lines of the selection ##",
+                "before\nfi{|}rst\nsec{|}ond\nafter",
+                "before\n<details>\n<summary>{||}</summary>\n\nfirst\nsecond\n\n</details>\nafter",
+            ),
+            (
+                "## wraps the line of the cursor ##",
+                "- a{||}",
+                "<details>\n<summary>{||}</summary>\n\n- a\n\n</details>",
+            ),
+            (
+                "## empty line ##",
+                "a\n{||}\nb",
+                "a\n<details>\n<summary>{||}</summary>\n\n</details>\nb",
+            ),
+        ];
+
+        for (desc, input, output) in test_cases {
+            let (mut text, cursor) = TextChange::try_extract_cursor(input.to_string());
+            let cursor = cursor.unwrap();
+
+            let structure = TextStructure::new(&text);
+            let changes =
+                wrap_in_details(TextCommandContext::new(&structure, &text, cursor)).unwrap();
+
+            let cursor = apply_text_changes(&mut text, Some(cursor.unordered()), changes).unwrap();
+            assert_eq!(
+                TextChange::encode_cursor(&text, cursor.unwrap()),
+                output,
+                "test case: {desc}"
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_set_all_details_open() {
+        let collapsed = "<details>\n<summary>A</summary>\n\n- a\n\n</details>\n\n";
+        let expanded = "<details open>\n<summary>A</summary>\n\n- a\n\n</details>\n\n";
+
+        let test_cases = [
+            (
+                "## collapses the open blocks ##",
+                format!("{expanded}{collapsed}{expanded}text{{||}}"),
+                false,
+                Some(format!("{collapsed}{collapsed}{collapsed}text{{||}}")),
+            ),
+            (
+                "## expands the collapsed blocks ##",
+                format!("{collapsed}<details class=\"x\">\n\nb\n\n</details>\n\n{{||}}"),
+                true,
+                Some(format!(
+                    "{expanded}<details class=\"x\" open>\n\nb\n\n</details>\n\n{{||}}"
+                )),
+            ),
+            (
+                "## nothing to change ##",
+                format!("{collapsed}text{{||}}"),
+                false,
+                None,
+            ),
+        ];
+
+        for (desc, input, open, expected) in test_cases {
+            let (mut text, cursor) = TextChange::try_extract_cursor(input);
+            let cursor = cursor.unwrap();
+
+            let structure = TextStructure::new(&text);
+
+            let res = set_all_details_open(
+                TextCommandContext::new(&structure, &text, cursor),
+                open,
+            )
+            .map(|changes| {
+                let cursor =
+                    apply_text_changes(&mut text, Some(cursor.unordered()), changes).unwrap();
+                TextChange::encode_cursor(&text, cursor.unwrap())
+            });
+
+            assert_eq!(res, expected, "test case: {desc}");
+        }
+    }
+}
@@ -177,14 +177,23 @@ impl<'a> Feedback<'a> {
 
                     t.style(flex_row().gap(sizes.s)).add(|t| {
                         let [positive_clicked, negative_clicked] = [
-                            (FeedbackType::Positive, AppIcon::Feedback),
-                            (FeedbackType::Negative, AppIcon::NegFeedback),
+                            (
+                                FeedbackType::Positive,
+                                AppIcon::Feedback,
+                                "Positive feedback",
+                            ),
+                            (
+                                FeedbackType::Negative,
+                                AppIcon::NegFeedback,
+                                "Negative feedback",
+                            ),
                         ]
-                        .map(|(feedback_type, icon)| {
+                        .map(|(feedback_type, icon, label)| {
                             let selected = self.data.feedback_type == Some(feedback_type);
                             t.ui_add(
                                 IconButton::new(icon, self.theme)
                                     .size(IconButtonSize::ExtraLarge)
+                                    .label(label)
                                     .toggled(selected),
                             )
                             .clicked()
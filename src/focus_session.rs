@@ -0,0 +1,85 @@
+use std::time::{Duration, Instant};
+
+/// A writing session started with `/focus`, tracks how long it lasts
+/// and how many times Shelv lost focus (context switches) since then
+#[derive(Debug, Clone, Copy)]
+pub struct FocusSession {
+    pub started_at: Instant,
+    pub context_switches: u32,
+    was_focused: bool,
+}
+
+impl FocusSession {
+    pub fn start(now: Instant) -> Self {
+        Self {
+            started_at: now,
+            context_switches: 0,
+            was_focused: true,
+        }
+    }
+
+    /// Called every frame, losing the window focus counts as a context switch
+    pub fn track_focus(&mut self, is_focused: bool) {
+        if self.was_focused && !is_focused {
+            self.context_switches += 1;
+        }
+
+        self.was_focused = is_focused;
+    }
+
+    /// e.g. "1 h 05 min · 3 switches"
+    pub fn label(&self, now: Instant) -> String {
+        let minutes = now.saturating_duration_since(self.started_at).as_secs() / 60;
+
+        let elapsed = match minutes {
+            0..60 => format!("{minutes} min"),
+            _ => format!("{} h {:02} min", minutes / 60, minutes % 60),
+        };
+
+        match self.context_switches {
+            1 => format!("{elapsed} · 1 switch"),
+            switches => format!("{elapsed} · {switches} switches"),
+        }
+    }
+
+    /// The label shows whole minutes, so there is no need to repaint more often
+    pub fn until_next_minute(&self, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.started_at).as_secs();
+        Duration::from_secs(60 - elapsed % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_focus_session() {
+        let start = Instant::now();
+        let mut session = FocusSession::start(start);
+
+        assert_eq!(session.label(start), "0 min · 0 switches");
+
+        // staying unfocused for several frames is a single switch
+        session.track_focus(false);
+        session.track_focus(false);
+        session.track_focus(true);
+        assert_eq!(
+            session.label(start + Duration::from_secs(25 * 60 + 10)),
+            "25 min · 1 switch"
+        );
+
+        session.track_focus(false);
+        session.track_focus(true);
+        session.track_focus(false);
+        assert_eq!(
+            session.label(start + Duration::from_secs(65 * 60)),
+            "1 h 05 min · 3 switches"
+        );
+
+        assert_eq!(
+            session.until_next_minute(start + Duration::from_secs(90)),
+            Duration::from_secs(30)
+        );
+    }
+}
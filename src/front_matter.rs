@@ -0,0 +1,122 @@
+use serde_json::{Map, Number, Value};
+
+/// Parses a minimal yaml subset of the front matter:
+/// `key: value` pairs, inline `[a, b]` lists and `- item` lists under an empty key.
+/// Lines that don't fit are skipped, the front matter is never an error
+pub fn parse_front_matter(yaml: &str) -> Map<String, Value> {
+    let mut result = Map::new();
+    let mut list_key: Option<String> = None;
+
+    for line in yaml.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if let (Some(key), Some(item)) = (&list_key, trimmed.strip_prefix("- ")) {
+            if let Some(Value::Array(items)) = result.get_mut(key) {
+                items.push(parse_scalar(item));
+            }
+            continue;
+        }
+
+        // nested values are not supported
+        if line.starts_with(char::is_whitespace) {
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+
+        let key = key.trim().to_string();
+        let value = value.trim();
+
+        list_key = None;
+        let value = match value {
+            "" => {
+                list_key = Some(key.clone());
+                Value::Array(vec![])
+            }
+            v if v.starts_with('[') && v.ends_with(']') => Value::Array(
+                v[1..v.len() - 1]
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(parse_scalar)
+                    .collect(),
+            ),
+            v => parse_scalar(v),
+        };
+
+        result.insert(key, value);
+    }
+
+    result
+}
+
+fn parse_scalar(value: &str) -> Value {
+    let value = value.trim();
+
+    let quoted = ['"', '\'']
+        .into_iter()
+        .find_map(|q| value.strip_prefix(q)?.strip_suffix(q));
+
+    if let Some(quoted) = quoted {
+        return Value::String(quoted.to_string());
+    }
+
+    match value {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        "null" | "~" => Value::Null,
+        v => {
+            let number = v
+                .parse::<i64>()
+                .map(Number::from)
+                .ok()
+                .or_else(|| v.parse::<f64>().ok().and_then(Number::from_f64));
+
+            match number {
+                Some(number) => Value::Number(number),
+                None => Value::String(v.to_string()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    pub fn test_parse_front_matter() {
+        let yaml = r#"
+title: "Weekly: sync"
+tags: [work, 'meetings']
+priority: 2
+done: false
+aliases:
+  - sync
+  - standup
+  # comment
+nested:
+  key: skipped
+broken line
+"#;
+
+        assert_eq!(
+            Value::Object(parse_front_matter(yaml)),
+            json!({
+                "title": "Weekly: sync",
+                "tags": ["work", "meetings"],
+                "priority": 2,
+                "done": false,
+                "aliases": ["sync", "standup"],
+                "nested": [],
+            })
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::text_structure::{SpanKind, SpanMeta, TextStructure};
+
+/// Remote images are fetched once per launch, only to tell whether they can be loaded
+#[derive(Debug, Clone, PartialEq)]
+pub enum RemoteImageStatus {
+    Loading,
+    Loaded,
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkMarkerKind {
+    /// a local file that doesn't exist
+    Missing,
+    /// a remote image that is still being fetched
+    Loading,
+    /// a remote image that couldn't be fetched, with the reason
+    FetchFailed(String),
+}
+
+/// Painted at the end of the row of an image or a link, see `render_link_markers`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkMarker {
+    pub url: String,
+    pub kind: LinkMarkerKind,
+}
+
+/// Path of the file an image or a link points to, `None` for remote urls (`https://`, `shelv://`, `data:` etc)
+/// and anchors. Relative paths are resolved against the folder of the notes, `~/` against the home folder
+pub fn local_link_path(url: &str, notes_folder: &Path) -> Option<PathBuf> {
+    let url = url.split('#').next().unwrap_or(url).trim();
+
+    // e.g. `https:`, `mailto:` or `data:image/png;base64,...`
+    let has_scheme = url
+        .split_once(':')
+        .is_some_and(|(scheme, _)| !scheme.contains('/'));
+
+    let path = match url.strip_prefix("file://") {
+        Some(path) => path,
+        None if url.is_empty() || has_scheme => return None,
+        None => url,
+    };
+
+    // markdown urls can't contain spaces without escaping them
+    let path = path.replace("%20", " ");
+
+    let resolved = match path.strip_prefix("~/") {
+        Some(rest) => directories_next::BaseDirs::new()?.home_dir().join(rest),
+        None => notes_folder.join(&path),
+    };
+
+    Some(resolved)
+}
+
+/// Urls of the images and links of the note that point to local files that don't exist
+pub fn missing_local_links(structure: &TextStructure, notes_folder: &Path) -> Vec<String> {
+    structure
+        .iter()
+        .filter(|(_, desc)| matches!(desc.kind, SpanKind::Image | SpanKind::MdLink))
+        .filter_map(|(index, _)| match structure.find_meta(index)? {
+            SpanMeta::Image { url } | SpanMeta::Link { url } => Some(url),
+            _ => None,
+        })
+        .filter(|url| local_link_path(url, notes_folder).is_some_and(|path| !path.exists()))
+        .cloned()
+        .collect()
+}
+
+/// Urls of the `http(s)` images of the note
+pub fn remote_image_urls(structure: &TextStructure) -> Vec<String> {
+    structure
+        .iter()
+        .filter(|(_, desc)| desc.kind == SpanKind::Image)
+        .filter_map(|(index, _)| match structure.find_meta(index)? {
+            SpanMeta::Image { url } => Some(url),
+            _ => None,
+        })
+        .filter(|url| url.starts_with("https://") || url.starts_with("http://"))
+        .cloned()
+        .collect()
+}
+
+/// Links to missing local files and remote images that are loading or failed to load,
+/// remote images that were never requested get no marker
+pub fn link_markers(
+    structure: &TextStructure,
+    notes_folder: &Path,
+    remote_images: &BTreeMap<String, RemoteImageStatus>,
+) -> Vec<LinkMarker> {
+    let missing = missing_local_links(structure, notes_folder)
+        .into_iter()
+        .map(|url| LinkMarker {
+            url,
+            kind: LinkMarkerKind::Missing,
+        });
+
+    let remote = remote_image_urls(structure).into_iter().filter_map(|url| {
+        let kind = match remote_images.get(&url)? {
+            RemoteImageStatus::Loading => LinkMarkerKind::Loading,
+            RemoteImageStatus::Failed(reason) => LinkMarkerKind::FetchFailed(reason.clone()),
+            RemoteImageStatus::Loaded => return None,
+        };
+        Some(LinkMarker { url, kind })
+    });
+
+    missing.chain(remote).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        app_actions::AppAction,
+        app_state::MsgToApp,
+        persistent_state::NoteFile,
+        test_harness::{IOCall, TestHarness},
+    };
+
+    use super::*;
+
+    #[test]
+    pub fn test_missing_local_links() {
+        let folder = Path::new("/notes");
+
+        assert_eq!(
+            local_link_path("img/cat%20photo.png", folder),
+            Some(PathBuf::from("/notes/img/cat photo.png"))
+        );
+        assert_eq!(
+            local_link_path("/tmp/diagram.svg", folder),
+            Some(PathBuf::from("/tmp/diagram.svg"))
+        );
+        assert_eq!(
+            local_link_path("file:///tmp/report.pdf", folder),
+            Some(PathBuf::from("/tmp/report.pdf"))
+        );
+        assert_eq!(local_link_path("https://example.com/cat.png", folder), None);
+        assert_eq!(local_link_path("shelv://note1", folder), None);
+        assert_eq!(local_link_path("data:image/png;base64,AAAA", folder), None);
+        assert_eq!(local_link_path("mailto:hi@example.com", folder), None);
+        assert_eq!(local_link_path("#heading", folder), None);
+
+        let text = "![](missing.png) ![](README.md) [cargo](Cargo.toml) [gone](gone.pdf)\n\n\
+                    ![](https://example.com/remote.png)";
+        let structure = TextStructure::new(text);
+
+        assert_eq!(
+            missing_local_links(&structure, Path::new(env!("CARGO_MANIFEST_DIR"))),
+            ["missing.png", "gone.pdf"]
+        );
+    }
+
+    #[test]
+    pub fn test_remote_image_markers() {
+        let text = "![](https://example.com/a.png) ![](http://example.com/b.png)\n\n\
+                    ![](https://example.com/c.png) [page](https://example.com) ![](gone.png)";
+        let structure = TextStructure::new(text);
+
+        assert_eq!(
+            remote_image_urls(&structure),
+            [
+                "https://example.com/a.png",
+                "http://example.com/b.png",
+                "https://example.com/c.png"
+            ]
+        );
+
+        let remote_images = BTreeMap::from([
+            (
+                "https://example.com/a.png".to_string(),
+                RemoteImageStatus::Loading,
+            ),
+            (
+                "http://example.com/b.png".to_string(),
+                RemoteImageStatus::Failed("404 Not Found".to_string()),
+            ),
+            (
+                "https://example.com/c.png".to_string(),
+                RemoteImageStatus::Loaded,
+            ),
+        ]);
+
+        assert_eq!(
+            link_markers(&structure, Path::new("/notes"), &remote_images),
+            [
+                LinkMarker {
+                    url: "gone.png".to_string(),
+                    kind: LinkMarkerKind::Missing
+                },
+                LinkMarker {
+                    url: "https://example.com/a.png".to_string(),
+                    kind: LinkMarkerKind::Loading
+                },
+                LinkMarker {
+                    url: "http://example.com/b.png".to_string(),
+                    kind: LinkMarkerKind::FetchFailed("404 Not Found".to_string())
+                },
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_remote_image_is_fetched_once() {
+        let url = "https://example.com/cat.png";
+        let mut harness = TestHarness::new(&format!("![]({url}){{||}}"));
+
+        harness.dispatch(AppAction::EvalNote(NoteFile::Note(0)));
+        harness.dispatch(AppAction::EvalNote(NoteFile::Note(0)));
+        assert_eq!(
+            harness.io.take_calls(),
+            [IOCall::FetchRemoteImage(url.to_string())]
+        );
+
+        let markers = |harness: &TestHarness| {
+            harness.state.notes[&NoteFile::Note(0)]
+                .derived_state
+                .link_markers
+                .iter()
+                .map(|marker| marker.kind.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(markers(&harness), [LinkMarkerKind::Loading]);
+
+        harness.dispatch(AppAction::HandleMsgToApp(MsgToApp::RemoteImageFetched {
+            url: url.to_string(),
+            result: Err("404 Not Found".to_string()),
+        }));
+        assert_eq!(
+            markers(&harness),
+            [LinkMarkerKind::FetchFailed("404 Not Found".to_string())]
+        );
+
+        harness.dispatch(AppAction::HandleMsgToApp(MsgToApp::RemoteImageFetched {
+            url: url.to_string(),
+            result: Ok(()),
+        }));
+        assert!(markers(&harness).is_empty());
+    }
+}
@@ -1,10 +1,24 @@
 use app_actions::{
-    AppAction, AppIO, HideMode, SlashPaletteAction, compute_app_focus, process_app_action,
+    AppAction, AppIO, HideMode, NoteSwitcherAction, SlashPaletteAction, compute_app_focus,
+    process_app_action,
 };
 use app_io::RealAppIO;
-use app_state::{AppInitData, AppState, MsgToApp, compute_editor_text_id};
-use app_ui::{AppRenderData, RenderAppResult, is_shortcut_match, render_app};
-use command::{AppFocusState, CommandContext, EditorCommandOutput};
+use app_state::{
+    AppInitData, AppState, INLINE_SUGGESTION_DELAY, InlineSuggestion, InlineSuggestionStatus,
+    LARGE_NOTE_EVAL_DELAY, MsgToApp, compute_editor_text_id,
+};
+use app_ui::{
+    AppRenderData, PoppedOutRenderData, RenderAppResult, is_shortcut_match, render_app,
+    render_popped_out_note,
+};
+use command::{
+    AppFocus, AppFocusState, CommandContext, EditorCommandOutput, try_extract_text_command_context,
+};
+use commands::{
+    multi_cursor::consume_multi_cursor_input,
+    paste_list::{on_pasted_text, single_pasted_text},
+    smart_typography::{on_typed_char, single_typed_char},
+};
 use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
 
 use hotwatch::{
@@ -12,8 +26,15 @@ use hotwatch::{
     notify::event::{DataChange, ModifyKind},
 };
 use image::ImageFormat;
-use persistent_state::{load_and_migrate, try_save, v1};
+use note_snapshot::take_note_snapshot;
+use note_switcher::note_title;
+use panic_save::{UnsavedNotesBackup, install_panic_save_hook};
+use persistent_state::{
+    NoteFile, PersistenceWarning, SelfWrites, ensure_folder_is_writable, load_and_migrate,
+    read_crash_reporting_preference, try_save, v1,
+};
 use scripting::settings_eval::Scripts;
+use settings_parsing::FocusLostBehavior;
 use smallvec::SmallVec;
 use theme::{configure_styles, get_font_definitions};
 use tokio::runtime::Runtime;
@@ -24,7 +45,11 @@ use tray_icon::{
 };
 // use tray_item::TrayItem;G1
 
-use std::{path::PathBuf, sync::mpsc::sync_channel};
+use std::{
+    path::PathBuf,
+    sync::mpsc::{SyncSender, sync_channel},
+    time::Instant,
+};
 
 use eframe::{
     CreationContext,
@@ -41,27 +66,53 @@ mod app_io;
 mod app_state;
 mod app_ui;
 mod byte_span;
+mod clipboard_history;
+mod code_block_wrap;
+mod code_highlight;
 mod command;
+mod command_log;
+mod command_usage;
 mod commands;
 mod effects;
 mod egui_hotkey;
 mod feedback;
+mod focus_session;
+mod front_matter;
 mod knus_test;
+mod local_links;
 mod nord;
+mod note_encryption;
+mod note_lang;
+mod note_management;
+mod note_snapshot;
+mod note_switcher;
+mod note_template;
+mod panic_save;
 mod persistent_state;
 mod picker;
+mod recovery_bin;
 mod scripting;
 mod settings_parsing;
 mod taffy_styles;
+mod tags;
+#[cfg(test)]
+mod test_harness;
 mod text_structure;
 mod theme;
+mod tutorial;
 mod ui_components;
+mod word_goal;
+
+/// Window opacity when it is out of focus with `FocusLostBehavior::Dim`
+const DIMMED_WINDOW_OPACITY: f32 = 0.5;
 
 pub struct MyApp<IO: AppIO> {
     state: AppState,
-    hotwatch: Hotwatch,
+    hotwatch: Option<Hotwatch>,
+    self_writes: SelfWrites,
     tray: TrayIcon,
     persistence_folder: PathBuf,
+    unsaved_notes_backup: UnsavedNotesBackup,
     app_io: IO,
 
     // begining of the frame
@@ -69,7 +120,12 @@ pub struct MyApp<IO: AppIO> {
 }
 
 impl MyApp<RealAppIO> {
-    pub fn new(cc: &CreationContext) -> Self {
+    pub fn new(
+        cc: &CreationContext,
+        persistence_folder: PathBuf,
+        mut persistence_warning: Option<PersistenceWarning>,
+        unsaved_notes_backup: UnsavedNotesBackup,
+    ) -> Self {
         let theme = Default::default();
         configure_styles(&cc.egui_ctx, &theme);
 
@@ -77,9 +133,12 @@ impl MyApp<RealAppIO> {
 
         cc.egui_ctx.set_fonts(fonts);
 
-        let persistence_folder = directories_next::ProjectDirs::from("app", "", "Shelv")
-            .map(|proj_dirs| proj_dirs.data_dir().to_path_buf())
-            .unwrap();
+        if let Err(err) = ensure_folder_is_writable(&persistence_folder) {
+            println!("persistence folder is not writable, err={err:#?}");
+            persistence_warning = Some(PersistenceWarning::PersistenceDisabled(
+                persistence_folder.clone(),
+            ));
+        }
 
         let (msg_queue_tx, msg_queue_rx) = sync_channel::<MsgToApp>(10);
 
@@ -96,7 +155,11 @@ impl MyApp<RealAppIO> {
         let current_version = env!("CARGO_PKG_VERSION");
 
         let app_io = RealAppIO {
-            hotkeys_manager: GlobalHotKeyManager::new().unwrap(),
+            hotkeys_manager: GlobalHotKeyManager::new()
+                .inspect_err(|err| {
+                    println!("failed to create global hotkeys manager, err={err:#?}")
+                })
+                .map_err(|err| err.to_string()),
             registered_hotkeys: Default::default(),
             egui_ctx: cc.egui_ctx.clone(),
             msg_queue: msg_queue_tx.clone(),
@@ -141,6 +204,8 @@ impl MyApp<RealAppIO> {
         )
         .unwrap();
 
+        // the window is hidden rather than closed (see `closeButton` setting),
+        // so the tray is always there: left click shows/hides, the menu has Quit
         let tray_quit_menu_button = MenuItem::new("Quit", true, None);
         let tray_quit_menu_button_id = tray_quit_menu_button.id().clone();
         let tray_menu = Menu::with_items(&[&tray_quit_menu_button]).unwrap();
@@ -169,36 +234,26 @@ impl MyApp<RealAppIO> {
         let (persistent_state, load_kind) =
             load_and_migrate(number_of_notes, v1_save, &persistence_folder);
 
-        let sender = msg_queue_tx.clone();
-        let ctx = cc.egui_ctx.clone();
-        let mut hotwatch = Hotwatch::new().expect("hotwatch failed to initialize!");
-        hotwatch
-            .watch(&persistence_folder, move |event: Event| {
-                // println!("\nhotwatch event\n{:#?}\n", event);
-                if let EventKind::Modify(ModifyKind::Data(DataChange::Content)) = event.kind {
-                    let filter_map: SmallVec<[_; 4]> = event
-                        .paths
-                        .iter()
-                        .filter_map(|p| {
-                            p.file_name()
-                                .and_then(|f| f.to_str())
-                                .and_then(extract_note_file)
-                                .map(|(note_file, _)| (note_file, p))
-                        })
-                        .collect();
-
-                    let has_updates = !filter_map.is_empty();
-                    for (note_file, path) in filter_map {
-                        sender
-                            .send(MsgToApp::NoteFileChanged(note_file, path.clone()))
-                            .unwrap();
-                    }
-                    if has_updates {
-                        ctx.request_repaint();
-                    }
-                }
+        let is_persistence_disabled = persistence_warning
+            .as_ref()
+            .is_some_and(|w| w.is_persistence_disabled());
+
+        // there is nothing to watch if the folder can't be written to,
+        // watching is not essential otherwise, so failures are only logged
+        let self_writes = SelfWrites::default();
+        let hotwatch = (!is_persistence_disabled)
+            .then(|| {
+                watch_persistence_folder(
+                    &persistence_folder,
+                    &msg_queue_tx,
+                    &cc.egui_ctx,
+                    self_writes.clone(),
+                )
             })
-            .expect("failed to watch file!");
+            .and_then(|res| {
+                res.inspect_err(|err| println!("failed to watch notes folder, err={err:#?}"))
+                    .ok()
+            });
 
         let last_saved = persistent_state.state.last_saved;
 
@@ -208,6 +263,9 @@ impl MyApp<RealAppIO> {
             persistent_state,
             last_saved,
             load_kind,
+            persistence_warning,
+            persistence_folder: persistence_folder.clone(),
+            start_at_login: app_io.is_start_at_login_enabled(),
         });
 
         app_io.start_update_checker();
@@ -222,7 +280,9 @@ impl MyApp<RealAppIO> {
                 viewport_focused: false,
             },
             persistence_folder,
+            unsaved_notes_backup,
             hotwatch,
+            self_writes,
         }
     }
 }
@@ -249,14 +309,36 @@ impl<IO: AppIO> eframe::App for MyApp<IO> {
                 .map(AppAction::HandleMsgToApp),
         );
 
+        // screenshot requested by `CopyNoteAsImage` arrives as an input event
+        if let Some(snapshot) = take_note_snapshot(ctx) {
+            action_list.push(AppAction::NoteSnapshotTaken(snapshot));
+        }
+
         let app_focus = self.app_focus_state.clone();
         let focused_id = ctx.memory(|m| m.focused());
 
+        // typing is applied at every selection when there are multiple
+        let is_multi_cursor_active = app_state
+            .multi_cursor
+            .as_ref()
+            .is_some_and(|m| m.note_file == selected_note_file);
+
+        if is_multi_cursor_active && app_focus.internal_focus == Some(AppFocus::NoteEditor) {
+            action_list.extend(
+                ctx.input_mut(consume_multi_cursor_input)
+                    .into_iter()
+                    .map(AppAction::MultiCursor),
+            );
+        }
+
         let mut scripts = app_state
             .settings_scripts
             .take()
             .unwrap_or_else(|| Scripts::new());
 
+        // instructions that were matched by a shortcut, used for the command log
+        let mut executed_instructions: SmallVec<[_; 2]> = SmallVec::new();
+
         // handling commands
         // sych as {tab, enter} inside a list
         let actions_from_keyboard_commands = ctx
@@ -283,11 +365,14 @@ impl<IO: AppIO> eframe::App for MyApp<IO> {
                                         "---Found a match for {:?}, focus = {app_focus:#?}, focused_id = {focused_id:?}",
                                         editor_command.instruction.human_description()
                                     );
-                                    app_state.commands.run(
+                                    let res = app_state.commands.run(
                                         &editor_command.instruction,
                                         editor_command.scope,
                                         ctx,
-                                    )
+                                    );
+                                    executed_instructions
+                                        .push((editor_command.instruction.clone(), res.len()));
+                                    res
                                 }
                                 command::KeyboardBinding::FrameBinding(frame_hotkey) => {
                                     (frame_hotkey.run)(ctx)
@@ -318,8 +403,17 @@ impl<IO: AppIO> eframe::App for MyApp<IO> {
 
         app_state.settings_scripts = Some(scripts);
 
+        for (instruction, action_count) in executed_instructions {
+            app_state.record_executed_command(&instruction, action_count);
+        }
+
         action_list.extend(actions_from_keyboard_commands.into_iter());
 
+        // the quick switcher stays open while Ctrl is held
+        if app_state.note_switcher.is_some() && !ctx.input(|i| i.modifiers.ctrl) {
+            action_list.push(AppAction::NoteSwitcher(NoteSwitcherAction::Commit));
+        }
+
         action_list.insert_many(0, app_state.deferred_actions.drain(0..));
 
         // now apply prepared changes, and update text structure and cursor appropriately
@@ -377,35 +471,129 @@ impl<IO: AppIO> eframe::App for MyApp<IO> {
         // note that we have a settings note amoung them
         let note_count = app_state.notes.len() - 1;
 
+        let note_switcher_items = app_state
+            .note_switcher
+            .as_ref()
+            .map(|switcher| switcher.items(&app_state.notes, &app_state.locked_notes));
+
+        let clear_note_title = app_state
+            .clear_note_confirmation
+            .map(|note_file| note_title(note_file, &app_state.notes[&note_file].text));
+
         let note = app_state.notes.get_mut(&app_state.selected_note).unwrap();
         let text_structure = std::mem::take(&mut note.derived_state.structure);
         let cursor = note.cursor().or(note.last_cursor());
 
+        let is_frame_actually_focused = ctx.input(|i| i.viewport().focused.unwrap_or(false));
+
+        // the window is dimmed only while it is out of focus
+        if is_frame_actually_focused && app_state.is_window_dimmed {
+            app_state.is_window_dimmed = false;
+        }
+
         // if the app is pinned it is OK not re-requesting focus
         // neither hiding if focus lost
         if !app_state.is_pinned {
-            let is_frame_actually_focused = ctx.input(|i| i.viewport().focused.unwrap_or(false));
-
             // handling focus lost
             if app_state.prev_focused != is_frame_actually_focused && !is_frame_actually_focused {
                 println!("lost focus");
-                app_state.hidden = true;
-                self.app_io.hide_app(HideMode::HideApp);
+                app_state.focus_lost_at = Some(Instant::now());
+            }
+
+            // regaining focus within the grace period cancels it
+            if is_frame_actually_focused {
+                app_state.focus_lost_at = None;
+            }
+
+            let delay = app_state.focus_lost_delay();
+            let pending_focus_lost = app_state.focus_lost_at.map(|at| at.elapsed());
+
+            if let Some(elapsed) = pending_focus_lost {
+                if elapsed < delay {
+                    ctx.request_repaint_after(delay - elapsed);
+                } else {
+                    app_state.focus_lost_at = None;
+                    match app_state.on_focus_lost() {
+                        FocusLostBehavior::Hide => {
+                            app_state.hidden = true;
+                            self.app_io.hide_app(HideMode::HideApp);
+                        }
+                        FocusLostBehavior::Dim => {
+                            app_state.is_window_dimmed = true;
+                        }
+                        FocusLostBehavior::Stay => {}
+                    }
+                }
             }
 
             app_state.prev_focused = is_frame_actually_focused;
         }
 
+        // context switches are counted even if the window is pinned
+        let focus_session_label = app_state.focus_session.as_mut().map(|session| {
+            let now = Instant::now();
+            session.track_focus(is_frame_actually_focused);
+            ctx.request_repaint_after(session.until_next_minute(now));
+            session.label(now)
+        });
+
+        // covers the startup, settings changes and dimming
+        let window_opacity = match app_state.is_window_dimmed {
+            true => DIMMED_WINDOW_OPACITY.min(app_state.window_opacity()),
+            false => app_state.window_opacity(),
+        };
+
+        if window_opacity != app_state.applied_window_opacity {
+            app_state.applied_window_opacity = window_opacity;
+            self.app_io.set_window_opacity(window_opacity);
+        }
+
+        // read before the note is borrowed, popped out notes are rendered with them too
+        let close_button = app_state.close_button();
+        let live_preview = app_state.live_preview();
+        let checkbox_tasks = app_state.checkbox_tasks();
+        let wrap_code_blocks = app_state.wrap_code_blocks();
+        let scroll_past_end = app_state.scroll_past_end();
+        let auto_links = app_state.auto_links();
+        let is_large_note = app_state.is_large_note(app_state.selected_note);
+        let inline_suggestion = app_state.ready_inline_suggestion().map(str::to_string);
+
         let edited_note = app_state.notes.get_mut(&app_state.selected_note).unwrap();
 
         let editor_text = &mut edited_note.text;
         let code_block_annotations = &mut edited_note.derived_state.code_block_annotations;
+        let link_markers = &edited_note.derived_state.link_markers;
 
         let mut frame_hotkeys = app_state.commands.prepare_frame_hotkeys();
+        if let Some(scripts) = app_state.settings_scripts.as_ref() {
+            scripts.add_frame_hotkeys(&mut frame_hotkeys);
+        }
 
         let vis_state = AppRenderData {
             selected_note: app_state.selected_note,
             is_window_pinned: app_state.is_pinned,
+            close_button,
+            // a locked note is rendered as in reading mode, the text edit is not interactive then
+            is_reading_mode: app_state
+                .reading_mode_notes
+                .contains(&app_state.selected_note)
+                || app_state.is_read_only(app_state.selected_note),
+            is_locked: app_state.is_read_only(app_state.selected_note),
+            // markers around the cursor would relayout the whole note on every cursor move
+            live_preview: live_preview && !is_large_note,
+            checkbox_tasks,
+            soft_wrap: !app_state.unwrapped_notes.contains(&app_state.selected_note),
+            wrap_code_blocks,
+            scroll_past_end,
+            auto_links,
+            is_large_note,
+            inline_suggestion: inline_suggestion.as_deref(),
+            show_preview_panel: app_state.show_preview_panel,
+            preview_layout: app_state.preview_layout.take(),
+            crash_reporting: app_state.crash_reporting,
+            start_at_login: app_state.start_at_login,
+            show_start_at_login_prompt: app_state.show_start_at_login_prompt
+                && app_state.tutorial.is_none(),
             note_count,
             text_edit_id,
             command_list: &app_state.commands,
@@ -413,20 +601,48 @@ impl<IO: AppIO> eframe::App for MyApp<IO> {
             syntax_set: &app_state.syntax_set,
             theme_set: &app_state.theme_set,
             computed_layout: app_state.computed_layout.take(),
+            highlight_cache: &mut app_state.highlight_cache,
             inline_llm_prompt: (&mut app_state.inline_llm_prompt).as_mut(),
             slash_palette: app_state.slash_palette.as_ref(),
+            table_picker: app_state.table_picker.as_ref(),
+            multi_cursor_selections: app_state
+                .multi_cursor
+                .as_ref()
+                .filter(|m| m.note_file == app_state.selected_note)
+                .map(|m| m.selections.as_slice())
+                .unwrap_or_default(),
+            rename_prompt: app_state.rename_prompt.as_mut(),
+            find_replace: app_state.find_replace.as_mut(),
+            tag_filter: app_state.tag_filter.as_mut(),
+            pinned_snippet: app_state.pinned_snippet.as_deref(),
+            recovery_bin: app_state.recovery_bin.as_mut(),
+            note_switcher_items,
+            tutorial: app_state.tutorial.as_ref(),
             render_actions: (app_state.render_actions.drain(..)).collect(),
             frame_hotkeys: &mut frame_hotkeys,
             feedback: (&mut app_state.feedback).as_mut(),
-            version_state: &app_state.version_state,
+            version_state: app_state.version_state.respecting_quiet_hours(
+                app_state.quiet_hours.as_ref(),
+                app_state.focus_session.is_some(),
+            ),
+            persistence_warning: app_state.persistence_warning.as_ref(),
+            global_hotkey_warning: app_state.global_hotkey_warning.as_ref(),
+            show_cursor_context: app_state
+                .dev_settings
+                .as_ref()
+                .is_some_and(|dev| dev.show_cursor_context),
+            clear_note_title,
+            focus_session_label,
             code_block_annotations,
+            link_markers,
         };
 
         let RenderAppResult {
-            requested_actions: actions,
+            requested_actions: mut actions,
             updated_text_structure: updated_structure,
             latest_cursor: byte_cursor,
             latest_layout: updated_layout,
+            latest_preview_layout: updated_preview_layout,
             text_changed,
         } = render_app(
             text_structure,
@@ -438,14 +654,26 @@ impl<IO: AppIO> eframe::App for MyApp<IO> {
 
         app_state.commands.add_frame_hotkeys(frame_hotkeys);
 
+        // the text edit changed the text or moved the cursor on its own, e.g. on click
+        if app_state.multi_cursor.is_some() && (text_changed || byte_cursor != cursor) {
+            app_state.multi_cursor = None;
+        }
+
         if text_changed {
             println!("----note changed during render");
             app_state
                 .add_unsaved_change(UnsavedChange::NoteContentChanged(app_state.selected_note));
         }
 
+        if text_changed && is_large_note {
+            app_state
+                .large_note_edits
+                .insert(app_state.selected_note, Instant::now());
+        }
+
         // TODO it seems that this can be done inside process_app_action
         app_state.computed_layout = updated_layout;
+        app_state.preview_layout = updated_preview_layout;
         let note = app_state.notes.get_mut(&app_state.selected_note).unwrap();
         note.derived_state.structure = updated_structure;
         match byte_cursor {
@@ -463,6 +691,153 @@ impl<IO: AppIO> eframe::App for MyApp<IO> {
             }
         }
 
+        // a continuation is requested once the typing pauses at the end of the note,
+        // moving the cursor away or editing the note drops the current one
+        let suggestion_address = app_state.inline_suggestion_address();
+        let current_address = app_state
+            .inline_suggestion
+            .as_ref()
+            .map(|suggestion| suggestion.address);
+
+        match (text_changed, suggestion_address) {
+            (true, Some(address)) => {
+                app_state.inline_suggestion = Some(InlineSuggestion {
+                    address,
+                    status: InlineSuggestionStatus::Waiting(Instant::now()),
+                })
+            }
+            _ if current_address != suggestion_address => app_state.inline_suggestion = None,
+            _ => {}
+        }
+
+        // typography is applied on top of the char that the text edit has just inserted
+        if let (true, Some(editor_settings)) = (text_changed, app_state.editor_settings.as_ref()) {
+            let changes = ctx
+                .input(single_typed_char)
+                .zip(try_extract_text_command_context(app_state))
+                .and_then(|(typed, text_ctx)| on_typed_char(text_ctx, typed, editor_settings));
+
+            if let Some(changes) = changes {
+                actions.push(AppAction::apply_text_changes(
+                    app_state.selected_note,
+                    changes,
+                ));
+            }
+        }
+
+        // same for a pasted list, it is already in the text at this point
+        if text_changed && app_state.normalize_pasted_lists() {
+            let changes = ctx
+                .input(single_pasted_text)
+                .zip(try_extract_text_command_context(app_state))
+                .and_then(|(pasted, text_ctx)| on_pasted_text(text_ctx, &pasted));
+
+            if let Some(changes) = changes {
+                actions.push(AppAction::apply_text_changes(
+                    app_state.selected_note,
+                    changes,
+                ));
+            }
+        }
+
+        // notes in their own windows, rendered after the main one
+        let large_note_bytes = app_state.large_note_bytes();
+        let popped_out_notes: SmallVec<[NoteFile; 2]> =
+            app_state.popped_out_notes.keys().copied().collect();
+
+        for note_file in popped_out_notes {
+            let Some(note) = app_state.notes.get_mut(&note_file) else {
+                continue;
+            };
+
+            let text_structure = std::mem::take(&mut note.derived_state.structure);
+            let is_large_note = note.text.len() >= large_note_bytes;
+
+            let render_data = PoppedOutRenderData {
+                note_file,
+                title: format!("Shelv - {}", note_title(note_file, &note.text)),
+                code_block_annotations: &note.derived_state.code_block_annotations,
+                link_markers: &note.derived_state.link_markers,
+                command_list: &app_state.commands,
+                syntax_set: &app_state.syntax_set,
+                theme_set: &app_state.theme_set,
+                computed_layout: app_state
+                    .popped_out_notes
+                    .get_mut(&note_file)
+                    .and_then(Option::take),
+                highlight_cache: &mut app_state.highlight_cache,
+                is_reading_mode: app_state.reading_mode_notes.contains(&note_file)
+                    || app_state.is_read_only(note_file),
+                checkbox_tasks,
+                soft_wrap: !app_state.unwrapped_notes.contains(&note_file),
+                wrap_code_blocks,
+                auto_links,
+                is_large_note,
+            };
+
+            let RenderAppResult {
+                requested_actions,
+                updated_text_structure,
+                latest_layout,
+                text_changed,
+                ..
+            } = render_popped_out_note(
+                text_structure,
+                &mut note.text,
+                render_data,
+                &app_state.theme,
+                ctx,
+            );
+
+            note.derived_state.structure = updated_text_structure;
+
+            if text_changed {
+                // the cursor of the main window could point past the edited text now,
+                // the text edit restores it on the next render
+                note.reset_cursor();
+                app_state.add_unsaved_change(UnsavedChange::NoteContentChanged(note_file));
+            }
+
+            if text_changed && is_large_note {
+                app_state.large_note_edits.insert(note_file, Instant::now());
+            }
+
+            if let Some(layout) = app_state.popped_out_notes.get_mut(&note_file) {
+                *layout = latest_layout;
+            }
+
+            actions.extend(requested_actions);
+        }
+
+        // edits of large notes are evaluated once the typing pauses, not on every keystroke
+        let now = Instant::now();
+        app_state.large_note_edits.retain(|note_file, edited_at| {
+            let elapsed = now - *edited_at;
+            match elapsed < LARGE_NOTE_EVAL_DELAY {
+                true => {
+                    ctx.request_repaint_after(LARGE_NOTE_EVAL_DELAY - elapsed);
+                    true
+                }
+                false => {
+                    actions.push(AppAction::EvalNote(*note_file));
+                    false
+                }
+            }
+        });
+
+        if let Some(suggestion) = app_state.inline_suggestion.as_mut() {
+            if let InlineSuggestionStatus::Waiting(edited_at) = suggestion.status {
+                let elapsed = now - edited_at;
+                match elapsed < INLINE_SUGGESTION_DELAY {
+                    true => ctx.request_repaint_after(INLINE_SUGGESTION_DELAY - elapsed),
+                    false => {
+                        suggestion.status = InlineSuggestionStatus::Requested;
+                        actions.push(AppAction::RequestInlineSuggestion(suggestion.address));
+                    }
+                }
+            }
+        }
+
         // post render processing
         for action in actions {
             let mut action_buffer: SmallVec<[AppAction; 4]> = SmallVec::from_iter([action]);
@@ -479,6 +854,10 @@ impl<IO: AppIO> eframe::App for MyApp<IO> {
                 action_buffer.extend(new_actions);
             }
         }
+
+        if app_state.take_unsaved_notes_edited() {
+            self.unsaved_notes_backup.sync(app_state.unsaved_notes());
+        }
     }
 
     fn on_exit(&mut self) {
@@ -498,14 +877,53 @@ impl<IO: AppIO> eframe::App for MyApp<IO> {
     }
 
     fn save(&mut self, _storage: &mut dyn eframe::Storage) {
+        let is_persistence_disabled = self
+            .state
+            .persistence_warning
+            .as_ref()
+            .is_some_and(|w| w.is_persistence_disabled());
+
         if let Some(persistent_state) = self.state.should_persist() {
+            if is_persistence_disabled {
+                return;
+            }
+
             // set_value(storage, "persistent_state", &persistent_state);
             //
             println!("\npersisted state: {persistent_state:#?}\n");
 
+            self.self_writes.mark(
+                persistent_state
+                    .files
+                    .iter()
+                    .map(|(note_file, _)| *note_file),
+            );
+
             match try_save(persistent_state, &self.persistence_folder) {
-                Ok(save_state) => {
-                    self.state.last_saved = save_state.last_saved;
+                Ok(save_result) => {
+                    self.state.last_saved = save_result.state.last_saved;
+
+                    // never written in plain, so the changes stay pending until they can be encrypted
+                    for note_file in &save_result.skipped_notes {
+                        self.state
+                            .add_unsaved_change(UnsavedChange::NoteContentChanged(*note_file));
+                    }
+
+                    // the folder warnings concern all notes and the undecryptable notes stay read only,
+                    // so those warnings stay as they are
+                    let is_folder_warning_shown = !matches!(
+                        self.state.persistence_warning,
+                        None | Some(PersistenceWarning::EncryptedNotesNotSaved(_))
+                    );
+                    if !is_folder_warning_shown {
+                        let skipped = save_result.skipped_notes;
+                        self.state.persistence_warning = match skipped.is_empty() {
+                            true => None,
+                            false => Some(PersistenceWarning::EncryptedNotesNotSaved(skipped)),
+                        };
+                    }
+
+                    self.unsaved_notes_backup.sync(self.state.unsaved_notes());
                 }
                 Err(err) => {
                     println!("failed to persist state with err={err:#?}")
@@ -515,14 +933,79 @@ impl<IO: AppIO> eframe::App for MyApp<IO> {
     }
 }
 
+/// Falls back to a temp folder if the platform data folder can't be determined
+fn get_persistence_folder() -> (PathBuf, Option<PersistenceWarning>) {
+    match directories_next::ProjectDirs::from("app", "", "Shelv") {
+        Some(proj_dirs) => (proj_dirs.data_dir().to_path_buf(), None),
+        None => {
+            let fallback = std::env::temp_dir().join("Shelv");
+            println!("failed to determine the data folder, falling back to {fallback:?}");
+            (
+                fallback.clone(),
+                Some(PersistenceWarning::TempFolderFallback(fallback)),
+            )
+        }
+    }
+}
+
+/// Notifies the app about notes changed outside of Shelv
+fn watch_persistence_folder(
+    persistence_folder: &PathBuf,
+    msg_queue_tx: &SyncSender<MsgToApp>,
+    egui_ctx: &egui::Context,
+    self_writes: SelfWrites,
+) -> Result<Hotwatch, hotwatch::Error> {
+    let sender = msg_queue_tx.clone();
+    let ctx = egui_ctx.clone();
+    let mut hotwatch = Hotwatch::new()?;
+    hotwatch.watch(persistence_folder, move |event: Event| {
+        // println!("\nhotwatch event\n{:#?}\n", event);
+        if let EventKind::Modify(ModifyKind::Data(DataChange::Content)) = event.kind {
+            let filter_map: SmallVec<[_; 4]> = event
+                .paths
+                .iter()
+                .filter_map(|p| {
+                    p.file_name()
+                        .and_then(|f| f.to_str())
+                        .and_then(extract_note_file)
+                        .map(|(note_file, _)| (note_file, p))
+                })
+                // the app has just saved it, there is nothing new to read
+                .filter(|(note_file, _)| !self_writes.is_self_write(*note_file))
+                .collect();
+
+            let has_updates = !filter_map.is_empty();
+            for (note_file, path) in filter_map {
+                sender
+                    .send(MsgToApp::NoteFileChanged(note_file, path.clone()))
+                    .unwrap();
+            }
+            if has_updates {
+                ctx.request_repaint();
+            }
+        }
+    })?;
+
+    Ok(hotwatch)
+}
+
 fn main() {
-    let _guard = sentry::init((
-        "https://10f977d35f32b70d88180f4875543208@o4507879687454720.ingest.us.sentry.io/4507879689945088",
-        sentry::ClientOptions {
-            release: sentry::release_name!(),
-            ..Default::default()
-        },
-    ));
+    // the preference is read before the app state is loaded, so crashes during loading are reported
+    let (persistence_folder, persistence_warning) = get_persistence_folder();
+
+    let _guard = read_crash_reporting_preference(&persistence_folder).then(|| {
+        sentry::init((
+            "https://10f977d35f32b70d88180f4875543208@o4507879687454720.ingest.us.sentry.io/4507879689945088",
+            sentry::ClientOptions {
+                release: sentry::release_name!(),
+                ..Default::default()
+            },
+        ))
+    });
+
+    let unsaved_notes_backup = UnsavedNotesBackup::default();
+    // installed after sentry, so notes are saved before the panic is reported
+    install_panic_save_hook(unsaved_notes_backup.clone(), persistence_folder.clone());
 
     let rt = Runtime::new().expect("Unable to create Runtime");
     // Enter the runtime so that `tokio::spawn` is available immediately.
@@ -571,7 +1054,14 @@ fn main() {
     eframe::run_native(
         "Shelv",
         options,
-        Box::new(|cc| Ok(Box::new(MyApp::new(cc)))),
+        Box::new(|cc| {
+            Ok(Box::new(MyApp::new(
+                cc,
+                persistence_folder,
+                persistence_warning,
+                unsaved_notes_backup,
+            )))
+        }),
     )
     .unwrap();
 }
@@ -0,0 +1,233 @@
+use std::sync::OnceLock;
+
+use aes_gcm::{
+    Aes256Gcm, KeyInit, Nonce,
+    aead::{Aead, AeadCore, OsRng, rand_core::RngCore},
+};
+use argon2::Argon2;
+use base64::{Engine, engine::general_purpose::STANDARD};
+
+/// First line of an encrypted note file, the rest is base64 of `salt | nonce | ciphertext`
+pub const ENCRYPTED_NOTE_HEADER: &str = "shelv-encrypted-v1\n";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+const KEYCHAIN_SERVICE: &str = "app.shelv";
+const KEYCHAIN_ACCOUNT: &str = "note-encryption";
+
+#[derive(Debug)]
+pub enum EncryptionError {
+    /// The passphrase can't be read from (or written to) the Keychain
+    Keychain(String),
+    /// Wrong passphrase or the file was tampered with
+    Crypto,
+    /// Not produced by `encrypt_note`
+    Malformed,
+}
+
+pub fn is_encrypted(content: &str) -> bool {
+    content.starts_with(ENCRYPTED_NOTE_HEADER)
+}
+
+/// The key is derived from the passphrase with argon2, a fresh salt and nonce are used for every write
+pub fn encrypt_note(text: &str, passphrase: &str) -> Result<String, EncryptionError> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+
+    let cipher = derive_cipher(passphrase, &salt)?;
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, text.as_bytes())
+        .map_err(|_| EncryptionError::Crypto)?;
+
+    let mut payload = Vec::with_capacity(SALT_LEN + NONCE_LEN + ciphertext.len());
+    payload.extend_from_slice(&salt);
+    payload.extend_from_slice(&nonce);
+    payload.extend_from_slice(&ciphertext);
+
+    Ok(format!(
+        "{ENCRYPTED_NOTE_HEADER}{}",
+        STANDARD.encode(payload)
+    ))
+}
+
+pub fn decrypt_note(content: &str, passphrase: &str) -> Result<String, EncryptionError> {
+    let encoded = content
+        .strip_prefix(ENCRYPTED_NOTE_HEADER)
+        .ok_or(EncryptionError::Malformed)?;
+
+    let payload = STANDARD
+        .decode(encoded.trim())
+        .map_err(|_| EncryptionError::Malformed)?;
+
+    if payload.len() < SALT_LEN + NONCE_LEN {
+        return Err(EncryptionError::Malformed);
+    }
+
+    let (salt, rest) = payload.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let plaintext = derive_cipher(passphrase, salt)?
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| EncryptionError::Crypto)?;
+
+    String::from_utf8(plaintext).map_err(|_| EncryptionError::Malformed)
+}
+
+fn derive_cipher(passphrase: &str, salt: &[u8]) -> Result<Aes256Gcm, EncryptionError> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|_| EncryptionError::Crypto)?;
+
+    Aes256Gcm::new_from_slice(&key).map_err(|_| EncryptionError::Crypto)
+}
+
+/// Encrypts with the passphrase from the Keychain
+pub fn encrypt_with_keychain_passphrase(text: &str) -> Result<String, EncryptionError> {
+    encrypt_note(text, keychain_passphrase()?)
+}
+
+/// Decrypts with the passphrase from the Keychain
+pub fn decrypt_with_keychain_passphrase(content: &str) -> Result<String, EncryptionError> {
+    decrypt_note(content, keychain_passphrase()?)
+}
+
+/// Read once per launch, a random passphrase is generated on the first use
+fn keychain_passphrase() -> Result<&'static str, EncryptionError> {
+    static PASSPHRASE: OnceLock<String> = OnceLock::new();
+
+    if let Some(passphrase) = PASSPHRASE.get() {
+        return Ok(passphrase);
+    }
+
+    let passphrase = read_or_create_keychain_passphrase()?;
+    Ok(PASSPHRASE.get_or_init(|| passphrase))
+}
+
+#[cfg(target_os = "macos")]
+fn read_or_create_keychain_passphrase() -> Result<String, EncryptionError> {
+    use security_framework::passwords::{get_generic_password, set_generic_password};
+
+    // https://developer.apple.com/documentation/security/errsecitemnotfound
+    const ERR_SEC_ITEM_NOT_FOUND: i32 = -25300;
+
+    match get_generic_password(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT) {
+        Ok(passphrase) => String::from_utf8(passphrase)
+            .map_err(|_| EncryptionError::Keychain("passphrase is not utf8".to_string())),
+
+        // only when it is missing, otherwise the existing notes would become unreadable
+        Err(err) if err.code() == ERR_SEC_ITEM_NOT_FOUND => {
+            let mut bytes = [0u8; 32];
+            OsRng.fill_bytes(&mut bytes);
+            let passphrase = STANDARD.encode(bytes);
+
+            set_generic_password(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT, passphrase.as_bytes())
+                .map_err(|err| EncryptionError::Keychain(err.to_string()))?;
+
+            Ok(passphrase)
+        }
+
+        Err(err) => Err(EncryptionError::Keychain(err.to_string())),
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn read_or_create_keychain_passphrase() -> Result<String, EncryptionError> {
+    Err(EncryptionError::Keychain(
+        "Keychain is only available on macOS".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        app_actions::{AppAction, ClearNoteAction},
+        command::{CommandInstruction, ForwardToChild, TextSource},
+        persistent_state::NoteFile,
+        test_harness::{IOCall, TestHarness},
+    };
+
+    use super::*;
+
+    #[test]
+    pub fn test_encrypt_decrypt_note() {
+        let text = "# Secret\n- [ ] rotate the keys";
+
+        let encrypted = encrypt_note(text, "passphrase").unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.contains("Secret"));
+
+        // fresh salt and nonce every time
+        assert_ne!(encrypted, encrypt_note(text, "passphrase").unwrap());
+
+        assert_eq!(decrypt_note(&encrypted, "passphrase").unwrap(), text);
+        assert!(matches!(
+            decrypt_note(&encrypted, "another"),
+            Err(EncryptionError::Crypto)
+        ));
+        assert!(matches!(
+            decrypt_note(text, "passphrase"),
+            Err(EncryptionError::Malformed)
+        ));
+    }
+
+    #[test]
+    pub fn test_encrypted_note_backup_is_encrypted() {
+        let mut harness = TestHarness::new("secret{||}");
+        harness.state.encrypted_notes.insert(NoteFile::Note(0));
+
+        harness.run_command(CommandInstruction::ClearNote);
+        harness.dispatch(AppAction::ClearNote(ClearNoteAction::Confirm));
+        assert!(harness.io.take_calls().contains(&IOCall::BackupNote(
+            NoteFile::Note(0),
+            "secret".to_string(),
+            true
+        )));
+    }
+
+    #[test]
+    pub fn test_undecryptable_note_is_never_saved() {
+        let mut harness = TestHarness::new("shelv-encrypted-v1\n{||}AAAA");
+        harness.state.undecryptable_notes.insert(NoteFile::Note(0));
+
+        harness.run_command(CommandInstruction::InsertText(ForwardToChild(
+            TextSource::Str("typo".to_string()),
+        )));
+        assert_eq!(harness.text_with_cursor(), "shelv-encrypted-v1\n{||}AAAA");
+
+        // e.g. the encryption was toggled, the ciphertext still isn't written over
+        harness.dispatch(AppAction::ToggleNoteEncryption(NoteFile::Note(0)));
+        assert!(harness.state.unsaved_notes().next().is_none());
+        assert!(
+            harness
+                .state
+                .should_persist()
+                .is_some_and(|data| data.files.is_empty())
+        );
+    }
+
+    #[test]
+    pub fn test_duplicate_of_encrypted_note_is_encrypted() {
+        let mut harness = TestHarness::new("secret{||}");
+        harness
+            .state
+            .notes
+            .get_mut(&NoteFile::Note(1))
+            .unwrap()
+            .text
+            .clear();
+        harness.dispatch(AppAction::ToggleNoteEncryption(NoteFile::Note(0)));
+
+        harness.dispatch(AppAction::DuplicateNote(NoteFile::Note(0)));
+        assert_eq!(harness.state.notes[&NoteFile::Note(1)].text, "secret");
+        assert!(harness.state.encrypted_notes.contains(&NoteFile::Note(1)));
+        assert!(
+            harness
+                .state
+                .unsaved_notes()
+                .all(|(note_file, _)| note_file != NoteFile::Note(1))
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use serde_json::Value;
+
+use crate::{front_matter::parse_front_matter, text_structure::TextStructure};
+
+/// Front matter key of the per note language, e.g. `lang: fr`
+pub const LANG_KEY: &str = "lang";
+
+/// Language of a note, e.g. `fr` or `pt-BR`, stored lowercase.
+/// Picks the locale dependent typography, english rules are used for unknown languages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteLang(String);
+
+impl Default for NoteLang {
+    fn default() -> Self {
+        Self("en".to_string())
+    }
+}
+
+impl NoteLang {
+    /// `None` if the tag is not a language tag, e.g. `lang: 42`
+    pub fn parse(tag: &str) -> Option<Self> {
+        let tag = tag.trim().replace('_', "-").to_lowercase();
+        let primary = tag.split('-').next()?;
+
+        let is_valid = (2..=3).contains(&primary.len())
+            && primary.chars().all(|ch| ch.is_ascii_alphabetic())
+            && tag
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '-');
+
+        is_valid.then_some(Self(tag))
+    }
+
+    /// From the front matter of the note, `default` (the `lang` of the editor settings) otherwise
+    pub fn of_note(text: &str, structure: &TextStructure, default: Option<&str>) -> Self {
+        let from_front_matter = structure
+            .front_matter(text)
+            .map(parse_front_matter)
+            .and_then(|front_matter| match front_matter.get(LANG_KEY)? {
+                Value::String(tag) => Self::parse(tag),
+                _ => None,
+            });
+
+        from_front_matter
+            .or_else(|| default.and_then(Self::parse))
+            .unwrap_or_default()
+    }
+
+    /// e.g. `pt` of `pt-br`
+    pub fn primary(&self) -> &str {
+        self.0.split('-').next().unwrap_or(&self.0)
+    }
+
+    /// Opening and closing double quotes.
+    /// Single quotes are the same everywhere, the closing one is also an apostrophe
+    pub fn double_quotes(&self) -> (&'static str, &'static str) {
+        match self.primary() {
+            "de" | "cs" | "sk" | "lt" | "is" => ("„", "“"),
+            "pl" | "ro" | "hu" | "hr" | "bg" => ("„", "”"),
+            "fr" | "es" | "it" | "pt" | "ru" | "uk" | "be" | "el" | "ca" | "no" | "nb" => {
+                ("«", "»")
+            }
+            "sv" | "fi" => ("”", "”"),
+            "ja" => ("「", "」"),
+            _ => ("“", "”"),
+        }
+    }
+
+    /// Locale dependent case mapping, e.g. the dotted capital `İ` of `i` in Turkish
+    pub fn to_uppercase(&self, ch: char) -> String {
+        match (self.primary(), ch) {
+            ("tr" | "az", 'i') => "İ".to_string(),
+            _ => ch.to_uppercase().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_note_lang() {
+        let text = "---\nlang: fr_CA\n---\ntext";
+        let structure = TextStructure::new(text);
+
+        let lang = NoteLang::of_note(text, &structure, Some("de"));
+        assert_eq!(lang, NoteLang("fr-ca".to_string()));
+        assert_eq!(lang.primary(), "fr");
+        assert_eq!(lang.double_quotes(), ("«", "»"));
+
+        let text = "no front matter";
+        let structure = TextStructure::new(text);
+        assert_eq!(
+            NoteLang::of_note(text, &structure, Some("de")).double_quotes(),
+            ("„", "“")
+        );
+        assert_eq!(
+            NoteLang::of_note(text, &structure, None),
+            NoteLang::default()
+        );
+
+        assert_eq!(NoteLang::parse("42"), None);
+        assert_eq!(NoteLang::parse("english please"), None);
+        assert_eq!(NoteLang::parse("tr").unwrap().to_uppercase('i'), "İ");
+        assert_eq!(NoteLang::parse("en").unwrap().to_uppercase('i'), "I");
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::BTreeSet;
+
+use crate::persistent_state::NoteFile;
+
+/// The first empty shelf after the source one (wrapping around), never the settings or a locked one
+pub fn next_empty_note<'a>(
+    source: NoteFile,
+    notes: impl Iterator<Item = (NoteFile, &'a str)>,
+    locked_notes: &BTreeSet<NoteFile>,
+) -> Option<NoteFile> {
+    let source_index = match source {
+        NoteFile::Note(index) => Some(index),
+        NoteFile::Settings => None,
+    };
+
+    notes
+        .filter(|(note_file, text)| text.trim().is_empty() && !locked_notes.contains(note_file))
+        .filter_map(|(note_file, _)| match note_file {
+            NoteFile::Note(index) if Some(index) != source_index => Some(index),
+            _ => None,
+        })
+        // shelves after the source go first
+        .min_by_key(|index| (source_index.is_some_and(|source| *index < source), *index))
+        .map(NoteFile::Note)
+}
+
+/// Suggested in the export dialog, the first line of the note (e.g. `Groceries.md`) or `Shelf 2.md`
+pub fn export_file_name(note_file: NoteFile, text: &str) -> String {
+    const MAX_CHARS: usize = 60;
+
+    let body = text
+        .strip_prefix("---\n")
+        .and_then(|front_matter| front_matter.split_once("\n---"))
+        .map_or(text, |(_, body)| body);
+
+    let title = body
+        .lines()
+        .map(|line| line.trim_start_matches('#').trim())
+        // empty lines and thematic breaks
+        .find(|line| !line.chars().all(|ch| ch == '-'))
+        .map(|line| {
+            line.chars()
+                .take(MAX_CHARS)
+                .map(|ch| match ch {
+                    '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+                    ch => ch,
+                })
+                .collect::<String>()
+        });
+
+    let title = match (title, note_file) {
+        (Some(title), _) => title.trim().to_string(),
+        (None, NoteFile::Note(index)) => format!("Shelf {}", index + 1),
+        (None, NoteFile::Settings) => "Settings".to_string(),
+    };
+
+    format!("{title}.md")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        app_actions::{AppAction, FindReplaceAction},
+        app_state::FindReplace,
+        command::{CommandInstruction, ForwardToChild, TextSource},
+        test_harness::{IOCall, TestHarness},
+    };
+
+    use super::*;
+
+    #[test]
+    pub fn test_next_empty_note() {
+        let notes = [
+            (NoteFile::Note(0), ""),
+            (NoteFile::Note(1), "# b"),
+            (NoteFile::Note(2), "# c"),
+            (NoteFile::Note(3), " \n"),
+            (NoteFile::Settings, ""),
+        ];
+        let no_locks = BTreeSet::new();
+
+        assert_eq!(
+            next_empty_note(NoteFile::Note(1), notes.into_iter(), &no_locks),
+            Some(NoteFile::Note(3))
+        );
+        assert_eq!(
+            next_empty_note(NoteFile::Note(3), notes.into_iter(), &no_locks),
+            Some(NoteFile::Note(0)),
+            "wraps around"
+        );
+        assert_eq!(
+            next_empty_note(NoteFile::Settings, notes.into_iter(), &no_locks),
+            Some(NoteFile::Note(0))
+        );
+        assert_eq!(
+            next_empty_note(NoteFile::Note(0), notes.into_iter().take(3), &no_locks),
+            None,
+            "no empty shelves"
+        );
+        assert_eq!(
+            next_empty_note(
+                NoteFile::Note(1),
+                notes.into_iter(),
+                &BTreeSet::from([NoteFile::Note(3)])
+            ),
+            Some(NoteFile::Note(0)),
+            "skips locked shelves"
+        );
+    }
+
+    #[test]
+    pub fn test_export_file_name() {
+        assert_eq!(
+            export_file_name(NoteFile::Note(0), "\n# Trip: Oslo/Bergen\ntext"),
+            "Trip- Oslo-Bergen.md"
+        );
+        assert_eq!(
+            export_file_name(NoteFile::Note(0), "---\ntags: [a]\n---\n# Title"),
+            "Title.md"
+        );
+        assert_eq!(export_file_name(NoteFile::Note(1), " \n"), "Shelf 2.md");
+        assert_eq!(export_file_name(NoteFile::Settings, ""), "Settings.md");
+    }
+
+    #[test]
+    pub fn test_note_is_exported_as_markdown() {
+        let mut harness = TestHarness::new("# Groceries\n- milk{||}");
+
+        harness.run_command(CommandInstruction::ExportNote);
+        assert_eq!(
+            harness.io.take_calls(),
+            vec![IOCall::ExportNote(
+                "Groceries.md".to_string(),
+                "# Groceries\n- milk".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    pub fn test_locked_note_is_read_only() {
+        let mut harness = TestHarness::new("make {|}this{|} bold");
+
+        harness.run_command(CommandInstruction::ToggleNoteLock);
+        assert!(
+            harness
+                .state
+                .should_persist()
+                .is_some_and(|data| data.locked_notes.contains(&NoteFile::Note(0)))
+        );
+
+        harness.run_command(CommandInstruction::MarkdownBold);
+        harness.run_command(CommandInstruction::ClearNote);
+        assert_eq!(harness.text_with_cursor(), "make {|}this{|} bold");
+        assert_eq!(harness.state.clear_note_confirmation, None);
+
+        harness.run_command(CommandInstruction::ToggleNoteLock);
+        harness.run_command(CommandInstruction::MarkdownBold);
+        assert_eq!(harness.text_with_cursor(), "make **{|}this{|}** bold");
+    }
+
+    #[test]
+    pub fn test_locked_note_rejects_all_edits() {
+        let mut harness = TestHarness::new("make {|}this{|} bold");
+        harness.run_command(CommandInstruction::ToggleNoteLock);
+
+        harness.run_command(CommandInstruction::InsertText(ForwardToChild(
+            TextSource::Str("that".to_string()),
+        )));
+        assert_eq!(harness.text_with_cursor(), "make {|}this{|} bold");
+
+        harness.state.find_replace = Some(FindReplace {
+            note_file: NoteFile::Note(0),
+            query: "bold".to_string(),
+            replacement: "italic".to_string(),
+            is_regex: false,
+            selection: None,
+            in_selection: false,
+        });
+        harness.dispatch(AppAction::FindReplace(FindReplaceAction::ReplaceAll));
+        assert_eq!(harness.text_with_cursor(), "make {|}this{|} bold");
+
+        // duplicating never writes into an empty locked shelf
+        for index in [1, 2] {
+            harness
+                .state
+                .notes
+                .get_mut(&NoteFile::Note(index))
+                .unwrap()
+                .text
+                .clear();
+        }
+        harness.state.locked_notes.insert(NoteFile::Note(1));
+
+        harness.dispatch(AppAction::DuplicateNote(NoteFile::Note(0)));
+        assert_eq!(harness.state.notes[&NoteFile::Note(1)].text, "");
+        assert_eq!(harness.state.selected_note, NoteFile::Note(2));
+        assert_eq!(
+            harness.state.notes[&NoteFile::Note(2)].text,
+            "make this bold"
+        );
+    }
+}
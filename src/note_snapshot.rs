@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use eframe::egui::{ColorImage, Context, Event, Id, Rect, UserData, ViewportCommand};
+
+/// Attached to the screenshot request to tell it apart from other screenshots
+struct NoteSnapshotRequest;
+
+fn editor_rect_id() -> Id {
+    Id::new("note_snapshot_editor_rect")
+}
+
+/// Called during rendering, the rect is used to crop the screenshot to the editor
+pub fn register_editor_rect(ctx: &Context, rect: Rect) {
+    ctx.data_mut(|data| data.insert_temp(editor_rect_id(), rect));
+}
+
+/// The screenshot arrives as an input event on one of the next frames, see `take_note_snapshot`
+pub fn request_note_snapshot(ctx: &Context) {
+    ctx.send_viewport_cmd(ViewportCommand::Screenshot(UserData::new(
+        NoteSnapshotRequest,
+    )));
+}
+
+/// Screenshot of the editor region, if it was requested via `request_note_snapshot`
+pub fn take_note_snapshot(ctx: &Context) -> Option<ColorImage> {
+    let screenshot = ctx.input(|input| {
+        input.raw.events.iter().find_map(|event| match event {
+            Event::Screenshot {
+                image, user_data, ..
+            } => user_data
+                .data
+                .as_ref()
+                .is_some_and(|data| data.downcast_ref::<NoteSnapshotRequest>().is_some())
+                .then(|| image.clone()),
+            _ => None,
+        })
+    })?;
+
+    let editor_rect = ctx.data(|data| data.get_temp::<Rect>(editor_rect_id()));
+
+    Some(match editor_rect {
+        Some(rect) => screenshot.region(&rect, Some(ctx.pixels_per_point())),
+        None => Arc::unwrap_or_clone(screenshot),
+    })
+}
@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use smallvec::SmallVec;
+
+use crate::{app_state::Note, persistent_state::NoteFile};
+
+const MAX_TITLE_CHARS: usize = 32;
+
+/// Notes in the most recently used order, the selected one goes first
+#[derive(Debug)]
+pub struct RecentNotes(SmallVec<[NoteFile; 5]>);
+
+impl RecentNotes {
+    pub fn new(notes: impl IntoIterator<Item = NoteFile>, selected: NoteFile) -> Self {
+        let mut recent = Self(notes.into_iter().collect());
+        recent.touch(selected);
+        recent
+    }
+
+    pub fn touch(&mut self, note_file: NoteFile) {
+        self.0.retain(|n| *n != note_file);
+        self.0.insert(0, note_file);
+    }
+
+    pub fn as_slice(&self) -> &[NoteFile] {
+        &self.0
+    }
+}
+
+/// Ctrl+Tab overlay, cycles through the recent notes while Ctrl is held,
+/// the note is switched to when Ctrl is released
+#[derive(Debug)]
+pub struct NoteSwitcher {
+    /// snapshot of `RecentNotes` at the moment the switcher was opened
+    order: SmallVec<[NoteFile; 5]>,
+    selected: usize,
+}
+
+#[derive(Debug)]
+pub struct NoteSwitcherItem {
+    pub title: String,
+    pub is_selected: bool,
+    pub is_locked: bool,
+}
+
+impl NoteSwitcher {
+    pub fn new(recent: &RecentNotes) -> Self {
+        Self {
+            order: recent.as_slice().into(),
+            selected: 0,
+        }
+    }
+
+    pub fn cycle(&mut self, backwards: bool) {
+        let len = self.order.len().max(1);
+        self.selected = match backwards {
+            true => (self.selected + len - 1) % len,
+            false => (self.selected + 1) % len,
+        };
+    }
+
+    pub fn selected_note(&self) -> Option<NoteFile> {
+        self.order.get(self.selected).copied()
+    }
+
+    pub fn items(
+        &self,
+        notes: &BTreeMap<NoteFile, Note>,
+        locked_notes: &BTreeSet<NoteFile>,
+    ) -> SmallVec<[NoteSwitcherItem; 5]> {
+        self.order
+            .iter()
+            .enumerate()
+            .map(|(index, note_file)| NoteSwitcherItem {
+                title: note_title(
+                    *note_file,
+                    notes.get(note_file).map(|n| n.text.as_str()).unwrap_or(""),
+                ),
+                is_selected: index == self.selected,
+                is_locked: locked_notes.contains(note_file),
+            })
+            .collect()
+    }
+}
+
+/// The first non empty line without markdown heading markers, e.g. "Shelf 2: Groceries"
+pub fn note_title(note_file: NoteFile, text: &str) -> String {
+    let prefix = match note_file {
+        NoteFile::Note(index) => format!("Shelf {}", index + 1),
+        NoteFile::Settings => return "Settings".to_string(),
+    };
+
+    let first_line = text
+        .lines()
+        .map(|line| line.trim_start_matches('#').trim())
+        .find(|line| !line.is_empty());
+
+    match first_line {
+        Some(line) if line.chars().count() > MAX_TITLE_CHARS => {
+            let truncated: String = line.chars().take(MAX_TITLE_CHARS).collect();
+            format!("{prefix}: {truncated}…")
+        }
+        Some(line) => format!("{prefix}: {line}"),
+        None => prefix,
+    }
+}
+
+/// The shelf after (or before) the current one, wrapping around at the ends.
+/// Settings come after the last shelf only if `include_settings` is set
+pub fn adjacent_note(
+    current: NoteFile,
+    shelf_count: u32,
+    backwards: bool,
+    include_settings: bool,
+) -> NoteFile {
+    let last = shelf_count.saturating_sub(1);
+
+    match (current, backwards) {
+        (NoteFile::Note(index), false) if index < last => NoteFile::Note(index + 1),
+        (NoteFile::Note(index), true) if index > 0 => NoteFile::Note(index - 1),
+        (NoteFile::Note(_), false) if include_settings => NoteFile::Settings,
+        (NoteFile::Note(_), false) => NoteFile::Note(0),
+        (NoteFile::Note(_), true) if include_settings => NoteFile::Settings,
+        (NoteFile::Note(_), true) => NoteFile::Note(last),
+        (NoteFile::Settings, false) => NoteFile::Note(0),
+        (NoteFile::Settings, true) => NoteFile::Note(last),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{command::CommandInstruction, test_harness::TestHarness};
+
+    use super::*;
+
+    #[test]
+    pub fn test_recent_notes_order() {
+        let mut recent = RecentNotes::new((0..4).map(NoteFile::Note), NoteFile::Note(2));
+        assert_eq!(
+            recent.as_slice(),
+            &[2, 0, 1, 3].map(NoteFile::Note),
+            "selected note goes first"
+        );
+
+        recent.touch(NoteFile::Note(3));
+        recent.touch(NoteFile::Settings);
+        assert_eq!(
+            recent.as_slice(),
+            &[
+                NoteFile::Settings,
+                NoteFile::Note(3),
+                NoteFile::Note(2),
+                NoteFile::Note(0),
+                NoteFile::Note(1),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_note_switcher_cycle() {
+        let recent = RecentNotes::new((0..3).map(NoteFile::Note), NoteFile::Note(1));
+        let mut switcher = NoteSwitcher::new(&recent);
+
+        switcher.cycle(false);
+        assert_eq!(switcher.selected_note(), Some(NoteFile::Note(0)));
+
+        switcher.cycle(false);
+        switcher.cycle(false);
+        assert_eq!(
+            switcher.selected_note(),
+            Some(NoteFile::Note(1)),
+            "wraps around"
+        );
+
+        switcher.cycle(true);
+        assert_eq!(switcher.selected_note(), Some(NoteFile::Note(2)));
+    }
+
+    #[test]
+    pub fn test_note_title() {
+        assert_eq!(
+            note_title(NoteFile::Note(0), "\n# Groceries\n- milk"),
+            "Shelf 1: Groceries"
+        );
+        assert_eq!(note_title(NoteFile::Note(1), "  \n"), "Shelf 2");
+        assert_eq!(note_title(NoteFile::Settings, "# Settings"), "Settings");
+    }
+
+    #[test]
+    pub fn test_adjacent_note_wraps_around() {
+        let next =
+            |note_file, include_settings| adjacent_note(note_file, 4, false, include_settings);
+        let prev =
+            |note_file, include_settings| adjacent_note(note_file, 4, true, include_settings);
+
+        assert_eq!(next(NoteFile::Note(1), false), NoteFile::Note(2));
+        assert_eq!(next(NoteFile::Note(3), false), NoteFile::Note(0));
+        assert_eq!(prev(NoteFile::Note(0), false), NoteFile::Note(3));
+        assert_eq!(next(NoteFile::Settings, false), NoteFile::Note(0));
+
+        assert_eq!(next(NoteFile::Note(3), true), NoteFile::Settings);
+        assert_eq!(prev(NoteFile::Note(0), true), NoteFile::Settings);
+        assert_eq!(prev(NoteFile::Settings, true), NoteFile::Note(3));
+    }
+
+    #[test]
+    pub fn test_switching_to_adjacent_notes_wraps_around() {
+        let mut harness = TestHarness::new("first{||}");
+
+        harness.run_command(CommandInstruction::SwitchToPrevNote);
+        assert_eq!(harness.state.selected_note, NoteFile::Note(3));
+
+        harness.run_command(CommandInstruction::SwitchToNextNote);
+        assert_eq!(harness.state.selected_note, NoteFile::Note(0));
+    }
+}
@@ -0,0 +1,129 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, Local};
+
+use crate::effects::text_change_effect::TextChange;
+
+/// Used by `/now` unless `timestampFormat` is set, e.g. `2025-03-14 09:30`
+pub const DEFAULT_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// Fills the placeholders of a note template from the settings:
+/// `{date}` (2025-03-14), `{time}` (09:30), `{weekday}` (Friday) and `{cursor}`.
+/// Without `{cursor}` the cursor is placed at the end of the text
+pub fn expand_template(template: &str, now: DateTime<Local>) -> String {
+    let text = template
+        .replace("{date}", &format_timestamp(now, "%Y-%m-%d"))
+        .replace("{time}", &format_timestamp(now, "%H:%M"))
+        .replace("{weekday}", &format_timestamp(now, "%A"));
+
+    match text.contains("{cursor}") {
+        // there is only one cursor, the rest of the placeholders are dropped
+        true => text
+            .replacen("{cursor}", TextChange::CURSOR, 1)
+            .replace("{cursor}", ""),
+        false => format!("{text}{}", TextChange::CURSOR),
+    }
+}
+
+/// Formats `now` with a strftime `format`, e.g. `%A, %d %B` for `Friday, 14 March`.
+/// An invalid format falls back to `DEFAULT_TIMESTAMP_FORMAT`
+pub fn format_timestamp(now: DateTime<Local>, format: &str) -> String {
+    let mut formatted = String::new();
+
+    // unlike `to_string`, `write!` reports an invalid format instead of panicking
+    match write!(formatted, "{}", now.format(format)) {
+        Ok(()) => formatted,
+        Err(_) => {
+            println!("invalid timestamp format {format:?}, using the default one");
+            now.format(DEFAULT_TIMESTAMP_FORMAT).to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use crate::{
+        app_actions::{AppAction, ClearNoteAction},
+        byte_span::ByteSpan,
+        persistent_state::NoteFile,
+        test_harness::TestHarness,
+    };
+
+    use super::*;
+
+    #[test]
+    pub fn test_expand_template() {
+        let now = Local.with_ymd_and_hms(2025, 3, 14, 9, 30, 0).unwrap();
+
+        let test_cases = [
+            (
+                "## date heading ##",
+                "# {weekday}, {date}\n\n## Todo\n- [ ] ",
+                "# Friday, 2025-03-14\n\n## Todo\n- [ ] {||}",
+            ),
+            (
+                "## cursor placeholder ##",
+                "## {time}\n{cursor}\n\n## Notes\n",
+                "## 09:30\n{||}\n\n## Notes\n",
+            ),
+            (
+                "## only the first cursor is kept ##",
+                "{cursor}a{cursor}",
+                "{||}a",
+            ),
+            ("## unknown placeholders stay ##", "{month}", "{month}{||}"),
+        ];
+
+        for (desc, template, expected) in test_cases {
+            assert_eq!(
+                expand_template(template, now),
+                expected,
+                "test case: {desc}"
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_format_timestamp() {
+        let now = Local.with_ymd_and_hms(2025, 3, 14, 9, 30, 0).unwrap();
+
+        assert_eq!(
+            format_timestamp(now, DEFAULT_TIMESTAMP_FORMAT),
+            "2025-03-14 09:30"
+        );
+        assert_eq!(format_timestamp(now, "%A, %d %B"), "Friday, 14 March");
+        assert_eq!(format_timestamp(now, "%Q"), "2025-03-14 09:30");
+    }
+
+    #[test]
+    pub fn test_empty_notes_are_filled_from_templates() {
+        let mut harness = TestHarness::new("note{||}");
+
+        harness.with_settings("template 1 \"## Todo\\n- [ ] \"");
+        assert_eq!(harness.text_with_cursor(), "note{||}");
+
+        // the cleared note starts from the template
+        harness.dispatch(AppAction::ClearNote(ClearNoteAction::Ask(NoteFile::Note(
+            0,
+        ))));
+        harness.dispatch(AppAction::ClearNote(ClearNoteAction::Confirm));
+        assert_eq!(harness.text_with_cursor(), "## Todo\n- [ ] {||}");
+
+        // empty notes are filled once opened
+        let len = harness.state.notes[&NoteFile::Note(0)].text.len();
+        harness.dispatch(AppAction::apply_text_changes(
+            NoteFile::Note(0),
+            vec![TextChange::Insert(
+                ByteSpan::new(0, len),
+                TextChange::CURSOR.to_string(),
+            )],
+        ));
+        harness.dispatch(AppAction::SwitchToNote {
+            note_file: NoteFile::Note(0),
+            via_shortcut: true,
+        });
+        assert_eq!(harness.text_with_cursor(), "## Todo\n- [ ] {||}");
+    }
+}
@@ -0,0 +1,75 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex, TryLockError},
+};
+
+use crate::persistent_state::{NoteFile, save_note_files};
+
+/// Copy of the notes that were edited but not persisted yet.
+/// `AppState` is owned by the app and can be borrowed at the moment of a panic,
+/// so the panic hook reads from this copy instead
+#[derive(Clone, Default)]
+pub struct UnsavedNotesBackup {
+    notes: Arc<Mutex<Vec<(NoteFile, String)>>>,
+}
+
+impl UnsavedNotesBackup {
+    pub fn sync<'a>(&self, unsaved_notes: impl Iterator<Item = (NoteFile, &'a str)>) {
+        let Ok(mut notes) = self.notes.lock() else {
+            return;
+        };
+
+        notes.clear();
+        notes.extend(unsaved_notes.map(|(note_file, text)| (note_file, text.to_string())));
+    }
+}
+
+/// Best effort attempt to save unsaved notes before the process goes down,
+/// the previous hook (e.g. crash reporting) runs afterwards
+pub fn install_panic_save_hook(backup: UnsavedNotesBackup, folder: PathBuf) {
+    let prev_hook = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        // `try_lock` because the panic might have happened while the lock was held
+        let notes = match backup.notes.try_lock() {
+            Ok(notes) => Some(notes),
+            Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        };
+
+        if let Some(notes) = notes.filter(|notes| !notes.is_empty()) {
+            let files = notes
+                .iter()
+                .map(|(note_file, text)| (*note_file, text.as_str()))
+                .collect();
+
+            match save_note_files(files, &folder) {
+                Ok(_) => eprintln!("saved {} note(s) before panicking", notes.len()),
+                Err(err) => eprintln!("failed to save notes before panicking err={err:#?}"),
+            }
+        }
+
+        prev_hook(info);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        app_actions::AppAction, byte_span::ByteSpan, effects::text_change_effect::TextChange,
+        persistent_state::NoteFile, test_harness::TestHarness,
+    };
+
+    #[test]
+    pub fn test_edits_are_reported_once_for_the_panic_backup() {
+        let mut harness = TestHarness::new("note{||}");
+        harness.state.take_unsaved_notes_edited();
+
+        harness.dispatch(AppAction::apply_text_changes(
+            NoteFile::Note(0),
+            vec![TextChange::Insert(ByteSpan::new(4, 4), "s".to_string())],
+        ));
+        assert!(harness.state.take_unsaved_notes_edited());
+        assert!(!harness.state.take_unsaved_notes_edited());
+    }
+}
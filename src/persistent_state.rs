@@ -1,12 +1,25 @@
 use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsString,
     fs, io,
     path::{Path, PathBuf},
-    time::{SystemTime, UNIX_EPOCH},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 const CURRENT_VERSION: i32 = 2;
 
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    command_log::{COMMAND_LOG_FILE_NAME, CommandLog},
+    command_usage::CommandUsage,
+    note_encryption::{
+        decrypt_with_keychain_passphrase, encrypt_with_keychain_passphrase, is_encrypted,
+    },
+    scripting::note_storage::{SCRIPT_STORAGE_FILE_NAME, ScriptStorage},
+};
+
 #[derive(Debug, Hash, Clone, PartialEq, Ord, PartialOrd, Eq, Copy, Deserialize, Serialize)]
 pub enum NoteFile {
     Note(u32),
@@ -21,16 +34,46 @@ pub struct SaveState {
     pub is_pinned: bool,
     pub last_saved: u128,
     pub selected: NoteFile,
+
+    #[serde(default = "default_crash_reporting_value")]
+    pub crash_reporting: bool,
+
+    #[serde(default)]
+    pub command_usage: CommandUsage,
+
+    /// Notes that are stored encrypted with the passphrase from the Keychain
+    #[serde(default)]
+    pub encrypted_notes: BTreeSet<NoteFile>,
+
+    /// The "start at login" prompt is shown only once
+    #[serde(default)]
+    pub start_at_login_prompted: bool,
+
+    /// The last executed inline prompt per note, as pairs since `NoteFile` can't be a json key
+    #[serde(default)]
+    pub last_llm_prompts: Vec<(NoteFile, String)>,
+
+    /// Notes that are read only until unlocked
+    #[serde(default)]
+    pub locked_notes: BTreeSet<NoteFile>,
 }
 
 fn default_window_pinned_value() -> bool {
     true
 }
 
+fn default_crash_reporting_value() -> bool {
+    true
+}
+
 pub struct RestoredData {
     pub state: SaveState,
     pub notes: Vec<String>,
     pub settings: String,
+    pub script_storage: ScriptStorage,
+    pub command_log: CommandLog,
+    /// Encrypted files that couldn't be decrypted, their content is loaded as is, but never written back
+    pub undecryptable_notes: BTreeSet<NoteFile>,
 }
 
 #[derive(Debug)]
@@ -38,6 +81,16 @@ pub struct DataToSave<'a> {
     pub files: Vec<(NoteFile, &'a str)>,
     pub selected: NoteFile,
     pub is_pinned: bool,
+    pub crash_reporting: bool,
+    pub command_usage: CommandUsage,
+    pub command_log: Option<String>,
+    pub script_storage: Option<String>,
+    pub encrypted_notes: BTreeSet<NoteFile>,
+    pub start_at_login_prompted: bool,
+    pub last_llm_prompts: Vec<(NoteFile, String)>,
+    pub locked_notes: BTreeSet<NoteFile>,
+    /// Saved notes are also copied to these files, it is not a part of `state.json`
+    pub note_mirrors: Vec<(NoteFile, PathBuf)>,
 }
 
 #[derive(Debug)]
@@ -58,6 +111,56 @@ impl From<serde_json::Error> for LoadSaveError {
     }
 }
 
+/// Notes are not persisted where they are expected to be, shown as a warning in the header
+#[derive(Debug)]
+pub enum PersistenceWarning {
+    /// Platform data folder can't be determined, notes are saved to a temp folder instead
+    TempFolderFallback(PathBuf),
+    /// Folder can't be created or written to, notes are kept in memory only
+    PersistenceDisabled(PathBuf),
+    /// The passphrase can't be read from the Keychain, the changes are saved once it can
+    EncryptedNotesNotSaved(Vec<NoteFile>),
+    /// Encrypted notes that couldn't be decrypted on launch, they are read only until the next one
+    EncryptedNotesNotDecrypted(Vec<NoteFile>),
+}
+
+impl PersistenceWarning {
+    pub fn title(&self) -> &'static str {
+        match self {
+            PersistenceWarning::TempFolderFallback(_) => "Temporary Folder",
+            PersistenceWarning::PersistenceDisabled(_) => "Notes Not Saved",
+            PersistenceWarning::EncryptedNotesNotSaved(_) => "Encrypted Notes Not Saved",
+            PersistenceWarning::EncryptedNotesNotDecrypted(_) => "Encrypted Notes Locked",
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            PersistenceWarning::TempFolderFallback(folder) => format!(
+                "Couldn't find the app data folder, notes are saved to {}",
+                folder.to_string_lossy()
+            ),
+            PersistenceWarning::PersistenceDisabled(folder) => format!(
+                "Couldn't write to {}, changes will be lost on exit",
+                folder.to_string_lossy()
+            ),
+            PersistenceWarning::EncryptedNotesNotSaved(notes) => format!(
+                "Couldn't encrypt {}, check that Shelv can access the Keychain",
+                note_names(notes)
+            ),
+            PersistenceWarning::EncryptedNotesNotDecrypted(notes) => format!(
+                "Couldn't decrypt {}, they are read only and kept on disk as they are. \
+                Check that Shelv can access the Keychain and restart it",
+                note_names(notes)
+            ),
+        }
+    }
+
+    pub fn is_persistence_disabled(&self) -> bool {
+        matches!(self, PersistenceWarning::PersistenceDisabled(_))
+    }
+}
+
 pub enum HydrationResult {
     FolderIsMissing,
     Success(RestoredData),
@@ -84,11 +187,15 @@ pub fn load_and_migrate<'s>(
                 Some(v1_save) => (fn_migrate_from_v1(&v1_save), LoadKind::Migrated),
                 None => (bootstrap(number_of_notes), LoadKind::FreshInstall),
             };
-            try_save(to_save, &folder).unwrap();
+            if let Err(err) = try_save(to_save, &folder) {
+                println!("load_and_migrate: failed to save initial state err={err:#?}");
+            }
             (data, load_kind)
         }
         (Ok(HydrationResult::Partial(data, to_save)), _) => {
-            try_save(to_save, &folder).unwrap();
+            if let Err(err) = try_save(to_save, &folder) {
+                println!("load_and_migrate: failed to save restored notes err={err:#?}");
+            }
             (data, LoadKind::Normal)
         }
     }
@@ -101,6 +208,7 @@ fn try_hydrate(number_of_notes: u32, folder: &PathBuf) -> Result<HydrationResult
     };
 
     let mut retrieved_files: Vec<(NoteFile, String)> = vec![];
+    let mut undecryptable_notes = BTreeSet::new();
 
     let mut state: Option<SaveState> = None;
 
@@ -114,8 +222,20 @@ fn try_hydrate(number_of_notes: u32, folder: &PathBuf) -> Result<HydrationResult
             let note_file = extract_note_file(file_name);
 
             if let Some((note_file, file_name)) = note_file {
-                let content = fs::read_to_string(folder.join(file_name))?;
+                let mut content = fs::read_to_string(folder.join(file_name))?;
                 println!("try_hydrate: detected file {file_name} as {note_file:?}");
+
+                if is_encrypted(&content) {
+                    // kept as is when it can't be decrypted, the app never writes it back, so it isn't lost
+                    match decrypt_with_keychain_passphrase(&content) {
+                        Ok(decrypted) => content = decrypted,
+                        Err(err) => {
+                            println!("try_hydrate: failed to decrypt {file_name} err={err:?}");
+                            undecryptable_notes.insert(note_file);
+                        }
+                    }
+                }
+
                 retrieved_files.push((note_file, content));
             }
 
@@ -155,10 +275,22 @@ fn try_hydrate(number_of_notes: u32, folder: &PathBuf) -> Result<HydrationResult
         is_pinned: true,
         last_saved: get_current_utc_timestamp(),
         selected: NoteFile::Note(0),
+        crash_reporting: true,
+        command_usage: CommandUsage::default(),
+        encrypted_notes: BTreeSet::new(),
+        start_at_login_prompted: false,
+        last_llm_prompts: vec![],
+        locked_notes: BTreeSet::new(),
     });
 
     let selected = state.selected;
     let is_pinned = state.is_pinned;
+    let crash_reporting = state.crash_reporting;
+    let command_usage = state.command_usage.clone();
+    let encrypted_notes = state.encrypted_notes.clone();
+    let start_at_login_prompted = state.start_at_login_prompted;
+    let last_llm_prompts = state.last_llm_prompts.clone();
+    let locked_notes = state.locked_notes.clone();
 
     let restored = RestoredData {
         state,
@@ -168,6 +300,9 @@ fn try_hydrate(number_of_notes: u32, folder: &PathBuf) -> Result<HydrationResult
             .find(|(note_file, _)| *note_file == NoteFile::Settings)
             .map(|(_, content)| content)
             .unwrap_or_else(|| "".to_string()),
+        script_storage: ScriptStorage::load(folder),
+        command_log: CommandLog::load(folder),
+        undecryptable_notes,
     };
 
     if state_parsed && missing_notes.is_empty() {
@@ -184,6 +319,16 @@ fn try_hydrate(number_of_notes: u32, folder: &PathBuf) -> Result<HydrationResult
                 files: missing_notes,
                 selected,
                 is_pinned,
+                crash_reporting,
+                command_usage,
+                command_log: None,
+                script_storage: None,
+                encrypted_notes,
+                start_at_login_prompted,
+                last_llm_prompts,
+                locked_notes,
+                // mirrors are known only once the settings are evaluated
+                note_mirrors: vec![],
             },
         ))
     }
@@ -201,11 +346,38 @@ pub fn extract_note_file(file_name: &str) -> Option<(NoteFile, &str)> {
     }
 }
 
-pub fn try_save<'a>(data: DataToSave<'a>, folder: &PathBuf) -> Result<SaveState, LoadSaveError> {
+fn note_names(notes: &[NoteFile]) -> String {
+    notes
+        .iter()
+        .map(|note_file| match note_file {
+            NoteFile::Note(index) => format!("Shelf {}", index + 1),
+            NoteFile::Settings => "Settings".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// What `try_save` has written
+pub struct SaveResult {
+    pub state: SaveState,
+    /// Encrypted notes that couldn't be encrypted, so they weren't written at all
+    pub skipped_notes: Vec<NoteFile>,
+}
+
+pub fn try_save<'a>(data: DataToSave<'a>, folder: &PathBuf) -> Result<SaveResult, LoadSaveError> {
     let DataToSave {
         files,
         is_pinned,
         selected,
+        crash_reporting,
+        command_usage,
+        command_log,
+        script_storage,
+        encrypted_notes,
+        start_at_login_prompted,
+        last_llm_prompts,
+        locked_notes,
+        note_mirrors,
     } = data;
 
     fs::create_dir_all(folder)?;
@@ -215,23 +387,273 @@ pub fn try_save<'a>(data: DataToSave<'a>, folder: &PathBuf) -> Result<SaveState,
         is_pinned,
         last_saved: get_current_utc_timestamp(),
         selected,
+        crash_reporting,
+        command_usage,
+        encrypted_notes,
+        start_at_login_prompted,
+        last_llm_prompts,
+        locked_notes,
     };
 
+    let mut skipped_notes = vec![];
+
+    // one note that can't be encrypted doesn't prevent the rest from being saved
+    let files: Vec<(NoteFile, Cow<str>)> = files
+        .into_iter()
+        .filter_map(|(note_file, content)| {
+            match state.encrypted_notes.contains(&note_file) && !is_encrypted(content) {
+                true => match encrypt_with_keychain_passphrase(content) {
+                    Ok(encrypted) => Some((note_file, Cow::Owned(encrypted))),
+                    Err(err) => {
+                        println!("failed to encrypt {note_file:?}, err={err:?}");
+                        skipped_notes.push(note_file);
+                        None
+                    }
+                },
+                false => Some((note_file, Cow::Borrowed(content))),
+            }
+        })
+        .collect();
+
     fs::write(
         folder.join("state.json"),
         serde_json::to_string_pretty(&state)?,
     )?;
 
+    save_note_files(
+        files
+            .iter()
+            .map(|(note_file, content)| (*note_file, content.as_ref()))
+            .collect(),
+        folder,
+    )?;
+
+    // an unavailable mirror (e.g. an unmounted drive) doesn't prevent the notes from being saved,
+    // settings reject mirrors of encrypted notes, but the note could be encrypted since then
+    let mirrored = files
+        .iter()
+        .filter(|(note_file, _)| !state.encrypted_notes.contains(note_file))
+        .filter_map(|(note_file, content)| {
+            note_mirrors
+                .iter()
+                .find(|(mirrored, _)| mirrored == note_file)
+                .map(|(_, path)| (path, content))
+        });
+
+    for (path, content) in mirrored {
+        if let Err(err) = write_atomically(path, content) {
+            println!("failed to mirror a note to {path:?}, err={err}");
+        }
+    }
+
+    if let Some(command_log) = command_log {
+        fs::write(folder.join(COMMAND_LOG_FILE_NAME), command_log)?;
+    }
+
+    if let Some(script_storage) = script_storage {
+        fs::write(folder.join(SCRIPT_STORAGE_FILE_NAME), script_storage)?;
+    }
+
+    Ok(SaveResult {
+        state,
+        skipped_notes,
+    })
+}
+
+/// Writes into a temporary file next to `path` and renames it, so the file is never left half written
+pub fn write_atomically(path: &Path, content: &str) -> io::Result<()> {
+    let Some(file_name) = path.file_name() else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{path:?} is not a file path"),
+        ));
+    };
+
+    let mut tmp_name = OsString::from(".");
+    tmp_name.push(file_name);
+    tmp_name.push(".shelv-tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    fs::write(&tmp_path, content)?;
+    fs::rename(&tmp_path, path)
+}
+
+/// Creates the folder if needed and checks that files can be written into it
+pub fn ensure_folder_is_writable(folder: &Path) -> io::Result<()> {
+    fs::create_dir_all(folder)?;
+
+    let probe = folder.join(".write-probe");
+    fs::write(&probe, "")?;
+    fs::remove_file(&probe)
+}
+
+/// Writes only the note files, without touching `state.json`
+pub fn save_note_files<'a>(
+    files: Vec<(NoteFile, &'a str)>,
+    folder: &PathBuf,
+) -> Result<(), LoadSaveError> {
+    fs::create_dir_all(folder)?;
+
     for (note, content) in files {
-        let file_name = match note {
-            NoteFile::Note(zero_based_index) => format!("note-{}.md", zero_based_index + 1),
-            NoteFile::Settings => "settings.md".to_string(),
+        fs::write(folder.join(note_file_name(note)), content)?;
+    }
+
+    Ok(())
+}
+
+/// Name of the file the note is stored in, e.g. `note-1.md`
+pub fn note_file_name(note: NoteFile) -> String {
+    match note {
+        NoteFile::Note(zero_based_index) => format!("note-{}.md", zero_based_index + 1),
+        NoteFile::Settings => "settings.md".to_string(),
+    }
+}
+
+/// File events for the app's own writes arrive shortly after the write
+const SELF_WRITE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Notes that were just written by the app, shared with the folder watcher
+/// so it doesn't reload what has just been saved
+#[derive(Clone, Default)]
+pub struct SelfWrites {
+    written_at: Arc<Mutex<BTreeMap<NoteFile, Instant>>>,
+}
+
+impl SelfWrites {
+    pub fn mark(&self, note_files: impl Iterator<Item = NoteFile>) {
+        let Ok(mut written_at) = self.written_at.lock() else {
+            return;
         };
 
-        fs::write(folder.join(file_name), content)?;
+        let now = Instant::now();
+        written_at.extend(note_files.map(|note_file| (note_file, now)));
+    }
+
+    /// Changes outside of Shelv within the window are ignored too, but it is short enough
+    pub fn is_self_write(&self, note_file: NoteFile) -> bool {
+        self.written_at.lock().is_ok_and(|written_at| {
+            written_at
+                .get(&note_file)
+                .is_some_and(|at| at.elapsed() < SELF_WRITE_WINDOW)
+        })
+    }
+}
+
+/// Cleared notes are copied there, so they can be recovered
+pub const BACKUPS_FOLDER_NAME: &str = "backups";
+
+/// Writes a timestamped copy of the note into the backups folder, e.g. `backups/cleared-note-1-<timestamp>.md`.
+/// The name doesn't start with `note-`, so the folder watcher never mistakes it for a note.
+/// Backups of encrypted notes are encrypted as well, there is no backup if that fails
+pub fn save_note_backup(
+    note: NoteFile,
+    content: &str,
+    encrypt: bool,
+    folder: &Path,
+) -> io::Result<PathBuf> {
+    let content = match encrypt {
+        true => Cow::Owned(
+            encrypt_with_keychain_passphrase(content)
+                .map_err(|err| io::Error::other(format!("failed to encrypt, err={err:?}")))?,
+        ),
+        false => Cow::Borrowed(content),
+    };
+
+    let backups_folder = folder.join(BACKUPS_FOLDER_NAME);
+    fs::create_dir_all(&backups_folder)?;
+
+    let path = backups_folder.join(backup_file_name(note, get_current_utc_timestamp()));
+    fs::write(&path, content.as_ref())?;
+
+    Ok(path)
+}
+
+/// Content of a cleared note, read back from the backups folder
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoteBackup {
+    pub note_file: NoteFile,
+    /// utc timestamp in milliseconds
+    pub saved_at: u128,
+    pub content: String,
+}
+
+/// The most recent backups first, files that don't follow the backup naming are ignored
+/// and so are encrypted backups that can't be decrypted
+pub fn read_recent_note_backups(folder: &Path, limit: usize) -> io::Result<Vec<NoteBackup>> {
+    let backups_folder = folder.join(BACKUPS_FOLDER_NAME);
+    if !backups_folder.exists() {
+        return Ok(vec![]);
     }
 
-    Ok(state)
+    let mut backups: Vec<(NoteFile, u128, PathBuf)> = fs::read_dir(&backups_folder)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let (note_file, saved_at) = parse_backup_file_name(entry.file_name().to_str()?)?;
+            Some((note_file, saved_at, entry.path()))
+        })
+        .collect();
+
+    backups.sort_by(|a, b| b.1.cmp(&a.1));
+
+    Ok(backups
+        .into_iter()
+        .take(limit)
+        .filter_map(|(note_file, saved_at, path)| {
+            let content = fs::read_to_string(path).ok()?;
+            let content = match is_encrypted(&content) {
+                true => match decrypt_with_keychain_passphrase(&content) {
+                    Ok(decrypted) => decrypted,
+                    Err(err) => {
+                        println!("failed to decrypt a backup of {note_file:?}, err={err:?}");
+                        return None;
+                    }
+                },
+                false => content,
+            };
+
+            Some(NoteBackup {
+                note_file,
+                saved_at,
+                content,
+            })
+        })
+        .collect())
+}
+
+fn backup_file_name(note: NoteFile, timestamp: u128) -> String {
+    let note_name = match note {
+        NoteFile::Note(zero_based_index) => format!("note-{}", zero_based_index + 1),
+        NoteFile::Settings => "settings".to_string(),
+    };
+
+    format!("cleared-{note_name}-{timestamp}.md")
+}
+
+/// `cleared-note-2-1700000000000.md` -> `(NoteFile::Note(1), 1700000000000)`
+fn parse_backup_file_name(file_name: &str) -> Option<(NoteFile, u128)> {
+    let name = file_name.strip_prefix("cleared-")?.strip_suffix(".md")?;
+    let (note_name, timestamp) = name.rsplit_once('-')?;
+    let timestamp = timestamp.parse().ok()?;
+
+    let note_file = match note_name {
+        "settings" => NoteFile::Settings,
+        _ => {
+            let number: u32 = note_name.strip_prefix("note-")?.parse().ok()?;
+            NoteFile::Note(number.checked_sub(1)?)
+        }
+    };
+
+    Some((note_file, timestamp))
+}
+
+/// Read straight from `state.json`, because crash reporting is initialized before the app state is loaded.
+/// Missing or unreadable state means that crash reporting is on
+pub fn read_crash_reporting_preference(folder: &Path) -> bool {
+    fs::read_to_string(folder.join("state.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<SaveState>(&content).ok())
+        .map(|state| state.crash_reporting)
+        .unwrap_or_else(default_crash_reporting_value)
 }
 
 fn get_current_utc_timestamp() -> u128 {
@@ -263,6 +685,15 @@ pub fn fn_migrate_from_v1<'s>(
             .collect(),
         is_pinned: true,
         selected,
+        crash_reporting: true,
+        command_usage: CommandUsage::default(),
+        command_log: None,
+        script_storage: None,
+        encrypted_notes: BTreeSet::new(),
+        start_at_login_prompted: false,
+        last_llm_prompts: vec![],
+        locked_notes: BTreeSet::new(),
+        note_mirrors: vec![],
     };
 
     let restored_data = RestoredData {
@@ -271,9 +702,18 @@ pub fn fn_migrate_from_v1<'s>(
             last_saved: get_current_utc_timestamp(),
             is_pinned: true,
             selected,
+            crash_reporting: true,
+            command_usage: CommandUsage::default(),
+            encrypted_notes: BTreeSet::new(),
+            start_at_login_prompted: false,
+            last_llm_prompts: vec![],
+            locked_notes: BTreeSet::new(),
         },
         notes: old_state.notes.iter().map(|s| s.to_string()).collect(),
         settings: get_default_note_content(NoteFile::Settings).to_string(),
+        script_storage: ScriptStorage::default(),
+        command_log: CommandLog::default(),
+        undecryptable_notes: BTreeSet::new(),
     };
 
     (to_save, restored_data)
@@ -290,6 +730,15 @@ pub fn bootstrap(number_of_notes: u32) -> (DataToSave<'static>, RestoredData) {
             .collect(),
         selected,
         is_pinned: true,
+        crash_reporting: true,
+        command_usage: CommandUsage::default(),
+        command_log: None,
+        script_storage: None,
+        encrypted_notes: BTreeSet::new(),
+        start_at_login_prompted: false,
+        last_llm_prompts: vec![],
+        locked_notes: BTreeSet::new(),
+        note_mirrors: vec![],
     };
 
     let restored_data = RestoredData {
@@ -298,6 +747,12 @@ pub fn bootstrap(number_of_notes: u32) -> (DataToSave<'static>, RestoredData) {
             version: CURRENT_VERSION,
             last_saved: get_current_utc_timestamp(),
             selected,
+            crash_reporting: true,
+            command_usage: CommandUsage::default(),
+            encrypted_notes: BTreeSet::new(),
+            start_at_login_prompted: false,
+            last_llm_prompts: vec![],
+            locked_notes: BTreeSet::new(),
         },
         notes: (0..number_of_notes)
             .into_iter()
@@ -305,6 +760,9 @@ pub fn bootstrap(number_of_notes: u32) -> (DataToSave<'static>, RestoredData) {
             .map(|s| s.to_string())
             .collect(),
         settings: get_default_note_content(NoteFile::Settings).to_string(),
+        script_storage: ScriptStorage::default(),
+        command_log: CommandLog::default(),
+        undecryptable_notes: BTreeSet::new(),
     };
 
     (to_save, restored_data)
@@ -330,3 +788,49 @@ pub mod v1 {
         pub selected_note: u32,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::note_encryption::ENCRYPTED_NOTE_HEADER;
+
+    use super::*;
+
+    #[test]
+    pub fn test_backup_file_name_roundtrip() {
+        for note_file in [NoteFile::Note(0), NoteFile::Note(3), NoteFile::Settings] {
+            let name = backup_file_name(note_file, 1700000000000);
+            assert_eq!(
+                parse_backup_file_name(&name),
+                Some((note_file, 1700000000000)),
+                "file name: {name}"
+            );
+        }
+
+        assert_eq!(parse_backup_file_name("note-1.md"), None);
+        assert_eq!(parse_backup_file_name("cleared-note-0-1.md"), None);
+        assert_eq!(parse_backup_file_name("cleared-note-1-abc.md"), None);
+    }
+
+    #[test]
+    pub fn test_undecryptable_note_is_loaded_as_is() {
+        let folder =
+            std::env::temp_dir().join(format!("shelv-undecryptable-{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+
+        let ciphertext = format!("{ENCRYPTED_NOTE_HEADER}not a valid payload");
+        fs::write(folder.join("note-1.md"), &ciphertext).unwrap();
+        fs::write(folder.join("note-2.md"), "# plain").unwrap();
+
+        let restored = match try_hydrate(2, &folder) {
+            Ok(HydrationResult::Success(data) | HydrationResult::Partial(data, _)) => data,
+            _ => panic!("expected the notes to be restored"),
+        };
+        fs::remove_dir_all(&folder).unwrap();
+
+        assert_eq!(restored.notes, [ciphertext.as_str(), "# plain"]);
+        assert_eq!(
+            restored.undecryptable_notes,
+            BTreeSet::from([NoteFile::Note(0)])
+        );
+    }
+}
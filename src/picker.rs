@@ -24,6 +24,8 @@ pub struct PickerItem<Item: PartialEq> {
 pub struct Picker<'a, Item: PartialEq> {
     pub current: usize,
     pub items: &'a [PickerItem<Item>],
+    /// name of the whole group for screen readers, items are announced by their tooltips
+    pub label: &'a str,
     pub gap: f32,
     pub item_size: f32,
     // colors
@@ -54,6 +56,7 @@ impl<'a, 'b, Item: PartialEq> Widget for PickerResultWrapper<'a, 'b, Item> {
             result,
             Picker {
                 items,
+                label,
                 gap,
                 item_size: box_size,
                 current: original_current,
@@ -80,7 +83,7 @@ impl<'a, 'b, Item: PartialEq> Widget for PickerResultWrapper<'a, 'b, Item> {
         let (rect, response) = ui.allocate_exact_size(desired_size, Sense::hover());
         // println!("allocated={:?}, available = {:?}", rect, avail);
 
-        response.widget_info(|| WidgetInfo::selected(WidgetType::RadioButton, true, true, ""));
+        response.widget_info(|| WidgetInfo::labeled(WidgetType::RadioGroup, true, label));
 
         if ui.is_rect_visible(rect) {
             // let visuals = ui.style().interact_selectable(&response, checked); // too colorful
@@ -106,6 +109,15 @@ impl<'a, 'b, Item: PartialEq> Widget for PickerResultWrapper<'a, 'b, Item> {
 
                         let is_selected = i == current;
 
+                        point_response.widget_info(|| {
+                            WidgetInfo::selected(
+                                WidgetType::RadioButton,
+                                true,
+                                is_selected,
+                                &item.tooltip,
+                            )
+                        });
+
                         if !is_selected {
                             let tooltip_ui = |ui: &mut egui::Ui| {
                                 ui.label(RichText::new(&item.tooltip).color(tooltip_text));
@@ -0,0 +1,167 @@
+use crate::{
+    note_switcher::note_title,
+    persistent_state::{NoteBackup, NoteFile},
+};
+
+/// How many of the most recently cleared shelves are listed
+pub const RECOVERY_BIN_SIZE: usize = 20;
+
+#[derive(Debug, Clone)]
+pub struct RecoveryEntry {
+    pub note_title: String,
+    /// e.g. "5 min ago"
+    pub age: String,
+    pub content: String,
+}
+
+/// Palette listing the content of recently cleared shelves (read from their backups),
+/// the selected one is restored into the current note on confirm
+#[derive(Debug)]
+pub struct RecoveryBin {
+    pub selected: usize,
+    pub entries: Vec<RecoveryEntry>,
+}
+
+impl RecoveryBin {
+    /// `now` is a utc timestamp in milliseconds, the same as `NoteBackup::saved_at`.
+    /// Settings backups are left out, they don't belong to a shelf
+    pub fn new(backups: Vec<NoteBackup>, now: u128) -> Self {
+        let entries = backups
+            .into_iter()
+            .filter(|backup| matches!(backup.note_file, NoteFile::Note(_)))
+            .map(|backup| RecoveryEntry {
+                note_title: note_title(backup.note_file, &backup.content),
+                age: age_label(backup.saved_at, now),
+                content: backup.content,
+            })
+            .collect();
+
+        Self {
+            selected: 0,
+            entries,
+        }
+    }
+
+    pub fn move_selection(&mut self, backwards: bool) {
+        let len = self.entries.len().max(1);
+        self.selected = match backwards {
+            true => (self.selected.min(len - 1) + len - 1) % len,
+            false => (self.selected + 1) % len,
+        };
+    }
+
+    pub fn selected_entry(&self) -> Option<&RecoveryEntry> {
+        self.entries.get(self.selected).or(self.entries.last())
+    }
+}
+
+/// e.g. "just now", "5 min ago", "3 h ago", "2 days ago"
+fn age_label(saved_at: u128, now: u128) -> String {
+    let minutes = now.saturating_sub(saved_at) / 60_000;
+
+    match minutes {
+        0 => "just now".to_string(),
+        1..60 => format!("{minutes} min ago"),
+        60..1440 => format!("{} h ago", minutes / 60),
+        1440..2880 => "1 day ago".to_string(),
+        _ => format!("{} days ago", minutes / 1440),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        app_actions::{AppAction, ClearNoteAction, RecoveryBinAction},
+        byte_span::ByteSpan,
+        command::CommandInstruction,
+        effects::text_change_effect::TextChange,
+        test_harness::{IOCall, TestHarness},
+    };
+
+    use super::*;
+
+    const MINUTE: u128 = 60_000;
+
+    #[test]
+    pub fn test_recovery_bin() {
+        let now = 1000 * 24 * 60 * MINUTE;
+        let backup = |note_file, minutes_ago, content: &str| NoteBackup {
+            note_file,
+            saved_at: now - minutes_ago * MINUTE,
+            content: content.to_string(),
+        };
+
+        let mut bin = RecoveryBin::new(
+            vec![
+                backup(NoteFile::Note(1), 0, "# Groceries\n- milk"),
+                backup(NoteFile::Settings, 3, "// settings"),
+                backup(NoteFile::Note(0), 5, "todo"),
+                backup(NoteFile::Note(2), 3 * 60 + 20, "ideas"),
+                backup(NoteFile::Note(3), 3 * 24 * 60, "old"),
+            ],
+            now,
+        );
+
+        let labels: Vec<(&str, &str)> = bin
+            .entries
+            .iter()
+            .map(|entry| (entry.note_title.as_str(), entry.age.as_str()))
+            .collect();
+
+        assert_eq!(
+            labels,
+            [
+                ("Shelf 2: Groceries", "just now"),
+                ("Shelf 1: todo", "5 min ago"),
+                ("Shelf 3: ideas", "3 h ago"),
+                ("Shelf 4: old", "3 days ago"),
+            ]
+        );
+
+        bin.move_selection(true);
+        assert_eq!(bin.selected_entry().unwrap().content, "old");
+
+        bin.move_selection(false);
+        bin.move_selection(false);
+        assert_eq!(bin.selected_entry().unwrap().content, "todo");
+    }
+
+    #[test]
+    pub fn test_cleared_note_is_restored_from_recovery_bin() {
+        let mut harness = TestHarness::new("groceries{||}");
+
+        harness.run_command(CommandInstruction::ClearNote);
+        harness.dispatch(AppAction::ClearNote(ClearNoteAction::Confirm));
+        harness.dispatch(AppAction::apply_text_changes(
+            NoteFile::Note(0),
+            vec![TextChange::Insert(ByteSpan::new(0, 0), "todo".to_string())],
+        ));
+        harness.io.take_calls();
+
+        harness.run_command(CommandInstruction::ShowRecoveryBin);
+        let titles: Vec<String> = harness
+            .state
+            .recovery_bin
+            .as_ref()
+            .unwrap()
+            .entries
+            .iter()
+            .map(|entry| entry.note_title.clone())
+            .collect();
+        assert_eq!(titles, ["Shelf 1: groceries"]);
+
+        harness.dispatch(AppAction::RecoveryBin(RecoveryBinAction::Confirm));
+        assert_eq!(harness.state.notes[&NoteFile::Note(0)].text, "groceries");
+        assert!(harness.state.recovery_bin.is_none());
+
+        // the replaced content can be restored as well
+        assert_eq!(
+            harness.io.take_calls(),
+            [IOCall::BackupNote(
+                NoteFile::Note(0),
+                "todo".to_string(),
+                false
+            )]
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{
+    app_state::CodeBlockAnnotation,
+    text_structure::{SpanIndex, SpanKind, TextStructure},
+};
+
+use super::{note_eval::JsEvaluator, note_eval_context::SourceHash};
+
+/// Code blocks of the settings note with this lang are shared with live js blocks of all notes
+pub const SNIPPETS_BLOCK_LANG: &str = "js snippets";
+
+/// Shared code from the `js snippets` blocks of the settings note.
+/// It is evaluated before live js blocks, so they can call the functions defined there
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsSnippets {
+    /// mixed into the hash of live js blocks, so their outputs are refreshed when snippets change
+    pub hash: SourceHash,
+    pub code: String,
+}
+
+/// Each block is evaluated on top of the previous ones, blocks with errors are not shared
+pub fn collect_js_snippets(
+    text: &str,
+    text_structure: &TextStructure,
+) -> (Option<JsSnippets>, Vec<(SpanIndex, CodeBlockAnnotation)>) {
+    let mut evaluator = JsEvaluator::new();
+    let mut shared: Vec<&str> = vec![];
+    let mut annotations = vec![];
+
+    let blocks = text_structure
+        .filter_map_codeblocks(|lang| (lang == SNIPPETS_BLOCK_LANG).then_some(()))
+        .filter_map(|(index, _, _, _)| {
+            let (_, code_desc) = text_structure
+                .iterate_immediate_children_of(index)
+                .find(|(_, desc)| desc.kind == SpanKind::Text)?;
+
+            Some((index, &text[code_desc.byte_pos.range()]))
+        });
+
+    for (index, code) in blocks {
+        let annotation = match evaluator.eval_snippet(code) {
+            Ok(()) => {
+                shared.push(code);
+                CodeBlockAnnotation::Applied {
+                    message: "Shared with js blocks in all notes".to_string(),
+                }
+            }
+            Err(message) => CodeBlockAnnotation::Error {
+                title: "Error during evaluating snippets".to_string(),
+                message,
+            },
+        };
+
+        annotations.push((index, annotation));
+    }
+
+    let snippets = (!shared.is_empty()).then(|| {
+        let code = shared.join("\n");
+        JsSnippets {
+            hash: SourceHash::from(&code),
+            code,
+        }
+    });
+
+    (snippets, annotations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_collect_js_snippets() {
+        let text = r#"
+```js snippets
+function double(x) { return x * 2; }
+```
+
+```js snippets
+double(
+```
+
+```js
+not a snippet
+```
+"#;
+
+        let structure = TextStructure::new(text);
+        let (snippets, annotations) = collect_js_snippets(text, &structure);
+
+        assert_eq!(
+            snippets.map(|s| s.code.trim().to_string()),
+            Some("function double(x) { return x * 2; }".to_string())
+        );
+
+        assert!(matches!(
+            annotations.as_slice(),
+            [
+                (_, CodeBlockAnnotation::Applied { .. }),
+                (_, CodeBlockAnnotation::Error { .. })
+            ]
+        ));
+    }
+}
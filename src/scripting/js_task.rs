@@ -0,0 +1,317 @@
+use std::rc::Rc;
+
+use crate::{
+    effects::text_change_effect::TextChange,
+    persistent_state::NoteFile,
+    text_structure::{SpanIndex, SpanKind, SpanMeta, TextStructure},
+};
+
+use super::{
+    js_snippets::JsSnippets,
+    note_eval::{
+        JSBlockLang, JsEvaluator, assign_block_id, find_js_source_block_by_id, output_block_change,
+        source_hash,
+    },
+    note_eval_context::{BlockEvalResult, BlockId, SourceHash},
+    note_storage::NoteStorage,
+};
+
+/// Blocks calling `shelv.task(async (progress) => ...)` are long running scripts,
+/// they are evaluated in the background and never re-evaluated live
+pub fn is_js_task(code: &str) -> bool {
+    code.contains("shelv.task(")
+}
+
+/// Reported from js with `progress(0.5, "fetching")`, both arguments are optional
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JsTaskProgress {
+    /// between 0 and 1, None if the task didn't report how far it is
+    pub fraction: Option<f32>,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub enum JsTaskUpdate {
+    Progress(JsTaskProgress),
+    /// `storage` is None if it can't be read back
+    Done {
+        result: BlockEvalResult,
+        storage: Option<NoteStorage>,
+    },
+}
+
+/// Everything a task needs to run on another thread, the js context can't be shared between threads
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsTaskRequest {
+    pub note_file: NoteFile,
+    pub block_id: BlockId,
+    pub hash: SourceHash,
+    pub code: String,
+    pub snippets: Option<JsSnippets>,
+    pub front_matter: Option<String>,
+    pub storage: NoteStorage,
+}
+
+/// None if the block is not a task, the text change assigns an id to the block if it doesn't have one yet
+pub fn prepare_js_task(
+    note_file: NoteFile,
+    span_index: SpanIndex,
+    text_structure: &TextStructure,
+    text: &str,
+    snippets: Option<&JsSnippets>,
+    storage: &NoteStorage,
+) -> Option<(JsTaskRequest, Option<TextChange>)> {
+    let Some(SpanMeta::CodeBlock(code_meta)) = text_structure.find_meta(span_index) else {
+        return None;
+    };
+
+    let code = block_code(text_structure, text, span_index)?;
+    if !is_js_task(code) {
+        return None;
+    }
+
+    let (block_id, id_change) = assign_block_id(text_structure, code_meta)?;
+    let front_matter = text_structure.front_matter(text);
+
+    let request = JsTaskRequest {
+        note_file,
+        block_id,
+        hash: source_hash(code, snippets, front_matter),
+        code: code.to_string(),
+        snippets: snippets.cloned(),
+        front_matter: front_matter.map(str::to_string),
+        storage: storage.clone(),
+    };
+
+    Some((request, id_change))
+}
+
+/// Meant to be called on a blocking thread, the progress is reported while the task runs, the result at the end
+pub fn run_js_task(request: JsTaskRequest, report: impl Fn(JsTaskUpdate) + 'static) {
+    let report = Rc::new(report);
+
+    let mut evaluator = JsEvaluator::with_snippets(request.snippets.as_ref());
+    evaluator.expose_current_note(request.front_matter.as_deref());
+    evaluator.expose_storage(&request.storage);
+    evaluator.expose_task_api({
+        let report = report.clone();
+        move |progress| report(JsTaskUpdate::Progress(progress))
+    });
+
+    let result = evaluator.eval_task(&request.code, request.block_id, request.hash);
+    let storage = evaluator.read_storage();
+
+    report(JsTaskUpdate::Done { result, storage });
+}
+
+/// The note could have been edited while the task was running, so the source block is looked up by its id.
+/// None if it doesn't exist anymore
+pub fn js_task_output_changes(
+    text_structure: &TextStructure,
+    block_id: BlockId,
+    result: BlockEvalResult,
+) -> Option<Vec<TextChange>> {
+    let (_, source_block) = find_js_source_block_by_id(text_structure, block_id)?;
+
+    Some(vec![output_block_change(
+        text_structure,
+        source_block,
+        block_id,
+        result,
+    )])
+}
+
+/// Source blocks that are tasks, with their id if they have one
+pub fn js_task_blocks(
+    text_structure: &TextStructure,
+    text: &str,
+) -> Vec<(SpanIndex, Option<BlockId>)> {
+    text_structure
+        .filter_map_codeblocks(|lang| match JSBlockLang::parse(lang) {
+            Some(JSBlockLang::Source(block_id)) => Some(block_id),
+            _ => None,
+        })
+        .filter(|(index, ..)| block_code(text_structure, text, *index).is_some_and(is_js_task))
+        .map(|(index, _, _, block_id)| (index, block_id))
+        .collect()
+}
+
+fn block_code<'t>(
+    text_structure: &TextStructure,
+    text: &'t str,
+    span_index: SpanIndex,
+) -> Option<&'t str> {
+    text_structure
+        .iterate_immediate_children_of(span_index)
+        .find(|(_, desc)| desc.kind == SpanKind::Text)
+        .map(|(_, desc)| &text[desc.byte_pos.range()])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use crate::{
+        app_actions::AppAction,
+        app_state::{CodeBlockAnnotation, MsgToApp},
+        effects::text_change_effect::apply_text_changes,
+        test_harness::{IOCall, TestHarness},
+    };
+
+    use super::*;
+
+    #[test]
+    pub fn test_js_task_reports_progress_and_result() {
+        let text = r#"```js
+shelv.task(async (progress) => {
+  progress(0.5, "half way");
+  await null;
+  progress(2);
+  return 40 + 2;
+})
+```"#;
+
+        let structure = TextStructure::new(text);
+        let (index, ..) = structure
+            .filter_map_codeblocks(|lang| (lang == "js").then_some(()))
+            .next()
+            .unwrap();
+
+        let (request, id_change) = prepare_js_task(
+            NoteFile::Note(0),
+            index,
+            &structure,
+            text,
+            None,
+            &NoteStorage::default(),
+        )
+        .unwrap();
+
+        assert_eq!(request.block_id, BlockId(1));
+        assert!(id_change.is_some());
+
+        let updates = Rc::new(RefCell::new(vec![]));
+        run_js_task(request, {
+            let updates = updates.clone();
+            move |update| updates.borrow_mut().push(update)
+        });
+
+        let mut updates = updates.take().into_iter();
+
+        let progress: Vec<JsTaskProgress> = updates
+            .by_ref()
+            .take(2)
+            .map(|update| match update {
+                JsTaskUpdate::Progress(progress) => progress,
+                other => panic!("expected progress, got {other:?}"),
+            })
+            .collect();
+
+        assert_eq!(
+            progress,
+            [
+                JsTaskProgress {
+                    fraction: Some(0.5),
+                    message: "half way".to_string()
+                },
+                JsTaskProgress {
+                    fraction: Some(1.0),
+                    message: "".to_string()
+                },
+            ]
+        );
+
+        let Some(JsTaskUpdate::Done { result, .. }) = updates.next() else {
+            panic!("the task didn't finish");
+        };
+
+        let mut text = text.to_string();
+        apply_text_changes(&mut text, None, id_change.into_iter().collect()).unwrap();
+
+        let structure = TextStructure::new(&text);
+        let changes = js_task_output_changes(&structure, BlockId(1), result).unwrap();
+        apply_text_changes(&mut text, None, changes).unwrap();
+
+        assert!(text.starts_with("```js 1\n"), "text: {text}");
+        assert!(text.contains("```\n```js 1 > #"), "text: {text}");
+        assert!(text.ends_with("\n42\n```"), "text: {text}");
+    }
+
+    #[test]
+    pub fn test_regular_blocks_are_not_tasks() {
+        let text = "```js\n1 + 1\n```";
+        let structure = TextStructure::new(text);
+
+        assert_eq!(js_task_blocks(&structure, text), []);
+
+        let (index, ..) = structure
+            .filter_map_codeblocks(|lang| (lang == "js").then_some(()))
+            .next()
+            .unwrap();
+
+        assert!(
+            prepare_js_task(
+                NoteFile::Note(0),
+                index,
+                &structure,
+                text,
+                None,
+                &NoteStorage::default()
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    pub fn test_js_task_shows_progress_until_it_is_done() {
+        let mut harness = TestHarness::new(
+            "```js\nshelv.task(async (progress) => {\n  progress(0.5);\n  return 1 + 1;\n})\n```{||}",
+        );
+
+        let code_block = harness.state.notes[&NoteFile::Note(0)]
+            .derived_state
+            .structure
+            .iter()
+            .find(|(_, desc)| desc.kind == SpanKind::CodeBlock)
+            .map(|(index, _)| index)
+            .unwrap();
+
+        harness.dispatch(AppAction::RunCodeBlock(NoteFile::Note(0), code_block));
+
+        let Some(IOCall::RunJsTask(request)) = harness.io.take_calls().pop() else {
+            panic!("the task wasn't started");
+        };
+        let block_id = request.block_id;
+
+        let annotations = &harness.state.notes[&NoteFile::Note(0)]
+            .derived_state
+            .code_block_annotations;
+        assert!(matches!(
+            annotations.as_slice(),
+            [(_, CodeBlockAnnotation::Progress(_))]
+        ));
+
+        let updates = Rc::new(RefCell::new(vec![]));
+        run_js_task(request, {
+            let updates = updates.clone();
+            move |update| updates.borrow_mut().push(update)
+        });
+
+        for update in updates.take() {
+            harness.dispatch(AppAction::HandleMsgToApp(MsgToApp::JsTaskUpdate {
+                note_file: NoteFile::Note(0),
+                block_id,
+                update,
+            }));
+        }
+
+        let note = &harness.state.notes[&NoteFile::Note(0)];
+        assert!(note.text.starts_with("```js 1\n"), "text: {}", note.text);
+        assert!(note.text.ends_with("\n2\n```"), "text: {}", note.text);
+        assert!(harness.state.js_tasks.is_empty());
+        assert!(matches!(
+            note.derived_state.code_block_annotations.as_slice(),
+            [(_, CodeBlockAnnotation::RunButton)]
+        ));
+    }
+}
@@ -1,5 +1,8 @@
 pub mod js_console_logger;
 pub mod js_module_loader;
+pub mod js_snippets;
+pub mod js_task;
 pub mod note_eval;
 pub mod note_eval_context;
+pub mod note_storage;
 pub mod settings_eval;
@@ -1,18 +1,57 @@
-use boa_engine::{context::HostHooks, Context, Source};
+use std::rc::Rc;
+
+use boa_engine::{
+    builtins::promise::PromiseState, context::HostHooks, js_string, object::builtins::JsPromise,
+    Context, JsArgs, JsError, JsNativeError, JsObject, JsResult, JsValue, NativeFunction, Source,
+};
+use boa_gc::{Finalize, Trace};
 use boa_runtime::Console;
 use smallvec::SmallVec;
 
 use crate::{
     byte_span::ByteSpan,
     effects::text_change_effect::TextChange,
-    text_structure::{SpanIndex, SpanKind, SpanMeta, TextStructure},
+    front_matter::parse_front_matter,
+    text_structure::{CodeBlockMeta, SpanIndex, SpanKind, SpanMeta, TextStructure},
 };
 
 use super::{
     js_console_logger::JsLogCollector,
+    js_snippets::JsSnippets,
+    js_task::{JsTaskProgress, is_js_task},
     note_eval_context::{BlockEvalResult, BlockId, SourceHash},
+    note_storage::{MAX_NOTE_STORAGE_BYTES, NoteStorage},
 };
 
+/// Called with the stored data and the size limit, `set` with `undefined` removes the key
+const STORAGE_JS: &str = r#"(function (data, maxBytes) {
+    return {
+        get(key) {
+            return data[key];
+        },
+        set(key, value) {
+            const prev = data[key];
+            const json = JSON.stringify(value);
+            if (json === undefined) {
+                delete data[key];
+                return;
+            }
+            data[key] = JSON.parse(json);
+            if (JSON.stringify(data).length > maxBytes) {
+                if (prev === undefined) {
+                    delete data[key];
+                } else {
+                    data[key] = prev;
+                }
+                throw new RangeError(`shelv.storage is limited to ${maxBytes} bytes per note`);
+            }
+        },
+        toJSON() {
+            return data;
+        },
+    };
+})"#;
+
 #[derive(Debug, PartialEq)]
 pub enum JSBlockLang {
     Source(Option<BlockId>),
@@ -63,6 +102,16 @@ impl JSBlockLang {
     }
 }
 
+/// `shelv.task` passes the progress reporter to the task, the promise of the task is the result of the block
+const TASK_JS: &str = "shelv.task = (task) => task(__shelvTaskProgress);";
+
+/// Captured by the native `progress` function, closures are not traced by the garbage collector
+#[derive(Trace, Finalize)]
+struct ProgressReporter {
+    #[unsafe_ignore_trace]
+    report: Rc<dyn Fn(JsTaskProgress)>,
+}
+
 pub struct JsEvaluator {
     context: Context,
     console_logger: JsLogCollector,
@@ -102,8 +151,140 @@ impl JsEvaluator {
         }
     }
 
+    /// Evaluator with the shared snippets already defined in the context
+    pub fn with_snippets(snippets: Option<&JsSnippets>) -> Self {
+        let mut evaluator = Self::new();
+
+        if let Some(snippets) = snippets {
+            if let Err(err) = evaluator.eval_snippet(&snippets.code) {
+                println!("failed to evaluate js snippets err={err}");
+            }
+        }
+
+        evaluator
+    }
+
+    /// Exposes the note to js blocks as `shelv.currentNote`,
+    /// `frontmatter` is an empty object if the note doesn't have one
+    pub fn expose_current_note(&mut self, front_matter: Option<&str>) {
+        let front_matter = front_matter.map(parse_front_matter).unwrap_or_default();
+        let shelv = serde_json::json!({ "currentNote": { "frontmatter": front_matter } });
+
+        let registered = JsValue::from_json(&shelv, &mut self.context).and_then(|shelv| {
+            self.context.register_global_property(
+                js_string!("shelv"),
+                shelv,
+                boa_engine::property::Attribute::all(),
+            )
+        });
+
+        if let Err(err) = registered {
+            println!("failed to expose the current note to js err={err}");
+        }
+    }
+
+    /// Exposes the note storage as `shelv.storage`, has to be called after `expose_current_note`
+    pub fn expose_storage(&mut self, storage: &NoteStorage) {
+        let code = format!(
+            "shelv.storage = {STORAGE_JS}({}, {MAX_NOTE_STORAGE_BYTES});",
+            storage.to_json()
+        );
+
+        if let Err(err) = self.eval_snippet(&code) {
+            println!("failed to expose the note storage to js err={err}");
+        }
+    }
+
+    /// Storage as it was left by the evaluated blocks, None if it can't be read back
+    pub fn read_storage(&mut self) -> Option<NoteStorage> {
+        let json = self
+            .context
+            .eval(Source::from_bytes("JSON.stringify(shelv.storage)"))
+            .ok()?;
+
+        let json = json.as_string()?.to_std_string().ok()?;
+
+        NoteStorage::from_json(&json)
+            .map_err(|err| println!("failed to read the note storage err={err}"))
+            .ok()
+    }
+
+    /// Only the definitions matter, so the resulting value and logs are dropped
+    pub fn eval_snippet(&mut self, code: &str) -> Result<(), String> {
+        let result = self.context.eval(Source::from_bytes(code));
+        let _ = self.console_logger.flush();
+
+        result.map(|_| ()).map_err(|err| format!("{:#}", err))
+    }
+
+    /// Exposes `shelv.task(async (progress) => ...)`, it runs the task right away and returns its promise.
+    /// `progress(fraction, message)` is passed to `report`, has to be called after `expose_current_note`
+    pub fn expose_task_api(&mut self, report: impl Fn(JsTaskProgress) + 'static) {
+        let report_progress = NativeFunction::from_copy_closure_with_captures(
+            |_, args, reporter: &ProgressReporter, context| {
+                let fraction = args
+                    .get_or_undefined(0)
+                    .as_number()
+                    .map(|fraction| fraction.clamp(0., 1.) as f32);
+
+                let message = match args.get_or_undefined(1) {
+                    JsValue::Undefined => String::new(),
+                    message => message.to_string(context)?.to_std_string_escaped(),
+                };
+
+                (reporter.report)(JsTaskProgress { fraction, message });
+                Ok(JsValue::undefined())
+            },
+            ProgressReporter {
+                report: Rc::new(report),
+            },
+        );
+
+        let registered = self
+            .context
+            .register_global_builtin_callable(js_string!("__shelvTaskProgress"), 2, report_progress)
+            .map_err(|err| format!("{:#}", err))
+            .and_then(|_| self.eval_snippet(TASK_JS));
+
+        if let Err(err) = registered {
+            println!("failed to expose the task api to js err={err}");
+        }
+    }
+
     pub fn eval_block(&mut self, body: &str, id: BlockId, hash: SourceHash) -> BlockEvalResult {
         let result = self.context.eval(Source::from_bytes(body));
+        self.block_result(result, id, hash)
+    }
+
+    /// The block evaluates to the promise of `shelv.task`, the job queue is run until it settles
+    pub fn eval_task(&mut self, body: &str, id: BlockId, hash: SourceHash) -> BlockEvalResult {
+        let result = self.context.eval(Source::from_bytes(body));
+        self.context.run_jobs();
+
+        let result = result.and_then(|value| {
+            let promise = value
+                .as_object()
+                .and_then(|object| JsPromise::from_object(object.clone()).ok());
+
+            match promise.map(|promise| promise.state()) {
+                Some(PromiseState::Fulfilled(value)) => Ok(value),
+                Some(PromiseState::Rejected(err)) => Err(JsError::from_opaque(err)),
+                Some(PromiseState::Pending) => Err(JsNativeError::error()
+                    .with_message("the task is still pending, nothing is left to resolve it")
+                    .into()),
+                None => Ok(value),
+            }
+        });
+
+        self.block_result(result, id, hash)
+    }
+
+    fn block_result(
+        &mut self,
+        result: JsResult<JsValue>,
+        id: BlockId,
+        hash: SourceHash,
+    ) -> BlockEvalResult {
         let logged = self.console_logger.flush().ok();
 
         BlockEvalResult {
@@ -115,7 +296,7 @@ impl JsEvaluator {
                 Ok(res) => format!(
                     "{}{}",
                     logged.unwrap_or_default(),
-                    res.display().to_string()
+                    self.display_result(&res)
                 ),
 
                 Err(err) => format!("{}{:#}", logged.unwrap_or_default(), err),
@@ -124,6 +305,47 @@ impl JsEvaluator {
             output_lang: JSBlockLang::output_lang(id, hash),
         }
     }
+
+    /// Plain objects and arrays (or strings holding them) are printed as formatted JSON,
+    /// everything else as boa displays it
+    fn display_result(&mut self, value: &JsValue) -> String {
+        self.try_pretty_json(value)
+            .unwrap_or_else(|| value.display().to_string())
+    }
+
+    fn try_pretty_json(&mut self, value: &JsValue) -> Option<String> {
+        let json = match value {
+            JsValue::String(string) => serde_json::from_str(&string.to_std_string().ok()?).ok()?,
+            JsValue::Object(object) => {
+                let object_prototype = self
+                    .context
+                    .intrinsics()
+                    .constructors()
+                    .object()
+                    .prototype();
+
+                // e.g. Map or Date would be serialized as "{}"
+                let is_plain = object.is_array()
+                    || object
+                        .prototype()
+                        .is_some_and(|prototype| JsObject::equals(&prototype, &object_prototype));
+
+                if !is_plain {
+                    return None;
+                }
+
+                value.to_json(&mut self.context).ok()?
+            }
+            _ => return None,
+        };
+
+        match json {
+            serde_json::Value::Object(_) | serde_json::Value::Array(_) => {
+                serde_json::to_string_pretty(&json).ok()
+            }
+            _ => None,
+        }
+    }
 }
 
 // Evaluate a single JavaScript block
@@ -131,8 +353,13 @@ pub fn evaluate_js_block(
     span_index: SpanIndex,
     text_structure: &TextStructure,
     text: &str,
+    snippets: Option<&JsSnippets>,
+    storage: &mut NoteStorage,
 ) -> Option<Vec<TextChange>> {
-    let mut evaluator = JsEvaluator::new();
+    let front_matter = text_structure.front_matter(text);
+    let mut evaluator = JsEvaluator::with_snippets(snippets);
+    evaluator.expose_current_note(front_matter);
+    evaluator.expose_storage(storage);
 
     let Some((desc, SpanMeta::CodeBlock(code_meta))) =
         text_structure.get_span_with_meta(span_index)
@@ -151,48 +378,97 @@ pub fn evaluate_js_block(
 
     let mut changes = SmallVec::<[TextChange; 1]>::new();
 
-    // Check if this is a source block that needs ID assignment
-    let (block_id, source_change) = match JSBlockLang::parse(&code_meta.lang) {
-        Some(JSBlockLang::Source(Some(existing_id))) => (existing_id, None),
-        Some(JSBlockLang::Source(None)) => {
+    let (block_id, source_change) = assign_block_id(text_structure, code_meta)?;
+
+    if let Some(source_lang_change) = source_change {
+        changes.push(source_lang_change);
+    }
+
+    let hash = source_hash(code, snippets, front_matter);
+    let eval_result = evaluator.eval_block(code, block_id, hash);
+
+    if let Some(updated) = evaluator.read_storage() {
+        *storage = updated;
+    }
+
+    changes.push(output_block_change(
+        text_structure,
+        desc.byte_pos,
+        block_id,
+        eval_result,
+    ));
+
+    Some(changes.into_vec())
+}
+
+/// The id of a source block, blocks without one get the next available id (the change of the lang)
+pub fn assign_block_id(
+    text_structure: &TextStructure,
+    code_meta: &CodeBlockMeta,
+) -> Option<(BlockId, Option<TextChange>)> {
+    match JSBlockLang::parse(&code_meta.lang)? {
+        JSBlockLang::Source(Some(existing_id)) => Some((existing_id, None)),
+        JSBlockLang::Source(None) => {
             let next_id = find_next_available_block_id(text_structure);
 
-            (
+            Some((
                 next_id,
                 Some(TextChange::Insert(
                     code_meta.lang_byte_span,
                     JSBlockLang::source_lang_with_id(next_id),
                 )),
-            )
+            ))
         }
-        _ => return None,
-    };
-
-    if let Some(source_lang_change) = source_change {
-        changes.push(source_lang_change);
+        JSBlockLang::Output(..) => None,
     }
+}
 
-    let hash = SourceHash::from(code);
-    let eval_result = evaluator.eval_block(code, block_id, hash);
+/// Replaces the existing output block of the source block, or inserts a new one right after it
+pub fn output_block_change(
+    text_structure: &TextStructure,
+    source_block: ByteSpan,
+    block_id: BlockId,
+    eval_result: BlockEvalResult,
+) -> TextChange {
     let output_block = print_output_block(eval_result);
 
-    let output_block_range = find_js_output_block_by_id(text_structure, block_id);
-
-    if let Some((range, _hash)) = output_block_range {
-        // Replace the existing output block
-        changes.push(TextChange::Insert(range, output_block));
-    } else {
-        // Insert a new output block
-        changes.push(TextChange::Insert(
-            ByteSpan::point(desc.byte_pos.end),
+    match find_js_output_block_by_id(text_structure, block_id) {
+        Some((range, _hash)) => TextChange::Insert(range, output_block),
+        None => TextChange::Insert(
+            ByteSpan::point(source_block.end),
             "\n".to_string() + &output_block,
-        ));
+        ),
     }
+}
 
-    if changes.is_empty() {
-        None
-    } else {
-        Some(changes.into_vec())
+/// Source block with the id, e.g. "js 5", its index and the span of the entire block
+pub fn find_js_source_block_by_id(
+    text_structure: &TextStructure,
+    target_id: BlockId,
+) -> Option<(SpanIndex, ByteSpan)> {
+    text_structure
+        .filter_map_codeblocks(|lang| match JSBlockLang::parse(lang) {
+            Some(JSBlockLang::Source(Some(block_id))) if block_id == target_id => Some(()),
+            _ => None,
+        })
+        .next()
+        .map(|(index, desc, _, _)| (index, desc.byte_pos))
+}
+
+/// Outputs depend on the shared snippets and the front matter too, so they are a part of the hash
+pub fn source_hash(
+    code: &str,
+    snippets: Option<&JsSnippets>,
+    front_matter: Option<&str>,
+) -> SourceHash {
+    match (snippets, front_matter) {
+        (None, None) => SourceHash::from(code),
+        (snippets, front_matter) => SourceHash::from(&format!(
+            "{}{}{}",
+            snippets.map(|s| s.hash.to_string()).unwrap_or_default(),
+            front_matter.unwrap_or_default(),
+            code
+        )),
     }
 }
 
@@ -379,8 +655,9 @@ Error: yo!
                 .next()
                 .map(|(index, _, _, _)| index);
 
-            let changes =
-                js_block_index.and_then(|index| evaluate_js_block(index, &structure, &text));
+            let changes = js_block_index.and_then(|index| {
+                evaluate_js_block(index, &structure, &text, None, &mut NoteStorage::default())
+            });
 
             match (changes, expected_output) {
                 (Some(changes), Some(expected_output)) => {
@@ -400,6 +677,94 @@ Error: yo!
         }
     }
 
+    #[test]
+    fn test_front_matter_is_exposed_to_js() {
+        let text = r#"---
+title: Groceries
+tags: [food, weekly]
+---
+```js
+shelv.currentNote.frontmatter.tags.join(", ")
+```
+"#;
+        let structure = TextStructure::new(text);
+        let (index, ..) = structure
+            .filter_map_codeblocks(|lang| (lang == "js").then_some(()))
+            .next()
+            .unwrap();
+
+        let changes =
+            evaluate_js_block(index, &structure, text, None, &mut NoteStorage::default()).unwrap();
+        let output = changes
+            .iter()
+            .map(|TextChange::Insert(_, text)| text.as_str())
+            .collect::<String>();
+
+        assert!(output.contains(r#""food, weekly""#), "{output}");
+    }
+
+    #[test]
+    fn test_json_results_are_pretty_printed() {
+        let test_cases = [
+            (
+                "({ a: 1, b: [true] })",
+                "{\n  \"a\": 1,\n  \"b\": [\n    true\n  ]\n}",
+            ),
+            ("JSON.stringify([1, 2])", "[\n  1,\n  2\n]"),
+            ("'[not json'", "\"[not json\""),
+        ];
+
+        for (code, expected) in test_cases {
+            let mut evaluator = JsEvaluator::new();
+            let result = evaluator.eval_block(code, BlockId(1), SourceHash::from(code));
+            assert_eq!(result.body, expected, "{code}");
+        }
+    }
+
+    #[test]
+    fn test_storage_is_exposed_to_js() {
+        let text = r#"```js
+const runs = (shelv.storage.get("runs") ?? 0) + 1;
+shelv.storage.set("runs", runs);
+runs
+```
+"#;
+        let structure = TextStructure::new(text);
+        let (index, ..) = structure
+            .filter_map_codeblocks(|lang| (lang == "js").then_some(()))
+            .next()
+            .unwrap();
+
+        let mut storage = NoteStorage::from_json(r#"{"runs": 41}"#).unwrap();
+        let changes = evaluate_js_block(index, &structure, text, None, &mut storage).unwrap();
+        let output = changes
+            .iter()
+            .map(|TextChange::Insert(_, text)| text.as_str())
+            .collect::<String>();
+
+        assert!(output.contains("42"), "{output}");
+        assert_eq!(storage, NoteStorage::from_json(r#"{"runs": 42}"#).unwrap());
+
+        let text = r#"```js
+shelv.storage.set("big", "a".repeat(20000))
+```
+"#;
+        let structure = TextStructure::new(text);
+        let (index, ..) = structure
+            .filter_map_codeblocks(|lang| (lang == "js").then_some(()))
+            .next()
+            .unwrap();
+
+        let changes = evaluate_js_block(index, &structure, text, None, &mut storage).unwrap();
+        let output = changes
+            .iter()
+            .map(|TextChange::Insert(_, text)| text.as_str())
+            .collect::<String>();
+
+        assert!(output.contains("RangeError"), "{output}");
+        assert_eq!(storage, NoteStorage::from_json(r#"{"runs": 42}"#).unwrap());
+    }
+
     #[test]
     fn test_javascript_block_language_parse() {
         use super::JSBlockLang;
@@ -436,8 +801,13 @@ Error: yo!
 pub fn evaluate_all_live_js_blocks(
     text_structure: &TextStructure,
     text: &str,
+    snippets: Option<&JsSnippets>,
+    storage: &mut NoteStorage,
 ) -> Option<Vec<TextChange>> {
-    let mut evaluator = JsEvaluator::new();
+    let front_matter = text_structure.front_matter(text);
+    let mut evaluator = JsEvaluator::with_snippets(snippets);
+    evaluator.expose_current_note(front_matter);
+    evaluator.expose_storage(storage);
     let mut all_changes = Vec::new();
 
     let live_blocks = text_structure
@@ -454,11 +824,12 @@ pub fn evaluate_all_live_js_blocks(
             .find(|(_, desc)| desc.kind == SpanKind::Text)?;
 
         let code = &text[code_text_desc.byte_pos.range()];
-        if code.trim().is_empty() {
+        // tasks run in the background, only when they are started explicitly
+        if code.trim().is_empty() || is_js_task(code) {
             continue;
         }
 
-        let source_hash = SourceHash::from(code);
+        let source_hash = source_hash(code, snippets, front_matter);
 
         let output_block_range = find_js_output_block_by_id(text_structure, block_id);
         let needs_update = if let Some((_output_range, existing_output_hash)) = output_block_range {
@@ -478,6 +849,10 @@ pub fn evaluate_all_live_js_blocks(
         }
     }
 
+    if let Some(updated) = evaluator.read_storage() {
+        *storage = updated;
+    }
+
     if all_changes.is_empty() {
         None
     } else {
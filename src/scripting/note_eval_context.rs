@@ -19,7 +19,7 @@ impl SourceHash {
     }
 }
 
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
 pub struct BlockId(pub u32);
 
 impl BlockId {
@@ -28,6 +28,7 @@ impl BlockId {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct BlockEvalResult {
     pub body: String,
     pub output_lang: String,
@@ -0,0 +1,109 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::persistent_state::NoteFile;
+
+/// Kept next to the notes, in the persistence folder
+pub const SCRIPT_STORAGE_FILE_NAME: &str = "script-storage.json";
+
+/// Serialized size limit of a single note storage, writes over it are rejected
+pub const MAX_NOTE_STORAGE_BYTES: usize = 16 * 1024;
+
+/// Key-value store of js blocks in a note, exposed as `shelv.storage`
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct NoteStorage(Map<String, Value>);
+
+impl NoteStorage {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.0).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Storage written by a script, rejected if it is over the size limit
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        if json.len() > MAX_NOTE_STORAGE_BYTES {
+            return Err(format!(
+                "storage is limited to {MAX_NOTE_STORAGE_BYTES} bytes per note, got {} bytes",
+                json.len()
+            ));
+        }
+
+        serde_json::from_str(json).map_err(|err| err.to_string())
+    }
+}
+
+/// Storages of all notes, persisted in `SCRIPT_STORAGE_FILE_NAME`
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct ScriptStorage(BTreeMap<String, NoteStorage>);
+
+impl ScriptStorage {
+    /// Missing or unreadable file means an empty storage
+    pub fn load(folder: &Path) -> Self {
+        fs::read_to_string(folder.join(SCRIPT_STORAGE_FILE_NAME))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.0).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    pub fn note(&self, note_file: NoteFile) -> NoteStorage {
+        self.0
+            .get(&storage_key(note_file))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Returns true if the storage of the note was changed
+    pub fn update(&mut self, note_file: NoteFile, storage: NoteStorage) -> bool {
+        let key = storage_key(note_file);
+        if self.0.get(&key).cloned().unwrap_or_default() == storage {
+            return false;
+        }
+
+        match storage.0.is_empty() {
+            true => self.0.remove(&key),
+            false => self.0.insert(key, storage),
+        };
+
+        true
+    }
+}
+
+fn storage_key(note_file: NoteFile) -> String {
+    match note_file {
+        NoteFile::Note(zero_based_index) => format!("note-{}", zero_based_index + 1),
+        NoteFile::Settings => "settings".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_script_storage() {
+        let mut storage = ScriptStorage::default();
+        let note = NoteFile::Note(0);
+
+        let counter = NoteStorage::from_json(r#"{"counter": 1}"#).unwrap();
+        assert!(storage.update(note, counter.clone()));
+        assert!(!storage.update(note, counter.clone()));
+        assert_eq!(storage.note(note), counter);
+        assert_eq!(storage.note(NoteFile::Note(1)), NoteStorage::default());
+
+        let restored: ScriptStorage = serde_json::from_str(&storage.to_json()).unwrap();
+        assert_eq!(restored, storage);
+
+        let too_big = format!(r#"{{"key": "{}"}}"#, "a".repeat(MAX_NOTE_STORAGE_BYTES));
+        assert!(NoteStorage::from_json(&too_big).is_err());
+
+        assert!(storage.update(note, NoteStorage::default()));
+        assert_eq!(storage, ScriptStorage::default());
+    }
+}
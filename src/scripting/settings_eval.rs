@@ -1,9 +1,16 @@
-use std::{error::Error, rc::Rc};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    path::PathBuf,
+    rc::Rc,
+};
 
 use boa_engine::{
-    Context, JsError, JsValue, Module, builtins::promise::PromiseState, property::PropertyKey,
+    Context, JsError, JsValue, Module, builtins::promise::PromiseState, js_string,
+    property::PropertyKey,
 };
 use boa_parser::Source;
+use eframe::egui::KeyboardShortcut;
 use itertools::Itertools;
 use smallvec::SmallVec;
 
@@ -11,11 +18,16 @@ use crate::{
     app_actions::AppIO,
     app_state::{CodeBlockAnnotation, MsgToApp},
     command::{
-        AppFocus, CommandInstance, CommandInstruction, CommandList, CommandScope, ForwardToChild,
-        ScriptCall, SlashPaletteCmd, TextSource,
+        AppFocus, CommandInstruction, CommandList, CommandScope, ForwardToChild, FrameHotkeys,
+        ScriptCall, ScriptCallArgument, SlashPaletteCmd, TextSource,
     },
+    commands::insert_text::call_replace_text,
+    persistent_state::NoteFile,
     settings_parsing::{
-        GlobalBinding, GlobalCommand, LlmSettings, LocalBinding, parse_top_level_settings_block,
+        CommandLogSettings, DevSettings, EditorSettings, GlobalBinding, GlobalCommand, LlmSettings,
+        LocalBinding, NoteMirror, NoteTemplate, QuietHoursSettings, SlashPaletteSettings,
+        ThemeSettings, WindowSettings, format_mac_shortcut_with_symbols, parse_keyboard_shortcut,
+        parse_top_level_settings_block,
     },
     text_structure::{SpanIndex, SpanKind, TextStructure},
     theme::{AppTheme, FontTheme},
@@ -30,6 +42,10 @@ use super::{
 pub const SETTINGS_BLOCK_LANG: &str = "kdl";
 pub const SETTINGS_SCRIPT_BLOCK_LANG: &str = "js";
 
+/// Export that binds shortcuts to exported functions, e.g.
+/// `export const hotkeys = { "Cmd Shift U": makeUppercase }`
+pub const SCRIPT_HOTKEYS_EXPORT: &str = "hotkeys";
+
 #[derive(Debug, Clone, Copy)]
 pub enum ScriptExportType {
     Str,
@@ -42,6 +58,34 @@ pub struct Scripts {
     pub js_cx: Context,
     pub module_loader: Rc<InMemoryModuleLoader>,
     pub script_blocks: Vec<SriptBlock>,
+    pub hotkeys: Vec<ScriptHotkey>,
+}
+
+/// The OS refused to register a global shortcut from settings, e.g. it is taken by another app
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlobalHotkeyWarning {
+    pub shortcut: KeyboardShortcut,
+    pub error: String,
+}
+
+impl GlobalHotkeyWarning {
+    pub fn title(&self) -> &'static str {
+        "Hotkey Not Registered"
+    }
+
+    pub fn description(&self) -> String {
+        format!(
+            "{} doesn't show/hide Shelv: {}. It might be used by another app, click to bind a different shortcut in settings",
+            format_mac_shortcut_with_symbols(self.shortcut),
+            self.error
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptHotkey {
+    pub shortcut: KeyboardShortcut,
+    pub func_name: String,
 }
 
 impl Scripts {
@@ -63,6 +107,30 @@ impl Scripts {
             })
             .collect()
     }
+
+    /// Hotkeys are re-added every frame, so they go away as soon as they are removed from settings.
+    /// The selection is passed to the function and replaced with its result, same as `callFunc`
+    pub fn add_frame_hotkeys(&self, frame_hotkeys: &mut FrameHotkeys) {
+        for ScriptHotkey {
+            shortcut,
+            func_name,
+        } in self.hotkeys.iter()
+        {
+            let source = TextSource::Script(ScriptCall {
+                func_name: func_name.clone(),
+                arguments: SmallVec::from_buf([ScriptCallArgument::Selection]),
+            });
+
+            frame_hotkeys.add_key_with_modifier(
+                shortcut.modifiers,
+                shortcut.logical_key,
+                move |ctx| match ctx.app_focus.internal_focus {
+                    Some(AppFocus::NoteEditor) => call_replace_text(&source, ctx),
+                    _ => SmallVec::new(),
+                },
+            );
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -98,6 +166,7 @@ impl Scripts {
             module_loader: loader,
             js_cx: context,
             script_blocks: vec![],
+            hotkeys: vec![],
         }
     }
 }
@@ -182,12 +251,22 @@ fn eval_script_block(
         }
     };
 
+    let hotkeys = match read_script_hotkeys(&module, &exports, &mut scripts.js_cx) {
+        Ok(hotkeys) => hotkeys,
+        Err(message) => {
+            return CodeBlockAnnotation::Error {
+                title: format!("Invalid '{SCRIPT_HOTKEYS_EXPORT}' export"),
+                message,
+            };
+        }
+    };
+
     let hash = SourceHash::from(block_body);
     scripts
         .module_loader
         .insert(format!("{}", hash.to_string()), module);
 
-    let body = match exports.as_slice() {
+    let mut body = match exports.as_slice() {
         [] => "Block was evaluated by no exports were found".to_string(),
         exports => ["Registered exports:".to_string()]
             .into_iter()
@@ -207,9 +286,103 @@ fn eval_script_block(
         exports,
     });
 
+    // the functions can come from this block or any block above
+    for hotkey in hotkeys.iter() {
+        let is_func = scripts
+            .find_exports(&hotkey.func_name)
+            .iter()
+            .any(|(_, _, export_type)| matches!(export_type, ScriptExportType::Func));
+
+        if !is_func {
+            return CodeBlockAnnotation::Error {
+                title: format!("Invalid '{SCRIPT_HOTKEYS_EXPORT}' export"),
+                message: format!(
+                    "No exported function '{}' was found in js blocks",
+                    hotkey.func_name
+                ),
+            };
+        }
+    }
+
+    if !hotkeys.is_empty() {
+        body = ["Registered hotkeys:".to_string()]
+            .into_iter()
+            .chain(hotkeys.iter().map(|hotkey| {
+                format!(
+                    "{} -> {}",
+                    format_mac_shortcut_with_symbols(hotkey.shortcut),
+                    hotkey.func_name
+                )
+            }))
+            .join("\n\t");
+    }
+
+    scripts.hotkeys.extend(hotkeys);
+
     CodeBlockAnnotation::Applied { message: body }
 }
 
+/// Reads `{ "Cmd Shift U": "makeUppercase" }`, the function itself can be used instead of its name
+fn read_script_hotkeys(
+    module: &Module,
+    exports: &[ModuleExport],
+    context: &mut Context,
+) -> Result<Vec<ScriptHotkey>, String> {
+    let Some(export) = exports
+        .iter()
+        .find(|export| export.name == SCRIPT_HOTKEYS_EXPORT)
+    else {
+        return Ok(vec![]);
+    };
+
+    let namespace = module.namespace(context);
+    let value = namespace
+        .get(export.key.clone(), context)
+        .map_err(|err| err.to_string())?;
+
+    let Some(object) = value.as_object() else {
+        return Err(format!(
+            "'{SCRIPT_HOTKEYS_EXPORT}' must be an object, e.g. {{ \"Cmd Shift U\": \"makeUppercase\" }}"
+        ));
+    };
+
+    let mut hotkeys = vec![];
+    for key in object
+        .own_property_keys(context)
+        .map_err(|err| err.to_string())?
+    {
+        let shortcut_str = key.to_string();
+        let shortcut = parse_keyboard_shortcut(&shortcut_str)
+            .map_err(|err| format!("'{shortcut_str}': {err}"))?;
+
+        let target = object.get(key, context).map_err(|err| err.to_string())?;
+
+        let func_name = match target.as_callable() {
+            Some(func) => func
+                .get(js_string!("name"), context)
+                .map_err(|err| err.to_string())?,
+            None => target,
+        };
+
+        let Some(func_name) = func_name
+            .as_string()
+            .and_then(|name| name.to_std_string().ok())
+            .filter(|name| !name.is_empty())
+        else {
+            return Err(format!(
+                "'{shortcut_str}' has to be bound to an exported function or its name"
+            ));
+        };
+
+        hotkeys.push(ScriptHotkey {
+            shortcut,
+            func_name,
+        });
+    }
+
+    Ok(hotkeys)
+}
+
 pub fn eval_kdl_in_settings_note<IO: AppIO>(
     text: &str,
     text_structure: &TextStructure,
@@ -289,9 +462,16 @@ fn eval_settings_block<IO: AppIO>(
                             "error registering global {shortcut:?} to show/hide Shelv, err = {err:?}"
                         );
 
+                        *eval_ctx.global_hotkey_warning = Some(GlobalHotkeyWarning {
+                            shortcut,
+                            error: err.clone(),
+                        });
+
                         return CodeBlockAnnotation::Error {
                             title: "OS refused to register shortcut".to_string(),
-                            message: err,
+                            message: format!(
+                                "{err}\nIt might be used by another app, try a different shortcut"
+                            ),
                         };
                     }
                 }
@@ -368,22 +548,107 @@ fn eval_settings_block<IO: AppIO>(
 
         eval_ctx
             .cmd_list
-            .add_editor_cmd(CommandInstance::user_defined(
-                validated_instruction.clone(),
-                shortcut.map(|s| s.value()),
-                CommandScope::Focus(AppFocus::NoteEditor),
-            ));
+            .bind_user_cmd(validated_instruction.clone(), shortcut.map(|s| s.value()));
     }
 
     if let Some(last_llm_settings) = settings.llm_settings {
         *eval_ctx.llm_settings = Some(last_llm_settings);
     }
 
+    if let Some(command_log_settings) = settings.command_log {
+        *eval_ctx.command_log_settings = Some(command_log_settings);
+    }
+
+    if let Some(quiet_hours) = settings.quiet_hours {
+        *eval_ctx.quiet_hours = Some(quiet_hours);
+    }
+
+    if let Some(slash_palette_settings) = settings.slash_palette {
+        *eval_ctx.slash_palette_settings = Some(slash_palette_settings);
+    }
+
+    if let Some(hints) = settings.hints {
+        eval_ctx.cmd_list.set_promoted_commands(hints.commands);
+    }
+
+    if let Some(dev_settings) = settings.dev {
+        *eval_ctx.dev_settings = Some(dev_settings);
+    }
+
+    if let Some(window_settings) = settings.window {
+        if !window_settings.hide_on_escape {
+            // user bindings of `HideApp` stay, only the default Escape one is dropped
+            eval_ctx
+                .cmd_list
+                .unbind_built_in(&CommandInstruction::HideApp);
+        }
+        *eval_ctx.window_settings = Some(window_settings);
+    }
+
+    if let Some(editor_settings) = settings.editor {
+        *eval_ctx.editor_settings = Some(editor_settings);
+    }
+
+    if let Some(theme_settings) = settings.theme {
+        *eval_ctx.theme_settings = Some(theme_settings);
+    }
+
+    for NoteMirror { shelf, path } in settings.mirrors {
+        let Some(resolved) = resolve_mirror_path(&path) else {
+            return CodeBlockAnnotation::Error {
+                title: "Invalid mirror path".to_string(),
+                message: format!("'{path}' has to be absolute, e.g. \"~/notes/ideas.md\""),
+            };
+        };
+
+        if shelf == 0 {
+            return CodeBlockAnnotation::Error {
+                title: "Invalid mirror".to_string(),
+                message: "Shelves are numbered from 1".to_string(),
+            };
+        }
+
+        let note_file = NoteFile::Note(shelf - 1);
+
+        // the copy would be either unreadable or the plain text of the note
+        if eval_ctx.encrypted_notes.contains(&note_file) {
+            return CodeBlockAnnotation::Error {
+                title: "Invalid mirror".to_string(),
+                message: format!("Shelf {shelf} is encrypted, encrypted shelves can't be mirrored"),
+            };
+        }
+
+        eval_ctx.note_mirrors.insert(note_file, resolved);
+    }
+
+    for NoteTemplate { shelf, text } in settings.templates {
+        if shelf == 0 {
+            return CodeBlockAnnotation::Error {
+                title: "Invalid template".to_string(),
+                message: "Shelves are numbered from 1".to_string(),
+            };
+        }
+
+        eval_ctx
+            .note_templates
+            .insert(NoteFile::Note(shelf - 1), text);
+    }
+
     CodeBlockAnnotation::Applied {
         message: "Applied".to_string(),
     }
 }
 
+/// `~/` is expanded to the home folder, relative paths are rejected as there is no obvious folder for them
+fn resolve_mirror_path(path: &str) -> Option<PathBuf> {
+    let path = match path.strip_prefix("~/") {
+        Some(rest) => directories_next::BaseDirs::new()?.home_dir().join(rest),
+        None => PathBuf::from(path),
+    };
+
+    path.is_absolute().then_some(path)
+}
+
 // ------- KDL settings eval -------
 pub struct SettingsNoteEvalContext<'cx, IO: AppIO> {
     // parsed_bindings: Vec<Result<TopLevelKdlSettings, SettingsParseError>>,
@@ -391,6 +656,17 @@ pub struct SettingsNoteEvalContext<'cx, IO: AppIO> {
     pub scripts: &'cx Scripts,
     pub app_io: &'cx mut IO,
     pub llm_settings: &'cx mut Option<LlmSettings>,
+    pub command_log_settings: &'cx mut Option<CommandLogSettings>,
+    pub quiet_hours: &'cx mut Option<QuietHoursSettings>,
+    pub slash_palette_settings: &'cx mut Option<SlashPaletteSettings>,
+    pub dev_settings: &'cx mut Option<DevSettings>,
+    pub window_settings: &'cx mut Option<WindowSettings>,
+    pub editor_settings: &'cx mut Option<EditorSettings>,
+    pub theme_settings: &'cx mut Option<ThemeSettings>,
+    pub note_mirrors: &'cx mut BTreeMap<NoteFile, PathBuf>,
+    pub encrypted_notes: &'cx BTreeSet<NoteFile>,
+    pub note_templates: &'cx mut BTreeMap<NoteFile, String>,
+    pub global_hotkey_warning: &'cx mut Option<GlobalHotkeyWarning>,
 }
 
 pub fn parse_and_eval_settings_script_block(
@@ -475,3 +751,192 @@ fn test_run() {
     println!("result = {:#?}", result.as_ref().map(|r| &r.0));
     assert!(result.is_ok(), "run() should execute successfully");
 }
+
+#[cfg(test)]
+mod tests {
+    use eframe::egui::{Key, Modifiers};
+
+    use crate::{
+        app_actions::{AppAction, HideMode},
+        command::PROMOTED_COMMANDS,
+        effects::text_change_effect::TextChange,
+        test_harness::{IOCall, TestHarness},
+    };
+
+    use super::*;
+
+    #[test]
+    pub fn test_script_hotkeys() {
+        let text = r#"
+```js
+export function makeUppercase(selection) {
+    return selection.toUpperCase();
+}
+```
+
+```js
+export const hotkeys = {
+    "Cmd Shift U": makeUppercase,
+    "Ctrl D": "makeUppercase",
+};
+```
+"#;
+
+        let structure = TextStructure::new(text);
+        let (scripts, annotations) = eval_js_scripts_in_settings_note(text, &structure);
+
+        assert_eq!(
+            scripts.hotkeys,
+            vec![
+                ScriptHotkey {
+                    shortcut: KeyboardShortcut::new(Modifiers::MAC_CMD | Modifiers::SHIFT, Key::U),
+                    func_name: "makeUppercase".to_string(),
+                },
+                ScriptHotkey {
+                    shortcut: KeyboardShortcut::new(Modifiers::CTRL, Key::D),
+                    func_name: "makeUppercase".to_string(),
+                },
+            ]
+        );
+
+        assert!(matches!(
+            annotations.as_slice(),
+            [
+                (_, CodeBlockAnnotation::Applied { .. }),
+                (_, CodeBlockAnnotation::Applied { .. })
+            ]
+        ));
+
+        for invalid in [
+            r#"export const hotkeys = { "Cmd Shift L": "makeLowercase" };"#,
+            r#"export const hotkeys = { "Cmd Shift": () => "text" };"#,
+            r#"export const hotkeys = "Cmd Shift U";"#,
+        ] {
+            let text = format!("```js\n{invalid}\n```\n");
+            let structure = TextStructure::new(&text);
+            let (scripts, annotations) = eval_js_scripts_in_settings_note(&text, &structure);
+
+            assert!(scripts.hotkeys.is_empty());
+            assert!(matches!(
+                annotations.as_slice(),
+                [(_, CodeBlockAnnotation::Error { .. })]
+            ));
+        }
+    }
+
+    #[test]
+    pub fn test_refused_global_hotkey_is_reported() {
+        let mut harness = TestHarness::new("note{||}");
+        let shortcut = KeyboardShortcut::new(Modifiers::CTRL, Key::Space);
+        harness.io.refused_hotkeys.push(shortcut);
+
+        harness.with_settings("global \"Ctrl Space\" { ShowHideApp; }");
+
+        assert_eq!(
+            harness
+                .state
+                .global_hotkey_warning
+                .as_ref()
+                .map(|w| w.shortcut),
+            Some(shortcut)
+        );
+
+        // previous bindings are released before registering them again
+        harness.io.take_calls();
+        harness.io.refused_hotkeys.clear();
+        harness.dispatch(AppAction::EvalNote(NoteFile::Settings));
+
+        let calls = harness.io.take_calls();
+        assert_eq!(calls.first(), Some(&IOCall::CleanupGlobalHotkeys));
+        assert!(calls.contains(&IOCall::BindGlobalHotkey(shortcut)));
+        assert_eq!(harness.state.global_hotkey_warning, None);
+    }
+
+    #[test]
+    pub fn test_mirrors_are_saved_with_notes() {
+        let mut harness = TestHarness::new("note{||}");
+
+        harness.with_settings("mirror 1 \"/projects/app/README.md\"");
+
+        assert!(harness.state.should_persist().is_some_and(|data| {
+            data.note_mirrors == [(NoteFile::Note(0), PathBuf::from("/projects/app/README.md"))]
+        }));
+
+        // relative paths have no obvious base folder
+        harness.with_settings("mirror 2 \"README.md\"");
+
+        assert!(!harness.state.note_mirrors.contains_key(&NoteFile::Note(1)));
+    }
+
+    #[test]
+    pub fn test_encrypted_notes_are_not_mirrored() {
+        let mut harness = TestHarness::new("secret{||}");
+        harness.run_command(CommandInstruction::ToggleNoteEncryption);
+
+        harness.with_settings("mirror 1 \"/projects/app/README.md\"");
+        assert!(harness.state.note_mirrors.is_empty());
+
+        // the settings are evaluated again, so the mirror is back once the note is in plain text
+        harness.run_command(CommandInstruction::ToggleNoteEncryption);
+        assert!(harness.state.note_mirrors.contains_key(&NoteFile::Note(0)));
+    }
+
+    #[test]
+    pub fn test_escape_hides_only_when_nothing_else_is_open() {
+        let mut harness = TestHarness::new("note{||}");
+
+        // Escape closes the confirmation first
+        harness.state.clear_note_confirmation = Some(NoteFile::Note(0));
+        harness.run_command(CommandInstruction::HideApp);
+        assert_eq!(harness.io.take_calls(), vec![]);
+
+        harness.state.clear_note_confirmation = None;
+        harness.run_command(CommandInstruction::HideApp);
+        assert_eq!(
+            harness.io.take_calls(),
+            vec![IOCall::HideApp(HideMode::HideApp)]
+        );
+
+        let escape = KeyboardShortcut::new(Modifiers::NONE, Key::Escape);
+        let hides_on_escape = |harness: &TestHarness| {
+            harness
+                .state
+                .commands
+                .find(CommandInstruction::HideApp)
+                .is_some_and(|cmd| cmd.shortcut == Some(escape))
+        };
+        assert!(hides_on_escape(&harness));
+
+        harness.with_settings("window { hideOnEscape false; }");
+        assert!(!hides_on_escape(&harness));
+    }
+
+    #[test]
+    pub fn test_promoted_commands_are_configured_in_settings() {
+        let mut harness = TestHarness::new("{||}");
+        assert_eq!(
+            harness.state.commands.promoted_commands(),
+            PROMOTED_COMMANDS.as_slice()
+        );
+
+        let hints = harness.with_settings("hints { JoinLines; InsertTimestamp; }");
+        assert_eq!(
+            harness.state.commands.promoted_commands(),
+            [
+                CommandInstruction::JoinLines,
+                CommandInstruction::InsertTimestamp
+            ]
+        );
+
+        // the defaults are back once the block is removed
+        harness.dispatch(AppAction::ApplyTextChanges {
+            target: NoteFile::Settings,
+            changes: vec![TextChange::Insert(hints, String::new())],
+            should_trigger_eval: true,
+        });
+        assert_eq!(
+            harness.state.commands.promoted_commands(),
+            PROMOTED_COMMANDS.as_slice()
+        );
+    }
+}
@@ -1,4 +1,5 @@
-use eframe::egui::{Key, KeyboardShortcut, ModifierNames, Modifiers};
+use chrono::NaiveTime;
+use eframe::egui::{Color32, Key, KeyboardShortcut, ModifierNames, Modifiers};
 use knus::{DecodeScalar, ast::Literal, errors::DecodeError, span::Spanned, traits::ErrorSpan};
 use smallvec::SmallVec;
 
@@ -45,6 +46,91 @@ impl<S: ErrorSpan> DecodeScalar<S> for ParsedShortcut {
     }
 }
 
+/// Local time of the day in "HH:MM" format, e.g. "22:30"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParsedClockTime(NaiveTime);
+impl ParsedClockTime {
+    pub fn value(&self) -> NaiveTime {
+        self.0
+    }
+}
+
+impl<S: ErrorSpan> DecodeScalar<S> for ParsedClockTime {
+    fn raw_decode(
+        val: &Spanned<Literal, S>,
+        ctx: &mut knus::decode::Context<S>,
+    ) -> Result<ParsedClockTime, DecodeError<S>> {
+        match &**val {
+            Literal::String(s) => NaiveTime::parse_from_str(s, "%H:%M")
+                .map_err(|_| {
+                    DecodeError::conversion(val, format!("{s} is not a valid time, expected HH:MM"))
+                })
+                .map(ParsedClockTime),
+            _ => {
+                ctx.emit_error(DecodeError::scalar_kind(knus::decode::Kind::String, val));
+                Ok(ParsedClockTime(NaiveTime::MIN))
+            }
+        }
+    }
+    fn type_check(
+        type_name: &Option<Spanned<knus::ast::TypeName, S>>,
+        ctx: &mut knus::decode::Context<S>,
+    ) {
+        if let Some(typ) = type_name {
+            ctx.emit_error(DecodeError::TypeName {
+                span: typ.span().clone(),
+                found: None,
+                expected: knus::errors::ExpectedType::no_type(),
+                rust_type: "ParsedClockTime",
+            });
+        }
+    }
+}
+
+/// Hex color, e.g. "#88C0D0" or "#5E81AC80" with alpha
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParsedColor(Color32);
+impl ParsedColor {
+    pub fn value(&self) -> Color32 {
+        self.0
+    }
+}
+
+impl<S: ErrorSpan> DecodeScalar<S> for ParsedColor {
+    fn raw_decode(
+        val: &Spanned<Literal, S>,
+        ctx: &mut knus::decode::Context<S>,
+    ) -> Result<ParsedColor, DecodeError<S>> {
+        match &**val {
+            Literal::String(s) => Color32::from_hex(s)
+                .map_err(|_| {
+                    DecodeError::conversion(
+                        val,
+                        format!("{s} is not a valid color, expected hex like \"#88C0D0\""),
+                    )
+                })
+                .map(ParsedColor),
+            _ => {
+                ctx.emit_error(DecodeError::scalar_kind(knus::decode::Kind::String, val));
+                Ok(ParsedColor(Color32::TRANSPARENT))
+            }
+        }
+    }
+    fn type_check(
+        type_name: &Option<Spanned<knus::ast::TypeName, S>>,
+        ctx: &mut knus::decode::Context<S>,
+    ) {
+        if let Some(typ) = type_name {
+            ctx.emit_error(DecodeError::TypeName {
+                span: typ.span().clone(),
+                found: None,
+                expected: knus::errors::ExpectedType::no_type(),
+                rust_type: "ParsedColor",
+            });
+        }
+    }
+}
+
 #[derive(Debug, knus::Decode, Clone, PartialEq, Eq)]
 pub struct LlmSettings {
     #[knus(child(name = "model"), unwrap(argument))]
@@ -60,6 +146,230 @@ pub struct LlmSettings {
     pub use_shelv_system_prompt: Option<bool>,
 }
 
+#[derive(Debug, knus::Decode, Clone, PartialEq, Eq)]
+pub struct CommandLogSettings {
+    #[knus(child(name = "enabled"), unwrap(argument), default = true)]
+    pub enabled: bool,
+
+    #[knus(child(name = "maxEntries"), unwrap(argument))]
+    pub max_entries: Option<u32>,
+}
+
+/// Non critical notifications (such as "update available") are hidden during quiet hours
+#[derive(Debug, knus::Decode, Clone, PartialEq, Eq)]
+pub struct QuietHoursSettings {
+    #[knus(child(name = "from"), unwrap(argument))]
+    pub from: ParsedClockTime,
+
+    #[knus(child(name = "to"), unwrap(argument))]
+    pub to: ParsedClockTime,
+
+    /// Notifications are hidden while a `/focus` session is running as well
+    #[knus(child(name = "duringFocusSessions"), unwrap(argument), default = false)]
+    pub during_focus_sessions: bool,
+}
+
+impl QuietHoursSettings {
+    /// Note that the window can wrap around midnight, e.g. from "22:00" to "08:00"
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        let (from, to) = (self.from.value(), self.to.value());
+        if from <= to {
+            from <= time && time < to
+        } else {
+            from <= time || time < to
+        }
+    }
+
+    pub fn is_quiet(&self, time: NaiveTime, in_focus_session: bool) -> bool {
+        self.contains(time) || (in_focus_session && self.during_focus_sessions)
+    }
+}
+
+/// What happens to the typed "/" (and the search term) when the palette is closed without running a command
+#[derive(Debug, knus::Decode, Clone, PartialEq, Eq)]
+pub struct SlashPaletteSettings {
+    #[knus(child(name = "removeSlashOnClose"), unwrap(argument), default = false)]
+    pub remove_slash_on_close: bool,
+}
+
+/// Commands shown (with their shortcuts) on an empty note, in this order, instead of the default ones.
+/// Commands without a shortcut are skipped
+#[derive(Debug, knus::Decode, Clone, PartialEq)]
+pub struct HintsSettings {
+    #[knus(children)]
+    pub commands: Vec<CommandInstruction>,
+}
+
+/// Verbose helpers for learning how the markdown is structured and debugging the editor
+#[derive(Debug, knus::Decode, Clone, PartialEq, Eq)]
+pub struct DevSettings {
+    /// Shows the span stack at the cursor in the footer, e.g. "List > ListItem > Paragraph"
+    #[knus(child(name = "showCursorContext"), unwrap(argument), default = false)]
+    pub show_cursor_context: bool,
+}
+
+/// What happens with the (unpinned) window when it loses focus
+#[derive(Debug, knus::DecodeScalar, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FocusLostBehavior {
+    #[default]
+    Hide,
+    /// Stays visible with reduced opacity, as a glanceable overlay
+    Dim,
+    Stay,
+}
+
+/// What the X button of the header does, Shelv keeps running in the tray when the window is hidden
+#[derive(Debug, knus::DecodeScalar, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CloseButtonBehavior {
+    #[default]
+    Hide,
+    Quit,
+    /// X hides the window, and a separate button quits the app
+    HideAndQuit,
+}
+
+#[derive(Debug, knus::Decode, Clone, PartialEq, Eq)]
+pub struct WindowSettings {
+    #[knus(child(name = "onFocusLost"), unwrap(argument), default)]
+    pub on_focus_lost: FocusLostBehavior,
+
+    /// Grace period before `onFocusLost` kicks in, regaining focus within it cancels the hide
+    #[knus(child(name = "focusLostDelayMs"), unwrap(argument), default = 0)]
+    pub focus_lost_delay_ms: u64,
+
+    #[knus(child(name = "closeButton"), unwrap(argument), default)]
+    pub close_button: CloseButtonBehavior,
+
+    /// In percents, values below `MIN_WINDOW_OPACITY_PERCENT` are raised to it to keep text readable
+    #[knus(child(name = "opacity"), unwrap(argument), default = 100)]
+    pub opacity_percent: u8,
+
+    /// A bare Escape hides the window once no palette, prompt or modal is left to close
+    #[knus(child(name = "hideOnEscape"), unwrap(argument), default = true)]
+    pub hide_on_escape: bool,
+
+    /// Settings are a stop when cycling through shelves with `SwitchToNextNote`/`SwitchToPrevNote`
+    #[knus(child(name = "cycleThroughSettings"), unwrap(argument), default)]
+    pub cycle_through_settings: bool,
+}
+
+pub const MIN_WINDOW_OPACITY_PERCENT: u8 = 30;
+pub const DEFAULT_LARGE_NOTE_KB: u32 = 1024;
+
+#[derive(Debug, knus::Decode, Clone, PartialEq, Eq)]
+pub struct EditorSettings {
+    /// Markdown markers (e.g. `**` or `#`) are shown only on the line of the cursor
+    #[knus(child(name = "livePreview"), unwrap(argument), default = false)]
+    pub live_preview: bool,
+
+    /// Typing a lowercase letter at the start of a sentence capitalizes it
+    #[knus(child(name = "autoCapitalize"), unwrap(argument), default = false)]
+    pub auto_capitalize: bool,
+
+    /// `--` becomes an em dash
+    #[knus(child(name = "smartDashes"), unwrap(argument), default = false)]
+    pub smart_dashes: bool,
+
+    /// `...` becomes an ellipsis
+    #[knus(child(name = "smartEllipsis"), unwrap(argument), default = false)]
+    pub smart_ellipsis: bool,
+
+    /// Straight quotes become curly ones
+    #[knus(child(name = "smartQuotes"), unwrap(argument), default = false)]
+    pub smart_quotes: bool,
+
+    /// Default language of the notes, e.g. `lang "fr"`, `lang: de` in the front matter overrides it
+    #[knus(child(name = "lang"), unwrap(argument))]
+    pub lang: Option<String>,
+
+    /// Task markers are drawn as clickable checkboxes on top of `[ ]`
+    #[knus(child(name = "checkboxTasks"), unwrap(argument), default = false)]
+    pub checkbox_tasks: bool,
+
+    /// Long lines of code blocks wrap, otherwise they overflow and the note scrolls horizontally
+    #[knus(child(name = "wrapCodeBlocks"), unwrap(argument), default = true)]
+    pub wrap_code_blocks: bool,
+
+    /// Enter inside a code block keeps the indentation of the line
+    #[knus(child(name = "codeAutoIndent"), unwrap(argument), default = true)]
+    pub code_auto_indent: bool,
+
+    /// Pasted markdown lists get the markers and the tab indentation of the lists typed in Shelv
+    #[knus(child(name = "normalizePastedLists"), unwrap(argument), default = true)]
+    pub normalize_pasted_lists: bool,
+
+    /// Runs of blank lines are collapsed into one when switching to another note, as `/format` does
+    #[knus(child(name = "collapseBlankLines"), unwrap(argument), default = false)]
+    pub collapse_blank_lines: bool,
+
+    /// Empty space below the last line, so it can be scrolled up to the middle of the window
+    #[knus(child(name = "scrollPastEnd"), unwrap(argument), default = false)]
+    pub scroll_past_end: bool,
+
+    /// Bare urls are styled and clickable as links, urls in code never are
+    #[knus(child(name = "autoLinks"), unwrap(argument), default = true)]
+    pub auto_links: bool,
+
+    /// Once typing pauses at the end of a note, the LLM suggests how to continue it, Tab accepts it
+    #[knus(child(name = "inlineSuggestions"), unwrap(argument), default = false)]
+    pub inline_suggestions: bool,
+
+    /// Notes bigger than this (in KB) skip code highlighting, bare url detection and live preview
+    #[knus(child(name = "largeNoteKb"), unwrap(argument), default = DEFAULT_LARGE_NOTE_KB)]
+    pub large_note_kb: u32,
+
+    /// strftime format of `/now`, e.g. `timestampFormat "%A, %d %B %H:%M"`
+    #[knus(child(name = "timestampFormat"), unwrap(argument))]
+    pub timestamp_format: Option<String>,
+
+    /// Languages of code blocks highlighted with tree-sitter instead of syntect, e.g. `treeSitter "rust" "js"`
+    #[knus(child(name = "treeSitter"), unwrap(arguments), default)]
+    pub tree_sitter_langs: Vec<String>,
+
+    /// Command to open a note in another editor, e.g. "open -a TextEdit", `$EDITOR` is used otherwise
+    #[knus(child(name = "externalEditor"), unwrap(argument))]
+    pub external_editor: Option<String>,
+}
+
+/// Overrides of the default Nord colors
+#[derive(Debug, knus::Decode, Clone, PartialEq, Eq)]
+pub struct ThemeSettings {
+    /// Base of the interactive colors: the caret, the selection, links and hovered buttons
+    #[knus(child(name = "accentColor"), unwrap(argument))]
+    pub accent_color: Option<ParsedColor>,
+
+    /// Color of the text cursor
+    #[knus(child(name = "caretColor"), unwrap(argument))]
+    pub caret_color: Option<ParsedColor>,
+
+    /// Background of the selected text, a translucent color is blended with whatever is under it
+    #[knus(child(name = "selectionColor"), unwrap(argument))]
+    pub selection_color: Option<ParsedColor>,
+}
+
+/// Every save of the shelf also writes a copy of it to `path`, e.g. `mirror 1 "~/projects/app/README.md"`
+#[derive(Debug, knus::Decode, Clone, PartialEq, Eq)]
+pub struct NoteMirror {
+    /// 1-based, as in the names of the shelf files
+    #[knus(argument)]
+    pub shelf: u32,
+
+    #[knus(argument)]
+    pub path: String,
+}
+
+/// Text an empty shelf is filled with, e.g. `template 1 "# {date}\n\n## Todo\n"`,
+/// see `note_template` for the placeholders
+#[derive(Debug, knus::Decode, Clone, PartialEq, Eq)]
+pub struct NoteTemplate {
+    /// 1-based, as in the names of the shelf files
+    #[knus(argument)]
+    pub shelf: u32,
+
+    #[knus(argument)]
+    pub text: String,
+}
+
 #[derive(Debug, knus::Decode, PartialEq)]
 pub struct TopLevelKdlSettings {
     #[knus(children(name = "bind"))]
@@ -70,6 +380,36 @@ pub struct TopLevelKdlSettings {
 
     #[knus(child(name = "ai"))]
     pub llm_settings: Option<LlmSettings>,
+
+    #[knus(child(name = "commandLog"))]
+    pub command_log: Option<CommandLogSettings>,
+
+    #[knus(child(name = "quietHours"))]
+    pub quiet_hours: Option<QuietHoursSettings>,
+
+    #[knus(child(name = "slashPalette"))]
+    pub slash_palette: Option<SlashPaletteSettings>,
+
+    #[knus(child(name = "hints"))]
+    pub hints: Option<HintsSettings>,
+
+    #[knus(child(name = "dev"))]
+    pub dev: Option<DevSettings>,
+
+    #[knus(child(name = "window"))]
+    pub window: Option<WindowSettings>,
+
+    #[knus(child(name = "editor"))]
+    pub editor: Option<EditorSettings>,
+
+    #[knus(child(name = "theme"))]
+    pub theme: Option<ThemeSettings>,
+
+    #[knus(children(name = "mirror"))]
+    pub mirrors: Vec<NoteMirror>,
+
+    #[knus(children(name = "template"))]
+    pub templates: Vec<NoteTemplate>,
 }
 
 #[derive(Debug, knus::Decode, PartialEq)]
@@ -116,7 +456,7 @@ fn try_parse_modifier(mod_str: &str) -> Option<Modifiers> {
     }
 }
 
-fn parse_keyboard_shortcut(attr: &str) -> Result<KeyboardShortcut, String> {
+pub fn parse_keyboard_shortcut(attr: &str) -> Result<KeyboardShortcut, String> {
     let parts: Vec<_> = attr.split(' ').collect();
 
     let modifiers = parts
@@ -195,7 +535,17 @@ mod tests {
                 }]
                 .into(),
                 global_bindings: vec![],
-                llm_settings: None
+                llm_settings: None,
+                command_log: None,
+                quiet_hours: None,
+                slash_palette: None,
+                hints: None,
+                dev: None,
+                window: None,
+                editor: None,
+                theme: None,
+                mirrors: vec![],
+                templates: vec![],
             }
         );
     }
@@ -230,7 +580,17 @@ mod tests {
                 }]
                 .into(),
                 global_bindings: vec![],
-                llm_settings: None
+                llm_settings: None,
+                command_log: None,
+                quiet_hours: None,
+                slash_palette: None,
+                hints: None,
+                dev: None,
+                window: None,
+                editor: None,
+                theme: None,
+                mirrors: vec![],
+                templates: vec![],
             }
         );
     }
@@ -267,8 +627,379 @@ mod tests {
                 }]
                 .into(),
                 global_bindings: vec![],
-                llm_settings: None
+                llm_settings: None,
+                command_log: None,
+                quiet_hours: None,
+                slash_palette: None,
+                hints: None,
+                dev: None,
+                window: None,
+                editor: None,
+                theme: None,
+                mirrors: vec![],
+                templates: vec![],
             }
         );
     }
+
+    #[test]
+    pub fn test_command_log_settings_parsing() {
+        let doc_str = r#"
+        commandLog {
+            maxEntries 50
+        }
+        "#;
+
+        let settings = parse_top_level_settings_block(doc_str).unwrap();
+
+        assert_eq!(
+            settings.command_log,
+            Some(CommandLogSettings {
+                enabled: true,
+                max_entries: Some(50)
+            })
+        );
+
+        let doc_str = r#"
+        commandLog {
+            enabled false
+        }
+        "#;
+
+        let settings = parse_top_level_settings_block(doc_str).unwrap();
+
+        assert_eq!(
+            settings.command_log,
+            Some(CommandLogSettings {
+                enabled: false,
+                max_entries: None
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_quiet_hours_settings_parsing() {
+        let doc_str = r#"
+        quietHours {
+            from "22:30"
+            to "08:00"
+        }
+        "#;
+
+        let quiet_hours = parse_top_level_settings_block(doc_str)
+            .unwrap()
+            .quiet_hours
+            .unwrap();
+
+        let time = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+
+        assert_eq!(quiet_hours.from.value(), time(22, 30));
+        assert_eq!(quiet_hours.to.value(), time(8, 0));
+
+        assert!(quiet_hours.contains(time(23, 0)));
+        assert!(quiet_hours.contains(time(7, 59)));
+        assert!(!quiet_hours.contains(time(8, 0)));
+        assert!(!quiet_hours.contains(time(12, 0)));
+        assert!(!quiet_hours.is_quiet(time(12, 0), true));
+
+        let doc_str = r#"
+        quietHours {
+            from "22:30"
+            to "08:00"
+            duringFocusSessions true
+        }
+        "#;
+
+        let quiet_hours = parse_top_level_settings_block(doc_str)
+            .unwrap()
+            .quiet_hours
+            .unwrap();
+
+        assert!(quiet_hours.is_quiet(time(12, 0), true));
+        assert!(!quiet_hours.is_quiet(time(12, 0), false));
+        assert!(quiet_hours.is_quiet(time(23, 0), false));
+
+        let doc_str = r#"
+        quietHours {
+            from "9am"
+            to "17:00"
+        }
+        "#;
+
+        assert!(parse_top_level_settings_block(doc_str).is_err());
+    }
+
+    #[test]
+    pub fn test_slash_palette_settings_parsing() {
+        let doc_str = r#"
+        slashPalette {
+            removeSlashOnClose true
+        }
+        "#;
+
+        let settings = parse_top_level_settings_block(doc_str).unwrap();
+
+        assert_eq!(
+            settings.slash_palette,
+            Some(SlashPaletteSettings {
+                remove_slash_on_close: true
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_hints_settings_parsing() {
+        let doc_str = r#"
+        hints {
+            JoinLines
+            MarkdownCodeBlock lang="js"
+        }
+        "#;
+
+        let settings = parse_top_level_settings_block(doc_str).unwrap();
+
+        assert_eq!(
+            settings.hints,
+            Some(HintsSettings {
+                commands: vec![
+                    CommandInstruction::JoinLines,
+                    CommandInstruction::MarkdownCodeBlock(Some("js".to_string()))
+                ]
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_dev_settings_parsing() {
+        let doc_str = r#"
+        dev {
+            showCursorContext true
+        }
+        "#;
+
+        let settings = parse_top_level_settings_block(doc_str).unwrap();
+
+        assert_eq!(
+            settings.dev,
+            Some(DevSettings {
+                show_cursor_context: true
+            })
+        );
+
+        let settings = parse_top_level_settings_block("dev {}").unwrap();
+        assert_eq!(
+            settings.dev,
+            Some(DevSettings {
+                show_cursor_context: false
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_window_settings_parsing() {
+        let doc_str = r#"
+        window {
+            onFocusLost "dim"
+            focusLostDelayMs 500
+            closeButton "hide-and-quit"
+            opacity 85
+            hideOnEscape false
+            cycleThroughSettings true
+        }
+        "#;
+
+        let settings = parse_top_level_settings_block(doc_str).unwrap();
+
+        assert_eq!(
+            settings.window,
+            Some(WindowSettings {
+                on_focus_lost: FocusLostBehavior::Dim,
+                focus_lost_delay_ms: 500,
+                close_button: CloseButtonBehavior::HideAndQuit,
+                opacity_percent: 85,
+                hide_on_escape: false,
+                cycle_through_settings: true,
+            })
+        );
+
+        let settings = parse_top_level_settings_block("window {}").unwrap();
+        assert_eq!(
+            settings.window,
+            Some(WindowSettings {
+                on_focus_lost: FocusLostBehavior::Hide,
+                focus_lost_delay_ms: 0,
+                close_button: CloseButtonBehavior::Hide,
+                opacity_percent: 100,
+                hide_on_escape: true,
+                cycle_through_settings: false,
+            })
+        );
+
+        assert!(parse_top_level_settings_block(r#"window { onFocusLost "fade"; }"#).is_err());
+    }
+
+    #[test]
+    pub fn test_editor_settings_parsing() {
+        let doc_str = r#"
+        editor {
+            livePreview true
+            smartDashes true
+            smartQuotes true
+            lang "fr"
+            checkboxTasks true
+            wrapCodeBlocks false
+            codeAutoIndent false
+            normalizePastedLists false
+            collapseBlankLines true
+            scrollPastEnd true
+            autoLinks false
+            inlineSuggestions true
+            largeNoteKb 256
+            timestampFormat "%d.%m.%Y"
+            treeSitter "rust" "js"
+            externalEditor "zed --wait"
+        }
+        "#;
+
+        let settings = parse_top_level_settings_block(doc_str).unwrap();
+        assert_eq!(
+            settings.editor,
+            Some(EditorSettings {
+                live_preview: true,
+                auto_capitalize: false,
+                smart_dashes: true,
+                smart_ellipsis: false,
+                smart_quotes: true,
+                lang: Some("fr".to_string()),
+                checkbox_tasks: true,
+                wrap_code_blocks: false,
+                code_auto_indent: false,
+                normalize_pasted_lists: false,
+                collapse_blank_lines: true,
+                scroll_past_end: true,
+                auto_links: false,
+                inline_suggestions: true,
+                large_note_kb: 256,
+                timestamp_format: Some("%d.%m.%Y".to_string()),
+                tree_sitter_langs: vec!["rust".to_string(), "js".to_string()],
+                external_editor: Some("zed --wait".to_string()),
+            })
+        );
+
+        let settings = parse_top_level_settings_block("editor {}").unwrap();
+        assert_eq!(
+            settings.editor,
+            Some(EditorSettings {
+                live_preview: false,
+                auto_capitalize: false,
+                smart_dashes: false,
+                smart_ellipsis: false,
+                smart_quotes: false,
+                lang: None,
+                checkbox_tasks: false,
+                wrap_code_blocks: true,
+                code_auto_indent: true,
+                normalize_pasted_lists: true,
+                collapse_blank_lines: false,
+                scroll_past_end: false,
+                auto_links: true,
+                inline_suggestions: false,
+                large_note_kb: DEFAULT_LARGE_NOTE_KB,
+                timestamp_format: None,
+                tree_sitter_langs: vec![],
+                external_editor: None,
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_theme_settings_parsing() {
+        let doc_str = r##"
+        theme {
+            accentColor "#B48EAD"
+            caretColor "#88C0D0"
+            selectionColor "#5E81AC80"
+        }
+        "##;
+
+        let theme = parse_top_level_settings_block(doc_str)
+            .unwrap()
+            .theme
+            .unwrap();
+
+        assert_eq!(
+            theme.accent_color.map(|c| c.value()),
+            Some(Color32::from_rgb(0xB4, 0x8E, 0xAD))
+        );
+        assert_eq!(
+            theme.caret_color.map(|c| c.value()),
+            Some(Color32::from_rgb(0x88, 0xC0, 0xD0))
+        );
+        assert_eq!(
+            theme.selection_color.map(|c| c.value()),
+            Some(Color32::from_rgba_unmultiplied(0x5E, 0x81, 0xAC, 0x80))
+        );
+
+        let settings = parse_top_level_settings_block("theme {}").unwrap();
+        assert_eq!(
+            settings.theme,
+            Some(ThemeSettings {
+                accent_color: None,
+                caret_color: None,
+                selection_color: None,
+            })
+        );
+
+        assert!(parse_top_level_settings_block(r#"theme { caretColor "blue"; }"#).is_err());
+    }
+
+    #[test]
+    pub fn test_mirror_settings_parsing() {
+        let doc_str = r#"
+        mirror 1 "~/projects/app/README.md"
+        mirror 3 "/Users/me/Dropbox/ideas.md"
+        "#;
+
+        let settings = parse_top_level_settings_block(doc_str).unwrap();
+        assert_eq!(
+            settings.mirrors,
+            vec![
+                NoteMirror {
+                    shelf: 1,
+                    path: "~/projects/app/README.md".to_string(),
+                },
+                NoteMirror {
+                    shelf: 3,
+                    path: "/Users/me/Dropbox/ideas.md".to_string(),
+                },
+            ]
+        );
+
+        assert!(parse_top_level_settings_block(r#"mirror "README.md""#).is_err());
+    }
+
+    #[test]
+    pub fn test_template_settings_parsing() {
+        let doc_str = r#"
+        template 1 "{weekday} {date}\n\n## Todo\n"
+        template 2 r"- [ ] {cursor}"
+        "#;
+
+        let settings = parse_top_level_settings_block(doc_str).unwrap();
+        assert_eq!(
+            settings.templates,
+            vec![
+                NoteTemplate {
+                    shelf: 1,
+                    text: "{weekday} {date}\n\n## Todo\n".to_string(),
+                },
+                NoteTemplate {
+                    shelf: 2,
+                    text: "- [ ] {cursor}".to_string(),
+                },
+            ]
+        );
+
+        assert!(parse_top_level_settings_block(r#"template "{date}""#).is_err());
+    }
 }
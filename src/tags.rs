@@ -0,0 +1,196 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use eframe::egui::Id;
+use serde_json::Value;
+
+use crate::{
+    app_state::Note, front_matter::parse_front_matter, note_switcher::note_title,
+    persistent_state::NoteFile, text_structure::TextStructure,
+};
+
+pub fn tag_filter_input_id() -> Id {
+    Id::new("tag_filter_input")
+}
+
+/// Inline `#tags` and `tags` of the front matter.
+/// Heading markers are consumed by the markdown parser, so only `#` inside of text spans are looked at,
+/// code and the front matter itself are skipped
+pub fn extract_tags(text: &str, structure: &TextStructure) -> BTreeSet<String> {
+    let mut tags = BTreeSet::new();
+
+    for span in structure.prose_text_spans() {
+        for (pos, _) in text[span.range()].match_indices('#') {
+            if let Some(tag) = inline_tag_at(text, span.start + pos) {
+                tags.insert(tag.to_string());
+            }
+        }
+    }
+
+    let front_matter_tags = structure
+        .front_matter(text)
+        .map(parse_front_matter)
+        .and_then(|mut front_matter| front_matter.remove("tags"));
+
+    match front_matter_tags {
+        Some(Value::Array(items)) => tags.extend(
+            items
+                .iter()
+                .filter_map(|item| item.as_str())
+                .filter_map(normalize_front_matter_tag),
+        ),
+        Some(Value::String(items)) => {
+            tags.extend(items.split(',').filter_map(normalize_front_matter_tag))
+        }
+        _ => (),
+    }
+
+    tags
+}
+
+/// `#tag` starts with a letter and is not glued to a word, e.g. `page#anchor` or `#1` are not tags
+fn inline_tag_at(text: &str, hash_pos: usize) -> Option<&str> {
+    let is_separated = text[..hash_pos]
+        .chars()
+        .next_back()
+        .is_none_or(|prev| !prev.is_alphanumeric() && !matches!(prev, '#' | '&' | '/'));
+
+    if !is_separated {
+        return None;
+    }
+
+    let rest = &text[hash_pos + 1..];
+    let len = rest.find(|c: char| !is_tag_char(c)).unwrap_or(rest.len());
+
+    let tag = rest[..len].trim_end_matches(['-', '/']);
+    tag.starts_with(char::is_alphabetic).then_some(tag)
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '/')
+}
+
+fn normalize_front_matter_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().trim_start_matches('#');
+    (!tag.is_empty()).then(|| tag.to_string())
+}
+
+#[derive(Debug, Clone)]
+pub struct TagEntry {
+    pub tag: String,
+    pub note_file: NoteFile,
+    pub note_title: String,
+}
+
+/// Palette listing notes by tag, the selected note is switched to on confirm
+#[derive(Debug)]
+pub struct TagFilter {
+    pub query: String,
+    pub selected: usize,
+    /// snapshot of the tags at the moment the filter was opened, sorted by tag
+    entries: Vec<TagEntry>,
+}
+
+impl TagFilter {
+    pub fn new(notes: &BTreeMap<NoteFile, Note>) -> Self {
+        let mut entries: Vec<TagEntry> = notes
+            .iter()
+            .flat_map(|(note_file, note)| {
+                let title = note_title(*note_file, &note.text);
+                extract_tags(&note.text, &note.derived_state.structure)
+                    .into_iter()
+                    .map(move |tag| TagEntry {
+                        tag,
+                        note_file: *note_file,
+                        note_title: title.clone(),
+                    })
+            })
+            .collect();
+
+        entries.sort_by(|a, b| a.tag.to_lowercase().cmp(&b.tag.to_lowercase()));
+
+        Self {
+            query: String::new(),
+            selected: 0,
+            entries,
+        }
+    }
+
+    /// Case insensitive, the leading `#` of the query is optional
+    pub fn matching(&self) -> impl Iterator<Item = &TagEntry> {
+        let query = self.query.trim().trim_start_matches('#').to_lowercase();
+        self.entries
+            .iter()
+            .filter(move |entry| entry.tag.to_lowercase().contains(&query))
+    }
+
+    pub fn move_selection(&mut self, backwards: bool) {
+        let len = self.matching().count().max(1);
+        self.selected = match backwards {
+            true => (self.selected.min(len - 1) + len - 1) % len,
+            false => (self.selected + 1) % len,
+        };
+    }
+
+    pub fn selected_note(&self) -> Option<NoteFile> {
+        let matching: Vec<_> = self.matching().collect();
+        matching
+            .get(self.selected)
+            .or(matching.last())
+            .map(|entry| entry.note_file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_extract_tags() {
+        let text = r##"---
+tags: [project, "#shelv"]
+---
+# Heading with #idea
+#todo at the start of the line and #later/maybe.
+not a tag: page#anchor, #1, &#35;
+
+```js
+// #not-a-tag
+```
+and `#neither`
+"##;
+
+        let structure = TextStructure::new(text);
+        let tags: Vec<String> = extract_tags(text, &structure).into_iter().collect();
+
+        assert_eq!(
+            tags,
+            ["idea", "later/maybe", "project", "shelv", "todo"].map(String::from)
+        );
+    }
+
+    #[test]
+    pub fn test_tag_filter_matching() {
+        let filter = TagFilter {
+            query: "#Wo".to_string(),
+            selected: 0,
+            entries: [("home", 0), ("work", 1), ("homework", 2)]
+                .map(|(tag, index)| TagEntry {
+                    tag: tag.to_string(),
+                    note_file: NoteFile::Note(index),
+                    note_title: String::new(),
+                })
+                .to_vec(),
+        };
+
+        let matching: Vec<&str> = filter.matching().map(|e| e.tag.as_str()).collect();
+        assert_eq!(matching, ["work", "homework"]);
+
+        let mut filter = filter;
+        filter.move_selection(true);
+        assert_eq!(
+            filter.selected_note(),
+            Some(NoteFile::Note(2)),
+            "wraps around"
+        );
+    }
+}
@@ -0,0 +1,443 @@
+use std::{cell::RefCell, io, path::PathBuf, sync::mpsc::sync_channel, time::SystemTime};
+
+use eframe::egui::{ColorImage, Context, KeyboardShortcut, OutputCommand, RawInput};
+use smallvec::SmallVec;
+
+use crate::{
+    app_actions::{
+        AppAction, AppIO, HideMode, LLMBlockRequest, LLMPromptRequest, LLMSuggestionRequest,
+        SettingsForAiRequests, process_app_action,
+    },
+    app_state::{AppInitData, AppState, MsgToApp, compute_editor_text_id},
+    byte_span::ByteSpan,
+    command::{AppFocus, AppFocusState, CommandContext, CommandInstruction, CommandScope},
+    effects::text_change_effect::TextChange,
+    persistent_state::{LoadKind, NoteBackup, NoteFile, bootstrap, get_utc_timestamp},
+    scripting::{js_task::JsTaskRequest, settings_eval::Scripts},
+    theme::AppTheme,
+};
+
+/// Side effects requested from `AppIO`, recorded in the order they happened
+#[derive(Debug, Clone, PartialEq)]
+pub enum IOCall {
+    HideApp(HideMode),
+    SetWindowOpacity(f32),
+    /// the note, its text and whether the backup is encrypted
+    BackupNote(NoteFile, String, bool),
+    OpenShelvFolder,
+    OpenInExternalEditor(NoteFile, Option<String>),
+    BindGlobalHotkey(KeyboardShortcut),
+    CleanupGlobalHotkeys,
+    ExecuteLlmBlock(NoteFile),
+    ExecuteLlmPrompt(String),
+    /// the text before the cursor
+    RequestLlmSuggestion(String),
+    /// tests run the task themselves, e.g. with `run_js_task`
+    RunJsTask(JsTaskRequest),
+    /// tests send `MsgToApp::RemoteImageFetched` themselves
+    FetchRemoteImage(String),
+    CaptureSentryMessage(String),
+    CopyToClipboard(String),
+    CopyImageToClipboard,
+    /// the suggested file name and the content
+    ExportNote(String, String),
+    StartUpdateChecker,
+    OpenAppStore,
+    DisableCrashReporting,
+    SetStartAtLogin(bool),
+}
+
+/// `AppIO` that doesn't touch the system, only records what was requested
+#[derive(Default)]
+pub struct TestAppIO {
+    calls: RefCell<Vec<IOCall>>,
+    /// returned from `try_read_note_if_newer`, as if the file was edited outside of the app
+    pub newer_note_content: Option<String>,
+    /// `bind_global_hotkey` fails for these, as if they were taken by another app
+    pub refused_hotkeys: Vec<KeyboardShortcut>,
+    /// everything passed to `backup_note`, the most recent last
+    backups: RefCell<Vec<NoteBackup>>,
+    /// returned from `read_clipboard_text`
+    pub clipboard_text: Option<String>,
+}
+
+impl TestAppIO {
+    fn record(&self, call: IOCall) {
+        self.calls.borrow_mut().push(call);
+    }
+
+    pub fn take_calls(&self) -> Vec<IOCall> {
+        self.calls.take()
+    }
+}
+
+impl AppIO for TestAppIO {
+    fn hide_app(&self, mode: HideMode) {
+        self.record(IOCall::HideApp(mode));
+    }
+
+    fn set_window_opacity(&self, opacity: f32) {
+        self.record(IOCall::SetWindowOpacity(opacity));
+    }
+
+    fn backup_note(
+        &self,
+        note_file: NoteFile,
+        content: &str,
+        encrypt: bool,
+    ) -> Result<PathBuf, io::Error> {
+        self.record(IOCall::BackupNote(note_file, content.to_string(), encrypt));
+
+        let mut backups = self.backups.borrow_mut();
+        backups.push(NoteBackup {
+            note_file,
+            saved_at: get_utc_timestamp(SystemTime::now()),
+            content: content.to_string(),
+        });
+
+        Ok(PathBuf::from("backup.md"))
+    }
+
+    fn recent_note_backups(&self, limit: usize) -> Result<Vec<NoteBackup>, io::Error> {
+        Ok(self
+            .backups
+            .borrow()
+            .iter()
+            .rev()
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    fn open_shelv_folder(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.record(IOCall::OpenShelvFolder);
+        Ok(())
+    }
+
+    fn open_in_external_editor(
+        &self,
+        note_file: NoteFile,
+        command: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.record(IOCall::OpenInExternalEditor(
+            note_file,
+            command.map(str::to_string),
+        ));
+        Ok(())
+    }
+
+    fn try_read_note_if_newer(
+        &self,
+        _path: &PathBuf,
+        _last_saved: u128,
+    ) -> Result<Option<String>, io::Error> {
+        Ok(self.newer_note_content.clone())
+    }
+
+    fn cleanup_all_global_hotkeys(&mut self) -> Result<(), String> {
+        self.record(IOCall::CleanupGlobalHotkeys);
+        Ok(())
+    }
+
+    fn try_map_hotkey(&self, _hotkey_id: u32) -> Option<MsgToApp> {
+        None
+    }
+
+    fn bind_global_hotkey(
+        &mut self,
+        shortcut: KeyboardShortcut,
+        _to: Box<dyn Fn() -> MsgToApp>,
+    ) -> Result<(), String> {
+        self.record(IOCall::BindGlobalHotkey(shortcut));
+
+        match self.refused_hotkeys.contains(&shortcut) {
+            true => Err("Unable to register hotkey".to_string()),
+            false => Ok(()),
+        }
+    }
+
+    fn execute_llm_block<'s>(&self, question: LLMBlockRequest, _cx: SettingsForAiRequests<'s>) {
+        self.record(IOCall::ExecuteLlmBlock(question.note_id));
+    }
+
+    fn execute_llm_prompt<'s>(&self, question: LLMPromptRequest, _cx: SettingsForAiRequests<'s>) {
+        self.record(IOCall::ExecuteLlmPrompt(question.prompt));
+    }
+
+    fn request_llm_suggestion<'s>(
+        &self,
+        request: LLMSuggestionRequest,
+        _cx: SettingsForAiRequests<'s>,
+    ) {
+        self.record(IOCall::RequestLlmSuggestion(request.before_cursor));
+    }
+
+    fn run_js_task(&self, request: JsTaskRequest) {
+        self.record(IOCall::RunJsTask(request));
+    }
+
+    fn fetch_remote_image(&self, url: String) {
+        self.record(IOCall::FetchRemoteImage(url));
+    }
+
+    fn capture_sentry_message<F>(
+        &self,
+        message: &str,
+        _level: sentry::Level,
+        _scope: F,
+    ) -> sentry::types::Uuid
+    where
+        F: FnOnce(&mut sentry::Scope),
+    {
+        self.record(IOCall::CaptureSentryMessage(message.to_string()));
+        sentry::types::Uuid::nil()
+    }
+
+    fn copy_to_clipboard(&self, text: String) {
+        self.record(IOCall::CopyToClipboard(text));
+    }
+
+    fn copy_image_to_clipboard(&self, _image: ColorImage) {
+        self.record(IOCall::CopyImageToClipboard);
+    }
+
+    fn read_clipboard_text(&self) -> Option<String> {
+        self.clipboard_text.clone()
+    }
+
+    fn export_note(
+        &self,
+        suggested_file_name: &str,
+        content: &str,
+    ) -> Result<Option<PathBuf>, io::Error> {
+        self.record(IOCall::ExportNote(
+            suggested_file_name.to_string(),
+            content.to_string(),
+        ));
+        Ok(Some(PathBuf::from(suggested_file_name)))
+    }
+
+    fn start_update_checker(&self) {
+        self.record(IOCall::StartUpdateChecker);
+    }
+
+    fn open_app_store_for_shelv_update(&self) {
+        self.record(IOCall::OpenAppStore);
+    }
+
+    fn disable_crash_reporting(&self) {
+        self.record(IOCall::DisableCrashReporting);
+    }
+
+    fn is_start_at_login_enabled(&self) -> bool {
+        false
+    }
+
+    fn set_start_at_login(&self, enabled: bool) -> Result<(), Box<dyn std::error::Error>> {
+        self.record(IOCall::SetStartAtLogin(enabled));
+        Ok(())
+    }
+}
+
+/// Feeds actions through `process_app_action` without rendering,
+/// the editor of the selected note is treated as focused
+pub struct TestHarness {
+    pub state: AppState,
+    pub io: TestAppIO,
+    ctx: Context,
+    /// links are opened by egui, not by `AppIO`
+    opened_links: Vec<String>,
+}
+
+impl TestHarness {
+    /// `text` becomes the content of the first note, with the cursor encoded as in `TextChange`
+    pub fn new(text: &str) -> Self {
+        let (text, cursor) = TextChange::try_extract_cursor(text.to_string());
+        let (_, mut persistent_state) = bootstrap(4);
+        persistent_state.notes[0] = text;
+
+        let (_msg_queue_tx, msg_queue) = sync_channel(1);
+        let mut state = AppState::new(AppInitData {
+            theme: AppTheme::default(),
+            msg_queue,
+            persistent_state,
+            last_saved: 0,
+            load_kind: LoadKind::Normal,
+            persistence_warning: None,
+            persistence_folder: PathBuf::from("shelv"),
+            start_at_login: false,
+        });
+        state.selected_note = NoteFile::Note(0);
+
+        if let Some(cursor) = cursor {
+            let note = state.notes.get_mut(&NoteFile::Note(0)).unwrap();
+            note.update_cursor(cursor.unordered());
+        }
+
+        let mut harness = Self {
+            state,
+            io: TestAppIO::default(),
+            ctx: Context::default(),
+            opened_links: vec![],
+        };
+
+        // the app evaluates the notes after the first frame
+        let initial_actions = std::mem::take(&mut harness.state.deferred_actions);
+        for action in initial_actions {
+            harness.dispatch(action);
+        }
+        harness.io.take_calls();
+        harness.opened_links.clear();
+
+        harness
+    }
+
+    /// Processes the action with everything it produces, including the deferred ones
+    pub fn dispatch(&mut self, action: AppAction) {
+        let Self { state, io, ctx, .. } = self;
+
+        let focus_state = AppFocusState {
+            is_menu_opened: false,
+            viewport_focused: true,
+            internal_focus: Some(AppFocus::NoteEditor),
+        };
+
+        let mut pending = Some(action);
+        let output = ctx.run(RawInput::default(), |ctx| {
+            let Some(action) = pending.take() else {
+                return;
+            };
+
+            let mut action_buffer: SmallVec<[AppAction; 4]> = SmallVec::from_iter([action]);
+
+            // deferred actions can defer more, but never indefinitely
+            for _ in 0..8 {
+                while let Some(to_process) = action_buffer.pop() {
+                    let text_edit_id = compute_editor_text_id(state.selected_note);
+                    let new_actions =
+                        process_app_action(to_process, ctx, state, focus_state, text_edit_id, io);
+                    action_buffer.extend(new_actions);
+                }
+
+                if state.deferred_actions.is_empty() {
+                    break;
+                }
+
+                action_buffer.extend(state.deferred_actions.drain(..));
+            }
+        });
+
+        self.opened_links.extend(
+            output
+                .platform_output
+                .commands
+                .into_iter()
+                .filter_map(|cmd| match cmd {
+                    OutputCommand::OpenUrl(open_url) => Some(open_url.url),
+                    _ => None,
+                }),
+        );
+    }
+
+    /// Runs the command as if its shortcut was pressed, regardless of the bound scope
+    pub fn run_command(&mut self, instruction: CommandInstruction) {
+        let mut scripts = self
+            .state
+            .settings_scripts
+            .take()
+            .unwrap_or_else(Scripts::new);
+
+        let actions = self.state.commands.run(
+            &instruction,
+            CommandScope::Global,
+            CommandContext {
+                app_state: &self.state,
+                app_focus: AppFocusState {
+                    is_menu_opened: false,
+                    viewport_focused: true,
+                    internal_focus: Some(AppFocus::NoteEditor),
+                },
+                ui_state: self.state.to_ui_state(),
+                scripts: &mut scripts,
+            },
+        );
+
+        self.state.settings_scripts = Some(scripts);
+
+        for action in actions {
+            self.dispatch(action);
+        }
+    }
+
+    /// Prepends a kdl block to the settings note and evaluates it, as if it was typed there.
+    /// Returns the span of the block, e.g. to remove it later
+    pub fn with_settings(&mut self, kdl: &str) -> ByteSpan {
+        let block = format!("```kdl\n{kdl}\n```\n\n");
+        let span = ByteSpan::new(0, block.len());
+
+        self.dispatch(AppAction::ApplyTextChanges {
+            target: NoteFile::Settings,
+            changes: vec![TextChange::Insert(ByteSpan::new(0, 0), block)],
+            should_trigger_eval: true,
+        });
+
+        span
+    }
+
+    /// Text of the selected note with the cursor encoded as in `TextChange`
+    pub fn text_with_cursor(&self) -> String {
+        let note = self.state.notes.get(&self.state.selected_note).unwrap();
+        match note.cursor() {
+            Some(cursor) => TextChange::encode_cursor(&note.text, cursor),
+            None => note.text.clone(),
+        }
+    }
+
+    pub fn take_opened_links(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.opened_links)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_commands_edit_the_selected_note() {
+        let mut harness = TestHarness::new("- first{||}");
+        harness.run_command(CommandInstruction::SplitListItem);
+        assert_eq!(harness.text_with_cursor(), "- first\n- {||}");
+
+        let mut harness = TestHarness::new("make {|}this{|} bold");
+        harness.run_command(CommandInstruction::MarkdownBold);
+        assert_eq!(harness.text_with_cursor(), "make **{|}this{|}** bold");
+    }
+
+    #[test]
+    pub fn test_io_calls_are_recorded() {
+        let mut harness = TestHarness::new("```js\nlet a = 1;\n```\n{||}");
+
+        let (code_block, ..) = harness.state.notes[&NoteFile::Note(0)]
+            .derived_state
+            .structure
+            .filter_map_codeblocks(|lang| (lang == "js").then_some(()))
+            .next()
+            .unwrap();
+
+        harness.dispatch(AppAction::CopyCodeBlock(NoteFile::Note(0), code_block));
+        harness.dispatch(AppAction::HideApp);
+
+        let calls = harness.io.take_calls();
+        assert!(
+            matches!(
+                calls.as_slice(),
+                [IOCall::CopyToClipboard(code), IOCall::HideApp(HideMode::HideApp)]
+                    if code.trim_end() == "let a = 1;"
+            ),
+            "{calls:?}"
+        );
+
+        harness.dispatch(AppAction::OpenLink("https://shelv.app".to_string()));
+        assert_eq!(harness.take_opened_links(), vec!["https://shelv.app"]);
+    }
+}
@@ -7,22 +7,59 @@ use eframe::{
 use fxhash::hash64;
 use itertools::Itertools;
 use linkify::LinkFinder;
-use pulldown_cmark::{CodeBlockKind, HeadingLevel};
+use pulldown_cmark::{BlockQuoteKind, CodeBlockKind, HeadingLevel, MetadataBlockKind};
 use smallvec::{SmallVec, smallvec};
-use syntect::{
-    easy::HighlightLines, highlighting::ThemeSet, parsing::SyntaxSet, util::LinesWithEndings,
-};
-
-use tree_sitter::Parser;
-use tree_sitter_highlight::{HighlightConfiguration, HighlightEvent, Highlighter};
+use syntect::{highlighting::ThemeSet, parsing::SyntaxSet};
 
 use crate::{
     byte_span::ByteSpan,
+    code_highlight::CodeHighlightCache,
     nord::Nord,
-    scripting::note_eval::JSBlockLang,
-    theme::{AppTheme, ColorManipulation, ColorTheme, FontTheme},
+    scripting::{js_snippets::SNIPPETS_BLOCK_LANG, note_eval::JSBlockLang},
+    theme::{AppTheme, ColorTheme, FontTheme},
 };
 
+/// Small enough to be invisible, font sizes have to be positive
+const HIDDEN_MARKER_FONT_SIZE: f32 = 1.0;
+
+/// How markdown markers, e.g. `**` or `# `, are rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MarkerVisibility {
+    Shown,
+    /// Reading mode
+    Hidden,
+    /// Live preview: markers are shown only on the lines of the cursor
+    ShownOnLines(ByteSpan),
+}
+
+impl MarkerVisibility {
+    /// Works on bytes, so a stale cursor (e.g. from the previous frame) can't land in the middle of a char
+    pub fn around_cursor(text: &str, cursor: ByteSpan) -> Self {
+        let bytes = text.as_bytes();
+        let start = cursor.start.min(bytes.len());
+        let end = cursor.end.clamp(start, bytes.len());
+
+        let lines_start = bytes[..start]
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map_or(0, |i| i + 1);
+        let lines_end = bytes[end..]
+            .iter()
+            .position(|b| *b == b'\n')
+            .map_or(bytes.len(), |i| end + i);
+
+        Self::ShownOnLines(ByteSpan::new(lines_start, lines_end))
+    }
+
+    fn shown_range(&self, text_len: usize) -> Range<usize> {
+        match self {
+            MarkerVisibility::Shown => 0..text_len,
+            MarkerVisibility::Hidden => 0..0,
+            MarkerVisibility::ShownOnLines(lines) => lines.range(),
+        }
+    }
+}
+
 #[derive(Debug, Hash, Clone, Copy, PartialEq, Eq)]
 pub struct SpanIndex(usize);
 
@@ -39,6 +76,7 @@ enum Annotation {
     Emphasis,
     Text,
     TaskMarker,
+    TaskInProgress,
     Link,
     RawLink,
     Heading(HeadingLevel),
@@ -46,6 +84,11 @@ enum Annotation {
     CodeBlockLang,
     InlineCode,
     ListItemMarker,
+    FrontMatter,
+    DefinitionTerm,
+    DefinitionDescription,
+    DetailsSummary,
+    CollapsedDetails,
 }
 
 #[derive(Debug)]
@@ -72,9 +115,64 @@ pub enum SpanKind {
     Html,
     ListItem,
     Image,
+    Table,
+    TableHead,
+    TableRow,
+    TableCell,
+    /// `---` delimited yaml block at the very beginning of the note
+    FrontMatter,
+    /// `Term` line of a definition list, followed by `: description` lines
+    DefinitionTerm,
+    DefinitionDescription,
+    /// `> [!NOTE]` GitHub style alert, regular blockquotes are not supported
+    Callout(CalloutKind),
     Root,
 }
 
+/// Types of GitHub style alerts, rendered as colored callouts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, knus::DecodeScalar)]
+pub enum CalloutKind {
+    Note,
+    Tip,
+    Important,
+    Warning,
+    Caution,
+}
+
+impl CalloutKind {
+    pub fn title(&self) -> &'static str {
+        match self {
+            CalloutKind::Note => "Note",
+            CalloutKind::Tip => "Tip",
+            CalloutKind::Important => "Important",
+            CalloutKind::Warning => "Warning",
+            CalloutKind::Caution => "Caution",
+        }
+    }
+
+    pub fn marker(&self) -> &'static str {
+        match self {
+            CalloutKind::Note => "[!NOTE]",
+            CalloutKind::Tip => "[!TIP]",
+            CalloutKind::Important => "[!IMPORTANT]",
+            CalloutKind::Warning => "[!WARNING]",
+            CalloutKind::Caution => "[!CAUTION]",
+        }
+    }
+}
+
+impl From<BlockQuoteKind> for CalloutKind {
+    fn from(kind: BlockQuoteKind) -> Self {
+        match kind {
+            BlockQuoteKind::Note => CalloutKind::Note,
+            BlockQuoteKind::Tip => CalloutKind::Tip,
+            BlockQuoteKind::Important => CalloutKind::Important,
+            BlockQuoteKind::Warning => CalloutKind::Warning,
+            BlockQuoteKind::Caution => CalloutKind::Caution,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CodeBlockMeta {
     pub closed: bool,
@@ -85,9 +183,37 @@ pub struct CodeBlockMeta {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SpanMeta {
     CodeBlock(CodeBlockMeta),
-    TaskMarker { checked: bool },
+    TaskMarker(TaskState),
     List(ListDesc),
     Link { url: String },
+    Image { url: String },
+}
+
+/// `[ ]` -> `[/]` -> `[x]` -> `[ ]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskState {
+    Todo,
+    /// `[/]` is not a part of GFM task lists, so it is recognized by the builder itself
+    InProgress,
+    Done,
+}
+
+impl TaskState {
+    pub fn marker(&self) -> &'static str {
+        match self {
+            TaskState::Todo => "[ ]",
+            TaskState::InProgress => "[/]",
+            TaskState::Done => "[x]",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            TaskState::Todo => TaskState::InProgress,
+            TaskState::InProgress => TaskState::Done,
+            TaskState::Done => TaskState::Todo,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -105,14 +231,24 @@ struct MarkdownRunningState {
     link: i8,
     raw_link: i8,
     task_marker: i8,
+    task_in_progress: i8,
     list_marker: i8,
     code: i8,
     code_block: i8,
     code_block_lang: i8,
+    front_matter: i8,
+    definition_term: i8,
+    details_summary: i8,
+    collapsed_details: i8,
     heading: [i8; 6],
 }
 
 impl MarkdownRunningState {
+    /// Markdown syntax around the text, e.g. `**` or `# `, list and task markers are a part of the content
+    fn is_marker(&self) -> bool {
+        self.text == 0 && self.raw_link == 0 && self.list_marker == 0 && self.task_marker == 0
+    }
+
     fn new() -> Self {
         Self {
             // nesting: 0,
@@ -121,12 +257,17 @@ impl MarkdownRunningState {
             code: 0,
             code_block: 0,
             code_block_lang: 0,
+            front_matter: 0,
+            definition_term: 0,
+            details_summary: 0,
+            collapsed_details: 0,
             emphasis: 0,
             heading: Default::default(),
             text: 0,
             link: 0,
             raw_link: 0,
             task_marker: 0,
+            task_in_progress: 0,
             list_marker: 0,
         }
     }
@@ -158,6 +299,7 @@ impl LineLocation {
 pub struct TextStructure {
     points: Vec<AnnotationPoint>,
     raw_links: Vec<RawLink>,
+    details_blocks: Vec<DetailsBlock>,
     spans: Vec<SpanDesc>,
     metadata: Vec<(SpanIndex, SpanMeta)>,
     text_hash: TextHash,
@@ -170,6 +312,7 @@ impl Default for TextStructure {
         Self {
             points: Vec::with_capacity(0),
             raw_links: Vec::with_capacity(0),
+            details_blocks: Vec::with_capacity(0),
             spans: Vec::with_capacity(0),
             metadata: Vec::with_capacity(0),
             text_hash: TextHash::new(""),
@@ -184,6 +327,20 @@ struct RawLink {
     byte_pos: Range<usize>,
 }
 
+/// `<details>` html block, its body is collapsed unless the tag has the `open` attribute
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetailsBlock {
+    /// `<details>` or `<details open>`, toggling the block rewrites it
+    pub open_tag: ByteSpan,
+    /// `<summary>...</summary>` (or the opening tag if there is no summary), clicking it toggles the block
+    pub summary: ByteSpan,
+    /// Text between the summary tags
+    pub title: ByteSpan,
+    /// Everything after the summary, including `</details>`
+    pub body: ByteSpan,
+    pub is_open: bool,
+}
+
 /// this is for inline LLM prompts and suggestions in the future
 #[derive(Debug)]
 pub enum TextDiffPart {
@@ -199,12 +356,18 @@ pub struct TextStructureBuilder<'a> {
     raw_links: Vec<RawLink>,
     metadata: Vec<(SpanIndex, SpanMeta)>,
     lines: Vec<ByteSpan>,
+    /// end of the last `[/]` task marker, text events can't start before it
+    in_progress_marker_end: usize,
 }
 
 pub enum InteractiveTextPart<'a> {
     // byte pos the text, note that it is not the same as char
-    TaskMarker { byte_range: ByteSpan, checked: bool },
+    TaskMarker {
+        byte_range: ByteSpan,
+        state: TaskState,
+    },
     Link(&'a str),
+    DetailsSummary(&'a DetailsBlock),
 }
 
 impl<'a> TextStructureBuilder<'a> {
@@ -257,7 +420,54 @@ impl<'a> TextStructureBuilder<'a> {
             container_stack: smallvec![SpanIndex(0)],
             raw_links,
             lines,
+            in_progress_marker_end: 0,
+        }
+    }
+
+    /// pulldown_cmark knows only about `[ ]` and `[x]`,
+    /// so `[/]` at the start of a list item is split from its text here. Returns the rest of the text
+    fn split_in_progress_task_marker(&mut self, range: ByteSpan) -> ByteSpan {
+        // the text around brackets might come in several events
+        let range = ByteSpan::new(
+            range.start.max(self.in_progress_marker_end).min(range.end),
+            range.end,
+        );
+
+        let container_index = *self.container_stack.last().unwrap();
+        let container = &self.spans[container_index.0];
+        let has_children = self.spans.len() > container_index.0 + 1;
+
+        let is_item_start = !has_children
+            && match container.kind {
+                SpanKind::ListItem => true,
+                // items of loose lists are wrapped into paragraphs
+                SpanKind::Paragraph => {
+                    self.spans[container.parent.0].kind == SpanKind::ListItem
+                        && container.byte_pos.start == range.start
+                }
+                _ => false,
+            };
+
+        let marker = TaskState::InProgress.marker();
+        let marker_end = range.start + marker.len();
+        let is_marker = self.text[range.start..].starts_with(marker)
+            && self
+                .text
+                .get(marker_end..)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t', '\n']));
+
+        if !is_item_start || !is_marker {
+            return range;
         }
+
+        self.add_with_meta(
+            SpanKind::TaskMarker,
+            ByteSpan::new(range.start, marker_end),
+            SpanMeta::TaskMarker(TaskState::InProgress),
+        );
+        self.in_progress_marker_end = marker_end;
+
+        ByteSpan::new(marker_end.min(range.end), range.end)
     }
 
     fn add(&mut self, kind: SpanKind, pos: ByteSpan) -> SpanIndex {
@@ -286,7 +496,7 @@ impl<'a> TextStructureBuilder<'a> {
         let Self {
             spans,
             metadata,
-            raw_links,
+            mut raw_links,
             text,
             lines,
             ..
@@ -294,13 +504,30 @@ impl<'a> TextStructureBuilder<'a> {
 
         annotation_points.clear();
 
-        let points = fill_annotation_points(annotation_points, &spans, &metadata, &raw_links);
+        // urls in code are just text, e.g. an argument of `curl`
+        raw_links.retain(|link| {
+            let link_span = ByteSpan::from_range(&link.byte_pos);
+            !spans.iter().any(|desc| {
+                matches!(desc.kind, SpanKind::CodeBlock | SpanKind::InlineCode)
+                    && desc.byte_pos.contains(link_span)
+            })
+        });
+
+        let details_blocks = find_details_blocks(text, &spans);
+        let points = fill_annotation_points(
+            annotation_points,
+            &spans,
+            &metadata,
+            &raw_links,
+            &details_blocks,
+        );
 
         TextStructure {
             points,
             spans,
             metadata,
             raw_links,
+            details_blocks,
             lines,
             text_hash: TextHash::new(text),
         }
@@ -377,7 +604,11 @@ impl<'a> TextStructureBuilder<'a> {
 
         let md_parser_options = pulldown_cmark::Options::ENABLE_STRIKETHROUGH
             | pulldown_cmark::Options::ENABLE_TASKLISTS
-            | pulldown_cmark::Options::ENABLE_SMART_PUNCTUATION;
+            | pulldown_cmark::Options::ENABLE_TABLES
+            | pulldown_cmark::Options::ENABLE_SMART_PUNCTUATION
+            | pulldown_cmark::Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+            | pulldown_cmark::Options::ENABLE_DEFINITION_LIST
+            | pulldown_cmark::Options::ENABLE_GFM;
 
         let parser = pulldown_cmark::Parser::new_ext(self.text, md_parser_options);
 
@@ -487,6 +718,7 @@ impl TextStructure {
         let struture = Self {
             points: vec![],
             raw_links: vec![],
+            details_blocks: vec![],
             spans: vec![],
             metadata: vec![],
             lines: vec![],
@@ -500,6 +732,16 @@ impl TextStructure {
     }
 
     pub fn recycle(self, text: &str) -> Self {
+        self.parse(text, true)
+    }
+
+    /// Same as `recycle`, but bare urls are not looked for, they are plain text then.
+    /// Scanning a huge note (e.g. a pasted log) for them on every keystroke is noticeable
+    pub fn recycle_without_raw_links(self, text: &str) -> Self {
+        self.parse(text, false)
+    }
+
+    fn parse(self, text: &str, find_raw_links: bool) -> Self {
         let Self {
             points,
             raw_links,
@@ -510,18 +752,23 @@ impl TextStructure {
         } = self;
 
         let mut builder = TextStructureBuilder::start(text, (spans, raw_links, metadata, lines));
-        let finder = LinkFinder::new();
 
-        for link in finder.links(text) {
-            builder.raw_links.push(RawLink {
-                url: link.as_str().to_string(),
-                byte_pos: link.start()..link.end(),
-            });
+        if find_raw_links {
+            for link in LinkFinder::new().links(text) {
+                builder.raw_links.push(RawLink {
+                    url: link.as_str().to_string(),
+                    byte_pos: link.start()..link.end(),
+                });
+            }
         }
 
         let md_parser_options = pulldown_cmark::Options::ENABLE_STRIKETHROUGH
             | pulldown_cmark::Options::ENABLE_TASKLISTS
-            | pulldown_cmark::Options::ENABLE_SMART_PUNCTUATION;
+            | pulldown_cmark::Options::ENABLE_TABLES
+            | pulldown_cmark::Options::ENABLE_SMART_PUNCTUATION
+            | pulldown_cmark::Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+            | pulldown_cmark::Options::ENABLE_DEFINITION_LIST
+            | pulldown_cmark::Options::ENABLE_GFM;
 
         let parser = pulldown_cmark::Parser::new_ext(&text, md_parser_options);
         // println!("Parser output:\n{:?}", parser);
@@ -601,17 +848,50 @@ impl TextStructure {
                                 url: dest_url.to_string(),
                             },
                         )),
-                        Image { .. } => Some(builder.add(SpanKind::Image, range)),
+                        Image { dest_url, .. } => Some(builder.add_with_meta(
+                            SpanKind::Image,
+                            range,
+                            SpanMeta::Image {
+                                url: dest_url.to_string(),
+                            },
+                        )),
+
+                        Table(_) => Some(
+                            builder.add(SpanKind::Table, trim_trailing_new_lines(&text, range)),
+                        ),
+                        TableHead => Some(
+                            builder.add(SpanKind::TableHead, trim_trailing_new_lines(&text, range)),
+                        ),
+                        TableRow => Some(
+                            builder.add(SpanKind::TableRow, trim_trailing_new_lines(&text, range)),
+                        ),
+                        TableCell => Some(builder.add(SpanKind::TableCell, range)),
+
+                        MetadataBlock(MetadataBlockKind::YamlStyle) => {
+                            Some(builder.add(SpanKind::FrontMatter, range))
+                        }
+
+                        DefinitionListTitle => Some(builder.add(
+                            SpanKind::DefinitionTerm,
+                            trim_trailing_new_lines(&text, range),
+                        )),
+                        DefinitionListDefinition => Some(builder.add(
+                            SpanKind::DefinitionDescription,
+                            trim_trailing_new_lines(&text, range),
+                        )),
+
+                        BlockQuote(Some(kind)) => Some(builder.add(
+                            SpanKind::Callout(kind.into()),
+                            trim_trailing_new_lines(&text, range),
+                        )),
 
                         // We explicitly don't support these containers
-                        Table(_)
-                        | TableHead
-                        | TableRow
-                        | TableCell
-                        | FootnoteDefinition(_)
+                        // the range of a definition list can overlap the paragraph after it
+                        FootnoteDefinition(_)
                         | HtmlBlock
                         | MetadataBlock(_)
-                        | BlockQuote(_) => None,
+                        | BlockQuote(None)
+                        | DefinitionList => None,
                     };
 
                     if let Some(container_index) = container {
@@ -622,16 +902,15 @@ impl TextStructure {
                 End(tag) => {
                     use pulldown_cmark::TagEnd as T;
                     let is_supported_container = match tag {
+                        T::MetadataBlock(MetadataBlockKind::YamlStyle) => true,
+
                         // We explicitly don't support these containers
                         // note that it needs to match "Start" variant
-                        T::Table
-                        | T::TableHead
-                        | T::TableRow
-                        | T::TableCell
-                        | T::FootnoteDefinition
+                        T::FootnoteDefinition
                         | T::HtmlBlock
                         | T::MetadataBlock(_)
-                        | T::BlockQuote => false,
+                        | T::BlockQuote(None)
+                        | T::DefinitionList => false,
 
                         // supported containers. Note that it needs to match "Start" variant
                         T::Paragraph
@@ -643,7 +922,14 @@ impl TextStructure {
                         | T::Strong
                         | T::Strikethrough
                         | T::Link { .. }
-                        | T::Image => true,
+                        | T::Image
+                        | T::Table
+                        | T::TableHead
+                        | T::TableRow
+                        | T::TableCell
+                        | T::DefinitionListTitle
+                        | T::DefinitionListDefinition
+                        | T::BlockQuote(Some(_)) => true,
                     };
 
                     if is_supported_container {
@@ -652,14 +938,20 @@ impl TextStructure {
                 }
 
                 Text(_) => {
-                    builder.add(SpanKind::Text, range);
+                    let range = builder.split_in_progress_task_marker(range);
+                    if !range.is_empty() {
+                        builder.add(SpanKind::Text, range);
+                    }
                 }
 
                 TaskListMarker(checked) => {
                     builder.add_with_meta(
                         SpanKind::TaskMarker,
                         range.clone(),
-                        SpanMeta::TaskMarker { checked },
+                        SpanMeta::TaskMarker(match checked {
+                            true => TaskState::Done,
+                            false => TaskState::Todo,
+                        }),
                     );
                 }
 
@@ -681,13 +973,20 @@ impl TextStructure {
         builder.finish(points)
     }
 
+    /// Hidden markdown markers are rendered by `append_with_hidden_markers`,
+    /// only the code blocks missing from `highlight_cache` are highlighted (if `highlight_code` at all)
     pub fn create_layout_job(
         &self,
         text: &str,
         theme: &AppTheme,
         syntax_set: &SyntaxSet,
         theme_set: &ThemeSet,
+        highlight_cache: &mut CodeHighlightCache,
+        marker_visibility: MarkerVisibility,
+        auto_links: bool,
+        highlight_code: bool,
     ) -> LayoutJob {
+        let shown_markers = marker_visibility.shown_range(text.len());
         let mut pos: usize = 0;
         let mut job = LayoutJob::default();
 
@@ -712,7 +1011,18 @@ impl TextStructure {
             //     }
             // }
 
-            if state.code_block > 0 && state.text > 0 {
+            if state.collapsed_details > 0 {
+                // the rows of a collapsed block are shrunk, but the text stays in the galley for the cursor
+                let hidden = text.get(pos..point.str_offset).unwrap_or("");
+                job.append(
+                    hidden,
+                    0.0,
+                    TextFormat::simple(
+                        FontId::new(HIDDEN_MARKER_FONT_SIZE, theme.fonts.family.normal.clone()),
+                        Color32::TRANSPARENT,
+                    ),
+                );
+            } else if state.code_block > 0 && state.text > 0 {
                 // means that we are inside code block body
 
                 let code = text.get(pos..point.str_offset).unwrap_or("");
@@ -739,156 +1049,25 @@ impl TextStructure {
                     match lang.as_str() {
                         "ts" => "typescript",
                         "rust" => "rs",
+                        SNIPPETS_BLOCK_LANG => "js",
                         l => l,
                     }
                 };
 
-                match syntax_set.find_syntax_by_extension(lang) {
-                    Some(syntax) => {
-                        let mut h =
-                            HighlightLines::new(syntax, &theme_set.themes["base16-ocean.dark"]);
-                        // let s = "pub struct Wow { hi: u64 }\nfn blah() -> u64 {}";
-                        // for line in LinesWithEndings::from(s) {
-                        //     let ranges: Vec<(Style, &str)> = h.highlight_line(line, &ps).unwrap();
-                        //     let escaped = as_24_bit_terminal_escaped(&ranges[..], true);
-                        //     print!("{}", escaped);
-                        // }
-
-                        for line in LinesWithEndings::from(code) {
-                            let ranges = h.highlight_line(line, &syntax_set).unwrap();
-                            for (style, part) in ranges {
-                                let front = style.foreground;
-
-                                // println!("{:?}", (part, style.foreground));
-                                job.append(
-                                    part,
-                                    0.0,
-                                    TextFormat::simple(
-                                        code_font_id.clone(),
-                                        Color32::from_rgb(front.r, front.g, front.b),
-                                    ),
-                                );
-                            }
-                        }
-                    }
-
-                    None if lang == "kdl" => {
-                        let mut highlighter = Highlighter::new();
-
-                        let kdl_lang = tree_sitter_kdl::language();
+                let highlighted = match highlight_code {
+                    true => highlight_cache.highlight(code, lang, syntax_set, theme_set),
+                    false => None,
+                };
 
-                        let mut kdl_config = HighlightConfiguration::new(
-                            kdl_lang,
-                            tree_sitter_kdl::HIGHLIGHTS_QUERY,
-                            tree_sitter_kdl::INJECTIONS_QUERY,
-                            tree_sitter_kdl::LOCALS_QUERY,
-                        )
-                        .unwrap();
-
-                        let highlight_pairs = [
-                            ("type", Nord::NORD6),
-                            ("type.builtin", Nord::NORD9),
-                            ("property", Nord::NORD8),
-                            ("variable", Nord::NORD4),
-                            ("string", Nord::NORD14),
-                            ("string.escape", Nord::NORD13),
-                            ("number", Nord::NORD15),
-                            ("float", Nord::NORD15),
-                            ("boolean", Nord::NORD9),
-                            ("constant.builtin", Nord::NORD9),
-                            ("punctuation.bracket", Nord::NORD10),
-                            ("punctuation.delimiter", Nord::NORD9),
-                            ("comment", Nord::NORD3.shade(1.2)),
-                            ("spell", Nord::NORD3.shade(1.2)),
-                        ];
-
-                        let highlight_names = highlight_pairs.map(|(name, _)| name);
-
-                        kdl_config.configure(&highlight_names);
-
-                        let highlights = highlighter
-                            .highlight(&kdl_config, code.as_bytes(), None, |_| None)
-                            .unwrap();
-
-                        let mut color = Nord::NORD4;
-                        let mut ident = 0;
-
-                        for event in highlights {
-                            match event.unwrap() {
-                                HighlightEvent::Source { start, end } => {
-                                    // eprintln!("{}{}", "  ".repeat(ident), &code[start..end]);
-
-                                    job.append(
-                                        &code[start..end],
-                                        0.0,
-                                        TextFormat::simple(code_font_id.clone(), color),
-                                    )
-                                }
-                                HighlightEvent::HighlightStart(s) => {
-                                    color = highlight_pairs[s.0].1;
-                                    // eprintln!("{}{} {{", "  ".repeat(ident), highlight_names[s.0]);
-                                    ident = ident + 1;
-                                }
-                                HighlightEvent::HighlightEnd => {
-                                    ident = ident - 1;
-                                    // eprintln!("{}}}", "  ".repeat(ident));
-                                }
-                            }
+                match highlighted {
+                    Some(highlighted) => {
+                        for (range, color) in highlighted {
+                            job.append(
+                                &code[range.clone()],
+                                0.0,
+                                TextFormat::simple(code_font_id.clone(), *color),
+                            );
                         }
-
-                        let mut parser = Parser::new();
-                        parser
-                            .set_language(kdl_lang)
-                            .expect("Error setting language");
-
-                        // fn calculate_indentation(node: Node, source_code: &str) -> usize {
-                        //     let mut depth = 0;
-                        //     let mut current = Some(node);
-
-                        //     while let Some(n) = current {
-                        //         if n.kind() == "object" || n.kind() == "block" {
-                        //             depth += 1;
-                        //         }
-                        //         current = n.parent();
-                        //     }
-
-                        //     depth
-                        // }
-
-                        // let tree = parser.parse(code, None).unwrap();
-                        // println!("Tree structure:");
-                        // println!("{}", tree.root_node().to_sexp());
-
-                        // // println!("{tree:#?}");
-
-                        // fn format_code(parser: &mut Parser, source_code: &str) {
-                        //     let tree = parser.parse(source_code, None).unwrap();
-                        //     let query =
-                        //         Query::new(parser.language().unwrap(), "(object) @obj").unwrap();
-                        //     let mut cursor = QueryCursor::new();
-
-                        //     for m in
-                        //         cursor.matches(&query, tree.root_node(), source_code.as_bytes())
-                        //     {
-                        //         let node = m.captures[0].node;
-                        //         let indent = calculate_indentation(node, source_code);
-                        //         println!(
-                        //             "Node at {}: {} should be indented {} levels",
-                        //             node.start_position(),
-                        //             node.kind(),
-                        //             indent
-                        //         );
-                        //     }
-                        // }
-
-                        // job.append(
-                        //     code,
-                        //     0.0,
-                        //     TextFormat::simple(
-                        //         code_font_id.clone(),
-                        //         theme.colors.normal_text_color,
-                        //     ),
-                        // )
                     }
 
                     None => job.append(
@@ -897,28 +1076,43 @@ impl TextStructure {
                         TextFormat::simple(code_font_id.clone(), theme.colors.normal_text_color),
                     ),
                 }
-            } else if state.code_block_lang > 0 {
-                // Handle granular syntax highlighting for code block languages
-                let lang_text = text.get(pos..point.str_offset).unwrap_or("");
-                append_granular_lang_highlight(&mut job, lang_text, theme);
             } else if state.code_block > 0 {
-                job.append(
-                    text.get(pos..point.str_offset).unwrap_or(""),
-                    0.0,
-                    TextFormat::simple(
-                        FontId {
-                            size: theme.fonts.size.tiny,
-                            family: theme.fonts.family.code.clone(),
-                        },
-                        theme.colors.subtle_text_color,
-                    ),
-                )
-            } else {
-                job.append(
-                    text.get(pos..point.str_offset).unwrap_or(""),
-                    0.0,
-                    state.to_text_format(theme),
+                let fence_format = TextFormat::simple(
+                    FontId {
+                        size: theme.fonts.size.tiny,
+                        family: theme.fonts.family.code.clone(),
+                    },
+                    theme.colors.subtle_text_color,
                 );
+
+                let fence = text.get(pos..point.str_offset).unwrap_or("");
+
+                for (fence, is_shown) in split_by_marker_visibility(fence, pos, &shown_markers) {
+                    match (is_shown, state.code_block_lang > 0) {
+                        (false, _) => {
+                            append_with_hidden_markers(&mut job, fence, fence_format.clone())
+                        }
+                        // Handle granular syntax highlighting for code block languages
+                        (true, true) => append_granular_lang_highlight(&mut job, fence, theme),
+                        (true, false) => job.append(fence, 0.0, fence_format.clone()),
+                    }
+                }
+            } else {
+                let part = text.get(pos..point.str_offset).unwrap_or("");
+                let format = state.to_text_format(theme);
+                match state.is_marker() {
+                    true => {
+                        for (part, is_shown) in
+                            split_by_marker_visibility(part, pos, &shown_markers)
+                        {
+                            match is_shown {
+                                true => job.append(part, 0.0, format.clone()),
+                                false => append_with_hidden_markers(&mut job, part, format.clone()),
+                            }
+                        }
+                    }
+                    false => job.append(part, 0.0, format),
+                }
             }
 
             let delta = match point.kind {
@@ -932,8 +1126,10 @@ impl TextStructure {
                 Annotation::Bold => state.bold += delta,
                 Annotation::Text => state.text += delta,
                 Annotation::Link => state.link += delta,
-                Annotation::RawLink => state.raw_link += delta,
+                Annotation::RawLink if auto_links => state.raw_link += delta,
+                Annotation::RawLink => (),
                 Annotation::TaskMarker => state.task_marker += delta,
+                Annotation::TaskInProgress => state.task_in_progress += delta,
                 Annotation::ListItemMarker => state.list_marker += delta,
                 Annotation::Emphasis => state.emphasis += delta,
 
@@ -943,6 +1139,16 @@ impl TextStructure {
                 Annotation::InlineCode => state.code += delta,
                 Annotation::CodeBlock => state.code_block += delta,
                 Annotation::CodeBlockLang => state.code_block_lang += delta,
+                Annotation::FrontMatter => state.front_matter += delta,
+                Annotation::DefinitionTerm => state.definition_term += delta,
+                Annotation::DetailsSummary => state.details_summary += delta,
+                Annotation::CollapsedDetails => state.collapsed_details += delta,
+                Annotation::DefinitionDescription => {
+                    // wrapped lines of the description start at the left edge though
+                    if matches!(point.kind, PointKind::Start) {
+                        job.append("", theme.sizes.l, state.to_text_format(theme));
+                    }
+                }
             }
 
             pos = point.str_offset;
@@ -955,13 +1161,25 @@ impl TextStructure {
             state.to_text_format(theme),
         );
 
+        highlight_cache.retain_used();
+
         job
     }
 
+    /// `auto_links` is whether bare urls (outside of code) are clickable
     pub fn find_interactive_text_part(
         &self,
         byte_cursor_pos: usize,
+        auto_links: bool,
     ) -> Option<InteractiveTextPart> {
+        if let Some(details) = self
+            .details_blocks
+            .iter()
+            .find(|details| details.summary.contains_pos(byte_cursor_pos))
+        {
+            return Some(InteractiveTextPart::DetailsSummary(details));
+        }
+
         let interactive_span =
             self.spans
                 .iter()
@@ -979,7 +1197,7 @@ impl TextStructure {
             || {
                 // Raw links in a MD link won't be interactive, which is why this is only the default case
                 self.raw_links.iter().find_map(|RawLink { url, byte_pos }| {
-                    if byte_pos.contains(&byte_cursor_pos) {
+                    if auto_links && byte_pos.contains(&byte_cursor_pos) {
                         Some(InteractiveTextPart::Link(url.as_str()))
                     } else {
                         None
@@ -989,10 +1207,10 @@ impl TextStructure {
             |(index, SpanDesc { kind, byte_pos, .. })| {
                 find_metadata(SpanIndex(index), &self.metadata).and_then(|meta| {
                     match (kind, meta) {
-                        (SpanKind::TaskMarker, SpanMeta::TaskMarker { checked, .. }) => {
+                        (SpanKind::TaskMarker, SpanMeta::TaskMarker(state)) => {
                             Some(InteractiveTextPart::TaskMarker {
                                 byte_range: byte_pos.clone(),
-                                checked: *checked,
+                                state: *state,
                             })
                         }
                         (SpanKind::MdLink, SpanMeta::Link { url }) => {
@@ -1050,6 +1268,13 @@ impl TextStructure {
         find_span_line_location(&self.lines, byte_cursor)
     }
 
+    /// Lines touched by the cursor (without the line breaks)
+    pub fn lines_of(&self, byte_cursor: ByteSpan) -> &[ByteSpan] {
+        self.find_line_location(byte_cursor)
+            .map(|(loc, _, _)| &self.lines[loc.line_start as usize..=loc.line_end as usize])
+            .unwrap_or_default()
+    }
+
     pub fn find_span_on_the_line(
         &self,
         span_kind: SpanKind,
@@ -1178,22 +1403,81 @@ impl TextStructure {
             })
     }
 
-    // pub fn find_any_span_at(
-    //     &self,
-    //     byte_cursor: ByteSpan,
-    // ) -> Option<(ByteSpan, SpanKind, SpanIndex)> {
-    //     self.spans
-    //         .iter()
-    //         .enumerate()
-    //         .rev()
-    //         .find_map(|(i, SpanDesc { byte_pos, kind, .. })| {
-    //             if byte_pos.contains(byte_cursor) && *kind != SpanKind::Root {
-    //                 Some((*byte_pos, *kind, SpanIndex(i)))
-    //             } else {
-    //                 None
-    //             }
-    //         })
-    // }
+    pub fn find_any_span_at(
+        &self,
+        byte_cursor: ByteSpan,
+    ) -> Option<(ByteSpan, SpanKind, SpanIndex)> {
+        self.spans
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(i, SpanDesc { byte_pos, kind, .. })| {
+                if byte_pos.contains(byte_cursor) && *kind != SpanKind::Root {
+                    Some((*byte_pos, *kind, SpanIndex(i)))
+                } else {
+                    None
+                }
+            })
+    }
+
+    /// Kinds of the spans surrounding the cursor, from the outermost to the innermost one.
+    /// Plain `Text` spans are skipped as they don't add any context
+    pub fn span_stack_at(&self, byte_cursor: ByteSpan) -> SmallVec<[SpanKind; 8]> {
+        let Some((_, kind, index)) = self.find_any_span_at(byte_cursor) else {
+            return SmallVec::new();
+        };
+
+        let mut stack: SmallVec<[SpanKind; 8]> = [kind]
+            .into_iter()
+            .chain(self.iterate_parents_of(index).map(|(_, desc)| desc.kind))
+            .filter(|kind| *kind != SpanKind::Text)
+            .collect();
+
+        stack.reverse();
+        stack
+    }
+
+    /// Text spans of the prose, that is not inside of code, html or the front matter
+    pub fn prose_text_spans(&self) -> impl Iterator<Item = ByteSpan> {
+        self.iter()
+            .filter(|(index, desc)| {
+                desc.kind == SpanKind::Text
+                    && !self.iterate_parents_of(*index).any(|(_, parent)| {
+                        matches!(
+                            parent.kind,
+                            SpanKind::CodeBlock
+                                | SpanKind::InlineCode
+                                | SpanKind::Html
+                                | SpanKind::FrontMatter
+                        )
+                    })
+            })
+            .map(|(_, desc)| desc.byte_pos)
+    }
+
+    pub fn details_blocks(&self) -> &[DetailsBlock] {
+        &self.details_blocks
+    }
+
+    /// Whether the span is hidden inside the body of a collapsed `<details>` block
+    pub fn is_in_collapsed_details(&self, span: ByteSpan) -> bool {
+        self.details_blocks
+            .iter()
+            .any(|details| !details.is_open && details.body.contains(span))
+    }
+
+    /// Content of the front matter block, without the `---` delimiters
+    pub fn front_matter<'t>(&self, text: &'t str) -> Option<&'t str> {
+        let (index, _) = self
+            .spans
+            .iter()
+            .enumerate()
+            .find(|(_, desc)| desc.kind == SpanKind::FrontMatter)?;
+
+        self.iterate_immediate_children_of(SpanIndex(index))
+            .find(|(_, desc)| desc.kind == SpanKind::Text)
+            .and_then(|(_, desc)| text.get(desc.byte_pos.range()))
+    }
 
     pub fn get_span_inner_content(&self, idx: SpanIndex) -> ByteSpan {
         let SpanIndex(index) = idx;
@@ -1222,7 +1506,15 @@ impl TextStructure {
             | SpanKind::CodeBlock
             | SpanKind::Heading(_)
             | SpanKind::Paragraph
-            | SpanKind::List =>
+            | SpanKind::List
+            | SpanKind::Table
+            | SpanKind::TableHead
+            | SpanKind::TableRow
+            | SpanKind::TableCell
+            | SpanKind::FrontMatter
+            | SpanKind::DefinitionTerm
+            | SpanKind::DefinitionDescription
+            | SpanKind::Callout(_) =>
             // self
             // .spans
             // .iter()
@@ -1246,11 +1538,188 @@ impl TextStructure {
     }
 }
 
+/// Splits the markers of `part` (starting at `offset` of the text) into hidden and shown pieces
+fn split_by_marker_visibility<'a>(
+    part: &'a str,
+    offset: usize,
+    shown: &Range<usize>,
+) -> impl Iterator<Item = (&'a str, bool)> {
+    let end = offset + part.len();
+    let shown_start = shown.start.clamp(offset, end);
+    let shown_end = shown.end.clamp(shown_start, end);
+
+    [
+        (offset..shown_start, false),
+        (shown_start..shown_end, true),
+        (shown_end..end, false),
+    ]
+    .into_iter()
+    .filter(|(range, _)| !range.is_empty())
+    .map(move |(range, is_shown)| (&part[range.start - offset..range.end - offset], is_shown))
+}
+
+/// Markers stay in the layout, so the galley still maps 1:1 to the text,
+/// but they are transparent and (almost) zero width. Line breaks and indentation are kept
+fn append_with_hidden_markers(job: &mut LayoutJob, part: &str, visible: TextFormat) {
+    let hidden = TextFormat::simple(
+        FontId::new(HIDDEN_MARKER_FONT_SIZE, visible.font_id.family.clone()),
+        Color32::TRANSPARENT,
+    );
+
+    let mut run_start = 0;
+    let mut run_is_hidden = None;
+    // whitespace after a marker, e.g. in `# heading`, is a part of the marker
+    let mut after_marker = false;
+
+    for (i, ch) in part.char_indices() {
+        let is_hidden = match ch {
+            '\n' => {
+                after_marker = false;
+                false
+            }
+            ch if ch.is_whitespace() => after_marker,
+            _ => {
+                after_marker = true;
+                true
+            }
+        };
+
+        if let Some(was_hidden) = run_is_hidden.filter(|was_hidden| *was_hidden != is_hidden) {
+            let format = if was_hidden { &hidden } else { &visible };
+            job.append(&part[run_start..i], 0.0, format.clone());
+            run_start = i;
+        }
+
+        run_is_hidden = Some(is_hidden);
+    }
+
+    if let Some(was_hidden) = run_is_hidden {
+        let format = if was_hidden { hidden } else { visible };
+        job.append(&part[run_start..], 0.0, format);
+    }
+}
+
+/// Tags of an html span with their lowercase names, e.g. ("details", `<details open>`) or ("/summary", `</summary>`)
+fn html_tags(text: &str, span: ByteSpan) -> impl Iterator<Item = (String, ByteSpan)> + '_ {
+    let mut pos = span.start;
+    std::iter::from_fn(move || {
+        let start = pos + text[pos..span.end].find('<')?;
+        let end = start + text[start..span.end].find('>')? + 1;
+        pos = end;
+
+        let name = text[start + 1..end - 1]
+            .split_whitespace()
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+
+        Some((name, ByteSpan::new(start, end)))
+    })
+}
+
+fn is_open_attribute(attr: &str) -> bool {
+    attr.eq_ignore_ascii_case("open") || attr.to_ascii_lowercase().starts_with("open=")
+}
+
+/// Pairs `<details>` with `</details>` across the html spans, the blocks can be nested.
+/// The content between the tags is regular markdown, so the tags are usually in separate spans
+fn find_details_blocks(text: &str, spans: &[SpanDesc]) -> Vec<DetailsBlock> {
+    struct OpenedDetails {
+        open_tag: ByteSpan,
+        is_open: bool,
+        summary_start: Option<ByteSpan>,
+        summary: Option<(ByteSpan, ByteSpan)>,
+    }
+
+    let mut opened: SmallVec<[OpenedDetails; 2]> = SmallVec::new();
+    let mut blocks = vec![];
+
+    let tags = spans
+        .iter()
+        .filter(|desc| desc.kind == SpanKind::Html)
+        .flat_map(|desc| html_tags(text, desc.byte_pos));
+
+    for (name, tag) in tags {
+        match name.as_str() {
+            "details" => opened.push(OpenedDetails {
+                open_tag: tag,
+                is_open: text[tag.start + 1..tag.end - 1]
+                    .split_whitespace()
+                    .skip(1)
+                    .any(is_open_attribute),
+                summary_start: None,
+                summary: None,
+            }),
+
+            "summary" => {
+                if let Some(OpenedDetails {
+                    summary_start: summary_start @ None,
+                    ..
+                }) = opened.last_mut()
+                {
+                    *summary_start = Some(tag);
+                }
+            }
+
+            "/summary" => {
+                if let Some(OpenedDetails {
+                    summary_start: Some(start_tag),
+                    summary: summary @ None,
+                    ..
+                }) = opened.last_mut()
+                {
+                    *summary = Some((
+                        ByteSpan::new(start_tag.start, tag.end),
+                        ByteSpan::new(start_tag.end, tag.start),
+                    ));
+                }
+            }
+
+            "/details" => {
+                if let Some(details) = opened.pop() {
+                    let (summary, title) = details
+                        .summary
+                        .unwrap_or((details.open_tag, ByteSpan::point(details.open_tag.end)));
+
+                    blocks.push(DetailsBlock {
+                        open_tag: details.open_tag,
+                        summary,
+                        title,
+                        body: ByteSpan::new(summary.end, tag.end),
+                        is_open: details.is_open,
+                    });
+                }
+            }
+
+            _ => (),
+        }
+    }
+
+    // inner blocks are closed first
+    blocks.sort_by_key(|details| details.open_tag.start);
+    blocks
+}
+
+/// `<details>` becomes `<details open>` and vice versa, other attributes are kept
+pub fn toggle_details_open_attribute(open_tag: &str) -> String {
+    let inner = open_tag.trim_start_matches('<').trim_end_matches('>');
+    let mut parts: SmallVec<[&str; 4]> = inner.split_whitespace().collect();
+
+    let len = parts.len();
+    parts.retain(|attr| !is_open_attribute(attr));
+    if parts.len() == len {
+        parts.push("open");
+    }
+
+    format!("<{}>", parts.join(" "))
+}
+
 fn fill_annotation_points(
     mut points: Vec<AnnotationPoint>,
     spans: &Vec<SpanDesc>,
     metadata: &Vec<(SpanIndex, SpanMeta)>,
     raw_links: &Vec<RawLink>,
+    details_blocks: &[DetailsBlock],
 ) -> Vec<AnnotationPoint> {
     for (
         index,
@@ -1270,8 +1739,8 @@ fn fill_annotation_points(
             SpanKind::Emphasis => smallvec![(Annotation::Emphasis, pos)],
             SpanKind::Text => smallvec![(Annotation::Text, pos)],
             SpanKind::TaskMarker => match find_metadata(span_index, metadata) {
-                Some(SpanMeta::TaskMarker { checked }) => match *checked {
-                    true => {
+                Some(SpanMeta::TaskMarker(state)) => match state {
+                    TaskState::Done => {
                         let list_item_content = calc_total_range(
                             iterate_immediate_children_of(*parent, spans)
                                 .map(|(_, desc)| &desc.byte_pos),
@@ -1282,7 +1751,11 @@ fn fill_annotation_points(
                             (Annotation::Strike, list_item_content)
                         ]
                     }
-                    false => smallvec![(Annotation::TaskMarker, pos)],
+                    TaskState::InProgress => smallvec![
+                        (Annotation::TaskMarker, pos),
+                        (Annotation::TaskInProgress, pos)
+                    ],
+                    TaskState::Todo => smallvec![(Annotation::TaskMarker, pos)],
                 },
                 _ => smallvec![],
             },
@@ -1304,6 +1777,11 @@ fn fill_annotation_points(
                 _ => smallvec![(Annotation::CodeBlock, pos)],
             },
             SpanKind::MdLink => smallvec![(Annotation::Link, pos)],
+            SpanKind::FrontMatter => smallvec![(Annotation::FrontMatter, pos)],
+            SpanKind::DefinitionTerm => smallvec![(Annotation::DefinitionTerm, pos)],
+            SpanKind::DefinitionDescription => {
+                smallvec![(Annotation::DefinitionDescription, pos)]
+            }
             SpanKind::ListItem => smallvec![(
                 Annotation::ListItemMarker,
                 // We annotate only the bullet (*, -, or +) or number ('1.') as the ListItemMarker
@@ -1321,7 +1799,12 @@ fn fill_annotation_points(
             | SpanKind::Root
             | SpanKind::Html
             | SpanKind::Image
-            | SpanKind::Paragraph => smallvec![],
+            | SpanKind::Paragraph
+            | SpanKind::Table
+            | SpanKind::TableHead
+            | SpanKind::TableRow
+            | SpanKind::TableCell
+            | SpanKind::Callout(_) => smallvec![],
         };
 
         for (annotation, pos) in annotations {
@@ -1354,6 +1837,32 @@ fn fill_annotation_points(
         });
     }
 
+    for details in details_blocks {
+        // the title is a text, so it stays visible when the html tags are hidden
+        let mut annotations: SmallVec<[(Annotation, ByteSpan); 3]> = smallvec![
+            (Annotation::DetailsSummary, details.title),
+            (Annotation::Text, details.title)
+        ];
+
+        if !details.is_open {
+            annotations.push((Annotation::CollapsedDetails, details.body));
+        }
+
+        for (annotation, pos) in annotations {
+            points.push(AnnotationPoint {
+                str_offset: pos.start,
+                kind: PointKind::Start,
+                annotation,
+            });
+
+            points.push(AnnotationPoint {
+                str_offset: pos.end,
+                kind: PointKind::End,
+                annotation,
+            });
+        }
+    }
+
     points.sort_by_key(|p| p.str_offset);
     points
 }
@@ -1417,12 +1926,24 @@ impl MarkdownRunningState {
             md_header,
             md_link,
             md_code,
+            md_task_in_progress,
             subtle_text_color,
             ..
         } = colors;
 
+        // dimmed and boxed, it is metadata rather than a part of the note
+        if self.front_matter > 0 {
+            return TextFormat {
+                font_id: FontId::new(size.small, family.code.clone()),
+                color: *subtle_text_color,
+                background: colors.code_bg_color,
+                line_height: Some(size.small + 6.),
+                ..Default::default()
+            };
+        }
+
         let emphasis = self.emphasis > 0;
-        let bold = self.bold > 0;
+        let bold = self.bold > 0 || self.definition_term > 0 || self.details_summary > 0;
 
         let font_size = match self.heading {
             [h1, ..] if h1 > 0 => size.h1,
@@ -1499,10 +2020,10 @@ impl MarkdownRunningState {
 
         // && command_pressed;
         TextFormat {
-            color: if self.task_marker > 0 {
-                *md_link
-            } else {
-                color
+            color: match (self.task_marker > 0, self.task_in_progress > 0) {
+                (true, true) => *md_task_in_progress,
+                (true, false) => *md_link,
+                (false, _) => color,
             },
             font_id: FontId::new(font_size, font_family.clone()),
             strikethrough: if self.strike > 0 {
@@ -1801,6 +2322,347 @@ mod tests {
         assert_eq!(None, second_line);
     }
 
+    #[test]
+    pub fn test_span_stack_at() {
+        let md = "a **bold** word\n\n- item";
+
+        let structure = TextStructure::new(md);
+        let stack_at =
+            |needle: &str| structure.span_stack_at(ByteSpan::point(md.find(needle).unwrap() + 1));
+
+        assert_eq!(
+            stack_at("bold").as_slice(),
+            &[SpanKind::Paragraph, SpanKind::Bold]
+        );
+        assert_eq!(stack_at("word").as_slice(), &[SpanKind::Paragraph]);
+        assert_eq!(
+            stack_at("item").as_slice(),
+            &[SpanKind::List, SpanKind::ListItem]
+        );
+
+        assert!(
+            TextStructure::new("")
+                .span_stack_at(ByteSpan::point(0))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    pub fn test_callouts() {
+        let md =
+            "> [!WARNING]\n> careful **here**\n\n> plain quote\n\n- item\n  > [!TIP]\n  > nested";
+
+        let structure = TextStructure::new(md);
+        let stack_at =
+            |needle: &str| structure.span_stack_at(ByteSpan::point(md.find(needle).unwrap() + 1));
+
+        assert_eq!(
+            stack_at("here").as_slice(),
+            &[
+                SpanKind::Callout(CalloutKind::Warning),
+                SpanKind::Paragraph,
+                SpanKind::Bold
+            ]
+        );
+        assert_eq!(stack_at("plain").as_slice(), &[SpanKind::Paragraph]);
+        assert_eq!(
+            stack_at("nested").as_slice(),
+            &[
+                SpanKind::List,
+                SpanKind::ListItem,
+                SpanKind::Callout(CalloutKind::Tip),
+                SpanKind::Paragraph
+            ]
+        );
+
+        let (callout, _) = structure
+            .find_span_at(
+                SpanKind::Callout(CalloutKind::Warning),
+                ByteSpan::point(md.find("careful").unwrap()),
+            )
+            .unwrap();
+        assert_eq!(&md[callout.range()], "> [!WARNING]\n> careful **here**");
+    }
+
+    #[test]
+    pub fn test_reading_mode_hides_markers() {
+        let md = "# Title\n\n- **bold** item\n  - nested `code`\n";
+
+        let structure = TextStructure::new(md);
+        let job = structure.create_layout_job(
+            md,
+            &AppTheme::default(),
+            &SyntaxSet::load_defaults_newlines(),
+            &ThemeSet::load_defaults(),
+            &mut CodeHighlightCache::default(),
+            MarkerVisibility::Hidden,
+            true,
+            true,
+        );
+
+        assert_eq!(job.text, md, "markers are still in the galley");
+        assert_eq!(
+            visible_text(&job),
+            "Title\n\n- bold item\n  - nested code\n"
+        );
+    }
+
+    #[test]
+    pub fn test_live_preview_shows_markers_on_cursor_lines() {
+        let md = "# Title\n**bold**\n`code`";
+
+        let structure = TextStructure::new(md);
+        let cursor = ByteSpan::point(md.find("old").unwrap());
+
+        let job = structure.create_layout_job(
+            md,
+            &AppTheme::default(),
+            &SyntaxSet::load_defaults_newlines(),
+            &ThemeSet::load_defaults(),
+            &mut CodeHighlightCache::default(),
+            MarkerVisibility::around_cursor(md, cursor),
+            true,
+            true,
+        );
+
+        assert_eq!(visible_text(&job), "Title\n**bold**\ncode");
+
+        assert_eq!(
+            MarkerVisibility::around_cursor(md, ByteSpan::new(2, md.len() + 10)),
+            MarkerVisibility::ShownOnLines(ByteSpan::new(0, md.len())),
+            "stale cursor is clamped"
+        );
+    }
+
+    fn visible_text(job: &LayoutJob) -> String {
+        job.sections
+            .iter()
+            .filter(|section| section.format.color != Color32::TRANSPARENT)
+            .map(|section| &job.text[section.byte_range.clone()])
+            .collect()
+    }
+
+    #[test]
+    pub fn test_raw_links_in_code_are_plain_text() {
+        let md = "see https://a.com\n\n`https://b.com`\n\n```sh\ncurl https://c.com\n```";
+
+        let structure = TextStructure::new(md);
+        let at = |needle: &str| md.find(needle).unwrap();
+
+        assert!(matches!(
+            structure.find_interactive_text_part(at("a.com"), true),
+            Some(InteractiveTextPart::Link("https://a.com"))
+        ));
+        assert!(
+            structure
+                .find_interactive_text_part(at("a.com"), false)
+                .is_none()
+        );
+
+        assert!(
+            structure
+                .find_interactive_text_part(at("b.com"), true)
+                .is_none()
+        );
+        assert!(
+            structure
+                .find_interactive_text_part(at("c.com"), true)
+                .is_none()
+        );
+    }
+
+    #[test]
+    pub fn test_raw_links_are_skipped_on_request() {
+        let md = "see https://a.com and [b](https://b.com)";
+        let at = |needle: &str| md.find(needle).unwrap();
+
+        let structure = TextStructure::new("").recycle_without_raw_links(md);
+
+        assert!(
+            structure
+                .find_interactive_text_part(at("a.com"), true)
+                .is_none()
+        );
+        assert!(matches!(
+            structure.find_interactive_text_part(at("b]"), true),
+            Some(InteractiveTextPart::Link("https://b.com"))
+        ));
+
+        let structure = structure.recycle(md);
+        assert!(matches!(
+            structure.find_interactive_text_part(at("a.com"), true),
+            Some(InteractiveTextPart::Link("https://a.com"))
+        ));
+    }
+
+    #[test]
+    pub fn test_in_progress_task_marker() {
+        let md = "- [/] doing\n- [x] done\n- [/]glued is not a marker";
+
+        let structure = TextStructure::new(md);
+        let states: Vec<TaskState> = structure
+            .iter()
+            .filter_map(|(index, _)| match structure.find_meta(index) {
+                Some(SpanMeta::TaskMarker(state)) => Some(*state),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(states, [TaskState::InProgress, TaskState::Done]);
+        assert!(matches!(
+            structure.find_interactive_text_part(3, true),
+            Some(InteractiveTextPart::TaskMarker {
+                state: TaskState::InProgress,
+                ..
+            })
+        ));
+        assert_eq!(
+            structure
+                .span_stack_at(ByteSpan::point(md.find("doing").unwrap()))
+                .as_slice(),
+            &[SpanKind::List, SpanKind::ListItem]
+        );
+    }
+
+    #[test]
+    pub fn test_front_matter() {
+        let md = "---\ntags: [work]\n---\n# Title";
+
+        let structure = TextStructure::new(md);
+
+        assert_eq!(
+            structure.front_matter(md).map(str::trim),
+            Some("tags: [work]")
+        );
+        assert_eq!(
+            structure
+                .span_stack_at(ByteSpan::point(md.find("work").unwrap()))
+                .as_slice(),
+            &[SpanKind::FrontMatter]
+        );
+        assert!(
+            structure
+                .find_span_at(
+                    SpanKind::Heading(HeadingLevel::H1),
+                    ByteSpan::point(md.len())
+                )
+                .is_some()
+        );
+
+        let md = "# Title\n\n---\ntags: [work]\n---\n";
+        assert_eq!(
+            TextStructure::new(md).front_matter(md),
+            None,
+            "only at the beginning of the note"
+        );
+    }
+
+    #[test]
+    pub fn test_definition_list() {
+        let md = "Apple\n: A fruit\n\ntext after";
+
+        let structure = TextStructure::new(md);
+        let stack_at = |needle: &str| {
+            structure
+                .span_stack_at(ByteSpan::point(md.find(needle).unwrap() + 1))
+                .to_vec()
+        };
+
+        assert_eq!(stack_at("Apple"), [SpanKind::DefinitionTerm]);
+        assert_eq!(stack_at("fruit"), [SpanKind::DefinitionDescription]);
+        assert_eq!(stack_at("after"), [SpanKind::Paragraph]);
+
+        let theme = AppTheme::default();
+        let job = structure.create_layout_job(
+            md,
+            &theme,
+            &SyntaxSet::load_defaults_newlines(),
+            &ThemeSet::load_defaults(),
+            &mut CodeHighlightCache::default(),
+            MarkerVisibility::Hidden,
+            true,
+            true,
+        );
+
+        assert_eq!(visible_text(&job), "Apple\nA fruit\n\ntext after");
+
+        let term = job
+            .sections
+            .iter()
+            .find(|section| &job.text[section.byte_range.clone()] == "Apple")
+            .unwrap();
+        assert_eq!(term.format.font_id.family, theme.fonts.family.bold);
+
+        let indented: Vec<_> = job
+            .sections
+            .iter()
+            .filter(|section| section.leading_space > 0.0)
+            .collect();
+        assert_eq!(indented.len(), 1);
+        assert_eq!(indented[0].byte_range.start, md.find(':').unwrap());
+    }
+
+    #[test]
+    pub fn test_details_blocks() {
+        let md = "<details>\n<summary>Title</summary>\n\nhidden **text**\n\n</details>\nafter\n\n<details open><summary>Shown</summary>\n\nbody\n\n</details>";
+
+        let structure = TextStructure::new(md);
+        let at = |needle: &str| ByteSpan::point(md.find(needle).unwrap());
+
+        let blocks = &structure.details_blocks;
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(&md[blocks[0].title.range()], "Title");
+        assert_eq!(&md[blocks[0].open_tag.range()], "<details>");
+        assert!(!blocks[0].is_open);
+        assert_eq!(&md[blocks[1].title.range()], "Shown");
+        assert!(blocks[1].is_open);
+
+        assert!(structure.is_in_collapsed_details(at("hidden")));
+        assert!(!structure.is_in_collapsed_details(at("after")));
+        assert!(!structure.is_in_collapsed_details(at("body")));
+
+        assert!(matches!(
+            structure.find_interactive_text_part(at("Title").start, true),
+            Some(InteractiveTextPart::DetailsSummary(details)) if details == &blocks[0]
+        ));
+
+        let theme = AppTheme::default();
+        let job = structure.create_layout_job(
+            md,
+            &theme,
+            &SyntaxSet::load_defaults_newlines(),
+            &ThemeSet::load_defaults(),
+            &mut CodeHighlightCache::default(),
+            MarkerVisibility::Hidden,
+            true,
+            true,
+        );
+
+        let visible = visible_text(&job);
+        assert!(visible.contains("Title"));
+        assert!(visible.contains("after"));
+        assert!(visible.contains("body"));
+        assert!(!visible.contains("hidden"));
+        assert!(!visible.contains("text"));
+
+        let title = job
+            .sections
+            .iter()
+            .find(|section| &job.text[section.byte_range.clone()] == "Title")
+            .unwrap();
+        assert_eq!(title.format.font_id.family, theme.fonts.family.bold);
+    }
+
+    #[test]
+    pub fn test_toggle_details_open_attribute() {
+        assert_eq!(toggle_details_open_attribute("<details>"), "<details open>");
+        assert_eq!(toggle_details_open_attribute("<details open>"), "<details>");
+        assert_eq!(
+            toggle_details_open_attribute("<details class=\"x\" open>"),
+            "<details class=\"x\">"
+        );
+    }
+
     #[test]
     pub fn test_code_block_parsing() {
         let md = "```js\ncode\n```";
@@ -1852,6 +2714,40 @@ mod tests {
         );
     }
 
+    #[test]
+    pub fn test_table_parsing() {
+        let md = "| a | b |\n| --- | --- |\n| c | d |\n";
+
+        let structure = TextStructure::new(md);
+
+        let (table_range, table_index) = structure
+            .find_span_at(SpanKind::Table, ByteSpan::new(2, 2))
+            .unwrap();
+
+        assert_eq!(
+            Some("| a | b |\n| --- | --- |\n| c | d |"),
+            md.get(table_range.range())
+        );
+
+        let rows: Vec<_> = structure
+            .iterate_immediate_children_of(table_index)
+            .map(|(_, desc)| desc.kind)
+            .collect();
+
+        assert_eq!(rows, [SpanKind::TableHead, SpanKind::TableRow]);
+
+        let (_, cell_index) = structure
+            .find_span_at(SpanKind::TableCell, ByteSpan::new(26, 26))
+            .unwrap();
+
+        let (_, text_desc) = structure
+            .iterate_immediate_children_of(cell_index)
+            .next()
+            .unwrap();
+
+        assert_eq!(Some("c"), md.get(text_desc.byte_pos.range()));
+    }
+
     #[test]
     pub fn test_byte_range_relation() {
         let test_cases = [
@@ -1,16 +1,18 @@
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, f32::consts::TAU};
 
 use eframe::{
     egui::{
-        self, FontDefinitions, RichText, TextStyle, ThemePreference, Vec2, Visuals, WidgetText,
+        self, FontDefinitions, Response, RichText, Rot2, Sense, TextStyle, ThemePreference, Ui,
+        Vec2, Visuals, WidgetText,
         style::{NumericColorSpace, Selection, TextCursorStyle, WidgetVisuals, Widgets},
         vec2,
     },
-    epaint::{Color32, CornerRadius, FontFamily, FontId, Shadow, Stroke},
+    epaint::{Color32, CornerRadius, FontFamily, FontId, Rgba, Shadow, Stroke, TextShape},
 };
 
-use crate::nord::Nord;
+use crate::{nord::Nord, settings_parsing::ThemeSettings, text_structure::CalloutKind};
 
+#[derive(Debug)]
 pub enum AppIcon {
     More,
     Settings,
@@ -38,8 +40,21 @@ pub enum AppIcon {
     Error,
     Copy,
     Download,
+    Spinner,
+    Quit,
+    TaskTodo,
+    TaskInProgress,
+    TaskDone,
+    Lock,
+    Callout(CalloutKind),
 }
 
+/// WCAG AA minimum contrast ratio for regular text
+pub const MIN_TEXT_CONTRAST: f32 = 4.5;
+
+/// Full turns per second of `AppIcon::render_spinner`
+const SPINNER_TURNS_PER_SECOND: f64 = 1.0;
+
 impl AppIcon {
     pub fn render(&self, size: f32, color: Color32) -> RichText {
         RichText::new(self.to_icon_str())
@@ -48,6 +63,31 @@ impl AppIcon {
             .size(size)
     }
 
+    /// Themed replacement for `ui.spinner()`: `AppIcon::Spinner` rotating around its center
+    pub fn render_spinner(ui: &mut Ui, size: f32, color: Color32) -> Response {
+        let (rect, response) = ui.allocate_exact_size(Vec2::splat(size), Sense::hover());
+
+        if ui.is_rect_visible(rect) {
+            ui.ctx().request_repaint();
+
+            let galley = ui.painter().layout_no_wrap(
+                AppIcon::Spinner.to_icon_str().to_string(),
+                FontId::new(size, FontFamily::Name("phosphor".into())),
+                color,
+            );
+
+            let turns = ui.input(|i| i.time) * SPINNER_TURNS_PER_SECOND;
+            let angle = turns.fract() as f32 * TAU;
+
+            // text shapes are rotated around their top left corner
+            let pos = rect.center() - Rot2::from_angle(angle) * (galley.size() / 2.0);
+            ui.painter()
+                .add(TextShape::new(pos, galley, color).with_angle(angle));
+        }
+
+        response
+    }
+
     pub fn render_with_text_size(
         &self,
         icon_size: f32,
@@ -170,6 +210,19 @@ impl AppIcon {
             AppIcon::Error => P::WARNING,
             AppIcon::Copy => P::COPY_SIMPLE,
             AppIcon::Download => P::DOWNLOAD_SIMPLE,
+            AppIcon::Spinner => P::CIRCLE_NOTCH,
+            AppIcon::Quit => P::POWER,
+            AppIcon::TaskTodo => P::SQUARE,
+            AppIcon::TaskInProgress => P::MINUS_SQUARE,
+            AppIcon::TaskDone => P::CHECK_SQUARE,
+            AppIcon::Lock => P::LOCK_SIMPLE,
+            AppIcon::Callout(kind) => match kind {
+                CalloutKind::Note => P::INFO,
+                CalloutKind::Tip => P::LIGHTBULB,
+                CalloutKind::Important => P::WARNING_DIAMOND,
+                CalloutKind::Warning => P::WARNING,
+                CalloutKind::Caution => P::WARNING_OCTAGON,
+            },
         }
     }
 }
@@ -314,6 +367,10 @@ pub struct ColorTheme {
     pub md_header: Color32,
     pub md_link: Color32,
     pub md_code: Color32,
+    pub md_task_in_progress: Color32,
+    // code blocks that are not regular code get a distinct background tint
+    pub md_settings_code_bg: Color32,
+    pub md_output_code_bg: Color32,
 
     // ---------
     // General UI colors
@@ -339,6 +396,9 @@ pub struct ColorTheme {
     pub outline_fg: Color32,
     pub selection_bg: Color32,
     pub selection_stroke: Color32,
+    // the text cursor and the selected text of text edits
+    pub caret_color: Color32,
+    pub text_selection_bg: Color32,
     pub hyperlink_color: Color32,
     pub normal_text_color: Color32,
     pub subtle_text_color: Color32,
@@ -363,6 +423,79 @@ pub struct ColorTheme {
 }
 
 impl ColorTheme {
+    /// Text colors that are hard to read on top of their background
+    pub fn contrast_warnings(&self) -> Vec<String> {
+        [
+            ("Text", self.md_body, "the background", self.main_bg),
+            ("Headings", self.md_header, "the background", self.main_bg),
+            ("Links", self.md_link, "the background", self.main_bg),
+            ("Inline code", self.md_code, "the background", self.main_bg),
+            ("UI text", self.normal_text_color, "the background", self.main_bg),
+            ("Code", self.normal_text_color, "code blocks", self.code_bg_color),
+            (
+                "Selected text",
+                self.md_body,
+                "the selection",
+                self.main_bg.blend(self.text_selection_bg),
+            ),
+            (
+                "Selected code",
+                self.normal_text_color,
+                "the selection in code blocks",
+                self.code_bg_color.blend(self.text_selection_bg),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(text, text_color, bg, bg_color)| {
+            let ratio = text_color.contrast_ratio(bg_color);
+            (ratio < MIN_TEXT_CONTRAST).then(|| {
+                format!(
+                    "{text} on {bg} has a contrast ratio of {ratio:.1}:1, at least {MIN_TEXT_CONTRAST}:1 is recommended"
+                )
+            })
+        })
+        .collect()
+    }
+
+    /// Colors from the `theme` settings replace the defaults,
+    /// `caretColor` and `selectionColor` win over the ones derived from `accentColor`
+    pub fn with_settings(self, settings: Option<&ThemeSettings>) -> Self {
+        let Some(settings) = settings else {
+            return self;
+        };
+
+        let colors = match settings.accent_color {
+            Some(accent) => self.with_accent(accent.value()),
+            None => self,
+        };
+
+        Self {
+            caret_color: settings
+                .caret_color
+                .map_or(colors.caret_color, |c| c.value()),
+            text_selection_bg: settings
+                .selection_color
+                .map_or(colors.text_selection_bg, |c| c.value()),
+            ..colors
+        }
+    }
+
+    /// Recolors what is Nord frost by default: the caret, the selection, links,
+    /// hovered and pressed buttons, and the selected items of pickers
+    pub fn with_accent(self, accent: Color32) -> Self {
+        Self {
+            caret_color: accent,
+            // dark enough to keep the text readable on top of it
+            text_selection_bg: accent.shade(0.45),
+            selection_bg: accent.shade(0.3),
+            hyperlink_color: accent,
+            md_link: accent,
+            button_hover_fg: accent.shade(1.1),
+            button_pressed_fg: accent.shade(1.2),
+            ..self
+        }
+    }
+
     pub fn nord() -> Self {
         // ---------
         // editor specific colors
@@ -373,6 +506,9 @@ impl ColorTheme {
         // same as hyperlink_color
         let md_link = Nord::NORD7;
         let md_code = Nord::NORD13;
+        let md_task_in_progress = Nord::NORD15;
+        let md_settings_code_bg = Nord::NORD10.shade(0.25);
+        let md_output_code_bg = Nord::NORD14.shade(0.2);
 
         let secondary_icon = Nord::NORD3.shade(1.1);
 
@@ -403,6 +539,9 @@ impl ColorTheme {
         let outline_fg = Nord::NORD1;
         let selection_bg = Nord::NORD1;
         let selection_stroke = Nord::NORD6;
+        let caret_color = Nord::NORD8;
+        // dark enough to keep links and inline code readable on top of it
+        let text_selection_bg = Nord::NORD10.shade(0.55);
         let hyperlink_color = Nord::NORD7;
 
         // Something just barely different from the background color.
@@ -441,6 +580,8 @@ impl ColorTheme {
             outline_fg,
             selection_bg,
             selection_stroke,
+            caret_color,
+            text_selection_bg,
             hyperlink_color,
             normal_text_color,
             faint_bg_color,
@@ -454,6 +595,9 @@ impl ColorTheme {
             md_body,
             md_header,
             md_code,
+            md_task_in_progress,
+            md_settings_code_bg,
+            md_output_code_bg,
             subtle_text_color,
             md_link,
             success_fg_color,
@@ -462,6 +606,10 @@ impl ColorTheme {
 }
 
 pub fn configure_styles(ctx: &egui::Context, theme: &AppTheme) {
+    for warning in theme.colors.contrast_warnings() {
+        println!("theme warning: {warning}");
+    }
+
     let mut style = (*ctx.style()).clone();
 
     style.text_styles = text_styles(&theme.fonts);
@@ -618,6 +766,12 @@ fn text_styles(FontTheme { size, family }: &FontTheme) -> BTreeMap<TextStyle, Fo
 
 pub trait ColorManipulation {
     fn shade(self, by: f32) -> Self;
+
+    /// WCAG relative luminance: 0 for black, 1 for white. Alpha is ignored
+    fn relative_luminance(self) -> f32;
+
+    /// WCAG contrast ratio: from 1 (same luminance) to 21 (black on white)
+    fn contrast_ratio(self, other: Self) -> f32;
 }
 
 impl ColorManipulation for Color32 {
@@ -631,6 +785,22 @@ impl ColorManipulation for Color32 {
             a,
         )
     }
+
+    fn relative_luminance(self) -> f32 {
+        let [r, g, b, _] = self.to_array();
+        let linear = Rgba::from(Color32::from_rgb(r, g, b));
+
+        0.2126 * linear.r() + 0.7152 * linear.g() + 0.0722 * linear.b()
+    }
+
+    fn contrast_ratio(self, other: Self) -> f32 {
+        let [lighter, darker] = {
+            let (a, b) = (self.relative_luminance(), other.relative_luminance());
+            [a.max(b), a.min(b)]
+        };
+
+        (lighter + 0.05) / (darker + 0.05)
+    }
 }
 
 fn visuals(color_theme: &ColorTheme) -> Visuals {
@@ -649,6 +819,8 @@ fn visuals(color_theme: &ColorTheme) -> Visuals {
         outline_fg,
         selection_bg,
         selection_stroke,
+        caret_color,
+        text_selection_bg,
         hyperlink_color,
         normal_text_color,
         faint_bg_color,
@@ -664,12 +836,15 @@ fn visuals(color_theme: &ColorTheme) -> Visuals {
         md_link: _,
         subtle_text_color: _,
         md_code: _,
+        md_task_in_progress: _,
+        md_settings_code_bg: _,
+        md_output_code_bg: _,
         success_fg_color: _,
     } = color_theme.clone();
 
     // --- window ---
     let selection = Selection {
-        bg_fill: selection_bg,
+        bg_fill: text_selection_bg,
         stroke: Stroke::new(1.0, selection_stroke),
     };
 
@@ -758,7 +933,7 @@ fn visuals(color_theme: &ColorTheme) -> Visuals {
         slider_trailing_fill: false,
         widgets,
         text_cursor: TextCursorStyle {
-            stroke: Stroke::new(2.0, normal_text_color),
+            stroke: Stroke::new(2.0, caret_color),
             // preview: true,
             ..Default::default()
         },
@@ -769,3 +944,41 @@ fn visuals(color_theme: &ColorTheme) -> Visuals {
         numeric_color_space: NumericColorSpace::GammaByte,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_contrast_ratio() {
+        let ratio = Color32::BLACK.contrast_ratio(Color32::WHITE);
+        assert!((ratio - 21.0).abs() < 0.01, "ratio = {ratio}");
+        assert_eq!(Nord::NORD4.contrast_ratio(Nord::NORD4), 1.0);
+
+        assert_eq!(ColorTheme::nord().contrast_warnings(), Vec::<String>::new());
+
+        let unreadable = ColorTheme {
+            md_body: Nord::NORD1,
+            ..ColorTheme::nord()
+        };
+        assert_eq!(unreadable.contrast_warnings().len(), 1);
+
+        // both regular text and code are drawn on top of the selection
+        let bright_selection = ColorTheme {
+            text_selection_bg: Nord::NORD4,
+            ..ColorTheme::nord()
+        };
+        assert_eq!(bright_selection.contrast_warnings().len(), 2);
+    }
+
+    #[test]
+    pub fn test_accent_color() {
+        let colors = ColorTheme::nord().with_accent(Nord::NORD15);
+
+        assert_eq!(colors.caret_color, Nord::NORD15);
+        assert_eq!(colors.md_link, Nord::NORD15);
+        assert_eq!(colors.contrast_warnings(), Vec::<String>::new());
+        // not interactive, stays as is
+        assert_eq!(colors.md_header, ColorTheme::nord().md_header);
+    }
+}
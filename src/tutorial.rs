@@ -0,0 +1,155 @@
+use eframe::egui::{Context, Id, Rect};
+
+/// UI elements that tutorial steps can point at,
+/// their rects are registered during rendering and read back by the callout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TutorialTarget {
+    Editor,
+    NotePicker,
+    PinButton,
+    FeedbackButton,
+    SlashPalette,
+}
+
+impl TutorialTarget {
+    fn id(self) -> Id {
+        Id::new(("tutorial_target", self))
+    }
+
+    pub fn register_rect(self, ctx: &Context, rect: Rect) {
+        ctx.data_mut(|data| data.insert_temp(self.id(), rect));
+    }
+
+    pub fn clear_rect(self, ctx: &Context) {
+        ctx.data_mut(|data| data.remove::<Rect>(self.id()));
+    }
+
+    pub fn rect(self, ctx: &Context) -> Option<Rect> {
+        ctx.data(|data| data.get_temp(self.id()))
+    }
+
+    /// Points at the target or, for popups that aren't shown yet, at the element that opens them
+    pub fn rect_or_fallback(self, ctx: &Context) -> Option<Rect> {
+        let fallback = match self {
+            TutorialTarget::SlashPalette => Some(TutorialTarget::Editor),
+            _ => None,
+        };
+
+        self.rect(ctx)
+            .or_else(|| fallback.and_then(|target| target.rect(ctx)))
+    }
+}
+
+#[derive(Debug)]
+pub struct TutorialStep {
+    pub target: TutorialTarget,
+    pub title: &'static str,
+    pub text: &'static str,
+}
+
+pub const TUTORIAL_STEPS: &[TutorialStep] = &[
+    TutorialStep {
+        target: TutorialTarget::Editor,
+        title: "Your note",
+        text: "The tutorial checklist was added to this note, it is just markdown, feel free to edit it.",
+    },
+    TutorialStep {
+        target: TutorialTarget::SlashPalette,
+        title: "Slash palette",
+        text: "Type \"/\" in the editor to see all the commands, for example \"/table\" or \"/code\".",
+    },
+    TutorialStep {
+        target: TutorialTarget::NotePicker,
+        title: "Shelves",
+        text: "Shelv has 4 notes and settings, hover over them to see the shortcuts.",
+    },
+    TutorialStep {
+        target: TutorialTarget::PinButton,
+        title: "Pin window",
+        text: "Unpinned Shelv hides itself when it loses focus, pin it to keep it on the screen.",
+    },
+    TutorialStep {
+        target: TutorialTarget::FeedbackButton,
+        title: "Feedback",
+        text: "Found a bug or have an idea? Send the current note as feedback from here.",
+    },
+];
+
+/// Progress through `TUTORIAL_STEPS`
+#[derive(Debug, Default)]
+pub struct TutorialState {
+    step: usize,
+}
+
+impl TutorialState {
+    pub fn step_index(&self) -> usize {
+        self.step
+    }
+
+    pub fn current_step(&self) -> Option<&'static TutorialStep> {
+        TUTORIAL_STEPS.get(self.step)
+    }
+
+    pub fn is_last_step(&self) -> bool {
+        self.step + 1 >= TUTORIAL_STEPS.len()
+    }
+
+    /// Returns false if there are no more steps
+    pub fn next(&mut self) -> bool {
+        if self.is_last_step() {
+            return false;
+        }
+
+        self.step += 1;
+        true
+    }
+
+    pub fn prev(&mut self) {
+        self.step = self.step.saturating_sub(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        app_actions::{AppAction, TutorialAction},
+        test_harness::TestHarness,
+    };
+
+    use super::*;
+
+    #[test]
+    pub fn test_tutorial_navigation() {
+        let mut tutorial = TutorialState::default();
+        tutorial.prev();
+        assert_eq!(tutorial.step_index(), 0);
+
+        for _ in 1..TUTORIAL_STEPS.len() {
+            assert!(tutorial.next());
+        }
+
+        assert!(tutorial.is_last_step());
+        assert!(!tutorial.next());
+        assert_eq!(tutorial.step_index(), TUTORIAL_STEPS.len() - 1);
+
+        tutorial.prev();
+        assert_eq!(tutorial.step_index(), TUTORIAL_STEPS.len() - 2);
+    }
+
+    #[test]
+    pub fn test_tutorial_loads_bundled_note() {
+        let mut harness = TestHarness::new("my notes{||}");
+        harness.dispatch(AppAction::StartTutorial);
+
+        let text = harness.text_with_cursor();
+        assert!(text.starts_with("{||}# Welcome to Shelv"));
+        assert!(text.ends_with("-------end of tutorial-------\n\nmy notes"));
+        assert_eq!(
+            harness.state.tutorial.as_ref().map(|t| t.step_index()),
+            Some(0)
+        );
+
+        harness.dispatch(AppAction::Tutorial(TutorialAction::Finish));
+        assert!(harness.state.tutorial.is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use eframe::egui::{Color32, KeyboardShortcut, RichText, Stroke};
+use eframe::egui::{Color32, KeyboardShortcut, RichText, Stroke, WidgetInfo, WidgetType};
 use egui_taffy::{AsTuiBuilder, Tui, TuiBuilder, TuiBuilderLogic, TuiInnerResponse, TuiWidget};
 
 use crate::{
@@ -55,10 +55,13 @@ pub struct IconButton<'theme> {
     icon: AppIcon,
     size: IconButtonSize,
     tooltip: Option<(String, Option<KeyboardShortcut>)>,
+    /// accessible name for screen readers, falls back to the tooltip or the text
+    label: Option<String>,
     text: Option<String>,
     text_size: f32,
     fade: f32,
-    is_toggled: bool,
+    /// `None` if the button is not a toggle
+    is_toggled: Option<bool>,
     theme: &'theme AppTheme,
     color: Option<Color32>,
 }
@@ -71,9 +74,10 @@ impl<'theme> IconButton<'theme> {
             theme,
             size: IconButtonSize::Medium,
             tooltip: None,
+            label: None,
             text: None,
             fade: 1.0,
-            is_toggled: false,
+            is_toggled: None,
             color: None,
             text_size: theme.fonts.size.normal,
         }
@@ -91,6 +95,12 @@ impl<'theme> IconButton<'theme> {
         self
     }
 
+    /// Set the name announced by screen readers, needed when there is no tooltip or text
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
     /// Set the fade value from 0.0 to 1.0 for animations
     pub fn fade(mut self, fade: f32) -> Self {
         self.fade = fade.clamp(0.0, 1.0);
@@ -99,7 +109,7 @@ impl<'theme> IconButton<'theme> {
 
     /// Set whether the button is in a toggled state
     pub fn toggled(mut self, is_toggled: bool) -> Self {
-        self.is_toggled = is_toggled;
+        self.is_toggled = Some(is_toggled);
         self
     }
 
@@ -123,6 +133,7 @@ impl<'theme> TuiWidget for IconButton<'theme> {
             icon,
             size,
             tooltip,
+            label,
             fade,
             is_toggled,
             theme,
@@ -131,12 +142,21 @@ impl<'theme> TuiWidget for IconButton<'theme> {
             text_size,
         } = self;
 
+        let accessible_name = label
+            .or_else(|| {
+                tooltip
+                    .as_ref()
+                    .map(|(tooltip_text, _)| tooltip_text.clone())
+            })
+            .or_else(|| text.clone())
+            .unwrap_or_else(|| format!("{icon:?}"));
+
         {
             let icon_size = size.get_icon_font_size(theme);
 
             let base_color = if let Some(color) = color {
                 color
-            } else if is_toggled {
+            } else if is_toggled == Some(true) {
                 theme.colors.button_pressed_fg
             } else {
                 theme.colors.subtle_text_color
@@ -149,7 +169,8 @@ impl<'theme> TuiWidget for IconButton<'theme> {
                 .gamma_multiply(0.2)
                 .lerp_to_gamma(base_color, fade);
 
-            tui.mut_egui_style(apply_icon_btn_styling)
+            let response = tui
+                .mut_egui_style(apply_icon_btn_styling)
                 .button(|tui| {
                     let label = if let Some(text) = text.as_ref() {
                         tui.label(
@@ -167,7 +188,16 @@ impl<'theme> TuiWidget for IconButton<'theme> {
                         label
                     }
                 })
-                .response
+                .response;
+
+            response.widget_info(|| match is_toggled {
+                Some(is_toggled) => {
+                    WidgetInfo::selected(WidgetType::Button, true, is_toggled, &accessible_name)
+                }
+                None => WidgetInfo::labeled(WidgetType::Button, true, &accessible_name),
+            });
+
+            response
         }
     }
 }
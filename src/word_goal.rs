@@ -0,0 +1,101 @@
+use serde_json::Value;
+
+use crate::{front_matter::parse_front_matter, text_structure::TextStructure};
+
+/// Front matter key of the per note goal, e.g. `wordGoal: 500`
+pub const WORD_GOAL_KEY: &str = "wordGoal";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WordGoalProgress {
+    pub words: usize,
+    pub goal: usize,
+}
+
+impl WordGoalProgress {
+    /// `None` if the note doesn't have a (positive) goal in its front matter
+    pub fn of_note(text: &str, structure: &TextStructure) -> Option<Self> {
+        let goal = match parse_front_matter(structure.front_matter(text)?).get(WORD_GOAL_KEY)? {
+            Value::Number(goal) => goal.as_u64()?,
+            Value::String(goal) => goal.trim().parse().ok()?,
+            _ => return None,
+        };
+
+        (goal > 0).then(|| Self {
+            words: count_words(text, structure),
+            goal: goal as usize,
+        })
+    }
+
+    pub fn fraction(&self) -> f32 {
+        (self.words as f32 / self.goal as f32).min(1.0)
+    }
+
+    pub fn is_reached(&self) -> bool {
+        self.words >= self.goal
+    }
+}
+
+/// Words of the prose, code and the front matter don't count.
+/// A word split by markdown markers, e.g. `**bo**ld`, counts once
+pub fn count_words(text: &str, structure: &TextStructure) -> usize {
+    let mut words = 0;
+    let mut prev_end: Option<usize> = None;
+
+    for span in structure.prose_text_spans() {
+        let part = &text[span.range()];
+
+        let is_glued_to_prev = prev_end.is_some_and(|prev_end| {
+            let between = text.get(prev_end..span.start).unwrap_or(" ");
+            !between.contains(char::is_whitespace)
+                && !text[..prev_end].ends_with(char::is_whitespace)
+                && !part.starts_with(char::is_whitespace)
+        });
+
+        let part_words = part.split_whitespace().count();
+        words += match is_glued_to_prev {
+            true => part_words.saturating_sub(1),
+            false => part_words,
+        };
+
+        prev_end = Some(span.end);
+    }
+
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_word_goal_progress() {
+        let text = r#"---
+wordGoal: 10
+---
+# Draft title
+
+Some **bo**ld words, `code` is skipped.
+
+```js
+not counted at all
+```
+- and a list
+"#;
+
+        let structure = TextStructure::new(text);
+
+        assert_eq!(
+            WordGoalProgress::of_note(text, &structure),
+            Some(WordGoalProgress {
+                words: 10,
+                goal: 10
+            })
+        );
+
+        let text = "no goal here";
+        assert_eq!(
+            WordGoalProgress::of_note(text, &TextStructure::new(text)),
+            None
+        );
+    }
+}